@@ -0,0 +1,92 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::redact::redact;
+
+const PREVIEW_CHARS: usize = 80;
+
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A write operation performed through MCP, stored as one JSON line in the audit file.
+#[derive(Debug, Serialize)]
+pub struct AuditEntry<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub session: &'a str,
+    pub server: &'a str,
+    pub tool: &'a str,
+    /// Name of the memo or user that was changed, when known.
+    pub target: Option<&'a str>,
+    pub summary: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Appends `entry` to the audit file at `path`, creating the file if needed.
+pub fn append(path: &Path, entry: &AuditEntry) -> Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    let _guard = WRITE_LOCK.lock().unwrap();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open audit log {}: {}", path.display(), e))?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Shortened single-line preview of memo content with secrets scrubbed.
+pub fn preview(content: &str) -> String {
+    let line = content.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim();
+    let mut preview = line.chars().take(PREVIEW_CHARS).collect::<String>();
+    if line.chars().count() > PREVIEW_CHARS || content.trim().lines().count() > 1 {
+        preview.push('…');
+    }
+    redact(&preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_preview() {
+        let path = std::env::temp_dir().join(format!("mcp-memos-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        for tool in ["create_memo", "delete_memo"] {
+            append(&path, &AuditEntry {
+                timestamp: Utc::now(),
+                session: "1-0",
+                server: "default",
+                tool,
+                target: Some("memos/1"),
+                summary: preview("\n# Shopping\n\n- milk\n"),
+                success: true,
+                error: None,
+            }).unwrap();
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let entry: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(entry["tool"], "delete_memo");
+        assert_eq!(entry["summary"], "# Shopping…");
+        assert!(entry.get("error").is_none());
+    }
+}
@@ -0,0 +1,298 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// JSON Lines backups made through the Memos API, for disaster recovery without access to the Memos
+// database. Each line holds one memo with its comments; relations and attachment metadata are part
+// of the memo. Attachment files are not included, see the Obsidian export for those. The same files
+// serve as the account snapshots of the `snapshot_account` and `restore_snapshot` tools.
+
+pub mod schedule;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Write};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::memos::{
+    MemoName,
+    service::note::{Note, NoteService, Relation, RelationType, State, UpdateNote},
+};
+
+/// What a restore does with a memo of the backup when the server has a memo of the same name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, schemars::JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Conflict {
+    /// Keep the memo on the server and leave the one of the backup out.
+    #[default]
+    Skip,
+    /// Write the memo of the backup over the one on the server, adding its missing comments.
+    Overwrite,
+    /// Restore the memo of the backup as a new memo next to the one on the server.
+    Duplicate,
+}
+
+/// One line of a backup.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub memo: Note,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<Note>,
+}
+
+/// What a backup wrote.
+#[derive(Debug, Default, Serialize)]
+pub struct BackupSummary {
+    pub memos: usize,
+    pub comments: usize,
+}
+
+/// What a restore created.
+#[derive(Debug, Default, Serialize)]
+pub struct RestoreSummary {
+    pub memos: usize,
+    /// Memos on the server the backup was written over.
+    pub overwritten: usize,
+    /// Memos of the backup left out because the server has them.
+    pub skipped: usize,
+    pub comments: usize,
+    pub relations: usize,
+    pub attachments: usize,
+    /// Parts of the backup that could not be restored, with the reason.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// Writes every memo of `server`, archived ones included, as one JSON line each to `out`.
+pub async fn backup(server: &dyn NoteService, out: &mut impl Write) -> Result<BackupSummary> {
+    let mut memos = server.list_notes().await?;
+    memos.extend(server.list_archived_notes().await?);
+
+    let mut summary = BackupSummary::default();
+    for memo in memos {
+        let comments = server.list_note_comments(&memo.memo_name()?).await?;
+        summary.memos += 1;
+        summary.comments += comments.len();
+        serde_json::to_writer(&mut *out, &BackupRecord { memo, comments })?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(summary)
+}
+
+/// Recreates the memos of a backup on `server`. Memos get new names, so relations are pointed at
+/// the new names once every memo exists. Attachments are only linked again, which works while
+/// their files still exist on the server. A memo whose name the server already has is handled as
+/// `conflict` says.
+///
+/// The whole backup is read before anything is written, so a damaged file changes nothing.
+pub async fn restore(server: &dyn NoteService, input: impl BufRead, conflict: Conflict) -> Result<RestoreSummary> {
+    let mut records = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: BackupRecord = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("Invalid backup on line {}: {}", number + 1, e))?;
+        records.push((record.memo.memo_name()?, record));
+    }
+    let existing = match conflict {
+        Conflict::Duplicate => BTreeSet::new(),
+        Conflict::Skip | Conflict::Overwrite => {
+            let mut memos = server.list_notes().await?;
+            memos.extend(server.list_archived_notes().await?);
+            memos.iter().filter_map(|memo| memo.memo_name().ok()).collect()
+        }
+    };
+
+    let mut summary = RestoreSummary::default();
+    let mut restored = BTreeMap::new();
+    for (old_name, record) in &records {
+        let mut memo = record.memo.clone();
+        let overwrite = existing.contains(old_name);
+        if overwrite && conflict == Conflict::Skip {
+            summary.skipped += 1;
+            restored.insert(old_name.clone(), old_name.clone());
+            continue;
+        }
+        let written = if overwrite {
+            async { server.update_note(&UpdateNote::from_note(&memo)?).await?.memo_name() }.await
+        } else {
+            memo.name = None;
+            server.create_note(&memo).await.and_then(|created| created.memo_name())
+        };
+        let name = match written {
+            Ok(name) => name,
+            Err(e) => {
+                summary.errors.push(format!("{}: {}", old_name, e));
+                continue;
+            }
+        };
+        if overwrite {
+            summary.overwritten += 1;
+        } else {
+            summary.memos += 1;
+        }
+
+        if !overwrite
+            && memo.state == State::Archived
+            && let Err(e) = server.archive_note(&name).await
+        {
+            summary.errors.push(format!("archiving {} restored as {}: {}", old_name, name, e));
+        }
+        // Comments the overwritten memo already has are not added again.
+        let present = if overwrite {
+            match server.list_note_comments(&name).await {
+                Ok(comments) => comments.into_iter().map(|comment| comment.content).collect(),
+                // Adds none rather than risk doubling them.
+                Err(e) => {
+                    summary.errors.push(format!("comments of {}: {}", old_name, e));
+                    record.comments.iter().map(|comment| comment.content.clone()).collect()
+                }
+            }
+        } else {
+            BTreeSet::new()
+        };
+        for comment in record.comments.iter().filter(|comment| !present.contains(&comment.content)) {
+            let mut comment = comment.clone();
+            comment.name = None;
+            match server.create_note_comment(&name, &comment).await {
+                Ok(_) => summary.comments += 1,
+                Err(e) => summary.errors.push(format!("comment on {}: {}", old_name, e)),
+            }
+        }
+        if !memo.attachments.is_empty() {
+            match server.set_note_attachments(&name, &memo.attachments).await {
+                Ok(()) => summary.attachments += memo.attachments.len(),
+                Err(e) => summary.errors.push(format!("attachments of {}: {}", old_name, e)),
+            }
+        }
+        restored.insert(old_name.clone(), name);
+    }
+
+    for (old_name, record) in &records {
+        let Some(name) = restored.get(old_name) else {
+            continue;
+        };
+        if conflict == Conflict::Skip && existing.contains(old_name) {
+            continue;
+        }
+        let relations = record.memo
+            .relations
+            .iter()
+            .filter(|relation| relation.relation_type == RelationType::Reference && relation.memo["name"] == old_name.as_str())
+            .filter_map(|relation| relation.related_memo["name"].as_str()?.parse::<MemoName>().ok())
+            .map(|related| Relation::new(name, restored.get(&related).unwrap_or(&related), RelationType::Reference))
+            .collect::<Vec<_>>();
+        if relations.is_empty() {
+            continue;
+        }
+        match server.set_note_relations(name, &relations).await {
+            Ok(()) => summary.relations += relations.len(),
+            Err(e) => summary.errors.push(format!("relations of {}: {}", old_name, e)),
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::{Value, json};
+    use wiremock::{Mock, Request, ResponseTemplate, matchers::{method, path, path_regex, query_param}};
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[tokio::test]
+    async fn test_backup_and_restore() {
+        let (source, server) = mock::start().await;
+        let mut archived = mock::memo("memos/5", "Old plans");
+        archived["state"] = json!("ARCHIVED");
+        archived["attachments"] = json!([mock::attachment("attachments/a1", "plan.png", "image/png")]);
+        archived["relations"] = json!([
+            {"memo": {"name": "memos/5"}, "relatedMemo": {"name": "memos/1"}, "type": "REFERENCE"},
+            {"memo": {"name": "memos/9"}, "relatedMemo": {"name": "memos/5"}, "type": "COMMENT"},
+        ]);
+        Mock::given(method("GET")).and(path("/api/v1/memos")).and(query_param("state", "ARCHIVED"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"memos": [archived]})))
+            .with_priority(1)
+            .mount(&source).await;
+        mock::mount_memos_api(&source).await;
+
+        let mut out = Vec::new();
+        let summary = backup(&server, &mut out).await.unwrap();
+        assert_eq!((summary.memos, summary.comments), (3, 3));
+        assert_eq!(out.iter().filter(|&&byte| byte == b'\n').count(), 3);
+
+        let (target, server) = mock::start().await;
+        let created = AtomicUsize::new(0);
+        Mock::given(method("POST")).and(path("/api/v1/memos"))
+            .respond_with(move |request: &Request| {
+                let body = request.body_json::<Value>().unwrap();
+                let name = format!("memos/{}", created.fetch_add(1, Ordering::Relaxed) + 100);
+                ResponseTemplate::new(200).set_body_json(mock::memo(&name, body["content"].as_str().unwrap()))
+            })
+            .mount(&target).await;
+        mock::mount_memos_api(&target).await;
+        Mock::given(method("POST")).and(path_regex(r"^/api/v1/memos/[^/]+/(attachments|relations)$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&target).await;
+
+        let summary = restore(&server, out.as_slice(), Conflict::Duplicate).await.unwrap();
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+        assert_eq!((summary.memos, summary.comments, summary.attachments, summary.relations), (3, 3, 1, 1));
+
+        let requests = target.received_requests().await.unwrap();
+        assert!(requests.iter().all(|request| request.body_json::<Value>().map_or(true, |body| body["name"] != "memos/5")));
+        assert!(requests.iter().any(|request| request.method == "PATCH" && request.url.path() == "/api/v1/memos/102"));
+        let relations = requests.iter().find(|request| request.url.path().ends_with("/relations")).unwrap();
+        assert_eq!(relations.url.path(), "/api/v1/memos/102/relations");
+        let relations = relations.body_json::<Value>().unwrap()["relations"].clone();
+        assert_eq!(relations, json!([{"memo": {"name": "memos/102"}, "relatedMemo": {"name": "memos/100"}, "type": "REFERENCE"}]));
+    }
+
+    #[tokio::test]
+    async fn test_restore_conflicts() {
+        let (mock, server) = mock::start().await;
+        mock::mount_memos_api(&mock).await;
+        let record = |name: &str, content: &str, comment: &str| {
+            let mut line = serde_json::to_string(&json!({
+                "memo": mock::memo(name, content),
+                "comments": [mock::memo("memos/20", comment)],
+            })).unwrap();
+            line.push('\n');
+            line
+        };
+        // The server has memos/1, with the comment "A comment", but not memos/7.
+        let input = record("memos/1", "Grow tomatoes", "A comment") + &record("memos/7", "Gone", "Another comment");
+
+        let summary = restore(&server, input.as_bytes(), Conflict::Skip).await.unwrap();
+        assert_eq!((summary.memos, summary.skipped, summary.overwritten, summary.comments), (1, 1, 0, 1));
+
+        let summary = restore(&server, input.as_bytes(), Conflict::Overwrite).await.unwrap();
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+        assert_eq!((summary.memos, summary.skipped, summary.overwritten, summary.comments), (1, 0, 1, 1));
+        let requests = mock.received_requests().await.unwrap();
+        let overwrite = requests.iter().find(|request| request.method == "PATCH" && request.url.path() == "/api/v1/memos/1").unwrap();
+        assert_eq!(overwrite.body_json::<Value>().unwrap()["content"], "Grow tomatoes");
+
+        let summary = restore(&server, input.as_bytes(), Conflict::Duplicate).await.unwrap();
+        assert_eq!((summary.memos, summary.skipped, summary.overwritten, summary.comments), (2, 0, 0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_damaged_backup() {
+        let (mock, server) = mock::start().await;
+        let line = serde_json::to_string(&json!({"memo": mock::memo("memos/1", "Fine")})).unwrap();
+        let input = format!("{}\n{{\"memo\": \n", line);
+
+        let error = restore(&server, input.as_bytes(), Conflict::Duplicate).await.unwrap_err();
+        assert!(error.to_string().contains("line 2"), "{}", error);
+        assert!(mock.received_requests().await.unwrap().is_empty());
+    }
+}
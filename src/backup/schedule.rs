@@ -0,0 +1,269 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Backups of every server on a cron schedule, kept in a local directory or an S3-compatible bucket
+// and pruned by count and age. Files are named `{server}-{YYYYMMDDTHHMMSSZ}.jsonl`.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use cron::Schedule;
+use tracing::{info, warn};
+
+use crate::config::{BackupSettings, SharedSettings};
+use crate::memos::{Servers, service::note::NoteService};
+
+/// How often the scheduler looks at the settings again, so a reload applies before the next backup.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Parses a cron expression in UTC. The seconds field is optional: `0 3 * * *` is 03:00 daily.
+pub fn parse_schedule(expr: &str) -> Result<Schedule> {
+    let expr = match expr.split_whitespace().count() {
+        5 => format!("0 {}", expr.trim()),
+        _ => expr.trim().to_string(),
+    };
+    Schedule::from_str(&expr).map_err(|e| anyhow::anyhow!("`{}` is not a cron expression: {}", expr, e))
+}
+
+/// Backs up every server whenever the configured schedule is due. Idles while backups are not
+/// configured, and follows schedule changes made by a configuration reload.
+pub async fn run(servers: Arc<Servers>, settings: SharedSettings) {
+    let mut after = Utc::now();
+    loop {
+        let backup = settings.read().unwrap().backup.clone();
+        let next = backup
+            .as_ref()
+            .and_then(|backup| parse_schedule(&backup.schedule).ok())
+            .and_then(|schedule| schedule.after(&after).next());
+        let (Some(backup), Some(next)) = (backup, next) else {
+            after = Utc::now();
+            tokio::time::sleep(RECHECK_INTERVAL).await;
+            continue;
+        };
+
+        let now = Utc::now();
+        if next > now {
+            let wait = (next - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait.min(RECHECK_INTERVAL)).await;
+            continue;
+        }
+        after = now;
+        if let Err(e) = run_once(&servers, &backup, now).await {
+            warn!("Scheduled backup failed: {}", e);
+        }
+    }
+}
+
+/// Backs up every server to the configured destination and prunes its old backups.
+pub async fn run_once(servers: &Servers, settings: &BackupSettings, now: DateTime<Utc>) -> Result<()> {
+    let target = Target::from_settings(settings)?;
+    for (name, server) in servers.iter() {
+        if let Err(e) = backup_server(&target, name, server, settings, now).await {
+            warn!("Scheduled backup of server {} failed: {}", name, e);
+        }
+    }
+    Ok(())
+}
+
+async fn backup_server(
+    target: &Target,
+    name: &str,
+    server: &dyn NoteService,
+    settings: &BackupSettings,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let mut data = Vec::new();
+    let summary = super::backup(server, &mut data).await?;
+    let file = format!("{}-{}.jsonl", name, now.format(TIMESTAMP_FORMAT));
+    target.put(&file, data).await?;
+    info!("Backed up {} memos and {} comments of server {} to {}", summary.memos, summary.comments, name, file);
+
+    for file in expired(&target.list().await?, name, settings.keep, settings.max_age_days, now) {
+        target.delete(&file).await?;
+        info!("Deleted expired backup {}", file);
+    }
+    Ok(())
+}
+
+/// The backups of `server` among `files` beyond the newest `keep` or older than `max_age_days`.
+/// Files not named like a backup of `server` are left alone.
+fn expired(files: &[String], server: &str, keep: Option<usize>, max_age_days: Option<u32>, now: DateTime<Utc>) -> Vec<String> {
+    let mut backups = files
+        .iter()
+        .filter_map(|file| {
+            let timestamp = file.strip_prefix(server)?.strip_prefix('-')?.strip_suffix(".jsonl")?;
+            let time = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?.and_utc();
+            Some((time, file.clone()))
+        })
+        .collect::<Vec<_>>();
+    backups.sort();
+
+    let excess = keep.map_or(0, |keep| backups.len().saturating_sub(keep));
+    backups
+        .into_iter()
+        .enumerate()
+        .filter(|(index, (time, _))| {
+            *index < excess || max_age_days.is_some_and(|days| now - *time > chrono::Duration::days(days.into()))
+        })
+        .map(|(_, (_, file))| file)
+        .collect()
+}
+
+/// Where backups are kept.
+enum Target {
+    Dir(PathBuf),
+    #[cfg(feature = "s3")]
+    Bucket {
+        store: Arc<dyn object_store::ObjectStore>,
+        prefix: Option<object_store::path::Path>,
+    },
+}
+
+impl Target {
+    fn from_settings(settings: &BackupSettings) -> Result<Self> {
+        #[cfg(feature = "s3")]
+        if let Some(s3) = &settings.s3 {
+            let mut builder = object_store::aws::AmazonS3Builder::from_env().with_bucket_name(&s3.bucket);
+            if let Some(region) = &s3.region {
+                builder = builder.with_region(region);
+            }
+            if let Some(endpoint) = &s3.endpoint {
+                builder = builder
+                    .with_endpoint(endpoint)
+                    .with_allow_http(endpoint.starts_with("http://"))
+                    .with_virtual_hosted_style_request(false);
+            }
+            return Ok(Target::Bucket {
+                store: Arc::new(builder.build()?),
+                prefix: s3.prefix.as_deref().map(object_store::path::Path::from),
+            });
+        }
+        match &settings.dir {
+            Some(dir) => Ok(Target::Dir(dir.clone())),
+            None => Err(anyhow::anyhow!("No backup destination is configured")),
+        }
+    }
+
+    #[cfg(feature = "s3")]
+    fn object(prefix: &Option<object_store::path::Path>, file: &str) -> object_store::path::Path {
+        match prefix {
+            Some(prefix) => prefix.child(file),
+            None => object_store::path::Path::from(file),
+        }
+    }
+
+    async fn put(&self, file: &str, data: Vec<u8>) -> Result<()> {
+        match self {
+            Target::Dir(dir) => {
+                std::fs::create_dir_all(dir)?;
+                let partial = dir.join(format!("{}.partial", file));
+                std::fs::write(&partial, data)?;
+                std::fs::rename(&partial, dir.join(file))?;
+            }
+            #[cfg(feature = "s3")]
+            Target::Bucket { store, prefix } => {
+                store.put(&Self::object(prefix, file), data.into()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of the files kept, in no particular order.
+    async fn list(&self) -> Result<Vec<String>> {
+        match self {
+            Target::Dir(dir) => {
+                let mut files = Vec::new();
+                for entry in std::fs::read_dir(dir)? {
+                    files.push(entry?.file_name().to_string_lossy().into_owned());
+                }
+                Ok(files)
+            }
+            #[cfg(feature = "s3")]
+            Target::Bucket { store, prefix } => Ok(store
+                .list_with_delimiter(prefix.as_ref())
+                .await?
+                .objects
+                .into_iter()
+                .filter_map(|object| object.location.filename().map(str::to_string))
+                .collect()),
+        }
+    }
+
+    async fn delete(&self, file: &str) -> Result<()> {
+        match self {
+            Target::Dir(dir) => std::fs::remove_file(dir.join(file))?,
+            #[cfg(feature = "s3")]
+            Target::Bucket { store, prefix } => store.delete(&Self::object(prefix, file)).await?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[test]
+    fn test_parse_schedule() {
+        let after = "2025-01-31T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let next = |expr: &str| parse_schedule(expr).unwrap().after(&after).next().unwrap().to_rfc3339();
+        assert_eq!(next("0 3 * * *"), "2025-02-01T03:00:00+00:00");
+        assert_eq!(next("30 0 9 * * *"), "2025-01-31T09:00:30+00:00");
+        assert!(parse_schedule("every day").is_err());
+    }
+
+    #[test]
+    fn test_expired() {
+        let now = "2025-01-31T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let files = [
+            "work-20250130T030000Z.jsonl",
+            "work-20250131T030000Z.jsonl",
+            "work-20250120T030000Z.jsonl",
+            "work-old-20250101T030000Z.jsonl",
+            "notes.txt",
+        ].map(str::to_string);
+
+        assert!(expired(&files, "work", None, None, now).is_empty());
+        assert_eq!(expired(&files, "work", Some(2), None, now), ["work-20250120T030000Z.jsonl"]);
+        assert_eq!(expired(&files, "work", Some(1), None, now), ["work-20250120T030000Z.jsonl", "work-20250130T030000Z.jsonl"]);
+        assert_eq!(expired(&files, "work", None, Some(7), now), ["work-20250120T030000Z.jsonl"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_once() {
+        let (mock, server) = mock::start().await;
+        mock::mount_memos_api(&mock).await;
+        let servers = Servers::new("default", BTreeMap::from([("default".to_string(), server)]));
+        let dir = std::env::temp_dir().join(format!("mcp-memos-backups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("default-20250101T030000Z.jsonl"), "").unwrap();
+        let settings = BackupSettings {
+            schedule: "0 3 * * *".to_string(),
+            dir: Some(dir.clone()),
+            s3: None,
+            keep: Some(1),
+            max_age_days: None,
+        };
+
+        let now = "2025-01-31T03:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        run_once(&servers, &settings, now).await.unwrap();
+
+        let files = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect::<Vec<_>>();
+        assert_eq!(files, ["default-20250131T030000Z.jsonl"]);
+        let backup = std::fs::read_to_string(dir.join("default-20250131T030000Z.jsonl")).unwrap();
+        assert_eq!(backup.lines().count(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Pages saved as bookmark memos by `bookmark_url`. The title and description come from the Open
+// Graph and HTML metadata of the page; its readable text is the paragraphs of the `<article>` or
+// `<main>` element, or of the whole page, without scripts, styles and navigation. Pages on loopback,
+// link-local and private addresses are refused, checked after DNS resolution and on every redirect,
+// unless `bookmark_private_urls` is set; `check_memo_links` requests links through the same guard.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::Result;
+use regex::Regex;
+use reqwest::{Client, Method, Response, Url, redirect};
+
+/// Tag of bookmark memos.
+pub const BOOKMARK_TAG: &str = "bookmark";
+
+/// Longest wait for the page.
+const TIMEOUT: Duration = Duration::from_secs(15);
+/// Most redirects followed to a page.
+const MAX_REDIRECTS: usize = 5;
+/// Largest page read, in bytes.
+const MAX_PAGE_BYTES: usize = 5 * 1024 * 1024;
+/// Longest summary taken from the text when the page has no description, in characters.
+const MAX_SUMMARY_CHARS: usize = 300;
+
+static TITLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+static META: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<meta\s[^>]*>").unwrap());
+static ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?is)([a-z:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+static NOISE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<(script|style|noscript|nav|header|footer|aside|form)\b.*?</(?:script|style|noscript|nav|header|footer|aside|form)>").unwrap()
+});
+static ARTICLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<(article|main)\b[^>]*>(.*?)</(?:article|main)>").unwrap());
+static PARAGRAPH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<(p|h[1-6]|li|pre|blockquote)\b[^>]*>(.*?)</(?:p|h[1-6]|li|pre|blockquote)>").unwrap());
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub url: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// The readable text, one paragraph per line.
+    pub text: String,
+}
+
+impl Page {
+    /// Fetches and reads the HTML page at `url`, also on private addresses when `private_addresses`.
+    pub async fn fetch(url: &str, private_addresses: bool) -> Result<Page> {
+        let mut rsp = send(Method::GET, url, private_addresses, TIMEOUT).await?.error_for_status()?;
+        let html_type = rsp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_none_or(|value| value.contains("html"));
+        if !html_type {
+            return Err(anyhow::anyhow!("{} is not an HTML page", url));
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = rsp.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_PAGE_BYTES {
+                body.truncate(MAX_PAGE_BYTES);
+                break;
+            }
+        }
+        Ok(Page::parse(url, &String::from_utf8_lossy(&body)))
+    }
+
+    pub fn parse(url: &str, html: &str) -> Page {
+        let mut og_title = None;
+        let mut description = None;
+        let mut og_description = None;
+        for meta in META.find_iter(html) {
+            let mut key = None;
+            let mut content = None;
+            for attribute in ATTRIBUTE.captures_iter(meta.as_str()) {
+                let value = attribute.get(2).or(attribute.get(3)).map_or("", |value| value.as_str());
+                match attribute[1].to_ascii_lowercase().as_str() {
+                    "name" | "property" => key = Some(value.to_ascii_lowercase()),
+                    "content" => content = Some(text(value)),
+                    _ => {}
+                }
+            }
+            let content = content.filter(|content| !content.is_empty());
+            match key.as_deref() {
+                Some("og:title") => og_title = og_title.or(content),
+                Some("og:description") => og_description = og_description.or(content),
+                Some("description") => description = description.or(content),
+                _ => {}
+            }
+        }
+        let title = og_title
+            .or_else(|| TITLE.captures(html).map(|title| text(&title[1])).filter(|title| !title.is_empty()))
+            .unwrap_or_else(|| url.to_string());
+
+        let html = NOISE.replace_all(html, "");
+        let body = ARTICLE.captures(&html).map_or(html.to_string(), |article| article[2].to_string());
+        let text = PARAGRAPH
+            .captures_iter(&body)
+            .map(|paragraph| text(&paragraph[2]))
+            .filter(|paragraph| !paragraph.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Page { url: url.to_string(), title, description: og_description.or(description), text }
+    }
+
+    /// The description of the page, else the start of its text.
+    pub fn summary(&self) -> Option<String> {
+        if let Some(description) = &self.description {
+            return Some(description.clone());
+        }
+        let first = self.text.lines().find(|line| line.split_whitespace().count() > 5)?;
+        match first.char_indices().nth(MAX_SUMMARY_CHARS) {
+            Some((end, _)) => Some(format!("{}…", first[..end].trim_end())),
+            None => Some(first.to_string()),
+        }
+    }
+
+    /// The content of the bookmark memo: the link, the summary and the `#bookmark` tag.
+    pub fn memo_content(&self) -> String {
+        let title = self.title.replace('[', "\\[").replace(']', "\\]");
+        let mut content = format!("[{}]({})\n\n", title, self.url);
+        if let Some(summary) = self.summary() {
+            content.push_str(&format!("> {}\n\n", summary));
+        }
+        content.push_str(&format!("#{}", BOOKMARK_TAG));
+        content
+    }
+}
+
+/// Sends a `method` request to the http(s) `url`, also on private addresses when `private_addresses`,
+/// and follows its redirects, checking each address like the first. The final response is returned
+/// whatever its status.
+pub(crate) async fn send(method: Method, url: &str, private_addresses: bool, timeout: Duration) -> Result<Response> {
+    let mut next = Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid URL {}: {}", url, e))?;
+    let mut redirects = 0;
+    loop {
+        if !matches!(next.scheme(), "http" | "https") {
+            return Err(anyhow::anyhow!("Only http and https URLs can be requested, not {}", next));
+        }
+        let rsp = client(&next, private_addresses, timeout).await?.request(method.clone(), next.clone()).send().await?;
+        if !rsp.status().is_redirection() {
+            return Ok(rsp);
+        }
+        let location = rsp
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| anyhow::anyhow!("{} redirects without a location", next))?;
+        next = next.join(location).map_err(|e| anyhow::anyhow!("{} redirects to an invalid URL: {}", next, e))?;
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(anyhow::anyhow!("{} redirects more than {} times", url, MAX_REDIRECTS));
+        }
+    }
+}
+
+/// A client connecting to the host of `url` only at the addresses checked here, so that a second DNS
+/// lookup cannot point it elsewhere.
+async fn client(url: &Url, private_addresses: bool, timeout: Duration) -> Result<Client> {
+    let host = url.host_str().ok_or_else(|| anyhow::anyhow!("{} has no host", url))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addresses = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port)).await?.collect(),
+    };
+    if addresses.is_empty() {
+        return Err(anyhow::anyhow!("{} does not resolve to an address", host));
+    }
+    if !private_addresses && let Some(address) = addresses.iter().find(|address| !is_public(address.ip())) {
+        return Err(anyhow::anyhow!(
+            "{} is at the private address {}, set bookmark_private_urls to request such URLs",
+            host,
+            address.ip(),
+        ));
+    }
+    Ok(Client::builder()
+        .timeout(timeout)
+        .redirect(redirect::Policy::none())
+        .resolve_to_addrs(host, &addresses)
+        .build()?)
+}
+
+/// Whether `ip` is reachable on the internet, not loopback, link-local or private.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let unique_local = (ip.segments()[0] & 0xfe00) == 0xfc00;
+                let link_local = (ip.segments()[0] & 0xffc0) == 0xfe80;
+                !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+            }
+        },
+    }
+}
+
+/// The text of an HTML fragment, without tags and with whitespace collapsed.
+fn text(html: &str) -> String {
+    let text = TAG.replace_all(html, " ");
+    decode_entities(&text).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some((end, _)) = rest.char_indices().take(12).find(|&(_, c)| c == ';') else {
+            decoded.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"<!doctype html><html><head>
+        <title>Ignored &amp; replaced</title>
+        <meta property="og:title" content="Growing Tomatoes">
+        <meta name='description' content='How to grow tomatoes on a balcony.'>
+        <script>var p = "<p>not text</p>";</script>
+        </head><body>
+        <nav><p>Home</p></nav>
+        <article><h1>Growing <em>Tomatoes</em></h1><p>Tomatoes need sun &amp; water &#8212; daily.</p></article>
+        <footer><p>© 2025</p></footer>
+        </body></html>"#;
+
+    #[test]
+    fn test_parse() {
+        let page = Page::parse("https://example.com/tomatoes", HTML);
+        assert_eq!(page.title, "Growing Tomatoes");
+        assert_eq!(page.description.as_deref(), Some("How to grow tomatoes on a balcony."));
+        assert_eq!(page.text, "Growing Tomatoes\nTomatoes need sun & water — daily.");
+        assert_eq!(
+            page.memo_content(),
+            "[Growing Tomatoes](https://example.com/tomatoes)\n\n> How to grow tomatoes on a balcony.\n\n#bookmark",
+        );
+
+        let bare = Page::parse("https://example.com/", "<p>Just one short line</p><p>A first paragraph long enough to summarize.</p>");
+        assert_eq!(bare.title, "https://example.com/");
+        assert_eq!(bare.summary().as_deref(), Some("A first paragraph long enough to summarize."));
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("AT&T日本語のページ &amp; more"), "AT&T日本語のページ & more");
+        assert_eq!(decode_entities("Tom &amp Jerry &#x41;&unknown;"), "Tom &amp Jerry A&unknown;");
+    }
+
+    #[test]
+    fn test_is_public() {
+        for ip in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_refuses_private_addresses() {
+        for url in ["http://127.0.0.1:9/", "http://localhost:9/", "http://[::1]:9/"] {
+            let error = Page::fetch(url, false).await.unwrap_err().to_string();
+            assert!(error.contains("private address"), "{}: {}", url, error);
+        }
+    }
+}
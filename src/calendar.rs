@@ -0,0 +1,293 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Reminders found in memos, listed by the `list_upcoming_memos` tool and served as an iCalendar
+// feed at `/calendar.ics` for calendar apps to subscribe to. A memo is due on the dates of its
+// `@due(...)` markers, else on the ISO dates in its text, else on a display time set after it was
+// created. Times without a time zone are read in the time zone of the user.
+
+use std::fmt::Write;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::config::SharedSettings;
+use crate::dates;
+use crate::memos::{Servers, service::note::Note};
+
+static DUE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@due\(([^)]*)\)").unwrap());
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2})?(?:Z|[+-]\d{2}:\d{2})?)?\b").unwrap()
+});
+
+/// When a memo is due: a whole day, or a moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(untagged)]
+pub enum Due {
+    Date(NaiveDate),
+    Time(DateTime<Utc>),
+}
+
+impl Due {
+    fn parse(text: &str, tz: Tz) -> Option<Self> {
+        let text = text.trim();
+        if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+            return Some(Due::Time(time.with_timezone(&Utc)));
+        }
+        for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+                return Some(Due::Time(dates::local(time, tz)));
+            }
+        }
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().map(Due::Date)
+    }
+
+    /// The start of the day in `tz` or the moment, to order and filter reminders by.
+    fn start(&self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            Due::Date(date) => dates::start_of_day(*date, tz),
+            Due::Time(time) => *time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reminder {
+    pub due: Due,
+    pub memo: String,
+    pub title: String,
+    #[serde(skip)]
+    pub content: String,
+}
+
+/// The reminders of a memo, in the order they appear, with times without a zone read in `tz`.
+pub fn reminders(note: &Note, tz: Tz) -> Vec<Reminder> {
+    let Some(memo) = note.name.clone() else {
+        return Vec::new();
+    };
+    let mut dues = DUE
+        .captures_iter(&note.content)
+        .filter_map(|captures| Due::parse(&captures[1], tz))
+        .collect::<Vec<_>>();
+    if dues.is_empty() {
+        dues = DATE.find_iter(&note.content).filter_map(|date| Due::parse(date.as_str(), tz)).collect();
+    }
+    if dues.is_empty()
+        && let (Some(display), Some(created)) = (note.display_time, note.create_time)
+        && display > created
+    {
+        dues.push(Due::Time(display));
+    }
+    dues.dedup();
+
+    let title = DUE.replace_all(&crate::vault::title(note), "").split_whitespace().collect::<Vec<_>>().join(" ");
+    dues.into_iter()
+        .map(|due| Reminder { due, memo: memo.clone(), title: title.clone(), content: note.content.clone() })
+        .collect()
+}
+
+/// The reminders due from the start of today in `tz` until `days` days after `now`, soonest first.
+/// A window reaching past the last representable date runs until then.
+pub fn upcoming(notes: &[Note], now: DateTime<Utc>, days: u32, tz: Tz) -> Vec<Reminder> {
+    let from = dates::start_of_day(dates::today(now, tz), tz);
+    let until = chrono::Duration::try_days(days.into())
+        .and_then(|delta| now.checked_add_signed(delta))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC);
+    let mut reminders = notes
+        .iter()
+        .flat_map(|note| reminders(note, tz))
+        .filter(|reminder| (from..=until).contains(&reminder.due.start(tz)))
+        .collect::<Vec<_>>();
+    reminders.sort_by(|a, b| a.due.start(tz).cmp(&b.due.start(tz)).then_with(|| a.memo.cmp(&b.memo)));
+    reminders
+}
+
+/// The reminders of every memo as an iCalendar. Whole days stay floating dates, which calendar apps
+/// show on that day wherever they are.
+pub fn render(notes: &[Note], now: DateTime<Utc>, tz: Tz) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//mcp-memo//reminders//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for reminder in notes.iter().flat_map(|note| reminders(note, tz)) {
+        let id = reminder.memo.trim_start_matches("memos/");
+        let start = match reminder.due {
+            Due::Date(date) => format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+            Due::Time(time) => format!("DTSTART:{}", time.format("%Y%m%dT%H%M%SZ")),
+        };
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}-{}@mcp-memo", id, reminder.due.start(tz).format("%Y%m%dT%H%M%SZ")),
+            format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")),
+            start,
+            format!("SUMMARY:{}", escape(&reminder.title)),
+            format!("DESCRIPTION:{}", escape(&reminder.content)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in lines {
+        let _ = write!(ics, "{}\r\n", fold(&line));
+    }
+    ics
+}
+
+/// Escapes a text value per RFC 5545.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line into lines of at most 75 octets, continued with a leading space.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+/// The calendar of the default server served at `/calendar.ics?token=...`. The token keeps the
+/// reminders, which come from private memos as well, to those given the URL.
+pub struct Calendar {
+    servers: Arc<Servers>,
+    settings: SharedSettings,
+    token: String,
+    ttl: Duration,
+    cache: Mutex<Option<(Instant, String)>>,
+}
+
+#[derive(Deserialize)]
+pub struct CalendarQuery {
+    #[serde(default)]
+    token: String,
+}
+
+impl Calendar {
+    pub fn new(servers: Arc<Servers>, settings: SharedSettings, token: &str, ttl: Duration) -> Self {
+        Calendar {
+            servers,
+            settings,
+            token: token.to_string(),
+            ttl,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Makes the next request render the calendar again.
+    pub async fn invalidate(&self) {
+        *self.cache.lock().await = None;
+    }
+
+    /// The calendar as iCalendar text, rendered again when the cached one is older than the TTL.
+    pub async fn ics(&self) -> Result<String> {
+        let mut cache = self.cache.lock().await;
+        if let Some((rendered, ics)) = cache.as_ref()
+            && rendered.elapsed() < self.ttl
+        {
+            return Ok(ics.clone());
+        }
+
+        let notes = self.servers.get(None)?.list_notes().await?;
+        let tz = self.settings.read().unwrap().timezone();
+        let ics = render(&notes, Utc::now(), tz);
+        *cache = Some((Instant::now(), ics.clone()));
+        Ok(ics)
+    }
+}
+
+/// Handler of `GET /calendar.ics`.
+pub async fn serve(State(calendar): State<Arc<Calendar>>, Query(query): Query<CalendarQuery>) -> Response {
+    if query.token != calendar.token {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match calendar.ics().await {
+        Ok(ics) => ([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], ics).into_response(),
+        Err(e) => {
+            warn!("Failed to render the calendar: {}", e);
+            (StatusCode::BAD_GATEWAY, "The calendar is unavailable").into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    fn note(name: &str, content: &str) -> Note {
+        serde_json::from_value(mock::memo(name, content)).unwrap()
+    }
+
+    #[test]
+    fn test_reminders() {
+        let now = "2025-01-31T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut later = mock::memo("memos/4", "Dentist");
+        later["displayTime"] = json!("2025-02-03T09:00:00Z");
+        let notes = [
+            note("memos/1", "Pay rent @due(2025-02-01) and taxes @due(2025-04-15 12:00)"),
+            note("memos/2", "Met Ann on 2025-01-20, next call 2025-02-02"),
+            note("memos/3", "Nothing to do"),
+            serde_json::from_value(later).unwrap(),
+        ];
+
+        assert_eq!(reminders(&notes[0], Tz::UTC).len(), 2);
+        assert_eq!(reminders(&notes[0], Tz::UTC)[1].due, Due::Time("2025-04-15T12:00:00Z".parse().unwrap()));
+        assert!(reminders(&notes[2], Tz::UTC).is_empty());
+
+        let upcoming = upcoming(&notes, now, 7, Tz::UTC);
+        let memos = upcoming.iter().map(|reminder| reminder.memo.as_str()).collect::<Vec<_>>();
+        assert_eq!(memos, ["memos/1", "memos/2", "memos/4"]);
+        assert_eq!(super::upcoming(&notes, now, u32::MAX, Tz::UTC).len(), 4);
+        assert_eq!(json!(upcoming[0]), json!({"due": "2025-02-01", "memo": "memos/1", "title": "Pay rent and taxes"}));
+
+        // Past midnight in Berlin it is already February there, and times are read on Berlin clocks.
+        let berlin = "Europe/Berlin".parse::<Tz>().unwrap();
+        let notes = [note("memos/5", "Call @due(2025-01-31)"), note("memos/6", "Standup @due(2025-02-01 09:30)")];
+        let due = super::upcoming(&notes, "2025-01-31T23:30:00Z".parse().unwrap(), 7, berlin);
+        assert_eq!(due.iter().map(|reminder| reminder.due).collect::<Vec<_>>(), [Due::Time("2025-02-01T08:30:00Z".parse().unwrap())]);
+    }
+
+    #[test]
+    fn test_render() {
+        let now = "2025-01-31T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let content = format!("Pay rent, water; gas @due(2025-02-01)\n{}", "x".repeat(100));
+        let ics = render(&[note("memos/1", &content)], now, Tz::UTC);
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:1-20250201T000000Z@mcp-memo\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20250201\r\n"));
+        assert!(ics.contains("SUMMARY:Pay rent\\, water\\; gas\r\n"));
+        assert!(ics.lines().all(|line| line.len() <= 75));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+}
@@ -0,0 +1,221 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::backup::Conflict;
+
+#[derive(Parser, Debug)]
+#[command(name = "mcp-memo", version, about = "MCP server bridging AI assistants to a Memos instance.")]
+pub struct Cli {
+    #[command(flatten)]
+    pub memos: MemosArgs,
+
+    #[arg(long, env = "LOG_LEVEL", default_value = "info", global = true, help = "Log level or tracing filter directive. RUST_LOG takes precedence.")]
+    pub log_level: String,
+
+    #[arg(long, value_enum, env = "LOG_FORMAT", default_value = "text", global = true, help = "Log output format. `json` emits one object per line for log shippers.")]
+    pub log_format: LogFormat,
+
+    /// Options of `serve`, also taken without the subcommand as serving is the default.
+    #[command(flatten, next_help_heading = "Serve options")]
+    pub serve: ServeArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct MemosArgs {
+    #[arg(long, env = "MCP_MEMO_CONFIG", global = true, help = "TOML config file declaring named Memos servers. Overrides --host and --token.")]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, env = "MEMOS_SERVER", global = true, help = "Name of the configured server used when a tool does not select one.")]
+    pub server: Option<String>,
+
+    #[arg(long, env = "MEMOS_HOST", global = true, help = "Host (and port) of the Memos server, e.g. `localhost:5230`, or its URL, e.g. `https://example.com/memos`.")]
+    pub host: Option<String>,
+
+    #[arg(long, env = "MEMOS_TOKEN", hide_env_values = true, global = true, help = "Personal access token used to authenticate to Memos.")]
+    pub token: Option<String>,
+
+    #[arg(long, env = "MEMOS_TOKEN_FILE", global = true, help = "File containing the personal access token, e.g. a mounted secret. Takes precedence over --token and is reloaded when it changes.")]
+    pub token_file: Option<PathBuf>,
+
+    #[arg(long, env = "MEMOS_MAX_PAGES", global = true, help = "Most pages fetched by one listing before it fails, in case the server keeps returning page tokens. Defaults to 10000.")]
+    pub max_pages: Option<usize>,
+
+    #[arg(long, env = "MEMOS_MAX_ITEMS", global = true, help = "Most items fetched by one listing before it fails. Defaults to 1000000.")]
+    pub max_items: Option<usize>,
+
+    #[arg(long, env = "MEMOS_ENCRYPTION_KEY_FILE", global = true, help = "File containing a base64 encoded 32-byte key. Memo content is encrypted with it before upload and decrypted on read. Needs the `encryption` feature.")]
+    pub encryption_key_file: Option<PathBuf>,
+
+    #[arg(long, env = "MEMOS_ROTATE_TOKEN_DAYS", global = true, help = "Replace the token in --token-file with a new one this many days before it expires, and revoke the old one once it is known to be this server's.")]
+    pub rotate_token_days: Option<u32>,
+
+    #[arg(long, env = "READ_ONLY", global = true, default_value_t = false, help = "Only register read-only tools and reject every write.")]
+    pub read_only: bool,
+
+    #[arg(long, env = "DRY_RUN", global = true, default_value_t = false, help = "Make write tools report the requests they would send instead of sending them.")]
+    pub dry_run: bool,
+
+    #[arg(long, env = "AUDIT_LOG", global = true, help = "Append a JSON line to this file for every write made through MCP.")]
+    pub audit_log: Option<PathBuf>,
+
+    #[arg(long, env = "EXPORT_DIR", global = true, help = "Directory the export and import tools work in. They are unavailable when unset.")]
+    pub export_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Serve the MCP endpoint (default).
+    Serve,
+    /// Validate connectivity and authentication to Memos, then exit.
+    Check,
+    /// Export all memos as JSON or as an Obsidian vault.
+    Export(ExportArgs),
+    /// Import a folder of Markdown files, e.g. an Obsidian vault, as memos.
+    Import(ImportArgs),
+    /// Back up every memo with its comments, relations and attachment metadata as JSON Lines.
+    Backup(BackupArgs),
+    /// Recreate the memos of a backup. Memos get new names; restore into an empty server.
+    Restore(RestoreArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    #[arg(long, global = true, value_enum, env = "MCP_TRANSPORT", default_value = "http", help = "Transport used to serve MCP.")]
+    pub transport: Transport,
+
+    #[arg(long, global = true, env = "MCP_BIND", default_value = "0.0.0.0:3000", help = "Address the HTTP transport listens on.")]
+    pub bind: SocketAddr,
+
+    #[arg(long, global = true, env = "MEMOS_STARTUP_MAX_WAIT", default_value_t = 60, help = "Seconds to keep retrying the initial connection to Memos.")]
+    pub startup_max_wait: u64,
+
+    #[arg(long, global = true, env = "MEMOS_START_DEGRADED", default_value_t = false, help = "Start serving even when Memos is unreachable. Tools fail until it is back, then work again.")]
+    pub start_degraded: bool,
+
+    #[arg(long, global = true, env = "MCP_FEED_URL", help = "Public URL of /feed.xml. Serves the PUBLIC memos of the default server there as an Atom feed when set.")]
+    pub feed_url: Option<String>,
+
+    #[arg(long, global = true, env = "MCP_FEED_TITLE", default_value = "Memos", help = "Title and author of the Atom feed.")]
+    pub feed_title: String,
+
+    #[arg(long, global = true, env = "MCP_FEED_TTL", default_value_t = 300, help = "Seconds the rendered Atom feed and calendar are cached for.")]
+    pub feed_ttl: u64,
+
+    #[arg(long, global = true, env = "MCP_CALENDAR_TOKEN", hide_env_values = true, help = "Serves the reminders in the memos of the default server as an iCalendar at /calendar.ics?token=<this> when set.")]
+    pub calendar_token: Option<String>,
+
+    #[arg(long, global = true, env = "MCP_WEBHOOK_SECRET", hide_env_values = true, help = "Accept Memos webhooks at /webhooks/memos/<server>?secret=<this> when set. Each memo change invalidates the caches and notifies the MCP sessions subscribed to the memo.")]
+    pub webhook_secret: Option<String>,
+
+    #[arg(long, global = true, env = "MEMOS_MIRROR_DB", help = "SQLite file mirroring all memos locally. List, search and stats tools are served from it once synchronized.")]
+    pub mirror_db: Option<PathBuf>,
+
+    #[arg(long, global = true, env = "MEMOS_MIRROR_INTERVAL", default_value_t = 300, help = "Seconds between delta synchronizations of the local mirror.")]
+    pub mirror_interval: u64,
+
+    #[arg(long, global = true, env = "MEMOS_TAG_REFRESH_INTERVAL", default_value_t = 600, help = "Seconds between background refreshes of the tag index, prefetched at startup. 0 disables prefetching.")]
+    pub tag_refresh_interval: u64,
+
+    #[arg(long, global = true, env = "MEMOS_INBOX_POLL_INTERVAL", default_value_t = 0, help = "Seconds between polls of the Memos inbox of each server. New notifications, such as comments on your memos, are sent to the MCP clients as log messages. 0 disables polling.")]
+    pub inbox_poll_interval: u64,
+
+    #[arg(long, global = true, value_enum, env = "EMBEDDINGS_PROVIDER", help = "Embed mirrored memos for semantic search. Requires --mirror-db.")]
+    pub embeddings: Option<EmbeddingProvider>,
+
+    #[arg(long, global = true, env = "EMBEDDINGS_URL", default_value = "https://api.openai.com/v1", help = "Base URL of the OpenAI-compatible embeddings API.")]
+    pub embeddings_url: String,
+
+    #[arg(long, global = true, env = "EMBEDDINGS_MODEL", default_value = "text-embedding-3-small", help = "Embedding model requested from the OpenAI-compatible API.")]
+    pub embeddings_model: String,
+
+    #[arg(long, global = true, env = "EMBEDDINGS_API_KEY", hide_env_values = true, help = "API key sent to the OpenAI-compatible embeddings API.")]
+    pub embeddings_api_key: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    #[arg(long, value_enum, default_value = "json", help = "Format of the export.")]
+    pub format: ExportFormat,
+
+    #[arg(long, required_if_eq("format", "obsidian"), help = "File to write the export to, or the vault directory for `obsidian`. Defaults to stdout.")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    #[arg(long = "in", help = "Folder of Markdown files to import. Files imported before are skipped.")]
+    pub dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct BackupArgs {
+    #[arg(long, help = "JSON Lines file to write the backup to. Replaced only once the backup is complete.")]
+    pub out: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    #[arg(long = "in", help = "JSON Lines backup to restore.")]
+    pub input: PathBuf,
+
+    #[arg(long, value_enum, default_value = "duplicate", help = "What to do with a memo of the backup when the server has a memo of the same name.")]
+    pub conflict: Conflict,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// All memos as one JSON array.
+    Json,
+    /// A Markdown file per memo with YAML front matter, wikilinks and downloaded attachments.
+    Obsidian,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transport {
+    Http,
+    Stdio,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmbeddingProvider {
+    /// An OpenAI-compatible `/embeddings` endpoint, e.g. OpenAI, Ollama or LocalAI.
+    Openai,
+    /// A bundled ONNX model, requires the `local-embeddings` feature.
+    Local,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_serve_args() {
+        Cli::command().debug_assert();
+        for args in [&["mcp-memo", "--transport", "stdio"][..], &["mcp-memo", "serve", "--transport", "stdio"]] {
+            let cli = Cli::try_parse_from(args).unwrap();
+            assert_eq!(cli.serve.transport, Transport::Stdio, "{:?}", args);
+            assert!(matches!(cli.command, None | Some(Command::Serve)));
+        }
+        let cli = Cli::try_parse_from(["mcp-memo", "--config", "memos.toml", "export", "--format", "json"]).unwrap();
+        assert_eq!(cli.memos.config, Some(PathBuf::from("memos.toml")));
+        assert!(matches!(cli.command, Some(Command::Export(ExportArgs { format: ExportFormat::Json, .. }))));
+    }
+}
@@ -0,0 +1,251 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Tools declared in the config file as `[[custom_tools]]`, each calling one Memos API endpoint, so
+// that endpoints without a tool of their own can be reached without changing the crate. The
+// arguments of a call fill the `{param}` placeholders of the endpoint and body of the declaration.
+// Write tools take `dry_run` and are audited like the built-in ones.
+
+use anyhow::Result;
+use reqwest::Method;
+use rmcp::model::{JsonObject, Tool, ToolAnnotations};
+use serde_json::{Value, json};
+
+use crate::config::CustomTool;
+
+const METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE"];
+
+const TYPES: &[&str] = &["string", "integer", "number", "boolean", "array", "object"];
+
+/// Parameters every custom tool has.
+const RESERVED: &[&str] = &["server", "dry_run"];
+
+pub fn method(tool: &CustomTool) -> Method {
+    Method::from_bytes(tool.method.to_ascii_uppercase().as_bytes()).unwrap_or(Method::GET)
+}
+
+pub fn is_read_only(tool: &CustomTool) -> bool {
+    tool.read_only.unwrap_or(method(tool) == Method::GET)
+}
+
+/// Checks a declaration, `earlier` being the tools declared before it.
+pub fn validate(tool: &CustomTool, earlier: &[CustomTool]) -> Result<(), String> {
+    if tool.name.is_empty() || !tool.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("name `{}` must be letters, digits, `_` and `-`", tool.name));
+    }
+    if earlier.iter().any(|other| other.name == tool.name) {
+        return Err(format!("name `{}` is used by another custom tool", tool.name));
+    }
+    if !METHODS.contains(&tool.method.to_ascii_uppercase().as_str()) {
+        return Err(format!("method `{}` is not one of {}", tool.method, METHODS.join(", ")));
+    }
+    if tool.endpoint.contains("://") || tool.endpoint.contains("..") {
+        return Err(format!("endpoint `{}` must be a path below /api/v1", tool.endpoint));
+    }
+    if tool.read_only == Some(true) && method(tool) != Method::GET {
+        return Err(format!("method `{}` writes, so `read_only` cannot be true", tool.method));
+    }
+    for (name, param) in &tool.params {
+        if RESERVED.contains(&name.as_str()) {
+            return Err(format!("parameter `{}` is reserved", name));
+        }
+        if !TYPES.contains(&param.kind.as_str()) {
+            return Err(format!("parameter `{}` has type `{}`, not one of {}", name, param.kind, TYPES.join(", ")));
+        }
+    }
+    let mut used = placeholders(&tool.endpoint);
+    if let Some(body) = &tool.body {
+        body_placeholders(body, &mut used);
+    }
+    match used.iter().find(|name| !tool.params.contains_key(**name)) {
+        Some(name) => Err(format!("placeholder `{{{}}}` names no parameter in `params`", name)),
+        None => Ok(()),
+    }
+}
+
+/// The MCP tool of a declaration.
+pub fn tool(tool: &CustomTool) -> Tool {
+    let mut properties = tool.params
+        .iter()
+        .map(|(name, param)| (name.clone(), json!({"type": param.kind, "description": param.description})))
+        .collect::<JsonObject>();
+    properties.insert(
+        "server".to_string(),
+        json!({"type": "string", "description": "The configured Memos server to use. Defaults to the default server, see `list_servers`."}),
+    );
+    if !is_read_only(tool) {
+        properties.insert(
+            "dry_run".to_string(),
+            json!({"type": "boolean", "description": "Report the request that would be sent to Memos without sending it."}),
+        );
+    }
+    let required = tool.params.iter().filter(|(_, param)| param.required).map(|(name, _)| name.clone()).collect::<Vec<_>>();
+    let schema = json!({"type": "object", "properties": properties, "required": required});
+    let Value::Object(schema) = schema else {
+        unreachable!("the schema is an object");
+    };
+    Tool::new(tool.name.clone(), tool.description.clone(), schema)
+        .annotate(ToolAnnotations::with_title(tool.name.clone()).read_only(is_read_only(tool)))
+}
+
+/// The endpoint of a call, its placeholders filled with `arguments`, each a single path segment.
+pub fn endpoint(tool: &CustomTool, arguments: &JsonObject) -> Result<String> {
+    let mut endpoint = tool.endpoint.clone();
+    for name in placeholders(&tool.endpoint) {
+        let value = argument(tool, arguments, name)?.map(text).unwrap_or_default();
+        if value.is_empty() || value.contains("..") || value.contains(['/', '%', '?', '#']) || value.contains(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Argument {} is not usable in endpoint {}: `{}`", name, tool.endpoint, value));
+        }
+        endpoint = endpoint.replace(&format!("{{{}}}", name), &value);
+    }
+    Ok(endpoint.trim_start_matches('/').to_string())
+}
+
+/// The body of a call, its placeholders filled with `arguments`.
+pub fn body(tool: &CustomTool, arguments: &JsonObject) -> Result<Option<Value>> {
+    tool.body.as_ref().map(|body| fill(tool, body, arguments)).transpose()
+}
+
+fn fill(tool: &CustomTool, value: &Value, arguments: &JsonObject) -> Result<Value> {
+    Ok(match value {
+        Value::String(template) => {
+            let names = placeholders(template);
+            if let [name] = names[..] && template == &format!("{{{}}}", name) {
+                return Ok(argument(tool, arguments, name)?.cloned().unwrap_or_default());
+            }
+            let mut filled = template.clone();
+            for name in names {
+                let value = argument(tool, arguments, name)?.map(text).unwrap_or_default();
+                filled = filled.replace(&format!("{{{}}}", name), &value);
+            }
+            Value::String(filled)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| fill(tool, item, arguments)).collect::<Result<_>>()?),
+        Value::Object(fields) => Value::Object(
+            fields.iter().map(|(key, field)| Ok((key.clone(), fill(tool, field, arguments)?))).collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// The argument `name`, which must be given when the parameter is required.
+fn argument<'a>(tool: &CustomTool, arguments: &'a JsonObject, name: &str) -> Result<Option<&'a Value>> {
+    let value = arguments.get(name).filter(|value| !value.is_null());
+    if value.is_none() && tool.params.get(name).is_none_or(|param| param.required) {
+        return Err(anyhow::anyhow!("Missing argument {}", name));
+    }
+    Ok(value)
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The names of the `{name}` placeholders of `template`.
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let name = &rest[..end];
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            names.push(name);
+            rest = &rest[end + 1..];
+        }
+    }
+    names
+}
+
+fn body_placeholders<'a>(value: &'a Value, names: &mut Vec<&'a str>) {
+    match value {
+        Value::String(template) => names.extend(placeholders(template)),
+        Value::Array(items) => items.iter().for_each(|item| body_placeholders(item, names)),
+        Value::Object(fields) => fields.values().for_each(|field| body_placeholders(field, names)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shortcut_tool() -> CustomTool {
+        toml::from_str(r#"
+            name = "create_shortcut"
+            description = "Create a saved filter."
+            method = "post"
+            endpoint = "users/{user}/shortcuts"
+            body = { title = "{title}", filter = "tag in [\"{tag}\"]", pinned = "{pinned}" }
+
+            [params.user]
+            description = "The user id, e.g. 1."
+            [params.title]
+            description = "Title of the shortcut."
+            [params.tag]
+            description = "Tag to filter on."
+            [params.pinned]
+            description = "Pin the shortcut."
+            type = "boolean"
+            required = false
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_custom_tool() {
+        let tool = shortcut_tool();
+        assert_eq!(validate(&tool, &[]), Ok(()));
+        assert!(validate(&tool, std::slice::from_ref(&tool)).unwrap_err().contains("another custom tool"));
+        assert!(!is_read_only(&tool));
+
+        let schema = super::tool(&tool).input_schema;
+        assert_eq!(schema["required"], json!(["tag", "title", "user"]));
+        assert_eq!(schema["properties"]["pinned"]["type"], "boolean");
+        assert!(schema["properties"].get("dry_run").is_some());
+
+        let Value::Object(arguments) = json!({"user": "1", "title": "Work", "tag": "work", "pinned": true}) else {
+            unreachable!();
+        };
+        assert_eq!(endpoint(&tool, &arguments).unwrap(), "users/1/shortcuts");
+        assert_eq!(
+            body(&tool, &arguments).unwrap(),
+            Some(json!({"title": "Work", "filter": "tag in [\"work\"]", "pinned": true})),
+        );
+
+        for user in ["..", "1/../../auth", "%2E%2E", "1?x=1"] {
+            let Value::Object(arguments) = json!({"user": user, "title": "Work", "tag": "work"}) else {
+                unreachable!();
+            };
+            assert!(endpoint(&tool, &arguments).is_err(), "{}", user);
+        }
+        let Value::Object(arguments) = json!({"user": "..", "title": "Work", "tag": "work"}) else {
+            unreachable!();
+        };
+        assert_eq!(body(&tool, &arguments).unwrap().unwrap()["pinned"], Value::Null);
+        let Value::Object(arguments) = json!({"user": "1", "tag": "work"}) else {
+            unreachable!();
+        };
+        assert_eq!(body(&tool, &arguments).unwrap_err().to_string(), "Missing argument title");
+    }
+
+    #[test]
+    fn test_validate_placeholders() {
+        let mut tool = shortcut_tool();
+        tool.endpoint = "{owner}/shortcuts".to_string();
+        assert!(validate(&tool, &[]).unwrap_err().contains("`{owner}`"));
+        assert_eq!(placeholders("memos/{id}?filter={ not a placeholder }&x={x}"), ["id", "x"]);
+
+        let mut tool = shortcut_tool();
+        tool.read_only = Some(true);
+        assert!(validate(&tool, &[]).unwrap_err().contains("`read_only`"));
+        tool.method = "GET".to_string();
+        assert_eq!(validate(&tool, &[]), Ok(()));
+    }
+}
@@ -0,0 +1,307 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Days and times in the time zone of the user, configured as `timezone`. Memos keeps every time in
+// UTC, but today, a date written in a memo and the timestamps a summary shows are the user's own:
+// a day starts at midnight on their clocks, not at midnight UTC. Daylight saving makes some local
+// times skipped and others repeated; a skipped time is read as the hour after it and a repeated one
+// as its first moment.
+//
+// The date parameters of the query tools take date expressions as well as RFC 3339 times, since
+// clients asking for "the memos of last week" otherwise have to work out the bounds themselves and
+// often get them wrong. [`span`] turns an expression into the stretch of time it names.
+
+use anyhow::Result;
+use chrono::{
+    DateTime, Datelike, Days, Month, Months, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+/// The date in `tz` at `now`.
+pub fn today(now: DateTime<Utc>, tz: Tz) -> NaiveDate {
+    now.with_timezone(&tz).date_naive()
+}
+
+/// The moment `time` shows on the clocks of `tz`.
+pub fn local(time: NaiveDateTime, tz: Tz) -> DateTime<Utc> {
+    tz.from_local_datetime(&time)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(time + chrono::Duration::hours(1))).earliest())
+        .map_or_else(|| time.and_utc(), |time| time.with_timezone(&Utc))
+}
+
+/// The first moment of `date` in `tz`.
+pub fn start_of_day(date: NaiveDate, tz: Tz) -> DateTime<Utc> {
+    local(date.and_time(NaiveTime::MIN), tz)
+}
+
+/// The moment `days` days before `now`, an error when it is out of range.
+pub fn days_before(now: DateTime<Utc>, days: u32) -> Result<DateTime<Utc>> {
+    chrono::Duration::try_days(days.into())
+        .and_then(|delta| now.checked_sub_signed(delta))
+        .ok_or_else(|| anyhow::anyhow!("{} days before now is out of range", days))
+}
+
+/// `time` in `tz` as RFC 3339 with its offset, `Z` in UTC like Memos writes it.
+pub fn format(time: DateTime<Utc>, tz: Tz) -> String {
+    time.with_timezone(&tz).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// A stretch of time named by a date expression, from `start` up to, not including, `end`. A moment
+/// starts and ends at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Span {
+    fn moment(time: DateTime<Utc>) -> Self {
+        Span { start: time, end: time }
+    }
+
+    /// The days from `first` up to `after`, in `tz`.
+    fn days(first: NaiveDate, after: NaiveDate, tz: Tz) -> Self {
+        Span { start: start_of_day(first, tz), end: start_of_day(after, tz) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Unit {
+    fn parse(word: &str) -> Option<Self> {
+        match word.strip_suffix('s').unwrap_or(word) {
+            "hour" => Some(Unit::Hour),
+            "day" => Some(Unit::Day),
+            "week" => Some(Unit::Week),
+            "month" => Some(Unit::Month),
+            "year" => Some(Unit::Year),
+            _ => None,
+        }
+    }
+
+    /// `date` moved by `count` of this unit. Hours do not move a date.
+    fn shift(self, date: NaiveDate, count: i64) -> Option<NaiveDate> {
+        let months = match self {
+            Unit::Hour => return None,
+            Unit::Day => return date.checked_add_signed(chrono::Duration::try_days(count)?),
+            Unit::Week => return date.checked_add_signed(chrono::Duration::try_weeks(count)?),
+            Unit::Month => count,
+            Unit::Year => count.checked_mul(12)?,
+        };
+        let by = Months::new(months.unsigned_abs().try_into().ok()?);
+        match months < 0 {
+            true => date.checked_sub_months(by),
+            false => date.checked_add_months(by),
+        }
+    }
+
+    /// The first day of the day, week, month or year `date` is in. Weeks start on Monday.
+    fn first(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Unit::Hour => None,
+            Unit::Day => Some(date),
+            Unit::Week => date.checked_sub_days(Days::new(date.weekday().num_days_from_monday().into())),
+            Unit::Month => date.with_day(1),
+            Unit::Year => date.with_ordinal(1),
+        }
+    }
+}
+
+/// What `text` names at `now`, with days in `tz`. It is one of
+///
+/// - an RFC 3339 time, or a time without a zone like `2025-06-01 14:30`
+/// - a date `2025-06-01`, month `2025-06` or year `2025`
+/// - `now`, `today`, `yesterday` or `tomorrow`
+/// - a weekday like `monday`, the latest one up to today, or `last monday` and `next monday`, the
+///   one before and after today
+/// - a month like `June`, the latest June that has begun, `last June`, the one before the current
+///   month, or `June 2024`
+/// - `this`, `last` or `next` `week`, `month` or `year`, with weeks starting on Monday
+/// - `past 7 days` or `last 3 weeks`, up to now
+/// - `2 days ago`, the moment that long before now
+///
+/// ignoring case.
+pub fn span(text: &str, now: DateTime<Utc>, tz: Tz) -> Result<Span> {
+    parse(text.trim(), now, tz).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot read {:?} as a time; use an RFC 3339 time like `2025-06-01T08:00:00Z`, a date like `2025-06-01`, \
+             or an expression like `today`, `last monday`, `past 7 days`, `last month` or `June`",
+            text,
+        )
+    })
+}
+
+/// The bounds of a query from its `since` and `until` expressions, each open when not given.
+pub type Bounds = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// The bounds from the start of `since` up to the end of `until`, so that `until: June` includes June.
+pub fn bounds(since: Option<&str>, until: Option<&str>, now: DateTime<Utc>, tz: Tz) -> Result<Bounds> {
+    let start = since.map(|since| span(since, now, tz)).transpose()?.map(|span| span.start);
+    let end = until.map(|until| span(until, now, tz)).transpose()?.map(|span| span.end);
+    if let (Some(start), Some(end)) = (start, end)
+        && start >= end
+    {
+        return Err(anyhow::anyhow!("`since` {} is not before `until` {}", start.to_rfc3339(), end.to_rfc3339()));
+    }
+    Ok((start, end))
+}
+
+fn parse(text: &str, now: DateTime<Utc>, tz: Tz) -> Option<Span> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(Span::moment(time.with_timezone(&Utc)));
+    }
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(Span::moment(local(time, tz)));
+        }
+    }
+
+    let today = today(now, tz);
+    let day = |date: NaiveDate| Some(Span::days(date, date.succ_opt()?, tz));
+    let month = |year: i32, month: Month| {
+        let first = NaiveDate::from_ymd_opt(year, month.number_from_month(), 1)?;
+        Some(Span::days(first, Unit::Month.shift(first, 1)?, tz))
+    };
+    let text = text.to_lowercase();
+    match text.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["now"] => Some(Span::moment(now)),
+        ["today"] => day(today),
+        ["yesterday"] => day(today.pred_opt()?),
+        ["tomorrow"] => day(today.succ_opt()?),
+        [which @ ("this" | "last" | "next"), unit] if Unit::parse(unit).is_some() => {
+            let unit = Unit::parse(unit)?;
+            let offset = match *which {
+                "last" => -1,
+                "next" => 1,
+                _ => 0,
+            };
+            let first = unit.shift(unit.first(today)?, offset)?;
+            Some(Span::days(first, unit.shift(first, 1)?, tz))
+        }
+        ["past" | "last", count, unit] => {
+            let count = count.parse::<u32>().ok()?;
+            Some(Span { start: ago(now, count, Unit::parse(unit)?, tz)?, end: now })
+        }
+        [count, unit, "ago"] => Some(Span::moment(ago(now, count.parse().ok()?, Unit::parse(unit)?, tz)?)),
+        [which @ ("last" | "next"), name] if name.parse::<Weekday>().is_ok() => {
+            let weekday = name.parse::<Weekday>().ok()?;
+            let days = match *which {
+                "last" => (today.weekday().days_since(weekday) + 6) % 7 + 1,
+                _ => (weekday.days_since(today.weekday()) + 6) % 7 + 1,
+            };
+            match *which {
+                "last" => day(today.checked_sub_days(Days::new(days.into()))?),
+                _ => day(today.checked_add_days(Days::new(days.into()))?),
+            }
+        }
+        [name] if name.parse::<Weekday>().is_ok() => {
+            day(today.checked_sub_days(Days::new(today.weekday().days_since(name.parse().ok()?).into()))?)
+        }
+        ["last", name] => {
+            let name = name.parse::<Month>().ok()?;
+            let year = today.year() - i32::from(name.number_from_month() >= today.month());
+            month(year, name)
+        }
+        [name, year] => month(year.parse().ok()?, name.parse().ok()?),
+        [name] if name.parse::<Month>().is_ok() => {
+            let name = name.parse::<Month>().ok()?;
+            let year = today.year() - i32::from(name.number_from_month() > today.month());
+            month(year, name)
+        }
+        [date] => {
+            if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                return day(date);
+            }
+            if let Ok(first) = NaiveDate::parse_from_str(&format!("{}-01", date), "%Y-%m-%d") {
+                return Some(Span::days(first, Unit::Month.shift(first, 1)?, tz));
+            }
+            let year = date.parse::<i32>().ok().filter(|_| date.len() == 4)?;
+            let first = NaiveDate::from_ymd_opt(year, 1, 1)?;
+            Some(Span::days(first, Unit::Year.shift(first, 1)?, tz))
+        }
+        _ => None,
+    }
+}
+
+/// The moment `count` of `unit` before `now`, counting days, months and years on the clocks of `tz`.
+fn ago(now: DateTime<Utc>, count: u32, unit: Unit, tz: Tz) -> Option<DateTime<Utc>> {
+    if unit == Unit::Hour {
+        return now.checked_sub_signed(chrono::Duration::try_hours(count.into())?);
+    }
+    let local_now = now.with_timezone(&tz).naive_local();
+    let date = unit.shift(local_now.date(), -i64::from(count))?;
+    Some(local(date.and_time(local_now.time()), tz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_days() {
+        let berlin = "Europe/Berlin".parse::<Tz>().unwrap();
+        let now = "2025-01-31T23:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(today(now, Tz::UTC), NaiveDate::from_ymd_opt(2025, 1, 31).unwrap());
+        assert_eq!(today(now, berlin), NaiveDate::from_ymd_opt(2025, 2, 1).unwrap());
+        assert_eq!(start_of_day(today(now, berlin), berlin), "2025-01-31T23:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(
+            local("2025-02-01T14:30:00".parse().unwrap(), "America/New_York".parse().unwrap()),
+            "2025-02-01T19:30:00Z".parse::<DateTime<Utc>>().unwrap(),
+        );
+        assert_eq!(format(now, Tz::UTC), "2025-01-31T23:30:00Z");
+        assert_eq!(format(now, berlin), "2025-02-01T00:30:00+01:00");
+        assert_eq!(days_before(now, 31).unwrap(), "2024-12-31T23:30:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert!(days_before(now, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_span() {
+        // A Wednesday.
+        let now = "2025-06-18T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let span = |text: &str| {
+            let span = span(text, now, Tz::UTC).unwrap();
+            (span.start.to_rfc3339(), span.end.to_rfc3339())
+        };
+        let days = |start: &str, end: &str| (format!("{}T00:00:00+00:00", start), format!("{}T00:00:00+00:00", end));
+        assert_eq!(span("today"), days("2025-06-18", "2025-06-19"));
+        assert_eq!(span(" Yesterday "), days("2025-06-17", "2025-06-18"));
+        assert_eq!(span("monday"), days("2025-06-16", "2025-06-17"));
+        assert_eq!(span("wednesday"), days("2025-06-18", "2025-06-19"));
+        assert_eq!(span("last Wednesday"), days("2025-06-11", "2025-06-12"));
+        assert_eq!(span("last monday"), days("2025-06-16", "2025-06-17"));
+        assert_eq!(span("next mon"), days("2025-06-23", "2025-06-24"));
+        assert_eq!(span("this week"), days("2025-06-16", "2025-06-23"));
+        assert_eq!(span("last month"), days("2025-05-01", "2025-06-01"));
+        assert_eq!(span("next year"), days("2026-01-01", "2027-01-01"));
+        assert_eq!(span("June"), days("2025-06-01", "2025-07-01"));
+        assert_eq!(span("july"), days("2024-07-01", "2024-08-01"));
+        assert_eq!(span("last june"), days("2024-06-01", "2024-07-01"));
+        assert_eq!(span("Feb 2024"), days("2024-02-01", "2024-03-01"));
+        assert_eq!(span("2025-06-01"), days("2025-06-01", "2025-06-02"));
+        assert_eq!(span("2024-12"), days("2024-12-01", "2025-01-01"));
+        assert_eq!(span("2024"), days("2024-01-01", "2025-01-01"));
+        assert_eq!(span("past 7 days"), ("2025-06-11T10:00:00+00:00".to_string(), now.to_rfc3339()));
+        assert_eq!(span("last 2 months"), ("2025-04-18T10:00:00+00:00".to_string(), now.to_rfc3339()));
+        assert_eq!(span("3 hours ago").0, "2025-06-18T07:00:00+00:00");
+        assert_eq!(span("2025-06-01T08:00:00+02:00").1, "2025-06-01T06:00:00+00:00");
+
+        let kolkata = "Asia/Kolkata".parse().unwrap();
+        let today = super::span("today", now, kolkata).unwrap();
+        assert_eq!((today.start.to_rfc3339(), today.end.to_rfc3339()), ("2025-06-17T18:30:00+00:00".to_string(), "2025-06-18T18:30:00+00:00".to_string()));
+        assert_eq!(super::span("2025-06-01 14:30", now, kolkata).unwrap().start.to_rfc3339(), "2025-06-01T09:00:00+00:00");
+
+        for text in ["someday", "past x days", "last fortnight", "june 20x5", "12345"] {
+            assert!(super::span(text, now, Tz::UTC).unwrap_err().to_string().starts_with("Cannot read"), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Line diffs of memo content in the unified format, for `diff_memos`. Lines are matched with the
+// Myers algorithm; texts too different for it to finish quickly are shown as wholly replaced.
+
+use std::fmt::Write;
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+/// Most edits searched for before giving up on matching lines.
+const MAX_EDITS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A unified diff of the lines of `old` and `new`, empty when they are the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub text: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+pub fn unified(old: &str, new: &str, old_label: &str, new_label: &str) -> Diff {
+    let a = old.lines().collect::<Vec<_>>();
+    let b = new.lines().collect::<Vec<_>>();
+    let ops = edits(&a, &b);
+    let additions = ops.iter().filter(|op| **op == Op::Insert).count();
+    let deletions = ops.iter().filter(|op| **op == Op::Delete).count();
+    if additions + deletions == 0 {
+        return Diff { text: String::new(), additions, deletions };
+    }
+
+    // The position in `a` and `b` before each op.
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut x, mut y) = (0, 0);
+    for op in &ops {
+        positions.push((x, y));
+        match op {
+            Op::Equal => (x, y) = (x + 1, y + 1),
+            Op::Delete => x += 1,
+            Op::Insert => y += 1,
+        }
+    }
+
+    let mut text = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let changes = (0..ops.len()).filter(|&i| ops[i] != Op::Equal).collect::<Vec<_>>();
+    let mut index = 0;
+    while index < changes.len() {
+        // Changes closer than twice the context share a hunk.
+        let start = changes[index].saturating_sub(CONTEXT);
+        let mut last = changes[index];
+        while index + 1 < changes.len() && changes[index + 1] - last <= 2 * CONTEXT {
+            index += 1;
+            last = changes[index];
+        }
+        let end = (last + CONTEXT + 1).min(ops.len());
+        index += 1;
+
+        let hunk = &ops[start..end];
+        let old_count = hunk.iter().filter(|op| **op != Op::Insert).count();
+        let new_count = hunk.iter().filter(|op| **op != Op::Delete).count();
+        let (x, y) = positions[start];
+        let _ = writeln!(
+            text,
+            "@@ -{} +{} @@",
+            range(x, old_count),
+            range(y, new_count),
+        );
+        for (op, (x, y)) in hunk.iter().zip(&positions[start..end]) {
+            let _ = match op {
+                Op::Equal => writeln!(text, " {}", a[*x]),
+                Op::Delete => writeln!(text, "-{}", a[*x]),
+                Op::Insert => writeln!(text, "+{}", b[*y]),
+            };
+        }
+    }
+    Diff { text, additions, deletions }
+}
+
+/// A hunk range: the first line, starting at 1, and the number of lines when not 1. An empty range
+/// starts at the line before it.
+fn range(before: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{}", before + 1, count),
+    }
+}
+
+/// The shortest edit script turning `a` into `b`.
+fn edits(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // The furthest reaching x on each diagonal k from -d to d, before round d.
+    let mut trace = Vec::<Vec<isize>>::new();
+
+    let mut found = false;
+    'search: for d in 0..=(max.min(MAX_EDITS) as isize) {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = |k: isize| v[(offset + k) as usize];
+            let mut x = if k == -d || (k != d && at(k - 1) < at(k + 1)) { at(k + 1) } else { at(k - 1) + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return [vec![Op::Delete; a.len()], vec![Op::Insert; b.len()]].concat();
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        // `v` holds diagonals -d..=d of the round before, the outermost of which were never reached.
+        let at = |k: isize| if k.abs() > d { -1 } else { v[(k + d) as usize] };
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let previous_x = at(previous_k).max(0);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            ops.push(if x == previous_x { Op::Insert } else { Op::Delete });
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified() {
+        let old = "# Groceries\n- milk\n- eggs\n- bread\n- butter\n- jam\n- tea\n- coffee\n- rice\n- salt";
+        let new = "# Groceries\n- milk\n- oat milk\n- eggs\n- bread\n- butter\n- jam\n- tea\n- coffee\n- rice";
+        let diff = unified(old, new, "memos/1", "memos/2");
+        assert_eq!((diff.additions, diff.deletions), (1, 1));
+        assert_eq!(diff.text, "\
+--- memos/1
++++ memos/2
+@@ -1,5 +1,6 @@
+ # Groceries
+ - milk
++- oat milk
+ - eggs
+ - bread
+ - butter
+@@ -7,4 +8,3 @@
+ - tea
+ - coffee
+ - rice
+-- salt
+");
+
+        assert_eq!(unified(old, old, "a", "b").text, "");
+        assert_eq!(unified("", "new", "a", "b").text, "--- a\n+++ b\n@@ -0,0 +1 @@\n+new\n");
+    }
+
+    #[test]
+    fn test_edits() {
+        let lines = |text: &'static str| text.split(' ').filter(|line| !line.is_empty()).collect::<Vec<_>>();
+        for (a, b) in [("a b c a b b a", "c b a b a c"), ("", "x y"), ("x y", ""), ("a", "b"), ("a b", "a b")] {
+            let (a, b) = (lines(a), lines(b));
+            let ops = edits(&a, &b);
+            // Applying the script to `a` gives `b`.
+            let (mut x, mut y, mut result) = (0, 0, Vec::new());
+            for op in ops {
+                match op {
+                    Op::Equal => { assert_eq!(a[x], b[y]); result.push(a[x]); x += 1; y += 1; }
+                    Op::Delete => x += 1,
+                    Op::Insert => { result.push(b[y]); y += 1; }
+                }
+            }
+            assert_eq!(result, b);
+        }
+        assert_eq!(edits(&lines("a b c a b b a"), &lines("c b a b a c")).iter().filter(|op| **op != Op::Equal).count(), 5);
+    }
+}
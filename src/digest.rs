@@ -0,0 +1,249 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// A digest memo of the day's changes, created every evening on the configured schedule: how many
+// memos were created and updated, the memos worth a look and the tasks still open, tagged
+// `#digest`. The time of the last digest is kept in a JSON state file, so each digest covers the
+// memos changed since the one before it, including the days the server was down.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::backup::schedule::parse_schedule;
+use crate::config::{DigestSettings, SharedSettings};
+use crate::memos::{Servers, service::note::Note};
+use crate::review::REVIEW_TAG;
+use crate::tasks::{self, Task};
+
+/// Tag of the digest memos, which later digests leave out.
+pub const DIGEST_TAG: &str = "digest";
+
+/// How often the scheduler looks whether a digest is due, and at the settings again after a reload.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Number of memos listed as highlights.
+const HIGHLIGHTS: usize = 10;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    last_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct Digest {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub created: usize,
+    pub updated: usize,
+    /// Names and titles of the pinned memos, then of the newest ones.
+    pub highlights: Vec<(String, String)>,
+    pub open: Vec<Task>,
+}
+
+impl Digest {
+    /// The digest of `notes` created or updated after `since` up to `until`, of the memos the
+    /// settings let in.
+    pub fn new(notes: &[Note], since: DateTime<Utc>, until: DateTime<Utc>, settings: &DigestSettings) -> Self {
+        let in_window = |time: Option<DateTime<Utc>>| time.is_some_and(|time| time > since && time <= until);
+        let has_tag = |note: &Note, tags: &[String]| note.tags.iter().any(|tag| tags.iter().any(|t| t.trim_start_matches('#') == tag));
+        let mut notes = notes
+            .iter()
+            .filter(|note| !note.tags.iter().any(|tag| tag == DIGEST_TAG || tag == REVIEW_TAG) && !tasks::is_open_tasks(note))
+            .filter(|note| settings.tags.is_empty() || has_tag(note, &settings.tags))
+            .filter(|note| !has_tag(note, &settings.exclude_tags))
+            .filter(|note| in_window(note.create_time) || in_window(note.update_time))
+            .collect::<Vec<_>>();
+        notes.sort_by_key(|note| (!note.pinned, std::cmp::Reverse(note.update_time.or(note.create_time))));
+
+        let created = notes.iter().filter(|note| in_window(note.create_time)).count();
+        Digest {
+            since,
+            until,
+            created,
+            updated: notes.len() - created,
+            highlights: notes
+                .iter()
+                .take(HIGHLIGHTS)
+                .map(|note| (note.name.clone().unwrap_or_default(), crate::vault::title(note)))
+                .collect(),
+            open: notes.iter().flat_map(|note| tasks::tasks(note)).filter(|task| !task.done).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.created + self.updated == 0
+    }
+
+    pub fn markdown(&self) -> String {
+        let mut digest = format!("# Digest {}\n\n", self.until.format("%Y-%m-%d"));
+        let _ = writeln!(digest, "- Memos created: {}", self.created);
+        let _ = writeln!(digest, "- Memos updated: {}", self.updated);
+        let _ = writeln!(digest, "- Tasks open: {}", self.open.len());
+
+        if !self.highlights.is_empty() {
+            digest.push_str("\n## Highlights\n\n");
+            for (name, title) in &self.highlights {
+                let _ = writeln!(digest, "- [{}](/{})", title, name);
+            }
+        }
+        if !self.open.is_empty() {
+            digest.push_str("\n## Open tasks\n\n");
+            for task in &self.open {
+                let _ = writeln!(digest, "- [ ] {} ([{}](/{}))", task.text, task.memo, task.memo);
+            }
+        }
+        let _ = write!(digest, "\n#{}", DIGEST_TAG);
+        digest
+    }
+}
+
+/// Creates the digest whenever it is due. Idles while no digest is configured, and follows changes
+/// made by a configuration reload.
+pub async fn run(servers: Arc<Servers>, settings: SharedSettings) {
+    loop {
+        let digest = settings.read().unwrap().digest.clone();
+        if let Some(digest) = digest
+            && let Err(e) = run_due(&servers, &digest, Utc::now()).await
+        {
+            warn!("Daily digest failed: {}", e);
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Creates the digest when one is due at `now` and records the run. Returns the name of the created
+/// memo, none when nothing changed since the last digest.
+pub async fn run_due(servers: &Servers, settings: &DigestSettings, now: DateTime<Utc>) -> Result<Option<String>> {
+    let mut state = load(&settings.state_file)?;
+    let Some(last_run) = state.last_run else {
+        state.last_run = Some(now);
+        save(&settings.state_file, &state)?;
+        return Ok(None);
+    };
+    let schedule = parse_schedule(&settings.schedule)?;
+    // Missed digests are made up by one covering all the days since the last.
+    let Some(due) = schedule.after(&last_run).take_while(|time| *time <= now).last() else {
+        return Ok(None);
+    };
+    let server = servers.get(settings.server.as_deref())?;
+    let digest = Digest::new(&server.list_notes().await?, last_run, due, settings);
+    let created = if digest.is_empty() {
+        info!("Skipping the digest of {}, no memo changed", due.format("%Y-%m-%d"));
+        None
+    } else {
+        let mut note = Note::new(&digest.markdown());
+        if let Some(visibility) = &settings.visibility {
+            note.visibility = visibility.clone();
+        }
+        let name = server.create_note(&note).await?.name.unwrap_or_default();
+        info!("Created the digest of {} as {}", due.format("%Y-%m-%d"), name);
+        Some(name)
+    };
+    state.last_run = Some(due);
+    save(&settings.state_file, &state)?;
+    Ok(created)
+}
+
+fn load(path: &Path) -> Result<State> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Failed to parse digest state {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(State::default()),
+        Err(e) => Err(anyhow::anyhow!("Failed to read digest state {}: {}", path.display(), e)),
+    }
+}
+
+fn save(path: &Path, state: &State) -> Result<()> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, serde_json::to_string_pretty(state)?)?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    fn settings(state_file: &Path) -> DigestSettings {
+        toml::from_str(&format!("state_file = {:?}", state_file.display().to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_digest() {
+        let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        let memo = |name: &str, content: &str, tags: &[&str], created: &str| {
+            let mut memo = mock::memo(name, content);
+            memo["tags"] = json!(tags);
+            memo["createTime"] = json!(created);
+            memo["updateTime"] = json!(created);
+            serde_json::from_value::<Note>(memo).unwrap()
+        };
+        let mut pinned = serde_json::to_value(memo("memos/1", "# Plan\n- [x] Ship it\n- [ ] Write docs", &["work"], "2025-02-01T10:00:00Z")).unwrap();
+        pinned["pinned"] = json!(true);
+        pinned["updateTime"] = json!("2025-02-03T09:00:00Z");
+        let notes = [
+            serde_json::from_value::<Note>(pinned).unwrap(),
+            memo("memos/2", "Lunch ideas", &["food"], "2025-02-03T12:00:00Z"),
+            memo("memos/3", "Secret", &["private"], "2025-02-03T13:00:00Z"),
+            memo("memos/4", "# Digest 2025-02-02", &["digest"], "2025-02-02T20:00:00Z"),
+            memo("memos/5", "Yesterday", &["food"], "2025-02-02T12:00:00Z"),
+        ];
+        let mut settings = settings(Path::new("digest.json"));
+        settings.exclude_tags = vec!["private".to_string()];
+
+        let digest = Digest::new(&notes, at("2025-02-02T20:00:00Z"), at("2025-02-03T20:00:00Z"), &settings);
+        assert_eq!((digest.created, digest.updated), (1, 1));
+        assert_eq!(digest.highlights, [("memos/1".to_string(), "Plan".to_string()), ("memos/2".to_string(), "Lunch ideas".to_string())]);
+        let markdown = digest.markdown();
+        assert!(markdown.starts_with("# Digest 2025-02-03\n\n- Memos created: 1\n- Memos updated: 1\n- Tasks open: 1\n"));
+        assert!(markdown.contains("- [ ] Write docs ([memos/1](/memos/1))\n"));
+        assert!(markdown.ends_with("\n#digest"));
+
+        settings.tags = vec!["#work".to_string()];
+        let digest = Digest::new(&notes, at("2025-02-02T20:00:00Z"), at("2025-02-03T20:00:00Z"), &settings);
+        assert_eq!(digest.highlights.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_due() {
+        let (mock, server) = mock::start().await;
+        mock::mount_memos_api(&mock).await;
+        let servers = Servers::new("default", BTreeMap::from([("default".to_string(), server)]));
+        let state_file = std::env::temp_dir().join(format!("mcp-memos-digest-{}.json", std::process::id()));
+        let settings = settings(&state_file);
+        let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+
+        // Starts from the first time the digest is seen.
+        assert_eq!(run_due(&servers, &settings, at("2025-01-30T21:00:00Z")).await.unwrap(), None);
+        assert_eq!(run_due(&servers, &settings, at("2025-01-31T19:59:00Z")).await.unwrap(), None);
+        // The mock memos were created on 2025-01-31 at 08:00.
+        assert_eq!(run_due(&servers, &settings, at("2025-01-31T20:00:30Z")).await.unwrap().as_deref(), Some("memos/3"));
+        // Nothing changed the next day.
+        assert_eq!(run_due(&servers, &settings, at("2025-02-01T20:00:30Z")).await.unwrap(), None);
+
+        let posts = mock.received_requests().await.unwrap()
+            .into_iter()
+            .filter(|request| request.method.as_str() == "POST")
+            .map(|request| request.body_json::<serde_json::Value>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(posts.len(), 1);
+        assert!(posts[0]["content"].as_str().unwrap().starts_with("# Digest 2025-01-31\n\n- Memos created: 2\n"));
+        assert_eq!(load(&state_file).unwrap().last_run, Some(at("2025-02-01T20:00:00Z")));
+
+        std::fs::remove_file(&state_file).unwrap();
+    }
+}
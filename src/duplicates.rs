@@ -0,0 +1,149 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Duplicate and near-duplicate memos, found by `find_duplicate_memos`. Memos are compared by the
+// Jaccard similarity of their word 3-shingles, after lowercasing and dropping punctuation, so that
+// copies with small edits or different formatting still match. Memos similar to each other are
+// grouped into clusters, each with the memo suggested to keep: a pinned one, else the oldest.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use serde::Serialize;
+
+use crate::memos::service::note::Note;
+
+/// Number of consecutive words in a shingle.
+const SHINGLE_WORDS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Duplicate {
+    pub name: String,
+    pub title: String,
+    /// Similarity to the canonical memo, from 0 to 1.
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cluster {
+    /// The memo suggested to keep.
+    pub canonical: String,
+    /// The other memos of the cluster, most similar first.
+    pub duplicates: Vec<Duplicate>,
+}
+
+/// The hashed word shingles of a text.
+fn shingles(text: &str) -> BTreeSet<u64> {
+    let normalized = text
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_lowercase().next().unwrap_or(c) } else { ' ' })
+        .collect::<String>();
+    let words = normalized.split_whitespace().collect::<Vec<_>>();
+    words
+        .windows(SHINGLE_WORDS.min(words.len()).max(1))
+        .map(|shingle| {
+            let mut hasher = DefaultHasher::new();
+            shingle.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+fn jaccard(a: &BTreeSet<u64>, b: &BTreeSet<u64>) -> f64 {
+    let common = a.intersection(b).count();
+    match a.len() + b.len() - common {
+        0 => 0.0,
+        union => common as f64 / union as f64,
+    }
+}
+
+/// The clusters of memos at least `threshold` similar to another memo of the cluster, the largest
+/// first.
+pub fn clusters(notes: &[Note], threshold: f64) -> Vec<Cluster> {
+    let notes = notes.iter().filter(|note| note.name.is_some()).collect::<Vec<_>>();
+    let shingles = notes.iter().map(|note| shingles(&note.content)).collect::<Vec<_>>();
+
+    // Union-find over the memos, joined by each similar pair.
+    let mut parent = (0..notes.len()).collect::<Vec<_>>();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..notes.len() {
+        for j in i + 1..notes.len() {
+            let (a, b) = (&shingles[i], &shingles[j]);
+            // The similarity is at most the ratio of the sizes, which is much cheaper to check.
+            if a.is_empty() || (a.len().min(b.len()) as f64) < threshold * a.len().max(b.len()) as f64 {
+                continue;
+            }
+            if jaccard(a, b) >= threshold {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[ri] = rj;
+            }
+        }
+    }
+
+    let mut groups = BTreeMap::<usize, Vec<usize>>::new();
+    for i in 0..notes.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+    let mut clusters = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let canonical = *members
+                .iter()
+                .min_by_key(|&&i| (!notes[i].pinned, notes[i].create_time, notes[i].name.clone()))
+                .unwrap();
+            let mut duplicates = members
+                .iter()
+                .filter(|&&i| i != canonical)
+                .map(|&i| Duplicate {
+                    name: notes[i].name.clone().unwrap_or_default(),
+                    title: crate::vault::title(notes[i]),
+                    similarity: (jaccard(&shingles[canonical], &shingles[i]) * 100.0).round() / 100.0,
+                })
+                .collect::<Vec<_>>();
+            duplicates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+            Cluster { canonical: notes[canonical].name.clone().unwrap_or_default(), duplicates }
+        })
+        .collect::<Vec<_>>();
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.duplicates.len()));
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[test]
+    fn test_clusters() {
+        let memo = |name: &str, content: &str, created: &str| {
+            let mut memo = mock::memo(name, content);
+            memo["createTime"] = json!(created);
+            serde_json::from_value::<Note>(memo).unwrap()
+        };
+        let notes = [
+            memo("memos/1", "Buy tomatoes, basil and mozzarella for the salad on Friday", "2025-01-03T00:00:00Z"),
+            memo("memos/2", "Call the plumber about the kitchen sink", "2025-01-02T00:00:00Z"),
+            memo("memos/3", "buy tomatoes basil and mozzarella for the salad on friday!", "2025-01-01T00:00:00Z"),
+            memo("memos/4", "Buy tomatoes, basil and mozzarella for the salad on Friday evening", "2025-01-04T00:00:00Z"),
+            memo("memos/5", "Call the dentist", "2025-01-05T00:00:00Z"),
+        ];
+
+        let clusters = clusters(&notes, 0.8);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].canonical, "memos/3");
+        let duplicates = clusters[0].duplicates.iter().map(|d| (d.name.as_str(), d.similarity)).collect::<Vec<_>>();
+        assert_eq!(duplicates, [("memos/1", 1.0), ("memos/4", 0.89)]);
+    }
+}
@@ -0,0 +1,137 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+#[cfg(feature = "local-embeddings")]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::cli::{EmbeddingProvider, ServeArgs};
+
+/// Turns memo content into vectors for semantic search.
+pub enum Embedder {
+    /// Any endpoint implementing the OpenAI `/embeddings` API.
+    OpenAi {
+        client: Client,
+        url: String,
+        model: String,
+        api_key: Option<String>,
+    },
+    /// A local ONNX model run by fastembed.
+    #[cfg(feature = "local-embeddings")]
+    Local {
+        model: Arc<Mutex<fastembed::TextEmbedding>>,
+        name: String,
+    },
+}
+
+impl Embedder {
+    pub fn from_args(args: &ServeArgs) -> Result<Option<Embedder>> {
+        match args.embeddings {
+            None => Ok(None),
+            Some(EmbeddingProvider::Openai) => Ok(Some(Embedder::OpenAi {
+                client: Client::new(),
+                url: args.embeddings_url.trim_end_matches('/').to_string(),
+                model: args.embeddings_model.clone(),
+                api_key: args.embeddings_api_key.clone().filter(|key| !key.is_empty()),
+            })),
+            #[cfg(feature = "local-embeddings")]
+            Some(EmbeddingProvider::Local) => {
+                let options = fastembed::InitOptions::new(fastembed::EmbeddingModel::AllMiniLML6V2);
+                Ok(Some(Embedder::Local {
+                    model: Arc::new(Mutex::new(fastembed::TextEmbedding::try_new(options)?)),
+                    name: "all-MiniLM-L6-v2".to_string(),
+                }))
+            }
+            #[cfg(not(feature = "local-embeddings"))]
+            Some(EmbeddingProvider::Local) => Err(anyhow::anyhow!(
+                "Local embeddings require building with the `local-embeddings` feature"
+            )),
+        }
+    }
+
+    /// Identifies the vectors produced, so switching models re-indexes every memo.
+    pub fn model(&self) -> &str {
+        match self {
+            Embedder::OpenAi { model, .. } => model,
+            #[cfg(feature = "local-embeddings")]
+            Embedder::Local { name, .. } => name,
+        }
+    }
+
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        match self {
+            Embedder::OpenAi { client, url, model, api_key } => {
+                #[derive(Deserialize)]
+                struct Embedding {
+                    index: usize,
+                    embedding: Vec<f32>,
+                }
+                #[derive(Deserialize)]
+                struct EmbeddingsResponse {
+                    data: Vec<Embedding>,
+                }
+
+                let mut request = client
+                    .post(format!("{}/embeddings", url))
+                    .json(&json!({"model": model, "input": texts}));
+                if let Some(api_key) = api_key {
+                    request = request.bearer_auth(api_key);
+                }
+                let rsp = request.send().await?;
+                if !rsp.status().is_success() {
+                    let status = rsp.status();
+                    let text = rsp.text().await.unwrap_or_default();
+                    return Err(anyhow::anyhow!("Embedding request failed: {} - {}", status, crate::redact::redact(&text)));
+                }
+
+                let mut data = rsp.json::<EmbeddingsResponse>().await?.data;
+                data.sort_by_key(|embedding| embedding.index);
+                Ok(data.into_iter().map(|embedding| embedding.embedding).collect())
+            }
+            #[cfg(feature = "local-embeddings")]
+            Embedder::Local { model, .. } => {
+                let model = model.clone();
+                let texts = texts.to_vec();
+                tokio::task::spawn_blocking(move || model.lock().unwrap().embed(texts, None)).await?
+            }
+        }
+    }
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|y| y * y).sum::<f32>().sqrt();
+    if norm == 0.0 { 0.0 } else { dot / norm }
+}
+
+pub fn to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_encoding_and_similarity() {
+        let vector = vec![0.5, -1.25, 3.0];
+        assert_eq!(from_bytes(&to_bytes(&vector)), vector);
+
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+}
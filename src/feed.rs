@@ -0,0 +1,183 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// An Atom feed of the PUBLIC memos of the default server, served read-only at `/feed.xml` so people
+// can follow a memo-based microblog without access to the Memos instance. The rendered feed is
+// cached and only fetched again once it is older than the configured TTL.
+
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::memos::{
+    Servers,
+    service::note::{Note, NoteService, Visibility},
+};
+
+/// Number of most recent public memos in the feed.
+const MAX_ENTRIES: usize = 50;
+
+pub struct Feed {
+    servers: Arc<Servers>,
+    url: String,
+    title: String,
+    ttl: Duration,
+    cache: Mutex<Option<(Instant, String)>>,
+}
+
+impl Feed {
+    /// A feed published at `url`, which also identifies the feed and its entries.
+    pub fn new(servers: Arc<Servers>, url: &str, title: &str, ttl: Duration) -> Self {
+        Feed {
+            servers,
+            url: url.to_string(),
+            title: title.to_string(),
+            ttl,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Makes the next request render the feed again.
+    pub async fn invalidate(&self) {
+        *self.cache.lock().await = None;
+    }
+
+    /// The feed as Atom XML, rendered again when the cached one is older than the TTL.
+    pub async fn xml(&self) -> Result<String> {
+        let mut cache = self.cache.lock().await;
+        if let Some((rendered, xml)) = cache.as_ref()
+            && rendered.elapsed() < self.ttl
+        {
+            return Ok(xml.clone());
+        }
+
+        let server = self.servers.get(None)?;
+        let xml = render(&self.url, &self.title, &public_notes(server).await?, Utc::now());
+        *cache = Some((Instant::now(), xml.clone()));
+        Ok(xml)
+    }
+}
+
+/// Handler of `GET /feed.xml`.
+pub async fn serve(State(feed): State<Arc<Feed>>) -> Response {
+    match feed.xml().await {
+        Ok(xml) => ([(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")], xml).into_response(),
+        Err(e) => {
+            warn!("Failed to render the feed: {}", e);
+            (StatusCode::BAD_GATEWAY, "The feed is unavailable").into_response()
+        }
+    }
+}
+
+/// The public memos, most recently created first.
+async fn public_notes(server: &dyn NoteService) -> Result<Vec<Note>> {
+    let mut notes = server
+        .list_notes()
+        .await?
+        .into_iter()
+        .filter(|note| note.visibility == Visibility::Public)
+        .collect::<Vec<_>>();
+    notes.sort_by_key(|note| std::cmp::Reverse(note.create_time));
+    notes.truncate(MAX_ENTRIES);
+    Ok(notes)
+}
+
+fn render(url: &str, title: &str, notes: &[Note], now: DateTime<Utc>) -> String {
+    let updated = |note: &Note| note.update_time.or(note.create_time).unwrap_or(now);
+    let feed_updated = notes.iter().map(updated).max().unwrap_or(now);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let _ = writeln!(xml, "  <id>{}</id>", escape(url));
+    let _ = writeln!(xml, "  <title>{}</title>", escape(title));
+    let _ = writeln!(xml, "  <updated>{}</updated>", timestamp(feed_updated));
+    let _ = writeln!(xml, "  <link rel=\"self\" href=\"{}\"/>", escape(url));
+    for note in notes {
+        let id = note.name.as_deref().unwrap_or_default().trim_start_matches("memos/");
+        let _ = writeln!(xml, "  <entry>");
+        let _ = writeln!(xml, "    <id>{}#{}</id>", escape(url), escape(id));
+        let _ = writeln!(xml, "    <title>{}</title>", escape(&crate::vault::title(note)));
+        if let Some(created) = note.create_time {
+            let _ = writeln!(xml, "    <published>{}</published>", timestamp(created));
+        }
+        let _ = writeln!(xml, "    <updated>{}</updated>", timestamp(updated(note)));
+        let _ = writeln!(xml, "    <author><name>{}</name></author>", escape(title));
+        for tag in &note.tags {
+            let _ = writeln!(xml, "    <category term=\"{}\"/>", escape(tag));
+        }
+        let _ = writeln!(xml, "    <content type=\"text\">{}</content>", escape(&note.content));
+        let _ = writeln!(xml, "  </entry>");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // Control characters other than tab and newlines are not allowed in XML 1.0.
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+    use wiremock::{Mock, ResponseTemplate, matchers::{method, path}};
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[tokio::test]
+    async fn test_feed() {
+        let (mock, server) = mock::start().await;
+        let mut public = mock::memo("memos/1", "# Hello <world>\nTomatoes & #basil");
+        public["visibility"] = json!("PUBLIC");
+        public["tags"] = json!(["basil"]);
+        Mock::given(method("GET")).and(path("/api/v1/memos"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "memos": [public, mock::memo("memos/2", "Private thoughts")],
+            })))
+            .expect(1)
+            .mount(&mock).await;
+        let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+        let feed = Feed::new(servers, "https://example.com/feed.xml", "Notes", Duration::from_secs(300));
+
+        let xml = feed.xml().await.unwrap();
+        assert!(xml.contains("<id>https://example.com/feed.xml#1</id>"));
+        assert!(xml.contains("<title>Hello &lt;world&gt;</title>"));
+        assert!(xml.contains("<category term=\"basil\"/>"));
+        assert!(xml.contains("Tomatoes &amp; #basil"));
+        assert!(xml.contains("<updated>2025-01-31T08:00:00Z</updated>"));
+        assert!(!xml.contains("Private thoughts"));
+        assert_eq!(xml.matches("<entry>").count(), 1);
+
+        assert_eq!(feed.xml().await.unwrap(), xml);
+    }
+}
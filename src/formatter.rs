@@ -0,0 +1,227 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Normalization of the Markdown of memos for `format_memo`, so that notes written by agents and
+// by hand read alike: headings nest one level at a time from `#`, bullets are `-` and numbered
+// items `1.`, trailing whitespace and runs of blank lines go, and the lines holding only tags are
+// gathered into one last line. The structure comes from the pulldown-cmark AST and only the source
+// of the nodes concerned is rewritten, so code blocks, HTML and everything else stay as written.
+// Trailing double spaces are removed too; Memos breaks lines on every newline anyway.
+
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::Serialize;
+
+/// A line of nothing but tags.
+static TAG_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[ \t]*#[\p{L}\p{N}_/-]+(?:[ \t]+#[\p{L}\p{N}_/-]+)*[ \t]*$").unwrap()
+});
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"#[\p{L}\p{N}_/-]+").unwrap());
+
+type Pass = fn(&str) -> String;
+
+/// The passes of [`format`], named as reported in [`Formatted::fixes`].
+const PASSES: &[(&str, Pass)] = &[
+    ("heading levels", headings),
+    ("list markers", list_markers),
+    ("trailing whitespace", trailing_whitespace),
+    ("blank lines", blank_lines),
+    ("tag placement", tag_placement),
+];
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Formatted {
+    pub content: String,
+    /// The passes that changed something, e.g. `heading levels`. Empty when the memo was tidy.
+    pub fixes: Vec<&'static str>,
+}
+
+pub fn format(content: &str) -> Formatted {
+    let mut content = content.to_string();
+    let mut fixes = Vec::new();
+    for (fix, pass) in PASSES {
+        let formatted = pass(&content);
+        if formatted != content {
+            fixes.push(*fix);
+            content = formatted;
+        }
+    }
+    Formatted { content, fixes }
+}
+
+fn parse(text: &str) -> impl Iterator<Item = (Event<'_>, Range<usize>)> {
+    Parser::new_ext(text, Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES).into_offset_iter()
+}
+
+/// `text` with the replacements of `edits`, which must not overlap.
+fn apply(text: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut result = text.to_string();
+    for (range, replacement) in edits.into_iter().rev() {
+        result.replace_range(range, &replacement);
+    }
+    result
+}
+
+/// ATX headings whose levels start at 1 and go down one at a time, so `#` then `###` becomes `#`
+/// then `##`. Setext headings and closing `#`s are rewritten to the plain form.
+fn headings(text: &str) -> String {
+    let mut edits = Vec::new();
+    // The original and the new level of the enclosing headings.
+    let mut levels: Vec<(usize, usize)> = Vec::new();
+    let mut heading: Option<(Range<usize>, usize, Option<Range<usize>>)> = None;
+    for (event, range) in parse(text) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => heading = Some((range, level as usize, None)),
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((range, level, inner)) = heading.take() else {
+                    continue;
+                };
+                while levels.last().is_some_and(|(original, _)| *original >= level) {
+                    levels.pop();
+                }
+                let new = levels.last().map_or(1, |(_, new)| new + 1);
+                levels.push((level, new));
+                let Some(inner) = inner else {
+                    continue;
+                };
+                let source = text[range.clone()].trim_end_matches(['\r', '\n']);
+                let line = format!("{} {}", "#".repeat(new), text[inner].trim());
+                if source != line {
+                    edits.push((range.start..range.start + source.len(), line));
+                }
+            }
+            _ => {
+                if let Some((_, _, inner)) = &mut heading {
+                    *inner = Some(match inner.take() {
+                        Some(inner) => inner.start.min(range.start)..inner.end.max(range.end),
+                        None => range,
+                    });
+                }
+            }
+        }
+    }
+    apply(text, edits)
+}
+
+/// `-` for every bullet and `.` after every item number.
+fn list_markers(text: &str) -> String {
+    let mut edits = Vec::new();
+    let mut ordered = Vec::new();
+    for (event, range) in parse(text) {
+        match event {
+            Event::Start(Tag::List(first)) => ordered.push(first.is_some()),
+            Event::End(TagEnd::List(_)) => {
+                ordered.pop();
+            }
+            Event::Start(Tag::Item) => {
+                let item = &text[range.clone()];
+                let marker = match ordered.last() {
+                    Some(true) => item.find(|c: char| !c.is_ascii_digit()).filter(|&end| item[end..].starts_with(')')),
+                    _ => Some(0).filter(|_| item.starts_with(['*', '+'])),
+                };
+                if let Some(offset) = marker {
+                    let replacement = if item[offset..].starts_with(')') { "." } else { "-" };
+                    edits.push((range.start + offset..range.start + offset + 1, replacement.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    apply(text, edits)
+}
+
+fn trailing_whitespace(text: &str) -> String {
+    lines(text)
+        .map(|(line, verbatim)| match verbatim {
+            true => line.to_string(),
+            false if line.ends_with('\n') => format!("{}\n", line.trim_end()),
+            false => line.trim_end().to_string(),
+        })
+        .collect()
+}
+
+/// Single blank lines between blocks, none at the start or the end.
+fn blank_lines(text: &str) -> String {
+    let mut result = String::new();
+    let mut after_blank = false;
+    for (line, verbatim) in lines(text) {
+        let blank = !verbatim && line.trim().is_empty();
+        if blank && (after_blank || result.is_empty()) {
+            continue;
+        }
+        after_blank = blank;
+        result.push_str(if blank { "\n" } else { line });
+    }
+    result.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// The tags of all lines holding only tags, once each, on one line at the end.
+fn tag_placement(text: &str) -> String {
+    let mut tags = Vec::new();
+    let mut rest = String::new();
+    for (line, verbatim) in lines(text) {
+        if !verbatim && TAG_LINE.is_match(line.trim_end_matches(['\r', '\n'])) {
+            for tag in TAG.find_iter(line).map(|tag| tag.as_str()) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        } else {
+            rest.push_str(line);
+        }
+    }
+    if tags.is_empty() {
+        return text.to_string();
+    }
+    let mut result = blank_lines(&rest);
+    if !result.is_empty() {
+        result.push_str("\n\n");
+    }
+    result.push_str(&tags.join(" "));
+    result
+}
+
+/// The lines of `text` with their line break, each with whether it is inside a code or HTML block
+/// and so must be kept as it is.
+fn lines(text: &str) -> impl Iterator<Item = (&str, bool)> {
+    let verbatim = parse(text)
+        .filter_map(|(event, range)| matches!(event, Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock)).then_some(range))
+        .collect::<Vec<_>>();
+    text.split_inclusive('\n').scan(0, move |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((line, verbatim.iter().any(|range| range.contains(&start))))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let memo = "\n### Plan  \nSome text.   \n\n\n\n##### Steps\n* one\n+ two\n  1) nested\n\n#work #planning\n\n```\ncode   \n\n\n#not-a-tag\n```\nSetext\n======\n\n#work #review\n";
+        let formatted = format(memo);
+        assert_eq!(
+            formatted.content,
+            "# Plan\nSome text.\n\n## Steps\n- one\n- two\n  1. nested\n\n```\ncode   \n\n\n#not-a-tag\n```\n# Setext\n\n#work #planning #review",
+        );
+        assert_eq!(formatted.fixes, ["heading levels", "list markers", "trailing whitespace", "blank lines", "tag placement"]);
+
+        let tidy = format(&formatted.content);
+        assert_eq!(tidy.content, formatted.content);
+        assert!(tidy.fixes.is_empty());
+    }
+
+    #[test]
+    fn test_headings_keep_siblings() {
+        assert_eq!(headings("## A\ntext\n## B\n#### B.1\n# C"), "# A\ntext\n# B\n## B.1\n# C");
+        assert_eq!(headings("#tag is not a heading\n## Closed ##"), "#tag is not a heading\n# Closed");
+    }
+}
@@ -0,0 +1,246 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// YAML front matter at the start of memo content, as written by Obsidian, Jekyll and most other
+// Markdown tools. `create_memo`, `update_memo` and the vault import take it out of the content and
+// set the visibility, tags, pinned flag and display time of the memo from it, and `get_memo` can
+// put it back in front, so a memo edited in such a tool round-trips without the metadata ending up
+// as text.
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::memos::service::note::{Note, UpdateNote, Visibility};
+
+/// Front matter fields read, under the names Obsidian and other tools use.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FrontMatter {
+    pub visibility: Option<String>,
+    tags: Option<Tags>,
+    #[serde(alias = "date", alias = "created_at")]
+    pub created: Option<String>,
+    #[serde(alias = "updated_at", alias = "modified")]
+    pub updated: Option<String>,
+    #[serde(alias = "displayTime")]
+    pub display_time: Option<String>,
+    pub pinned: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Tags {
+    List(Vec<String>),
+    Text(String),
+}
+
+/// The front matter written by [`render`], in the form [`split`] reads back.
+#[derive(Serialize)]
+struct Rendered<'a> {
+    visibility: &'a Visibility,
+    pinned: bool,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tags: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_time: Option<DateTime<Utc>>,
+}
+
+impl FrontMatter {
+    pub fn visibility(&self) -> Result<Option<Visibility>> {
+        let Some(visibility) = &self.visibility else {
+            return Ok(None);
+        };
+        Ok(Some(match visibility.to_ascii_lowercase().as_str() {
+            "private" => Visibility::Private,
+            "protected" => Visibility::Protected,
+            "public" => Visibility::Public,
+            _ => return Err(anyhow::anyhow!("Unknown visibility {:?}, expected PRIVATE, PROTECTED or PUBLIC", visibility)),
+        }))
+    }
+
+    /// The tags, given as a list or as one comma or space separated string, without `#`.
+    pub fn tags(&self) -> Vec<String> {
+        let tags = match &self.tags {
+            Some(Tags::List(tags)) => tags.clone(),
+            Some(Tags::Text(tags)) => tags.split([',', ' ']).map(str::to_string).collect(),
+            None => vec![],
+        };
+        tags.iter()
+            .map(|tag| tag.trim().trim_start_matches('#').to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    /// The display time, falling back to the creation date most tools write.
+    pub fn display_time(&self) -> Result<Option<DateTime<Utc>>> {
+        self.display_time.as_deref().or(self.created.as_deref()).map(parse_time).transpose()
+    }
+}
+
+/// The YAML front matter of `text`, if it starts with one, and the content after it.
+pub fn split(text: &str) -> Result<(Option<FrontMatter>, &str)> {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return Ok((None, text));
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = &rest[..offset];
+            let front_matter = match yaml.trim().is_empty() {
+                true => FrontMatter::default(),
+                false => serde_yaml::from_str(yaml).map_err(|e| anyhow::anyhow!("Invalid front matter: {}", e))?,
+            };
+            return Ok((Some(front_matter), &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    Ok((None, text))
+}
+
+/// Moves the front matter of the content of `note` into its fields. Fields the note sets already
+/// are kept, and the tags are added to the content as `#tag`, which is where Memos reads them.
+pub fn apply(note: &mut Note) -> Result<()> {
+    let (Some(front_matter), body) = split(&note.content)? else {
+        return Ok(());
+    };
+    let content = with_tags(body, &front_matter.tags());
+    let (visibility, display_time) = (front_matter.visibility()?, front_matter.display_time()?);
+    if note.visibility == Visibility::VisibilityUnspecified
+        && let Some(visibility) = visibility
+    {
+        note.visibility = visibility;
+    }
+    if front_matter.pinned == Some(true) {
+        note.pinned = true;
+    }
+    if note.display_time.is_none() {
+        note.display_time = display_time;
+    }
+    note.content = content;
+    Ok(())
+}
+
+/// Like [`apply`], for an update whose new content starts with front matter.
+pub fn apply_to_update(update: &mut UpdateNote) -> Result<()> {
+    let Some(content) = &update.content else {
+        return Ok(());
+    };
+    let (Some(front_matter), body) = split(content)? else {
+        return Ok(());
+    };
+    let content = with_tags(body, &front_matter.tags());
+    let (visibility, display_time) = (front_matter.visibility()?, front_matter.display_time()?);
+    if update.visibility.is_none() {
+        update.visibility = visibility;
+    }
+    if update.pinned.is_none() {
+        update.pinned = front_matter.pinned;
+    }
+    if update.display_time.is_none() {
+        update.display_time = display_time;
+    }
+    update.content = Some(content);
+    Ok(())
+}
+
+/// The content of `note` behind front matter holding its visibility, pinned flag, tags and display
+/// time.
+pub fn render(note: &Note) -> Result<String> {
+    let front_matter = Rendered {
+        visibility: &note.visibility,
+        pinned: note.pinned,
+        tags: &note.tags,
+        display_time: note.display_time,
+    };
+    Ok(format!("---\n{}---\n\n{}", serde_yaml::to_string(&front_matter)?, note.content))
+}
+
+/// Accepts RFC 3339 timestamps and the `YYYY-MM-DD[ HH:MM[:SS]]` dates common in front matter, in UTC.
+pub fn parse_time(text: &str) -> Result<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(time.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| anyhow::anyhow!("Invalid date {:?}, expected RFC 3339 or YYYY-MM-DD", text))
+}
+
+/// `body` without the blank lines around it, with `#tag` added for the tags it lacks.
+fn with_tags(body: &str, tags: &[String]) -> String {
+    let mut builder = Note::builder().content(body.trim_start_matches(['\r', '\n']).trim_end());
+    for tag in tags {
+        builder = builder.tag(tag);
+    }
+    builder.build().content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut note = Note::new("---\nvisibility: public\ntags: [garden, \"#spring\"]\npinned: true\ndate: 2025-03-01\n---\n\nSow tomatoes #garden\n");
+        // As create_memo receives it when the client gives no visibility.
+        note.visibility = Visibility::VisibilityUnspecified;
+        apply(&mut note).unwrap();
+        assert_eq!(note.content, "Sow tomatoes #garden #spring");
+        assert_eq!(note.visibility, Visibility::Public);
+        assert!(note.pinned);
+        assert_eq!(note.display_time, Some("2025-03-01T00:00:00Z".parse().unwrap()));
+
+        // Fields set by the caller win over the front matter.
+        let mut note = Note::builder().content("---\nvisibility: PUBLIC\n---\nText").visibility(Visibility::Private).build();
+        apply(&mut note).unwrap();
+        assert_eq!((note.content.as_str(), &note.visibility), ("Text", &Visibility::Private));
+
+        let mut note = Note::new("--- not front matter\nText");
+        apply(&mut note).unwrap();
+        assert_eq!(note.content, "--- not front matter\nText");
+        assert!(apply(&mut Note::new("---\nvisibility: friends\n---\nText")).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut note = Note::builder()
+            .content("Trip notes #travel")
+            .tag("travel")
+            .visibility(Visibility::Protected)
+            .pinned(true)
+            .display_time("2025-04-02T09:30:00Z".parse().unwrap())
+            .build();
+        let rendered = render(&note).unwrap();
+        assert!(rendered.starts_with("---\nvisibility: PROTECTED\npinned: true\ntags:\n- travel\ndisplay_time: 2025-04-02T09:30:00Z\n---\n\n"));
+
+        let mut update = UpdateNote::new("memos/1".parse().unwrap());
+        update.content = Some(rendered);
+        apply_to_update(&mut update).unwrap();
+        assert_eq!(update.content.as_deref(), Some("Trip notes #travel"));
+        assert_eq!(update.visibility, Some(Visibility::Protected));
+        assert_eq!(update.pinned, Some(true));
+        assert_eq!(update.display_time, note.display_time);
+        assert_eq!(update.update_mask(), "content,visibility,pinned,display_time");
+
+        note.content = render(&note).unwrap();
+        apply(&mut note).unwrap();
+        assert_eq!(note.content, "Trip notes #travel");
+    }
+
+    #[test]
+    fn test_parse_time() {
+        for text in ["2024-05-01T10:30:00Z", "2024-05-01T12:30:00+02:00", "2024-05-01 10:30", "2024-05-01T10:30:00"] {
+            assert_eq!(parse_time(text).unwrap().to_rfc3339(), "2024-05-01T10:30:00+00:00", "{}", text);
+        }
+        assert_eq!(parse_time("2024-05-01").unwrap().to_rfc3339(), "2024-05-01T00:00:00+00:00");
+        assert!(parse_time("May 1st").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// The graph of memos, their relations and tags, exported by `get_memo_graph` for Graphviz (DOT) or
+// web viewers (JSON node-link, as read by d3 and networkx). Memos link to the memos they reference
+// or comment on and to their tags; tags used on the same memo are linked with the number of memos
+// they share as weight.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::memos::service::note::{Note, RelationType};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Memo,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Node {
+    /// `memos/{id}` for memos, `#{tag}` for tags.
+    pub id: String,
+    pub kind: NodeKind,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    Reference,
+    Comment,
+    Tagged,
+    Cooccurs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Link {
+    pub source: String,
+    pub target: String,
+    pub kind: LinkKind,
+    pub weight: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub links: Vec<Link>,
+}
+
+impl Graph {
+    /// The graph of `notes`. Relations to memos outside `notes` are left out.
+    pub fn new(notes: &[Note]) -> Self {
+        let mut graph = Graph::default();
+        let names = notes.iter().filter_map(|note| note.name.as_deref()).collect::<BTreeSet<_>>();
+        let mut tags = BTreeSet::new();
+        let mut cooccurrences = BTreeMap::<(&str, &str), usize>::new();
+
+        for note in notes {
+            let Some(name) = note.name.as_deref() else {
+                continue;
+            };
+            graph.nodes.push(Node { id: name.to_string(), kind: NodeKind::Memo, label: crate::vault::title(note) });
+            for relation in &note.relations {
+                let kind = match relation.relation_type {
+                    RelationType::Reference => LinkKind::Reference,
+                    RelationType::Comment => LinkKind::Comment,
+                    RelationType::RelationTypeUnspecified => continue,
+                };
+                let Some(target) = relation.related_memo["name"].as_str() else {
+                    continue;
+                };
+                // Memos list each relation on both memos.
+                if relation.memo["name"] == name && names.contains(target) {
+                    graph.links.push(Link { source: name.to_string(), target: target.to_string(), kind, weight: 1 });
+                }
+            }
+            let note_tags = note.tags.iter().map(String::as_str).collect::<BTreeSet<_>>();
+            for tag in &note_tags {
+                tags.insert(*tag);
+                graph.links.push(Link { source: name.to_string(), target: format!("#{}", tag), kind: LinkKind::Tagged, weight: 1 });
+            }
+            for (index, a) in note_tags.iter().enumerate() {
+                for b in note_tags.iter().skip(index + 1) {
+                    *cooccurrences.entry((a, b)).or_default() += 1;
+                }
+            }
+        }
+
+        graph.nodes.extend(tags.into_iter().map(|tag| Node { id: format!("#{}", tag), kind: NodeKind::Tag, label: tag.to_string() }));
+        graph.links.extend(cooccurrences.into_iter().map(|((a, b), weight)| Link {
+            source: format!("#{}", a),
+            target: format!("#{}", b),
+            kind: LinkKind::Cooccurs,
+            weight,
+        }));
+        graph
+    }
+
+    /// The graph in JSON node-link format.
+    pub fn node_link(&self) -> Value {
+        json!({
+            "directed": true,
+            "multigraph": false,
+            "nodes": self.nodes,
+            "links": self.links,
+        })
+    }
+
+    /// The graph in the Graphviz DOT language.
+    pub fn dot(&self) -> String {
+        let mut dot = String::from("digraph memos {\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Memo => "box",
+                NodeKind::Tag => "ellipse",
+            };
+            let _ = writeln!(dot, "  {} [label={}, shape={}];", quote(&node.id), quote(&node.label), shape);
+        }
+        for link in &self.links {
+            let attributes = match link.kind {
+                LinkKind::Reference => "label=\"reference\"".to_string(),
+                LinkKind::Comment => "label=\"comment\", style=dotted".to_string(),
+                LinkKind::Tagged => "style=dashed".to_string(),
+                LinkKind::Cooccurs => format!("dir=none, label=\"{}\", weight={}", link.weight, link.weight),
+            };
+            let _ = writeln!(dot, "  {} -> {} [{}];", quote(&link.source), quote(&link.target), attributes);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A DOT quoted string.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memos::mock;
+
+    #[test]
+    fn test_graph() {
+        let mut first = mock::memo("memos/1", "# Plan \"Q1\"\nSee memos/2 #work #q1");
+        first["tags"] = json!(["work", "q1"]);
+        first["relations"] = json!([
+            {"memo": {"name": "memos/1"}, "relatedMemo": {"name": "memos/2"}, "type": "REFERENCE"},
+            {"memo": {"name": "memos/1"}, "relatedMemo": {"name": "memos/9"}, "type": "REFERENCE"},
+        ]);
+        let mut second = mock::memo("memos/2", "Budget #work");
+        second["tags"] = json!(["work"]);
+        second["relations"] = json!([
+            {"memo": {"name": "memos/1"}, "relatedMemo": {"name": "memos/2"}, "type": "REFERENCE"},
+        ]);
+        let notes = [first, second].map(|memo| serde_json::from_value::<Note>(memo).unwrap());
+
+        let graph = Graph::new(&notes);
+        let ids = graph.nodes.iter().map(|node| node.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["memos/1", "memos/2", "#q1", "#work"]);
+        let links = graph.links.iter().map(|link| (link.source.as_str(), link.target.as_str(), link.weight)).collect::<Vec<_>>();
+        assert_eq!(links, [
+            ("memos/1", "memos/2", 1),
+            ("memos/1", "#q1", 1),
+            ("memos/1", "#work", 1),
+            ("memos/2", "#work", 1),
+            ("#q1", "#work", 1),
+        ]);
+
+        assert_eq!(graph.node_link()["links"][0], json!({"source": "memos/1", "target": "memos/2", "kind": "reference", "weight": 1}));
+        let dot = graph.dot();
+        assert!(dot.contains("  \"memos/1\" [label=\"Plan \\\"Q1\\\"\", shape=box];\n"));
+        assert!(dot.contains("  \"#q1\" -> \"#work\" [dir=none, label=\"1\", weight=1];\n"));
+    }
+}
@@ -0,0 +1,111 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Attachment housekeeping for `clean_up_attachments`: the attachments of the signed-in user, largest
+// first, flagged when they belong to no memo, when their memo no longer exists or when they are larger
+// than a threshold. Memos keeps the files of deleted memos and of uploads never set on a memo, so
+// these take storage until they are deleted. Attachments with an external link take none.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::memos::service::note::Attachment;
+
+/// Why an attachment is flagged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    /// Set on no memo.
+    Unattached,
+    /// Set on a memo that no longer exists.
+    Orphaned,
+    /// Larger than the threshold.
+    Oversized,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttachmentReport {
+    pub name: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub bytes: u64,
+    /// The memo it is set on, if any.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub memo: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<Flag>,
+}
+
+/// The stored attachments, largest first. `memos` are the names of the memos that exist, archived
+/// ones included, and attachments larger than `max_bytes` are flagged as oversized.
+pub fn review(attachments: &[Attachment], memos: &BTreeSet<String>, max_bytes: Option<u64>) -> Vec<AttachmentReport> {
+    let mut reports = attachments
+        .iter()
+        .filter(|attachment| attachment.external_link.is_empty())
+        .map(|attachment| {
+            let bytes = attachment.size.parse().unwrap_or(0);
+            let mut flags = Vec::new();
+            match attachment.memo.as_str() {
+                "" => flags.push(Flag::Unattached),
+                memo if !memos.contains(memo) => flags.push(Flag::Orphaned),
+                _ => {}
+            }
+            if max_bytes.is_some_and(|max| bytes > max) {
+                flags.push(Flag::Oversized);
+            }
+            AttachmentReport {
+                name: attachment.name.clone(),
+                filename: attachment.filename.clone(),
+                mime_type: attachment.mime_type.clone(),
+                bytes,
+                memo: attachment.memo.clone(),
+                flags,
+            }
+        })
+        .collect::<Vec<_>>();
+    reports.sort_by_key(|report| std::cmp::Reverse(report.bytes));
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    fn attachment(name: &str, size: u64, memo: &str) -> Attachment {
+        let mut attachment = mock::attachment(name, "file.bin", "application/octet-stream");
+        attachment["size"] = json!(size.to_string());
+        attachment["memo"] = json!(memo);
+        serde_json::from_value(attachment).unwrap()
+    }
+
+    #[test]
+    fn test_review() {
+        let mut link = mock::attachment("attachments/5", "site", "text/html");
+        link["externalLink"] = json!("https://example.com");
+        let attachments = [
+            attachment("attachments/1", 10, "memos/1"),
+            attachment("attachments/2", 5_000, "memos/1"),
+            attachment("attachments/3", 20, ""),
+            attachment("attachments/4", 30, "memos/7"),
+            serde_json::from_value(link).unwrap(),
+        ];
+        let memos = BTreeSet::from(["memos/1".to_string()]);
+        let reports = review(&attachments, &memos, Some(1_000));
+        assert_eq!(
+            reports.iter().map(|report| (report.name.as_str(), report.flags.as_slice())).collect::<Vec<_>>(),
+            [
+                ("attachments/2", [Flag::Oversized].as_slice()),
+                ("attachments/4", &[Flag::Orphaned]),
+                ("attachments/3", &[Flag::Unattached]),
+                ("attachments/1", &[]),
+            ],
+        );
+        assert!(review(&attachments, &memos, None)[0].flags.is_empty());
+    }
+}
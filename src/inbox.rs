@@ -0,0 +1,155 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Bridge from the Memos inbox of the signed-in user to the MCP sessions. The inbox of every server
+// is polled, and each new unread notification, e.g. a comment on one of the user's memos, is
+// broadcast to the sessions, which send it to their client as a log message. An agent can then
+// tell the user about it without being asked. The notifications already in the inbox when the
+// server starts are not sent.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::memos::{Backend, Servers};
+
+/// Notifications kept for MCP sessions that fall behind before older ones are dropped.
+const INBOX_BUFFER: usize = 64;
+
+/// A new notification in the inbox of the user of a server.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InboxEvent {
+    pub server: String,
+    /// The notification, e.g. `users/1/notifications/2`.
+    pub notification: String,
+    /// What happened, e.g. `MEMO_COMMENT`.
+    pub kind: String,
+    pub sender: String,
+    /// The memo commented on, for comments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// The comment memo, for comments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_time: Option<DateTime<Utc>>,
+    /// The notification in a sentence, for the client to show.
+    pub message: String,
+}
+
+/// The broadcast of inbox notifications, which every MCP session subscribes to.
+pub type Inbox = broadcast::Sender<InboxEvent>;
+
+pub fn inbox() -> Inbox {
+    broadcast::channel(INBOX_BUFFER).0
+}
+
+/// The notifications seen so far, by server. A server is missing until its inbox was first read.
+#[derive(Debug, Default)]
+pub struct Seen(BTreeMap<String, BTreeSet<String>>);
+
+/// Polls the inbox of every server every `interval` and broadcasts the new notifications.
+pub async fn run(servers: Arc<Servers>, inbox: Inbox, interval: Duration) {
+    let mut seen = Seen::default();
+    loop {
+        for (name, server) in servers.iter() {
+            match poll(name, server, &mut seen).await {
+                Ok(events) => {
+                    for event in events {
+                        // Fails only while no session is open to hear it.
+                        let _ = inbox.send(event);
+                    }
+                }
+                Err(e) => warn!("Failed to read the inbox of server {}: {}", name, e),
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// The unread notifications of server `name` not in `seen`, which records them. None the first time
+/// a server is polled.
+pub async fn poll(name: &str, server: &dyn Backend, seen: &mut Seen) -> Result<Vec<InboxEvent>> {
+    let me = server.get_current_user().await?;
+    let notifications = server.list_user_notifications(&me.name).await?;
+    let first = !seen.0.contains_key(name);
+    let seen = seen.0.entry(name.to_string()).or_default();
+    let mut events = Vec::new();
+    for notification in notifications {
+        if !notification.is_unread() || !seen.insert(notification.name.clone()) || first {
+            continue;
+        }
+        let comment = match notification.activity_id {
+            Some(id) if notification.kind == "MEMO_COMMENT" => match server.get_memo_comment_activity(id).await {
+                Ok(comment) => comment,
+                Err(e) => {
+                    warn!("Failed to look up activity {} of {}: {}", id, notification.name, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+        let message = match &comment {
+            Some(comment) => format!("{} commented on {} with {}", notification.sender, comment.related_memo, comment.memo),
+            None => format!("New {} notification from {}", notification.kind, notification.sender),
+        };
+        events.push(InboxEvent {
+            server: name.to_string(),
+            notification: notification.name,
+            kind: notification.kind,
+            sender: notification.sender,
+            memo: comment.as_ref().map(|comment| comment.related_memo.clone()),
+            comment: comment.map(|comment| comment.memo),
+            create_time: notification.create_time,
+            message,
+        });
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[tokio::test]
+    async fn test_poll() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET")).and(path("/api/v1/users/1/notifications"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"notifications": [
+                mock::notification("users/1/notifications/1", "UNREAD", 6),
+            ]})))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&mock).await;
+        mock::mount_memos_api(&mock).await;
+
+        let mut seen = Seen::default();
+        // What is in the inbox at the start is not reported.
+        assert!(poll("default", &server, &mut seen).await.unwrap().is_empty());
+        let events = poll("default", &server, &mut seen).await.unwrap();
+        assert_eq!(events, [InboxEvent {
+            server: "default".to_string(),
+            notification: "users/1/notifications/2".to_string(),
+            kind: "MEMO_COMMENT".to_string(),
+            sender: "users/2".to_string(),
+            memo: Some("memos/1".to_string()),
+            comment: Some("memos/9".to_string()),
+            create_time: Some("2025-01-31T08:00:00Z".parse().unwrap()),
+            message: "users/2 commented on memos/1 with memos/9".to_string(),
+        }]);
+        assert!(poll("default", &server, &mut seen).await.unwrap().is_empty());
+    }
+}
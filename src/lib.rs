@@ -0,0 +1,102 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+//! A typed client for the Memos API v1 in [`memos`], and the MCP server built on top of it.
+//!
+//! Depend on this crate with `default-features = false` to get just the client: the server
+//! modules and their dependencies (rmcp, axum, SQLite, tantivy, ...) sit behind the `server`
+//! feature, which the `mcp-memos` binary requires.
+//!
+//! ```no_run
+//! use mcp_memos::memos::{Server, service::note::{Note, NoteService}};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let server = Server::new("localhost:5230", "memos_pat_...")?;
+//! let note = Note::builder().content("Hello from Rust").tag("example").pinned(true).build();
+//! let note = server.create_note(&note).await?;
+//! println!("created {:?}", note.name);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod memos;
+pub mod redact;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+
+#[cfg(feature = "server")]
+pub mod audit;
+#[cfg(feature = "server")]
+pub mod backup;
+#[cfg(feature = "server")]
+pub mod bookmark;
+#[cfg(feature = "server")]
+pub mod calendar;
+#[cfg(feature = "server")]
+pub mod cli;
+#[cfg(feature = "server")]
+pub mod config;
+#[cfg(feature = "server")]
+pub mod custom_tools;
+#[cfg(feature = "server")]
+pub mod dates;
+#[cfg(feature = "server")]
+pub mod diff;
+#[cfg(feature = "server")]
+pub mod digest;
+#[cfg(feature = "server")]
+pub mod duplicates;
+#[cfg(feature = "server")]
+pub mod embedding;
+#[cfg(feature = "server")]
+pub mod feed;
+#[cfg(feature = "server")]
+pub mod formatter;
+#[cfg(feature = "server")]
+pub mod front_matter;
+#[cfg(feature = "server")]
+pub mod graph;
+#[cfg(feature = "server")]
+pub mod housekeeping;
+#[cfg(feature = "server")]
+pub mod inbox;
+#[cfg(feature = "server")]
+pub mod links;
+#[cfg(feature = "server")]
+pub mod macros;
+#[cfg(feature = "server")]
+pub mod mcp;
+#[cfg(feature = "server")]
+pub mod mirror;
+#[cfg(feature = "server")]
+pub mod ocr;
+#[cfg(feature = "server")]
+pub mod reactions;
+#[cfg(feature = "server")]
+pub mod recurring;
+#[cfg(feature = "server")]
+pub mod render;
+#[cfg(feature = "server")]
+pub mod review;
+#[cfg(feature = "server")]
+pub mod rotation;
+#[cfg(feature = "server")]
+pub mod search;
+#[cfg(feature = "server")]
+pub mod sections;
+#[cfg(feature = "server")]
+pub mod tag_digest;
+#[cfg(feature = "server")]
+pub mod tags;
+#[cfg(feature = "server")]
+pub mod tasks;
+#[cfg(feature = "server")]
+pub mod undo;
+#[cfg(feature = "server")]
+pub mod vault;
+#[cfg(feature = "server")]
+pub mod webhook;
+#[cfg(feature = "server")]
+pub mod workspace;
@@ -0,0 +1,124 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Dead links in memo content, found by `check_memo_links`. Every distinct http(s) URL is requested
+// once with HEAD, or with GET where the site does not allow HEAD, a few at a time. A link is broken
+// when the request fails or the final response, after redirects, is an error status. Links to
+// loopback, link-local and private addresses are refused like bookmarks, unless
+// `bookmark_private_urls` is set.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use regex::Regex;
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::bookmark;
+
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"https?://[^\s<>()\[\]"'`]+"#).unwrap());
+
+/// Longest wait for a response to a link.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The distinct http(s) URLs in `content`, in the order they first appear.
+pub fn urls(content: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    for url in URL.find_iter(content) {
+        // Punctuation ending a sentence is not part of the link.
+        let url = url.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?']).to_string();
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkStatus {
+    pub url: String,
+    /// The memos containing the link.
+    pub memos: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LinkStatus {
+    pub fn is_broken(&self) -> bool {
+        self.error.is_some() || self.status.is_some_and(|status| status >= 400)
+    }
+}
+
+/// Requests each of `links`, mapping URLs to the memos containing them, with at most `concurrency`
+/// requests at a time, also on private addresses when `private_addresses`. The result is in the order
+/// of the URLs.
+pub async fn check(links: BTreeMap<String, Vec<String>>, concurrency: usize, private_addresses: bool) -> Vec<LinkStatus> {
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut requests = JoinSet::new();
+    for (url, memos) in links {
+        let permits = permits.clone();
+        requests.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let (status, error) = match request(&url, private_addresses).await {
+                Ok(status) => (Some(status.as_u16()), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            LinkStatus { url, memos, status, error }
+        });
+    }
+    let mut statuses = requests.join_all().await;
+    statuses.sort_by(|a, b| a.url.cmp(&b.url));
+    statuses
+}
+
+async fn request(url: &str, private_addresses: bool) -> Result<StatusCode> {
+    let status = bookmark::send(Method::HEAD, url, private_addresses, TIMEOUT).await?.status();
+    if status == StatusCode::METHOD_NOT_ALLOWED || status == StatusCode::NOT_IMPLEMENTED {
+        return Ok(bookmark::send(Method::GET, url, private_addresses, TIMEOUT).await?.status());
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{method, path}};
+
+    use super::*;
+
+    #[test]
+    fn test_urls() {
+        let content = "See [docs](https://example.com/docs?a=1), https://example.com/docs?a=1 and <http://x.org/a_(b>.\nftp://no";
+        assert_eq!(urls(content), ["https://example.com/docs?a=1", "http://x.org/a_"]);
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let mock = MockServer::start().await;
+        Mock::given(method("HEAD")).and(path("/ok")).respond_with(ResponseTemplate::new(200)).mount(&mock).await;
+        Mock::given(method("HEAD")).and(path("/gone")).respond_with(ResponseTemplate::new(404)).mount(&mock).await;
+        Mock::given(method("HEAD")).and(path("/get-only")).respond_with(ResponseTemplate::new(405)).mount(&mock).await;
+        Mock::given(method("GET")).and(path("/get-only")).respond_with(ResponseTemplate::new(200)).mount(&mock).await;
+
+        let links: BTreeMap<_, _> = ["/ok", "/gone", "/get-only"]
+            .map(|link| (format!("{}{}", mock.uri(), link), vec!["memos/1".to_string()]))
+            .into_iter()
+            .chain([("http://127.0.0.1:1/refused".to_string(), vec!["memos/2".to_string()])])
+            .collect();
+        let statuses = check(links.clone(), 2, true).await;
+        let broken = statuses.iter().filter(|status| status.is_broken()).map(|status| status.url.rsplit('/').next().unwrap()).collect::<Vec<_>>();
+        assert_eq!(broken, ["refused", "gone"]);
+        assert_eq!(statuses.iter().find(|status| status.url.ends_with("/get-only")).unwrap().status, Some(200));
+
+        // Without bookmark_private_urls the loopback mock is never requested.
+        let statuses = check(links, 2, false).await;
+        assert!(statuses.iter().all(|status| status.error.as_ref().is_some_and(|error| error.contains("private address"))));
+    }
+}
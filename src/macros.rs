@@ -0,0 +1,410 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Macros declared in the config file as `[[macros]]`: named sequences of memo operations run as one
+// by `run_macro`, e.g. a `capture` that creates a memo, tags and pins it, then links it to the
+// latest daily note. Every step is undone when a later one fails, newest first, so that a failed
+// macro leaves no half-made memo behind. The arguments of a call fill the `{param}` placeholders of
+// the steps, and `{date}` is the current date of the user.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::config::{Macro, MacroStep};
+use crate::custom_tools::placeholders;
+use crate::memos::{
+    Backend, MemoName,
+    service::note::{Note, Relation, RelationType, UpdateNote},
+};
+
+/// What a macro did, for the response of `run_macro`.
+#[derive(Debug, Serialize)]
+pub struct Outcome {
+    /// The memo the macro worked on, as it is afterwards.
+    pub memo: Note,
+    /// What each step did, in order.
+    pub steps: Vec<String>,
+}
+
+/// What undoes a step of a macro.
+enum Undo {
+    /// Deletes a memo or comment the macro created.
+    Delete(MemoName),
+    /// Writes back the memo as it was before the macro.
+    Restore(Box<Note>),
+    /// Sets back the references of the memo.
+    Relations(MemoName, Vec<Relation>),
+}
+
+/// The memo a macro works on, and the undo entries recorded for it so far.
+/// A memo the macro created needs neither: deleting it undoes every change to it.
+struct Target {
+    note: Note,
+    restore_recorded: bool,
+    relations_recorded: bool,
+}
+
+/// Checks a declaration, `earlier` being the macros declared before it.
+pub fn validate(entry: &Macro, earlier: &[Macro]) -> Result<(), String> {
+    if entry.name.is_empty() || !entry.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("name `{}` must be letters, digits, `_` and `-`", entry.name));
+    }
+    if earlier.iter().any(|other| other.name == entry.name) {
+        return Err(format!("name `{}` is used by another macro", entry.name));
+    }
+    if entry.steps.is_empty() {
+        return Err("there are no steps".to_string());
+    }
+    if entry.params.contains_key("date") {
+        return Err("parameter `date` is reserved for the current date".to_string());
+    }
+    for (index, step) in entry.steps.iter().enumerate() {
+        if index > 0 && matches!(step, MacroStep::Create { .. }) {
+            return Err(format!("step {} creates a memo, only the first step can", index + 1));
+        }
+        if let MacroStep::Link { to, to_latest_tagged } = step
+            && to.is_some() == to_latest_tagged.is_some()
+        {
+            return Err(format!("step {} must link either `to` a memo or `to_latest_tagged`", index + 1));
+        }
+        if let Some(name) = templates(step)
+            .into_iter()
+            .flat_map(placeholders)
+            .find(|name| *name != "date" && !entry.params.contains_key(*name))
+        {
+            return Err(format!("placeholder `{{{}}}` of step {} names no parameter in `params`", name, index + 1));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the macro creates the memo it works on, rather than working on the memo of the call.
+pub fn creates_memo(entry: &Macro) -> bool {
+    matches!(entry.steps.first(), Some(MacroStep::Create { .. }))
+}
+
+/// Runs the steps of `entry` on `memo`, or on the memo its first step creates. When a step fails,
+/// the earlier ones are undone and the error tells the failed step and how the rollback went.
+pub async fn run(
+    server: &dyn Backend,
+    entry: &Macro,
+    arguments: &BTreeMap<String, String>,
+    memo: Option<MemoName>,
+    today: NaiveDate,
+) -> Result<Outcome> {
+    if let Some(name) = arguments.keys().find(|name| !entry.params.contains_key(*name)) {
+        return Err(anyhow::anyhow!("Macro {} has no parameter {}", entry.name, name));
+    }
+    if let Some(name) = entry.params.keys().find(|name| !arguments.contains_key(*name)) {
+        return Err(anyhow::anyhow!("Missing argument {} of macro {}", name, entry.name));
+    }
+    let mut target = match (memo, creates_memo(entry)) {
+        (Some(_), true) => return Err(anyhow::anyhow!("Macro {} creates its memo, do not pass `memo`", entry.name)),
+        (None, false) => return Err(anyhow::anyhow!("Macro {} works on an existing memo, pass it as `memo`", entry.name)),
+        (Some(name), false) => Some(Target {
+            note: server.get_note(&name).await?,
+            restore_recorded: false,
+            relations_recorded: false,
+        }),
+        (None, true) => None,
+    };
+
+    let mut undo = Vec::new();
+    let mut steps = Vec::new();
+    for (index, step) in entry.steps.iter().enumerate() {
+        match apply(server, step, arguments, today, &mut target, &mut undo).await {
+            Ok(done) => steps.push(done),
+            Err(e) => {
+                let rollback = rollback(server, undo).await;
+                let message = format!("Step {} ({}) of macro {} failed: {}. {}", index + 1, action(step), entry.name, e, rollback);
+                return Err(e.context(message));
+            }
+        }
+    }
+    let memo = target.map(|target| target.note).ok_or_else(|| anyhow::anyhow!("Macro {} has no memo", entry.name))?;
+    Ok(Outcome { memo, steps })
+}
+
+/// Runs one step, recording what undoes it. Returns what it did.
+async fn apply(
+    server: &dyn Backend,
+    step: &MacroStep,
+    arguments: &BTreeMap<String, String>,
+    today: NaiveDate,
+    target: &mut Option<Target>,
+    undo: &mut Vec<Undo>,
+) -> Result<String> {
+    if let MacroStep::Create { content, visibility } = step {
+        let mut note = Note::new(&fill(content, arguments, today));
+        if let Some(visibility) = visibility {
+            note.visibility = visibility.clone();
+        }
+        let created = server.create_note(&note).await?;
+        let name = created.memo_name()?;
+        undo.push(Undo::Delete(name.clone()));
+        *target = Some(Target { note: created, restore_recorded: true, relations_recorded: true });
+        return Ok(format!("created {}", name));
+    }
+    let target = target.as_mut().ok_or_else(|| anyhow::anyhow!("There is no memo to work on"))?;
+    let name = target.note.memo_name()?;
+
+    let mut update = UpdateNote::new(name.clone());
+    let done = match step {
+        MacroStep::Create { .. } => unreachable!("handled above"),
+        MacroStep::Append { content } => {
+            update.content = Some(format!("{}\n\n{}", target.note.content.trim_end(), fill(content, arguments, today)));
+            format!("appended to {}", name)
+        }
+        MacroStep::Tag { tags } => {
+            let missing = tags
+                .iter()
+                .map(|tag| format!("#{}", fill(tag, arguments, today).trim_start_matches('#')))
+                .filter(|tag| !target.note.content.split_whitespace().any(|word| word == tag))
+                .collect::<Vec<_>>();
+            if missing.is_empty() {
+                return Ok(format!("{} already has the tags", name));
+            }
+            update.content = Some(format!("{}\n\n{}", target.note.content.trim_end(), missing.join(" ")));
+            format!("tagged {} with {}", name, missing.join(" "))
+        }
+        MacroStep::Pin { pinned } => {
+            update.pinned = Some(*pinned);
+            format!("{} {}", if *pinned { "pinned" } else { "unpinned" }, name)
+        }
+        MacroStep::SetVisibility { visibility } => {
+            update.visibility = Some(visibility.clone());
+            format!("set the visibility of {} to {:?}", name, visibility)
+        }
+        MacroStep::Link { to, to_latest_tagged } => {
+            let related = match (to, to_latest_tagged) {
+                (Some(to), _) => fill(to, arguments, today).parse::<MemoName>()?,
+                (None, Some(tag)) => latest_tagged(server, &fill(tag, arguments, today), &name).await?,
+                (None, None) => return Err(anyhow::anyhow!("The step links to no memo")),
+            };
+            let mut relations = references(&target.note, &name);
+            if !target.relations_recorded {
+                undo.push(Undo::Relations(name.clone(), relations.clone()));
+                target.relations_recorded = true;
+            }
+            relations.push(Relation::new(&name, &related, RelationType::Reference));
+            server.set_note_relations(&name, &relations).await?;
+            target.note = server.get_note(&name).await?;
+            return Ok(format!("linked {} to {}", name, related));
+        }
+        MacroStep::Comment { content } => {
+            let comment = server.create_note_comment(&name, &Note::new(&fill(content, arguments, today))).await?;
+            let comment = comment.memo_name()?;
+            undo.push(Undo::Delete(comment.clone()));
+            return Ok(format!("commented on {} as {}", name, comment));
+        }
+    };
+    if !target.restore_recorded {
+        undo.push(Undo::Restore(Box::new(target.note.clone())));
+        target.restore_recorded = true;
+    }
+    target.note = server.update_note(&update).await?;
+    Ok(done)
+}
+
+/// Undoes the recorded steps, newest first, and tells how it went.
+async fn rollback(server: &dyn Backend, undo: Vec<Undo>) -> String {
+    let total = undo.len();
+    let mut failures = Vec::new();
+    for entry in undo.into_iter().rev() {
+        let (what, result) = match entry {
+            Undo::Delete(name) => (format!("deleting {}", name), server.delete_note(&name).await),
+            Undo::Restore(note) => {
+                let name = note.name.clone().unwrap_or_default();
+                let result = async { server.update_note(&UpdateNote::from_note(&note)?).await.map(|_| ()) }.await;
+                (format!("restoring {}", name), result)
+            }
+            Undo::Relations(name, relations) => {
+                (format!("resetting the links of {}", name), server.set_note_relations(&name, &relations).await)
+            }
+        };
+        if let Err(e) = result {
+            failures.push(format!("{}: {}", what, e));
+        }
+    }
+    match (total, failures.len()) {
+        (0, _) => "Nothing to roll back.".to_string(),
+        (_, 0) => format!("Rolled back {} change(s).", total),
+        (_, failed) => format!(
+            "Rolled back {} of {} change(s), failed {}",
+            total - failed,
+            total,
+            failures.join("; "),
+        ),
+    }
+}
+
+/// The newest memo tagged `tag`, other than `name`.
+async fn latest_tagged(server: &dyn Backend, tag: &str, name: &MemoName) -> Result<MemoName> {
+    let tag = tag.trim_start_matches('#');
+    server
+        .list_notes()
+        .await?
+        .into_iter()
+        .filter(|note| note.name.as_deref() != Some(name.as_str()) && note.tags.iter().any(|t| t == tag))
+        .max_by_key(|note| note.display_time.or(note.create_time))
+        .ok_or_else(|| anyhow::anyhow!("No memo is tagged #{}", tag))?
+        .memo_name()
+}
+
+/// The references `note` holds, as set with `set_note_relations`.
+fn references(note: &Note, name: &MemoName) -> Vec<Relation> {
+    note.relations
+        .iter()
+        .filter(|relation| relation.relation_type == RelationType::Reference && relation.memo["name"] == name.as_str())
+        .filter_map(|relation| Some(Relation::new(name, &relation.related_memo["name"].as_str()?.parse().ok()?, RelationType::Reference)))
+        .collect()
+}
+
+/// Fills in `{date}` and the `{param}` placeholders of a template with the arguments of a call.
+fn fill(template: &str, arguments: &BTreeMap<String, String>, today: NaiveDate) -> String {
+    let mut filled = template.replace("{date}", &today.format("%Y-%m-%d").to_string());
+    for (name, value) in arguments {
+        filled = filled.replace(&format!("{{{}}}", name), value);
+    }
+    filled
+}
+
+/// The templates of a step.
+fn templates(step: &MacroStep) -> Vec<&str> {
+    match step {
+        MacroStep::Create { content, .. } | MacroStep::Append { content } | MacroStep::Comment { content } => vec![content],
+        MacroStep::Tag { tags } => tags.iter().map(String::as_str).collect(),
+        MacroStep::Link { to, to_latest_tagged } => to.iter().chain(to_latest_tagged).map(String::as_str).collect(),
+        MacroStep::Pin { .. } | MacroStep::SetVisibility { .. } => vec![],
+    }
+}
+
+/// The `action` of a step, as it is written in the config file.
+fn action(step: &MacroStep) -> &'static str {
+    match step {
+        MacroStep::Create { .. } => "create",
+        MacroStep::Append { .. } => "append",
+        MacroStep::Tag { .. } => "tag",
+        MacroStep::Pin { .. } => "pin",
+        MacroStep::SetVisibility { .. } => "set_visibility",
+        MacroStep::Link { .. } => "link",
+        MacroStep::Comment { .. } => "comment",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    use super::*;
+    use crate::memos::mock;
+
+    fn capture() -> Macro {
+        toml::from_str(r#"
+            name = "capture"
+            description = "Capture a thought and file it under today."
+            params = { text = "The thought.", daily = "The daily note, e.g. memos/1." }
+            steps = [
+                { action = "create", content = "{text}", visibility = "PROTECTED" },
+                { action = "tag", tags = ["inbox", "{date}"] },
+                { action = "pin" },
+                { action = "comment", content = "Captured on {date}" },
+                { action = "link", to = "{daily}" },
+            ]
+        "#).unwrap()
+    }
+
+    fn arguments() -> BTreeMap<String, String> {
+        BTreeMap::from([("text".to_string(), "Call the plumber".to_string()), ("daily".to_string(), "memos/1".to_string())])
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 2, 3).unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        let entry = capture();
+        assert_eq!(validate(&entry, &[]), Ok(()));
+        assert!(validate(&entry, std::slice::from_ref(&entry)).unwrap_err().contains("another macro"));
+
+        let mut invalid = entry.clone();
+        invalid.steps.push(MacroStep::Create { content: "Again".to_string(), visibility: None });
+        assert!(validate(&invalid, &[]).unwrap_err().contains("only the first step"));
+        let mut invalid = entry.clone();
+        invalid.steps.push(MacroStep::Link { to: None, to_latest_tagged: None });
+        assert!(validate(&invalid, &[]).unwrap_err().contains("either `to`"));
+        let mut invalid = entry;
+        invalid.params.remove("daily");
+        assert!(validate(&invalid, &[]).unwrap_err().contains("`{daily}`"));
+    }
+
+    #[tokio::test]
+    async fn test_run_macro() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST")).and(path("/api/v1/memos/3/relations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .mount(&mock).await;
+        mock::mount_memos_api(&mock).await;
+
+        let outcome = run(&server, &capture(), &arguments(), None, today()).await.unwrap();
+        assert_eq!(outcome.memo.name.as_deref(), Some("memos/3"));
+        assert_eq!(outcome.steps, [
+            "created memos/3",
+            "tagged memos/3 with #inbox #2025-02-03",
+            "pinned memos/3",
+            "commented on memos/3 as memos/9",
+            "linked memos/3 to memos/1",
+        ]);
+        let requests = mock.received_requests().await.unwrap();
+        let relations = requests.iter().find(|request| request.url.path().ends_with("/relations")).unwrap();
+        assert_eq!(
+            relations.body_json::<serde_json::Value>().unwrap()["relations"][0]["relatedMemo"]["name"],
+            "memos/1",
+        );
+
+        let error = run(&server, &capture(), &arguments(), Some("memos/1".parse().unwrap()), today()).await.unwrap_err();
+        assert!(error.to_string().contains("do not pass `memo`"));
+        let error = run(&server, &capture(), &BTreeMap::new(), None, today()).await.unwrap_err();
+        assert_eq!(error.to_string(), "Missing argument daily of macro capture");
+    }
+
+    #[tokio::test]
+    async fn test_run_macro_rolls_back() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST")).and(path("/api/v1/memos/3/relations"))
+            .respond_with(ResponseTemplate::new(500).set_body_json(serde_json::json!({"code": 13, "message": "boom"})))
+            .mount(&mock).await;
+        mock::mount_memos_api(&mock).await;
+
+        let error = run(&server, &capture(), &arguments(), None, today()).await.unwrap_err();
+        assert!(error.to_string().starts_with("Step 5 (link) of macro capture failed: "));
+        assert!(error.to_string().ends_with("Rolled back 2 change(s)."));
+        let deleted = mock.received_requests().await.unwrap()
+            .into_iter()
+            .filter(|request| request.method.as_str() == "DELETE")
+            .map(|request| request.url.path().to_string())
+            .collect::<Vec<_>>();
+        // The comment goes first, then the created memo.
+        assert_eq!(deleted, ["/api/v1/memos/9", "/api/v1/memos/3"]);
+
+        // An existing memo is restored as it was.
+        let mut entry = capture();
+        entry.steps.remove(0);
+        let error = run(&server, &entry, &arguments(), Some("memos/3".parse().unwrap()), today()).await.unwrap_err();
+        assert!(error.to_string().starts_with("Step 4 (link) of macro capture failed: "));
+        let restore = mock.received_requests().await.unwrap()
+            .into_iter()
+            .filter(|request| request.method.as_str() == "PATCH" && request.url.path() == "/api/v1/memos/3")
+            .last()
+            .unwrap();
+        assert_eq!(restore.body_json::<serde_json::Value>().unwrap()["content"], "Content of memos/3");
+        assert_eq!(restore.body_json::<serde_json::Value>().unwrap()["pinned"], false);
+    }
+}
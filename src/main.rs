@@ -3,58 +3,415 @@
 // Date: 2025-12-28
 // License: Proprietary
 
-use std::net::SocketAddr;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
-use tracing::info;
+use tracing::{info, warn};
 use anyhow::Result;
+use clap::Parser;
+use tracing_subscriber::{EnvFilter, Layer, fmt::format::FmtSpan, layer::SubscriberExt, reload, util::SubscriberInitExt};
+use rmcp::{ServiceExt, transport::stdio};
 use rmcp::transport::streamable_http_server::StreamableHttpService;
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
-use axum::{routing::any_service, Router};
-use crate::{mcp::MemoMCP, memos::service::auth::AuthService};
-
-mod memos;
-mod mcp;
+use axum::{routing::{any_service, get, post}, Router};
+use mcp_memos::{
+    backup,
+    calendar::{self, Calendar},
+    cli::{BackupArgs, Cli, Command, ExportArgs, ExportFormat, ImportArgs, LogFormat, MemosArgs, RestoreArgs, ServeArgs, Transport},
+    config::{Config, SharedSettings, read_token_file},
+    digest,
+    mcp::{IdempotencyKeys, MemoMCP},
+    memos::{self, Servers, service::{auth::AuthService, note::NoteService}},
+    embedding::Embedder,
+    inbox,
+    feed::{self, Feed},
+    mirror::Mirror,
+    recurring,
+    redact,
+    rotation::Rotation,
+    tags::TagCache,
+    undo::UndoLog,
+    vault,
+    webhook::{self, Webhook},
+};
+#[cfg(feature = "otel")]
+use mcp_memos::telemetry;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_target(true)
-        .with_line_number(true)
-        .with_level(true)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "info".into())
-        )
-        .init();
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Serve);
+
+    // Stdout carries the protocol for the stdio transport, so logs must go elsewhere.
+    let log_to_stderr = (matches!(&command, Command::Serve) && cli.serve.transport == Transport::Stdio)
+        || matches!(&command, Command::Export(ExportArgs { out: None, .. }));
+
+    let (filter, filter_handle) = reload::Layer::new(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| cli.log_level.as_str().into())
+    );
+    let writer = redact::RedactingMakeWriter::new(log_to_stderr);
+    let fmt_layer = match cli.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_target(true)
+            .with_line_number(true)
+            .with_level(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(writer)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_target(true)
+            .with_line_number(true)
+            .with_current_span(true)
+            .with_span_list(true)
+            .flatten_event(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(writer)
+            .boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(fmt_layer);
+
+    #[cfg(feature = "otel")]
+    let (telemetry, otel_layer) = match telemetry::Telemetry::from_env() {
+        Ok(Some((telemetry, layer))) => (Some(telemetry), Some(layer)),
+        Ok(None) => (None, None),
+        Err(e) => {
+            eprintln!("Failed to set up OTLP export: {}", e);
+            (None, None)
+        }
+    };
+    #[cfg(feature = "otel")]
+    let registry = registry.with(otel_layer);
+    registry.init();
+
+    let set_log_level = move |level: &str| {
+        if std::env::var("RUST_LOG").is_ok() {
+            return;
+        }
+        match EnvFilter::try_new(level) {
+            Ok(level) => {
+                if let Err(e) = filter_handle.reload(level) {
+                    warn!("Failed to apply log level: {}", e);
+                }
+            }
+            Err(e) => warn!("Invalid log level {}: {}", level, e),
+        }
+    };
+
+    let config = match Config::load(&cli.memos) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(level) = &config.settings.log_level {
+        set_log_level(level);
+    }
+
+    let result = match command {
+        Command::Serve => serve(config, cli.serve, cli.memos, set_log_level).await,
+        Command::Check => check(&config).await,
+        Command::Export(args) => export(&config, args).await,
+        Command::Import(args) => import(&config, args).await,
+        Command::Backup(args) => backup_memos(&config, args).await,
+        Command::Restore(args) => restore_memos(&config, args).await,
+    };
+
+    #[cfg(feature = "otel")]
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+    result
+}
+
+async fn serve(
+    config: Config,
+    args: ServeArgs,
+    memos_args: MemosArgs,
+    set_log_level: impl Fn(&str) + Send + 'static,
+) -> Result<()> {
+    let max_wait = Duration::from_secs(args.startup_max_wait);
+    let mut servers = BTreeMap::new();
+
+    for (name, profile) in config.servers {
+        info!("Verifying connection to memos server {} at {}...", name, profile.host);
+        if let Err(e) = wait_for_memos(&profile.host, &profile.token, max_wait).await {
+            if !args.start_degraded {
+                return Err(e);
+            }
+            // Every tool call makes its own requests, so tools fail until the server is back and
+            // then work again without a reconnect.
+            warn!("Starting in degraded mode, memos server {} is unreachable: {}", name, e);
+        }
+
+        let server = profile.server()?;
+        if let Some(path) = profile.token_file {
+            if let Some(days) = profile.rotate_token_days {
+                tokio::spawn(Rotation::new(path.clone(), days).run(name.clone(), server.clone()));
+            }
+            tokio::spawn(watch_token_file(path, server.clone()));
+        }
+        servers.insert(name, server);
+    }
 
+    info!("Initializing Memo MCP Service with default server {}...", config.default_server);
+    let clients = servers.clone();
+    let servers = Arc::new(Servers::new(&config.default_server, servers));
+    let settings: SharedSettings = Arc::new(RwLock::new(config.settings));
 
-    let host = std::env::var("MEMOS_HOST").unwrap();
-    let token = std::env::var("MEMOS_TOKEN").unwrap();
+    let mirror = match &args.mirror_db {
+        Some(path) => {
+            let mirror = Arc::new(Mirror::open(path)?.with_embedder(Embedder::from_args(&args)?));
+            info!("Mirroring memos to {}...", path.display());
+            tokio::spawn(mirror.clone().run(servers.clone(), Duration::from_secs(args.mirror_interval.max(1))));
+            Some(mirror)
+        }
+        None => {
+            if args.embeddings.is_some() {
+                warn!("Ignoring EMBEDDINGS_PROVIDER, semantic search requires MEMOS_MIRROR_DB");
+            }
+            None
+        }
+    };
 
-    info!("Verifying connection to memos server at {}...", host);
-    {
-        let server = memos::Server::new(&host, &token);
-        let me = server.get_current_user().await?;
-        info!("Successfully authenticated to memos server as user: {}", me.username);
+    let tags = Arc::new(TagCache::default());
+    if args.tag_refresh_interval > 0 {
+        tokio::spawn(tags.clone().run(servers.clone(), Duration::from_secs(args.tag_refresh_interval)));
     }
 
-    info!("Initializing Memo MCP Service for host {}...", host);
+    let undo = Arc::new(UndoLog::default());
 
+    tokio::spawn(backup::schedule::run(servers.clone(), settings.clone()));
+    tokio::spawn(recurring::run(servers.clone(), settings.clone()));
+    tokio::spawn(digest::run(servers.clone(), settings.clone()));
+    let inbox = (args.inbox_poll_interval > 0).then(|| {
+        let inbox = inbox::inbox();
+        tokio::spawn(inbox::run(servers.clone(), inbox.clone(), Duration::from_secs(args.inbox_poll_interval)));
+        inbox
+    });
+
+    #[cfg(unix)]
+    tokio::spawn(reload_on_sighup(memos_args, clients, settings.clone(), set_log_level));
+
+    if args.transport == Transport::Stdio {
+        info!("Starting Memo MCP Server on stdio...");
+        MemoMCP::new(servers, settings, mirror, tags, undo)
+            .with_inbox(inbox)
+            .serve(stdio())
+            .await?
+            .waiting()
+            .await?;
+        info!("Shutting down Memo MCP Server...");
+        return Ok(());
+    }
+
+    let feed = args.feed_url.as_deref().map(|url| {
+        Arc::new(Feed::new(servers.clone(), url, &args.feed_title, Duration::from_secs(args.feed_ttl)))
+    });
+    let calendar = args.calendar_token.as_deref().map(|token| {
+        Arc::new(Calendar::new(servers.clone(), settings.clone(), token, Duration::from_secs(args.feed_ttl)))
+    });
+    let webhook = args.webhook_secret.as_deref().map(|secret| {
+        let webhook = Webhook::new(secret, webhook::events(), servers.clone(), tags.clone())
+            .with_mirror(mirror.clone())
+            .with_feeds(feed.clone(), calendar.clone());
+        Arc::new(webhook)
+    });
+    let events = webhook.as_ref().map(|webhook| webhook.events());
+    let idempotency_keys = Arc::new(IdempotencyKeys::default());
     let mcp_service = StreamableHttpService::new(
-        move || Ok(MemoMCP::new(&host, &token)),
+        move || {
+            let memo_mcp = MemoMCP::new(servers.clone(), settings.clone(), mirror.clone(), tags.clone(), undo.clone());
+            Ok(memo_mcp.with_events(events.clone()).with_inbox(inbox.clone()).with_idempotency_keys(idempotency_keys.clone()))
+        },
         LocalSessionManager::default().into(),
         Default::default(),
     );
 
     info!("Starting Memo MCP Server...");
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/mcp", any_service(mcp_service));
+    if let Some(feed) = feed {
+        info!("Serving the Atom feed of public memos at /feed.xml...");
+        app = app.merge(Router::new().route("/feed.xml", get(feed::serve)).with_state(feed));
+    }
+    if let Some(calendar) = calendar {
+        info!("Serving the reminder calendar at /calendar.ics...");
+        app = app.merge(Router::new().route("/calendar.ics", get(calendar::serve)).with_state(calendar));
+    }
+    if let Some(webhook) = webhook {
+        info!("Accepting Memos webhooks at /webhooks/memos/<server>...");
+        app = app.merge(Router::new().route("/webhooks/memos/{server}", post(webhook::receive)).with_state(webhook));
+    }
+
+    let listener = tokio::net::TcpListener::bind(args.bind).await?;
+    info!("Server listening on {}", args.bind);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    info!("Server listening on {}", addr);
-    
-    axum::serve(listener, app).await?;    
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async { tokio::signal::ctrl_c().await.ok(); })
+        .await?;
     info!("Shutting down Memo MCP Server...");
     Ok(())
 }
+
+async fn check(config: &Config) -> Result<()> {
+    for (name, profile) in &config.servers {
+        info!("Checking connection to memos server {} at {}...", name, profile.host);
+        wait_for_memos(&profile.host, &profile.token, Duration::ZERO).await?;
+    }
+    Ok(())
+}
+
+async fn export(config: &Config, args: ExportArgs) -> Result<()> {
+    let profile = &config.servers[&config.default_server];
+    let server = profile.server()?;
+    if let (ExportFormat::Obsidian, Some(dir)) = (args.format, &args.out) {
+        let export = vault::export_vault(&server, dir).await?;
+        for error in &export.errors {
+            warn!("Skipped attachment {}", error);
+        }
+        info!("Exported {} memos and {} attachments to {}", export.memos, export.attachments, dir.display());
+        return Ok(());
+    }
+
+    let notes = server.list_notes().await?;
+    let data = serde_json::to_string_pretty(&notes)?;
+
+    match args.out {
+        Some(path) => {
+            std::fs::write(&path, data)?;
+            info!("Exported {} memos to {}", notes.len(), path.display());
+        }
+        None => println!("{}", data),
+    }
+    Ok(())
+}
+
+async fn import(config: &Config, args: ImportArgs) -> Result<()> {
+    let profile = &config.servers[&config.default_server];
+    let server = profile.server()?;
+    let import = vault::import_vault(&server, &args.dir).await?;
+    for error in &import.errors {
+        warn!("Failed to import {}", error);
+    }
+    info!(
+        "Imported {} memos with {} attachments from {}, skipped {} imported before",
+        import.imported, import.attachments, args.dir.display(), import.skipped
+    );
+    Ok(())
+}
+
+async fn backup_memos(config: &Config, args: BackupArgs) -> Result<()> {
+    let profile = &config.servers[&config.default_server];
+    let server = profile.server()?;
+    let partial = args.out.with_extension("jsonl.partial");
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+    let summary = backup::backup(&server, &mut out).await?;
+    drop(out);
+    std::fs::rename(&partial, &args.out)?;
+    info!("Backed up {} memos and {} comments to {}", summary.memos, summary.comments, args.out.display());
+    Ok(())
+}
+
+async fn restore_memos(config: &Config, args: RestoreArgs) -> Result<()> {
+    let profile = &config.servers[&config.default_server];
+    let server = profile.server()?;
+    let input = std::io::BufReader::new(std::fs::File::open(&args.input)?);
+    let summary = backup::restore(&server, input, args.conflict).await?;
+    for error in &summary.errors {
+        warn!("Failed to restore {}", error);
+    }
+    info!(
+        "Restored {} memos, {} comments, {} relations and {} attachments from {}, overwrote {} and skipped {} memos",
+        summary.memos, summary.comments, summary.relations, summary.attachments, args.input.display(), summary.overwritten, summary.skipped
+    );
+    if !summary.errors.is_empty() {
+        return Err(anyhow::anyhow!("{} parts of the backup could not be restored", summary.errors.len()));
+    }
+    Ok(())
+}
+
+/// Re-reads the configuration on SIGHUP and applies log level, tool allowlist and token changes
+/// without restarting. Adding servers or changing hosts still requires a restart.
+#[cfg(unix)]
+async fn reload_on_sighup(
+    memos_args: MemosArgs,
+    servers: BTreeMap<String, memos::Server>,
+    settings: SharedSettings,
+    set_log_level: impl Fn(&str),
+) {
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        info!("Received SIGHUP, reloading configuration...");
+        let config = match Config::load(&memos_args) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Keeping the current configuration: {}", e);
+                continue;
+            }
+        };
+
+        for (name, profile) in &config.servers {
+            match servers.get(name) {
+                Some(server) => server.set_token(&profile.token),
+                None => warn!("Server {} was added to the configuration, restart to use it", name),
+            }
+        }
+        if let Some(level) = &config.settings.log_level {
+            set_log_level(level);
+        }
+        *settings.write().unwrap() = config.settings;
+        info!("Configuration reloaded");
+    }
+}
+
+/// Polls the token file and swaps the token of the shared server whenever its content changes.
+async fn watch_token_file(path: PathBuf, server: memos::Server) {
+    let mut current = read_token_file(&path).unwrap_or_default();
+    let mut interval = tokio::time::interval(Duration::from_secs(10));
+
+    loop {
+        interval.tick().await;
+        match read_token_file(&path) {
+            Ok(token) if !token.is_empty() && token != current => {
+                server.set_token(&token);
+                current = token;
+                info!("Reloaded memos token from {}", path.display());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+/// Retries the initial authentication check with exponential backoff until `max_wait` elapses.
+async fn wait_for_memos(host: &str, token: &str, max_wait: Duration) -> Result<()> {
+    let server = memos::Server::new(host, token)?;
+    let start = Instant::now();
+    let mut delay = Duration::from_secs(1);
+
+    loop {
+        match server.get_current_user().await {
+            Ok(me) => {
+                info!("Successfully authenticated to memos server as user: {}", me.username);
+                return Ok(());
+            }
+            Err(e) if start.elapsed() + delay < max_wait => {
+                warn!("Memos server not ready ({}), retrying in {:?}...", e, delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(30));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
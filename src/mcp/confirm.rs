@@ -0,0 +1,73 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::memos::MemoName;
+
+/// How long a confirmation token stays valid.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(300);
+
+struct Pending {
+    server: String,
+    names: Vec<MemoName>,
+    expires: Instant,
+}
+
+/// Short-lived tokens confirming that a client saw what a destructive call is about to remove.
+#[derive(Default)]
+pub struct Confirmations {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl Confirmations {
+    /// Issues a token that confirms deleting exactly `names` on `server`.
+    pub fn issue(&self, server: &str, names: &[MemoName]) -> String {
+        let token = format!("{:016x}", RandomState::new().hash_one(Instant::now()));
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.expires > Instant::now());
+        pending.insert(token.clone(), Pending {
+            server: server.to_string(),
+            names: names.to_vec(),
+            expires: Instant::now() + CONFIRMATION_TTL,
+        });
+        token
+    }
+
+    /// Consumes `token` if it was issued for the same memos and has not expired.
+    pub fn redeem(&self, token: &str, server: &str, names: &[MemoName]) -> anyhow::Result<()> {
+        let pending = self.pending.lock().unwrap().remove(token).ok_or_else(|| {
+            anyhow::anyhow!("Unknown or already used confirmation token, call again without one to get a new token")
+        })?;
+        if pending.expires <= Instant::now() {
+            return Err(anyhow::anyhow!("Confirmation token expired, call again without one to get a new token"));
+        }
+        if pending.server != server || pending.names != names {
+            return Err(anyhow::anyhow!("Confirmation token was issued for different memos, call again without one to get a new token"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_redeem() {
+        let confirmations = Confirmations::default();
+        let names = vec!["memos/1".parse().unwrap()];
+
+        let token = confirmations.issue("default", &names);
+        assert!(confirmations.redeem(&token, "default", &["memos/2".parse().unwrap()]).is_err());
+
+        let token = confirmations.issue("default", &names);
+        assert!(confirmations.redeem(&token, "default", &names).is_ok());
+        assert!(confirmations.redeem(&token, "default", &names).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Field selection for the listing tools. A client listing many memos rarely needs their whole
+// content, attachments and reactions, so `fields` trims each memo to the named fields before it is
+// serialized, which keeps the tool output small on large accounts. The summary output keeps just
+// enough to pick the memos worth a `get_memo`: the name, first line, tags, timestamps and what
+// Memos derived from the content, with the timestamps in the time zone of the user. `has` keeps
+// the memos whose derived property has all the given flags, e.g. the ones with unfinished tasks,
+// without reading their content.
+
+use chrono_tz::Tz;
+use serde_json::{Map, Value};
+
+use crate::dates;
+use crate::memos::service::note::{Note, Property};
+
+/// The fields of a memo a listing can be trimmed to, as they are named in its JSON.
+pub const FIELDS: &[&str] = &[
+    "name",
+    "state",
+    "creator",
+    "createTime",
+    "updateTime",
+    "displayTime",
+    "content",
+    "visibility",
+    "tags",
+    "pinned",
+    "attachments",
+    "relations",
+    "reactions",
+    "property",
+    "parent",
+    "snippet",
+    "location",
+    "firstLine",
+];
+
+/// The fields of the summary output.
+pub const SUMMARY_FIELDS: &[&str] = &["name", "firstLine", "tags", "createTime", "updateTime", "property"];
+
+/// A flag of the property Memos derives from the content.
+#[derive(schemars::JsonSchema, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Has {
+    Link,
+    TaskList,
+    Code,
+    IncompleteTasks,
+}
+
+impl Has {
+    fn of(self, property: &Property) -> bool {
+        match self {
+            Has::Link => property.has_link,
+            Has::TaskList => property.has_task_list,
+            Has::Code => property.has_code,
+            Has::IncompleteTasks => property.has_incomplete_tasks,
+        }
+    }
+}
+
+/// Whether the property of `note` has every flag of `has`. Memos older than the property have none.
+pub fn has(note: &Note, has: &[Has]) -> bool {
+    has.iter().all(|flag| note.property.as_ref().is_some_and(|property| flag.of(property)))
+}
+
+/// Characters kept of the first line of content.
+const FIRST_LINE_CHARS: usize = 100;
+
+/// The fields named in the comma-separated `fields`.
+pub fn parse(fields: &str) -> anyhow::Result<Vec<&str>> {
+    let fields = fields.split(',').map(str::trim).filter(|field| !field.is_empty()).collect::<Vec<_>>();
+    if fields.is_empty() {
+        return Err(anyhow::anyhow!("No fields given; use some of {}", FIELDS.join(", ")));
+    }
+    if let Some(field) = fields.iter().find(|field| !FIELDS.contains(field)) {
+        return Err(anyhow::anyhow!("Unknown field {}; use some of {}", field, FIELDS.join(", ")));
+    }
+    Ok(fields)
+}
+
+/// `notes` with only `fields` kept, in the order of `fields`. `firstLine` is the first line of the
+/// content, shortened.
+pub fn select(notes: &[Note], fields: &[&str]) -> Value {
+    notes
+        .iter()
+        .map(|note| {
+            let mut value = serde_json::to_value(note).unwrap_or_default();
+            let first_line = first_line(&note.content);
+            if value["snippet"].as_str().is_none_or(str::is_empty) {
+                value["snippet"] = Value::String(first_line.clone());
+            }
+            value["firstLine"] = Value::String(first_line);
+            let selected = fields
+                .iter()
+                .map(|field| (field.to_string(), value.get(*field).cloned().unwrap_or_default()))
+                .collect::<Map<_, _>>();
+            Value::Object(selected)
+        })
+        .collect()
+}
+
+/// `notes` trimmed to the summary fields, with their creation and update times in `tz`.
+pub fn summary(notes: &[Note], tz: Tz) -> Value {
+    let mut summary = select(notes, SUMMARY_FIELDS);
+    for (memo, note) in summary.as_array_mut().into_iter().flatten().zip(notes) {
+        for (field, time) in [("createTime", note.create_time), ("updateTime", note.update_time)] {
+            if let Some(time) = time {
+                memo[field] = Value::String(dates::format(time, tz));
+            }
+        }
+    }
+    summary
+}
+
+/// The first non-empty line of `content`, shortened to `FIRST_LINE_CHARS`.
+fn first_line(content: &str) -> String {
+    let line = content.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    match line.char_indices().nth(FIRST_LINE_CHARS) {
+        Some((end, _)) => format!("{}…", line[..end].trim_end()),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[test]
+    fn test_select_fields() {
+        let mut memo = mock::memo("memos/1", "\nGrow tomatoes #garden\n\nIn pots.");
+        memo["snippet"] = json!("");
+        let note = serde_json::from_value::<Note>(memo).unwrap();
+        let keys = serde_json::to_value(&note).unwrap().as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys.len() + 1, FIELDS.len());
+        assert!(keys.iter().all(|key| FIELDS.contains(&key.as_str())));
+
+        let fields = parse("name, snippet,tags").unwrap();
+        assert_eq!(
+            select(std::slice::from_ref(&note), &fields),
+            json!([{"name": "memos/1", "snippet": "Grow tomatoes #garden", "tags": []}]),
+        );
+        assert_eq!(
+            select(std::slice::from_ref(&note), SUMMARY_FIELDS)[0]["firstLine"],
+            "Grow tomatoes #garden",
+        );
+        let summary = summary(&[note], "Asia/Kolkata".parse().unwrap());
+        assert_eq!((&summary[0]["createTime"], &summary[0]["updateTime"]), (&json!("2025-01-31T13:30:00+05:30"), &json!("2025-01-31T13:30:00+05:30")));
+        assert!(parse("name,body").unwrap_err().to_string().starts_with("Unknown field body"));
+        assert!(parse(" , ").is_err());
+    }
+
+    #[test]
+    fn test_has() {
+        let mut memo = mock::memo("memos/1", "- [x] Sow\n- [ ] Water");
+        memo["property"] = json!({"hasTaskList": true, "hasIncompleteTasks": true});
+        let note = serde_json::from_value::<Note>(memo).unwrap();
+        assert!(has(&note, &[]));
+        assert!(has(&note, &[Has::TaskList, Has::IncompleteTasks]));
+        assert!(!has(&note, &[Has::IncompleteTasks, Has::Link]));
+        assert!(!has(&Note::new("No property"), &[Has::Code]));
+    }
+}
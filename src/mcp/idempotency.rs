@@ -0,0 +1,123 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::memos::service::note::Note;
+
+/// How long the memo created with an idempotency key is remembered.
+pub const IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug)]
+enum State {
+    /// The create is still running.
+    Pending,
+    Created(Box<Note>),
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// Identifies the request the key was first used with.
+    fingerprint: u64,
+    state: State,
+    expires: Instant,
+}
+
+/// What to do with a create carrying an idempotency key.
+#[derive(Debug)]
+pub enum Claim<'a> {
+    /// The key is new: create the memo, then report it with [`PendingKey::complete`].
+    New(PendingKey<'a>),
+    /// The memo was created before with the same key.
+    Created(Box<Note>),
+}
+
+/// A claimed key whose memo is being created. Dropping it before [`PendingKey::complete`], when the
+/// create fails or the request is cancelled, releases the key so that the create can be retried.
+#[derive(Debug)]
+#[must_use]
+pub struct PendingKey<'a> {
+    keys: &'a IdempotencyKeys,
+    id: Option<(String, String)>,
+}
+
+impl PendingKey<'_> {
+    /// Records `note` as the memo created for the key.
+    pub fn complete(mut self, note: &Note) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        if let Some(entry) = self.keys.entries.lock().unwrap().get_mut(&id) {
+            entry.state = State::Created(Box::new(note.clone()));
+        }
+    }
+}
+
+impl Drop for PendingKey<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.keys.entries.lock().unwrap().remove(&id);
+        }
+    }
+}
+
+/// Client-supplied keys of recent creates, so that a retried create returns the memo made the first
+/// time instead of a duplicate.
+#[derive(Debug, Default)]
+pub struct IdempotencyKeys {
+    entries: Mutex<HashMap<(String, String), Entry>>,
+}
+
+impl IdempotencyKeys {
+    /// Claims `key` on `server` for a create identified by `fingerprint`.
+    pub fn claim(&self, server: &str, key: &str, fingerprint: u64) -> anyhow::Result<Claim<'_>> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires > Instant::now());
+        let id = (server.to_string(), key.to_string());
+        let Some(entry) = entries.get(&id) else {
+            entries.insert(id.clone(), Entry { fingerprint, state: State::Pending, expires: Instant::now() + IDEMPOTENCY_TTL });
+            return Ok(Claim::New(PendingKey { keys: self, id: Some(id) }));
+        };
+        if entry.fingerprint != fingerprint {
+            return Err(anyhow::anyhow!("Idempotency key {} was already used for a different memo", key));
+        }
+        match &entry.state {
+            State::Pending => Err(anyhow::anyhow!("A create with idempotency key {} is still in progress", key)),
+            State::Created(note) => Ok(Claim::Created(note.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_and_complete() {
+        let keys = IdempotencyKeys::default();
+        let Claim::New(pending) = keys.claim("default", "k1", 1).unwrap() else {
+            panic!("expected a new key");
+        };
+        assert!(keys.claim("default", "k1", 1).unwrap_err().to_string().contains("in progress"));
+        let Claim::New(failed) = keys.claim("work", "k1", 1).unwrap() else {
+            panic!("expected a new key");
+        };
+
+        let mut note = Note::new("Buy milk");
+        note.name = Some("memos/3".to_string());
+        pending.complete(&note);
+        match keys.claim("default", "k1", 1).unwrap() {
+            Claim::Created(created) => assert_eq!(created.name.as_deref(), Some("memos/3")),
+            Claim::New(_) => panic!("expected the created memo"),
+        }
+        assert!(keys.claim("default", "k1", 2).unwrap_err().to_string().contains("different memo"));
+
+        // A failed create releases the key.
+        drop(failed);
+        assert!(matches!(keys.claim("work", "k1", 1).unwrap(), Claim::New(_)));
+    }
+}
@@ -3,132 +3,3570 @@
 // Date: 2025-12-28
 // License: Proprietary
 
+mod confirm;
+mod fields;
+mod idempotency;
+#[cfg(test)]
+mod tests;
+
 use rmcp::{
-    ServerHandler, handler::server::{
-        router::tool::ToolRouter,
-        tool::Parameters,
-    }, model::*, schemars, tool, tool_handler, tool_router
+    RoleServer, ServerHandler, handler::server::{
+        router::tool::{ToolRoute, ToolRouter},
+        tool::ToolCallContext,
+        wrapper::Parameters,
+    }, model::*, schemars, service::{NotificationContext, RequestContext}, tool, tool_router
 };
+use base64::Engine;
+use chrono_tz::Tz;
 use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+use rmcp::service::{ElicitationError, Peer};
+use tracing::Instrument;
+use crate::audit::{self, AuditEntry};
+use crate::backup::{self, Conflict};
+use crate::bookmark::Page;
+use crate::calendar;
+use crate::config::{CustomTool, MemoDefaults, SharedSettings};
+use crate::custom_tools;
+use crate::dates;
+use crate::macros;
+use crate::diff;
+use crate::duplicates;
+use crate::formatter;
+use crate::front_matter;
+use crate::graph::Graph;
+use crate::housekeeping::{self, Flag};
+use crate::inbox::{Inbox, InboxEvent};
+use crate::links;
+use crate::mirror::Mirror;
+use crate::ocr;
+use crate::reactions::{self, ReactionFilter};
+use crate::redact::redact;
+use crate::render;
+use crate::tag_digest;
+use crate::review::{REVIEW_TAG, Review};
+use crate::sections;
+use crate::tags::TagCache;
+use crate::tasks;
+use crate::undo::{Change, Snapshot, UndoLog};
+use crate::webhook::{Events, MemoEvent};
+use crate::workspace::WorkspaceStats;
+use crate::vault;
+use confirm::{CONFIRMATION_TTL, Confirmations};
+use idempotency::Claim;
+pub use idempotency::IdempotencyKeys;
 use crate::memos:: {
-    Server,
-    service::{note::{Note, NoteService}},
+    self, Backend, MemoName, MemosError, Servers,
+    service::{
+        note::{Attachment, Location, Note, Relation, RelationType, UpdateNote, Visibility},
+        user::{Shortcut, Token, UpdateUser, UpdateUserProfile, User},
+    },
 };
 
-#[derive(schemars::JsonSchema, serde::Deserialize)]
-struct MemoNameParam {
-    #[schemars(description = "The name of the memo.")]
-    name: String,
-}
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+/// Tools that need a HOST or ADMIN user on the selected server.
+const ADMIN_TOOLS: &[&str] = &["list_users", "update_user", "get_workspace_stats"];
+
+/// How long to wait for the user to approve a write over the session's budget.
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Most characters of a comment quoted by `reply_to_comment`.
+const QUOTE_CHARS: usize = 200;
+
+/// How often idle sessions check whether their client is gone, to stop forwarding events to it.
+const PEER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Scheme of the memo resources, `memo://{server}/{id}`.
+const MEMO_URI_SCHEME: &str = "memo://";
+
+/// Scheme of the shortcut resources, `shortcut://{server}/{id}`.
+const SHORTCUT_URI_SCHEME: &str = "shortcut://";
+
+/// Scheme of the attachment resources, `attachment://{server}/{id}`.
+const ATTACHMENT_URI_SCHEME: &str = "attachment://";
+
+/// Scheme of the tag digest resources, `memo-tag://{server}/{tag}`.
+const TAG_URI_SCHEME: &str = "memo-tag://";
+
+const SERVER_DESCRIPTION: &str = "The configured Memos server to use. Defaults to the default server, see `list_servers`.";
+const DRY_RUN_DESCRIPTION: &str = "Report the requests that would be sent to Memos without changing anything.";
+const CONFIRMATION_DESCRIPTION: &str = "Token returned by a previous call when deletes must be confirmed. Pass it to carry out that deletion.";
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ServerParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct OnServer<T> {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = DRY_RUN_DESCRIPTION)]
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(flatten)]
+    params: T,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Output {
+    /// Every field of each memo.
+    #[default]
+    Full,
+    /// The name, first line, tags, timestamps and property of each memo.
+    Summary,
+}
+
+/// The parameters of a listing tool, with the fields to trim each memo to.
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct Listing<T> {
+    #[schemars(description = "Comma-separated fields to return for each memo, e.g. `name,snippet,tags,updateTime`. Takes precedence over `output`.")]
+    #[serde(default)]
+    fields: Option<String>,
+    #[schemars(description = "`summary` to return only the name, first line, tags, timestamps and property of each memo, then `get_memo` the ones needed. Defaults to `full`.")]
+    #[serde(default)]
+    output: Output,
+    #[schemars(description = "Only memos whose property, derived by Memos from the content, has all of these, e.g. `[\"incomplete_tasks\"]` for the memos with unfinished tasks.")]
+    #[serde(default)]
+    has: Vec<fields::Has>,
+    #[serde(flatten)]
+    params: T,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct MemoNameParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The name of the memo.")]
+    name: MemoName,
+}
+
+fn default_search_limit() -> usize {
+    20
+}
+
+fn default_upcoming_days() -> u32 {
+    7
+}
+
+fn default_open_tasks_days() -> u32 {
+    7
+}
+
+fn default_review_days() -> u32 {
+    7
+}
+
+fn default_duplicate_threshold() -> f64 {
+    0.8
+}
+
+fn default_link_concurrency() -> usize {
+    8
+}
+
+fn default_archive_concurrency() -> usize {
+    4
+}
+
+fn default_radius_km() -> f64 {
+    1.0
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct GetMemoParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The name of the memo.")]
+    name: MemoName,
+    #[schemars(description = "Number of content characters to skip, for reading a long memo in parts.")]
+    #[serde(default)]
+    offset: usize,
+    #[schemars(description = "Maximum number of content characters to return. The whole content is returned when omitted.")]
+    #[serde(default, alias = "limit")]
+    max_chars: Option<usize>,
+    #[schemars(description = "Put YAML front matter with the visibility, pinned flag, tags and display time in front of the content, for editing the memo as a Markdown file. update_memo reads it back.")]
+    #[serde(default)]
+    front_matter: bool,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct GetMemoSectionParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The name of the memo.")]
+    name: MemoName,
+    #[schemars(description = "The text of the heading, e.g. `Open questions`. Case and leading `#`s are ignored.")]
+    heading: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct UpdateMemoSectionParam {
+    #[schemars(description = "The name of the memo.")]
+    name: MemoName,
+    #[schemars(description = "The text of the heading, e.g. `Open questions`. Case and leading `#`s are ignored.")]
+    heading: String,
+    #[schemars(description = "The new Markdown content under the heading, without the heading itself. Subsections of the old content are replaced too.")]
+    content: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct SearchParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The words to search for, see the tool description for the syntax.")]
+    query: String,
+    #[schemars(description = "Maximum number of memos to return. Defaults to 20.")]
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct UpdatedSinceParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "When to list from: an RFC 3339 timestamp, a date like `2025-06-01` or an expression like `yesterday`, `last monday`, `past 7 days`, `last month` or `June`, in the configured `timezone`. Pass the `until` of the previous call to continue from there.")]
+    since: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct CreatorParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The user, as a resource name like `users/1`, an id like `1` or a username.")]
+    user: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ReactionSummaryParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The memos to count the reactions of. Leave out to use `filter`.")]
+    #[serde(default)]
+    names: Vec<MemoName>,
+    #[schemars(description = "Memos filter in CEL, e.g. `visibility == \"PUBLIC\"` or `tag in [\"work\"]`, used when `names` is empty. Leave out for all memos.")]
+    #[serde(default)]
+    filter: Option<String>,
+    #[schemars(description = "Only count this emoji, e.g. `👍`.")]
+    #[serde(default)]
+    emoji: Option<String>,
+    #[schemars(description = "Only count reactions made from the start of this time: an RFC 3339 timestamp, a date like `2025-06-01` or an expression like `yesterday`, `last monday`, `past 7 days`, `last month` or `June`, in the configured `timezone`.")]
+    #[serde(default)]
+    since: Option<String>,
+    #[schemars(description = "Only count reactions made up to the end of this time, given like `since`; `until: June` includes June.")]
+    #[serde(default)]
+    until: Option<String>,
+    #[schemars(description = "Number of memos with the most reactions to return. Defaults to 20.")]
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct AuditVisibilityParam {
+    #[schemars(description = "Memos of the audit to make PRIVATE, e.g. the ones exposed by mistake. Leave out to only list them.")]
+    #[serde(default)]
+    make_private: Vec<MemoName>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct SetLocationParam {
+    #[schemars(description = "The name of the memo, e.g. `memos/1`.")]
+    name: MemoName,
+    #[schemars(description = "Latitude in degrees, -90 to 90.")]
+    latitude: f64,
+    #[schemars(description = "Longitude in degrees, -180 to 180.")]
+    longitude: f64,
+    #[schemars(description = "Name or address of the place, shown instead of the coordinates.")]
+    #[serde(default)]
+    placeholder: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct SetDisplayTimeParam {
+    #[schemars(description = "The name of the memo, e.g. `memos/1`.")]
+    name: MemoName,
+    #[schemars(description = "RFC 3339 timestamp the memo is filed under, e.g. `2025-01-31T20:00:00Z`.")]
+    display_time: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct FormatMemoParam {
+    #[schemars(description = "The name of the memo, e.g. `memos/1`.")]
+    name: MemoName,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct TasksParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Only list done tasks when true, or open tasks when false. All tasks when omitted.")]
+    #[serde(default)]
+    done: Option<bool>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct OpenTasksParam {
+    #[schemars(description = "Only collect tasks from memos created or updated in this many past days. Defaults to 7.")]
+    #[serde(default = "default_open_tasks_days")]
+    days: u32,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ReviewParam {
+    #[schemars(description = "Number of past days to review. Defaults to 7.")]
+    #[serde(default = "default_review_days")]
+    days: u32,
+    #[schemars(description = "Also save the report as a new memo tagged `#review`.")]
+    #[serde(default)]
+    save: bool,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ToggleTaskParam {
+    #[schemars(description = "The name of the memo, e.g. `memos/1`.")]
+    name: MemoName,
+    #[schemars(description = "The line index of the task, as returned by `list_tasks`.")]
+    line: usize,
+    #[schemars(description = "Check the task when true or uncheck it when false. Toggles it when omitted.")]
+    #[serde(default)]
+    done: Option<bool>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct NearParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Latitude of the center in degrees.")]
+    latitude: f64,
+    #[schemars(description = "Longitude of the center in degrees.")]
+    longitude: f64,
+    #[schemars(description = "Radius around the center in kilometres. Defaults to 1.")]
+    #[serde(default = "default_radius_km")]
+    radius_km: f64,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct UpcomingParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Number of days ahead to look. Defaults to 7.")]
+    #[serde(default = "default_upcoming_days")]
+    days: u32,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ListTagsParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Fetch the tags from the server instead of the prefetched index.")]
+    #[serde(default)]
+    refresh: bool,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct RelatedParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The name of the memo to find related memos for.")]
+    name: MemoName,
+    #[schemars(description = "Maximum number of memos to return. Defaults to 20.")]
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct DeleteMemoParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = DRY_RUN_DESCRIPTION)]
+    #[serde(default)]
+    dry_run: bool,
+    #[schemars(description = "The name of the memo to delete.")]
+    name: MemoName,
+    #[schemars(description = CONFIRMATION_DESCRIPTION)]
+    #[serde(default)]
+    confirmation_token: Option<String>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct BatchDeleteParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = DRY_RUN_DESCRIPTION)]
+    #[serde(default)]
+    dry_run: bool,
+    #[schemars(description = "The names of the memos to delete.")]
+    names: Vec<MemoName>,
+    #[schemars(description = CONFIRMATION_DESCRIPTION)]
+    #[serde(default)]
+    confirmation_token: Option<String>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ArchiveOldParam {
+    #[schemars(description = "Archive the memos not created or updated in this many days.")]
+    older_than_days: u32,
+    #[schemars(description = "Only archive memos with this tag.")]
+    #[serde(default)]
+    tag: Option<String>,
+    #[schemars(description = "Only archive memos containing this text, ignoring case.")]
+    #[serde(default)]
+    query: Option<String>,
+    #[schemars(description = "Also archive pinned memos, which are kept by default.")]
+    #[serde(default)]
+    include_pinned: bool,
+    #[schemars(description = "Number of memos archived at a time, at most 16. Defaults to 4.")]
+    #[serde(default = "default_archive_concurrency")]
+    concurrency: usize,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct UndoParam {
+    #[schemars(description = DRY_RUN_DESCRIPTION)]
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct RestoreMemoParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = DRY_RUN_DESCRIPTION)]
+    #[serde(default)]
+    dry_run: bool,
+    #[schemars(description = "The name the memo had before it was deleted.")]
+    name: MemoName,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct GetVersionParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The name of the memo, e.g. `memos/1`.")]
+    name: MemoName,
+    #[schemars(description = "The version number, as returned by `list_memo_versions`.")]
+    version: i64,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct RevertParam {
+    #[schemars(description = "The name of the memo, e.g. `memos/1`.")]
+    name: MemoName,
+    #[schemars(description = "The version number to revert to, as returned by `list_memo_versions`.")]
+    version: i64,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ReplyParam {
+    #[schemars(description = "The name of the comment to reply to, as listed by `list_memo_comments`.")]
+    comment: MemoName,
+    #[schemars(description = "The Markdown content of the reply.")]
+    content: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct CommentMemoParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = DRY_RUN_DESCRIPTION)]
+    #[serde(default)]
+    dry_run: bool,
+    #[schemars(description = "The name of the memo to comment on.")]
+    memo_name: MemoName,
+    comment: Note,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ExportParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Name of the folder inside the configured export directory to write to, created when missing.")]
+    folder: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum RenderFormat {
+    #[default]
+    Html,
+    Pdf,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct RenderParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Names of the memos to render, in this order.")]
+    #[serde(default)]
+    names: Vec<String>,
+    #[schemars(description = "Render every memo with this tag instead, newest first.")]
+    #[serde(default)]
+    tag: Option<String>,
+    #[schemars(description = "`html` (default) or `pdf`. PDF needs a server built with the `pdf` feature and a configured `pdf_renderer`.")]
+    #[serde(default)]
+    format: RenderFormat,
+    #[schemars(description = "Title of the document. Defaults to the title of the only memo or the tag.")]
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct DiffParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The name of the memo to compare from, e.g. `memos/1`.")]
+    a: MemoName,
+    #[schemars(description = "The name of the memo to compare to. Give either `b` or `text`.")]
+    #[serde(default)]
+    b: Option<MemoName>,
+    #[schemars(description = "Text to compare the memo to, e.g. the content an update would set.")]
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct DuplicatesParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Similarity from 0 to 1 above which two memos count as duplicates. Defaults to 0.8; 1 finds only copies.")]
+    #[serde(default = "default_duplicate_threshold")]
+    threshold: f64,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct CreateMemoParam {
+    #[serde(flatten)]
+    note: Note,
+    #[schemars(description = "A unique key for this memo, e.g. a UUID. Calling again with the same key returns the memo created the first time instead of creating a duplicate.")]
+    #[serde(default)]
+    idempotency_key: Option<String>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct BookmarkParam {
+    #[schemars(description = "The http(s) URL of the page to bookmark.")]
+    url: String,
+    #[schemars(description = "Also attach the readable text of the page as a text file, to keep it if the page changes or goes away.")]
+    #[serde(default)]
+    snapshot: bool,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ExtractTextParam {
+    #[schemars(description = "The name of the memo, e.g. `memos/1`.")]
+    name: MemoName,
+    #[schemars(description = "The name or file name of the image attachment to read. Reads every image attachment of the memo when omitted.")]
+    #[serde(default)]
+    attachment: Option<String>,
+    #[schemars(description = "Append the text to the memo, under a line naming the attachment, so that it can be searched. Attachments whose text was appended before are skipped.")]
+    #[serde(default)]
+    append: bool,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct CheckLinksParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "The name of the memo to check, e.g. `memos/1`. All memos when omitted.")]
+    #[serde(default)]
+    name: Option<MemoName>,
+    #[schemars(description = "Number of links requested at a time, at most 32. Defaults to 8.")]
+    #[serde(default = "default_link_concurrency")]
+    concurrency: usize,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum GraphFormat {
+    #[default]
+    Json,
+    Dot,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct GraphParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "`json` (default) for a node-link graph as read by d3 or networkx, or `dot` for Graphviz.")]
+    #[serde(default)]
+    format: GraphFormat,
+    #[schemars(description = "Only include memos with this tag.")]
+    #[serde(default)]
+    tag: Option<String>,
+    #[schemars(description = "Only include memos created from the start of this time: an RFC 3339 timestamp, a date like `2025-06-01` or an expression like `yesterday`, `last monday`, `past 7 days`, `last month` or `June`, in the configured `timezone`.")]
+    #[serde(default)]
+    since: Option<String>,
+    #[schemars(description = "Only include memos created up to the end of this time, given like `since`; `until: June` includes June.")]
+    #[serde(default)]
+    until: Option<String>,
+}
+
+fn default_signup_days() -> u32 {
+    30
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct WorkspaceStatsParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "How many days back signups count as recent. Defaults to 30.")]
+    #[serde(default = "default_signup_days")]
+    signup_days: u32,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct CleanUpAttachmentsParam {
+    #[schemars(description = "Flag attachments larger than this many bytes as `oversized`.")]
+    #[serde(default)]
+    max_bytes: Option<u64>,
+    #[schemars(description = "Delete the attachments with any of these flags, e.g. `[\"orphaned\", \"unattached\"]`. Leave out to only list them.")]
+    #[serde(default)]
+    delete: Vec<Flag>,
+    #[schemars(description = "Number of the largest attachments to list. Defaults to 20.")]
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+}
+
+fn default_pat_expiry_days() -> u32 {
+    90
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct CreatePatParam {
+    #[schemars(description = "The name field of the user to create the token for, e.g. `users/2`. Defaults to the current user; other users require a HOST or ADMIN user.")]
+    #[serde(default)]
+    user: Option<String>,
+    #[schemars(description = "What the token is for, e.g. the device it is used on.")]
+    description: String,
+    #[schemars(description = "Days until the token expires, 0 for a token that never expires. Defaults to 90.")]
+    #[serde(default = "default_pat_expiry_days")]
+    expires_in_days: u32,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct RevokePatParam {
+    #[schemars(description = "The name of the token, e.g. `users/2/personalAccessTokens/5`. Tokens of other users require a HOST or ADMIN user.")]
+    name: String,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct RunMacroParam {
+    #[schemars(description = "The name of the macro, see `list_macros`.")]
+    name: String,
+    #[schemars(description = "The arguments of the macro by parameter name, e.g. `{\"text\": \"Call the plumber\"}`.")]
+    #[serde(default)]
+    arguments: BTreeMap<String, String>,
+    #[schemars(description = "The memo to run the macro on, for macros that do not create their memo.")]
+    #[serde(default)]
+    memo: Option<MemoName>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct SnapshotParam {
+    #[schemars(description = SERVER_DESCRIPTION)]
+    #[serde(default)]
+    server: Option<String>,
+    #[schemars(description = "Name of the file inside the configured export directory to write the snapshot to. Defaults to `snapshot-<server>-<time>.jsonl`.")]
+    #[serde(default)]
+    file: Option<String>,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct RestoreSnapshotParam {
+    #[schemars(description = "Name of the snapshot file inside the configured export directory, as returned by `snapshot_account`.")]
+    file: String,
+    #[schemars(description = "What to do with a memo of the snapshot when the server has a memo of the same name: `skip` it (the default), `overwrite` the memo on the server, or restore it as a `duplicate`.")]
+    #[serde(default)]
+    conflict: Conflict,
+}
+
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct ImportParam {
+    #[schemars(description = "Name of the folder inside the configured export directory to import the Markdown files of.")]
+    folder: String,
+}
+
+/// Asked from the user through elicitation once the session's write budget is spent.
+#[derive(schemars::JsonSchema, serde::Deserialize)]
+struct WriteApproval {
+    #[schemars(description = "Allow another full budget of writes before asking again.")]
+    #[serde(default)]
+    renew_budget: bool,
+}
+
+rmcp::elicit_safe!(WriteApproval);
+
+fn is_read_only(tool: &Tool) -> bool {
+    tool.annotations
+        .as_ref()
+        .and_then(|annotations| annotations.read_only_hint)
+        .unwrap_or(false)
+}
+
+fn apply_memo_defaults(note: &mut Note, defaults: &MemoDefaults) {
+    if note.visibility == Visibility::VisibilityUnspecified
+        && let Some(visibility) = &defaults.visibility
+    {
+        note.visibility = visibility.clone();
+    }
+
+    let missing_tags = defaults.tags
+        .iter()
+        .map(|tag| format!("#{}", tag.trim_start_matches('#')))
+        .filter(|tag| !note.content.split_whitespace().any(|word| word == tag))
+        .collect::<Vec<_>>();
+    if !missing_tags.is_empty() {
+        note.content = format!("{}\n\n{}", note.content.trim_end(), missing_tags.join(" "));
+    }
+
+    if defaults.set_create_time && note.create_time.is_none() {
+        note.create_time = Some(chrono::Utc::now());
+    }
+}
+
+/// Keeps `max_chars` characters of `content` starting at character `offset`.
+fn content_window(content: &str, offset: usize, max_chars: Option<usize>) -> String {
+    let rest = content.chars().skip(offset);
+    match max_chars {
+        Some(max_chars) => rest.take(max_chars).collect(),
+        None => rest.collect(),
+    }
+}
+
+/// The content of a reply to `comment`: a quote of the start of the comment naming its author, then
+/// `reply`.
+fn reply_content(comment: &Note, name: &MemoName, reply: &str) -> String {
+    let text = comment.content.trim();
+    let mut quote = text.chars().take(QUOTE_CHARS).collect::<String>();
+    if text.chars().count() > QUOTE_CHARS {
+        quote = format!("{}…", quote.trim_end());
+    }
+    let author = comment.creator.as_deref().unwrap_or("someone");
+    let mut content = format!("> {} wrote in {}:\n", author, name);
+    for line in quote.lines().map(str::trim_end) {
+        match line.is_empty() {
+            true => content.push_str(">\n"),
+            false => content.push_str(&format!("> {}\n", line)),
+        }
+    }
+    content.push('\n');
+    content.push_str(reply.trim());
+    content
+}
+
+/// The name of the text file keeping the snapshot of a bookmarked page, after its title.
+fn snapshot_file_name(title: &str) -> String {
+    let slug = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-");
+    match slug.char_indices().nth(60) {
+        _ if slug.is_empty() => "page.txt".to_string(),
+        Some((end, _)) => format!("{}.txt", slug[..end].trim_end_matches('-')),
+        None => format!("{}.txt", slug),
+    }
+}
+
+/// The `notes` with all the `has` flags, trimmed to the comma-separated `fields` when given, else to
+/// the fields of `output`. Summaries show their times in `tz`.
+fn listing(notes: &[Note], fields: Option<&str>, output: Output, has: &[fields::Has], tz: Tz) -> anyhow::Result<serde_json::Value> {
+    let notes = notes.iter().filter(|note| fields::has(note, has)).cloned().collect::<Vec<_>>();
+    Ok(match (fields, output) {
+        (Some(fields), _) => fields::select(&notes, &fields::parse(fields)?),
+        (None, Output::Summary) => fields::summary(&notes, tz),
+        (None, Output::Full) => json!(notes),
+    })
+}
+
+/// Adds the warning of a truncated write to the JSON response.
+fn with_warning(mut value: serde_json::Value, warning: Option<String>) -> String {
+    if let Some(warning) = warning {
+        value["warning"] = json!(warning);
+    }
+    value.to_string()
+}
+
+fn memo_uri(server: &str, memo: &str) -> String {
+    format!("{}{}/{}", MEMO_URI_SCHEME, server, memo.trim_start_matches("memos/"))
+}
+
+/// The server and memo of a `memo://{server}/{id}` URI.
+fn parse_memo_uri(uri: &str) -> Option<(&str, MemoName)> {
+    let (server, id) = uri.strip_prefix(MEMO_URI_SCHEME)?.split_once('/')?;
+    Some((server, format!("memos/{}", id).parse().ok()?))
+}
+
+fn shortcut_uri(server: &str, shortcut: &Shortcut) -> String {
+    format!("{}{}/{}", SHORTCUT_URI_SCHEME, server, shortcut.id())
+}
+
+/// The server and shortcut id of a `shortcut://{server}/{id}` URI.
+fn parse_shortcut_uri(uri: &str) -> Option<(&str, &str)> {
+    uri.strip_prefix(SHORTCUT_URI_SCHEME)?.split_once('/').filter(|(_, id)| !id.is_empty())
+}
+
+fn attachment_uri(server: &str, attachment: &Attachment) -> String {
+    format!("{}{}/{}", ATTACHMENT_URI_SCHEME, server, attachment.name.trim_start_matches("attachments/"))
+}
+
+/// The server and attachment of an `attachment://{server}/{id}` URI.
+fn parse_attachment_uri(uri: &str) -> Option<(&str, String)> {
+    let (server, id) = uri.strip_prefix(ATTACHMENT_URI_SCHEME)?.split_once('/')?;
+    (!id.is_empty() && !id.contains('/')).then(|| (server, format!("attachments/{}", id)))
+}
+
+fn tag_uri(server: &str, tag: &str) -> String {
+    format!("{}{}/{}", TAG_URI_SCHEME, server, tag.trim_start_matches('#'))
+}
+
+/// The server and tag of a `memo-tag://{server}/{tag}` URI. Nested tags like `work/q1` keep their `/`.
+fn parse_tag_uri(uri: &str) -> Option<(&str, &str)> {
+    uri.strip_prefix(TAG_URI_SCHEME)?.split_once('/').filter(|(_, tag)| !tag.is_empty())
+}
+
+/// The error of a failed tool call. Errors returned by Memos also carry their status, code and
+/// details as `data`.
+fn error_response(e: anyhow::Error) -> String {
+    let mut response = json!({"error": redact(&e.to_string())});
+    if let Some(error) = MemosError::find(&e) {
+        response["data"] = json!(error);
+    }
+    response.to_string()
+}
+
+fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+/// The log message telling the client that `tool` failed because Memos rate limited it, with the
+/// wait Memos asked for.
+fn rate_limit_notice(tool: &str, result: &CallToolResult) -> Option<serde_json::Value> {
+    let text = result.content.first()?.as_text()?;
+    let response = serde_json::from_str::<serde_json::Value>(&text.text).ok()?;
+    let data = response.get("data")?;
+    if data["status"] != 429 {
+        return None;
+    }
+    let message = match data["retry_after"].as_u64() {
+        Some(retry_after) => format!("Memos rate limited {}, retry after {}s", tool, retry_after),
+        None => format!("Memos rate limited {}", tool),
+    };
+    Some(json!({"message": message, "tool": tool, "retry_after": data["retry_after"]}))
+}
+
+pub struct MemoMCP {
+    tool_router: ToolRouter<MemoMCP>,
+    servers: Arc<Servers>,
+    settings: SharedSettings,
+    /// Identifies the MCP session in the audit log.
+    session_id: String,
+    mirror: Option<Arc<Mirror>>,
+    tags: Arc<TagCache>,
+    confirmations: Confirmations,
+    /// Memos created with client-supplied idempotency keys, shared by the sessions given the same.
+    idempotency_keys: Arc<IdempotencyKeys>,
+    undo: Arc<UndoLog>,
+    /// The authenticated user on each server, looked up when the session starts.
+    users: RwLock<BTreeMap<String, User>>,
+    /// Writes made in this session, counted against `write_budget`.
+    writes: AtomicU32,
+    /// Memo changes reported by webhooks, forwarded to the client as resource updates.
+    events: Option<Events>,
+    /// URIs of the memo and tag digest resources the client subscribed to.
+    subscriptions: Arc<RwLock<BTreeSet<String>>>,
+    /// New notifications in the Memos inbox, forwarded to the client as log messages.
+    inbox: Option<Inbox>,
+    /// The least severe log messages the client asked for, see [`ServerHandler::set_level`].
+    log_level: Arc<RwLock<Option<LoggingLevel>>>,
+    /// Permits for the Memos requests of this session, from `max_concurrent_requests`.
+    request_limit: Option<Arc<tokio::sync::Semaphore>>,
+}
+
+#[tool_router]
+impl MemoMCP {
+    pub fn new(
+        servers: Arc<Servers>,
+        settings: SharedSettings,
+        mirror: Option<Arc<Mirror>>,
+        tags: Arc<TagCache>,
+        undo: Arc<UndoLog>,
+    ) -> Self {
+        let mut tool_router = Self::tool_router();
+        for custom in settings.read().unwrap().custom_tools.iter().cloned() {
+            if tool_router.has_route(&custom.name) {
+                tracing::warn!("Custom tool {} is ignored, a built-in tool has its name", custom.name);
+                continue;
+            }
+            let custom = Arc::new(custom);
+            tool_router.add_route(ToolRoute::new_dyn(custom_tools::tool(&custom), move |context: ToolCallContext<'_, MemoMCP>| {
+                let custom = custom.clone();
+                Box::pin(async move {
+                    let text = context.service.call_custom_tool(&custom, context.arguments.unwrap_or_default()).await;
+                    Ok(CallToolResult::success(vec![Content::text(text)]))
+                })
+            }));
+        }
+        if settings.read().unwrap().read_only {
+            tool_router.map.retain(|_, route| is_read_only(&route.attr));
+        }
+        let request_limit = settings.read().unwrap().max_concurrent_requests
+            .map(|limit| Arc::new(tokio::sync::Semaphore::new(limit)));
+
+        Self {
+            tool_router,
+            servers,
+            settings,
+            session_id: format!("{}-{}", std::process::id(), NEXT_SESSION.fetch_add(1, Ordering::Relaxed)),
+            mirror,
+            tags,
+            confirmations: Confirmations::default(),
+            idempotency_keys: Arc::default(),
+            undo,
+            users: RwLock::default(),
+            writes: AtomicU32::new(0),
+            events: None,
+            subscriptions: Arc::default(),
+            inbox: None,
+            log_level: Arc::default(),
+            request_limit,
+        }
+    }
+
+    /// Notifies the client when a memo resource it subscribed to changes.
+    pub fn with_events(mut self, events: Option<Events>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Tells the client about new notifications in the Memos inbox.
+    pub fn with_inbox(mut self, inbox: Option<Inbox>) -> Self {
+        self.inbox = inbox;
+        self
+    }
+
+    /// Shares the idempotency keys of `create_memo` with other sessions, so that a retry made in a
+    /// new session is still recognized.
+    pub fn with_idempotency_keys(mut self, keys: Arc<IdempotencyKeys>) -> Self {
+        self.idempotency_keys = keys;
+        self
+    }
+
+    /// The local mirror of server `name` once it has been synchronized.
+    fn synced_mirror(&self, name: &str) -> anyhow::Result<Option<Arc<Mirror>>> {
+        match &self.mirror {
+            Some(mirror) if mirror.is_synced(name)? => Ok(Some(mirror.clone())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Brings the mirror of `server_name` up to date after a successful write through MCP, so that
+    /// listings and searches served from it show the change before the next scheduled sync.
+    /// `removed` are the memos the write deleted or archived, which a delta sync does not notice.
+    async fn sync_mirror<T>(&self, server_name: Option<&str>, server: &dyn Backend, result: &anyhow::Result<T>, removed: &[&str]) {
+        if result.is_err() {
+            return;
+        }
+        let name = server_name.unwrap_or(self.servers.default_name());
+        let Ok(Some(mirror)) = self.synced_mirror(name) else {
+            return;
+        };
+        let synced = match mirror.remove(name, removed) {
+            Ok(()) => mirror.sync(name, server, false).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = synced {
+            tracing::warn!("Failed to update the mirror of server {} after a write: {}", name, e);
+        }
+    }
+
+    /// The mirror to query for server `name`, or a throwaway one built from a live listing.
+    async fn memo_index(&self, name: &str, server: &dyn Backend) -> anyhow::Result<Arc<Mirror>> {
+        if let Some(mirror) = self.synced_mirror(name)? {
+            return Ok(mirror);
+        }
+        Ok(Arc::new(Mirror::from_notes(name, &server.list_notes().await?)?))
+    }
+
+    fn check_tool(&self, tool: &Tool) -> Result<(), String> {
+        let settings = self.settings.read().unwrap();
+        if !settings.is_tool_allowed(&tool.name) {
+            return Err(format!("Tool {} is disabled by configuration", tool.name));
+        }
+        if settings.read_only && !is_read_only(tool) {
+            return Err(format!("Tool {} is unavailable in read-only mode", tool.name));
+        }
+        if ADMIN_TOOLS.contains(&tool.name.as_ref()) && !self.may_administer() {
+            return Err(format!("Insufficient role: tool {} requires a HOST or ADMIN user, the configured tokens only have the USER role", tool.name));
+        }
+        Ok(())
+    }
+
+    /// False once every server is known to authenticate a non-admin user.
+    fn may_administer(&self) -> bool {
+        let users = self.users.read().unwrap();
+        users.len() < self.servers.names().count() || users.values().any(|user| user.role.is_admin())
+    }
+
+    /// Appends a write operation to the audit log when one is configured.
+    fn audit<T>(&self, server: Option<&str>, tool: &str, target: Option<&str>, summary: String, result: &anyhow::Result<T>) {
+        let Some(path) = self.settings.read().unwrap().audit_log.clone() else {
+            return;
+        };
+        let entry = AuditEntry {
+            timestamp: chrono::Utc::now(),
+            session: &self.session_id,
+            server: server.unwrap_or(self.servers.default_name()),
+            tool,
+            target,
+            summary,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| redact(&e.to_string())),
+        };
+        if let Err(e) = audit::append(&path, &entry) {
+            tracing::warn!("Failed to write audit entry: {}", e);
+        }
+    }
+
+    /// The file name, MIME type and content of the memos selected by `params`, rendered.
+    async fn render_memos(&self, params: RenderParam) -> anyhow::Result<(String, &'static str, Vec<u8>)> {
+        let server = self.servers.get(params.server.as_deref())?;
+        let mut notes = Vec::new();
+        for name in &params.names {
+            notes.push(server.get_note(&name.parse()?).await?);
+        }
+        if let (true, Some(tag)) = (notes.is_empty(), &params.tag) {
+            let tag = tag.trim_start_matches('#');
+            notes = server.list_notes().await?.into_iter().filter(|note| note.tags.iter().any(|t| t == tag)).collect();
+            notes.sort_by_key(|note| std::cmp::Reverse(note.create_time));
+        }
+        let (stem, default_title) = match (notes.as_slice(), &params.tag) {
+            (_, Some(tag)) if params.names.is_empty() => {
+                let tag = tag.trim_start_matches('#');
+                (tag.replace('/', "-"), format!("#{}", tag))
+            }
+            ([], _) => return Err(anyhow::anyhow!("Select the memos to render with `names` or `tag`")),
+            ([note], _) => (note.memo_name()?.id().to_string(), vault::title(note)),
+            _ => ("memos".to_string(), "Memos".to_string()),
+        };
+
+        let redaction = self.settings.read().unwrap().content_redaction.clone();
+        for note in &mut notes {
+            note.content = redaction.apply_to_response(&note.content);
+        }
+        let html = render::html_document(params.title.as_deref().unwrap_or(&default_title), &notes);
+        match params.format {
+            RenderFormat::Html => Ok((format!("{}.html", stem), "text/html", html.into_bytes())),
+            #[cfg(feature = "pdf")]
+            RenderFormat::Pdf => {
+                let command = self.settings.read().unwrap().pdf_renderer.clone().ok_or_else(|| {
+                    anyhow::anyhow!("PDF rendering is disabled, set `pdf_renderer` to enable it")
+                })?;
+                Ok((format!("{}.pdf", stem), "application/pdf", render::html_to_pdf(&command, &html).await?))
+            }
+            #[cfg(not(feature = "pdf"))]
+            RenderFormat::Pdf => Err(anyhow::anyhow!("PDF rendering requires a server built with the `pdf` feature")),
+        }
+    }
+
+    /// The text recognized in `image` by the configured OCR command.
+    async fn recognize_text(&self, image: &[u8]) -> anyhow::Result<String> {
+        #[cfg(feature = "ocr")]
+        {
+            let command = self.settings.read().unwrap().ocr_command.clone().ok_or_else(|| {
+                anyhow::anyhow!("Text extraction is disabled, set `ocr_command` to enable it")
+            })?;
+            ocr::image_to_text(&command, image).await
+        }
+        #[cfg(not(feature = "ocr"))]
+        {
+            let _ = image;
+            Err(anyhow::anyhow!("Text extraction requires a server built with the `ocr` feature"))
+        }
+    }
+
+    /// Applies `update` like `update_memo` does, for the tools that change a single field.
+    async fn apply_update(&self, server_name: Option<&str>, dry_run: bool, update: UpdateNote, tool: &str) -> String {
+        let server = match self.servers.get(server_name) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let before = self.snapshot(server, &update.name, dry_run).await;
+        let result = match self.write(dry_run, server.update_note(&update)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.remember(server_name, Change::Updated, before, &result);
+        self.audit(server_name, tool, Some(update.name.as_str()), format!("updated {}", update.update_mask()), &result);
+        self.sync_mirror(server_name, server, &result, &[]).await;
+        match result {
+            Ok(note) => json!(note).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    fn is_dry_run(&self, dry_run: bool) -> bool {
+        dry_run || self.settings.read().unwrap().dry_run
+    }
+
+    fn timezone(&self) -> Tz {
+        self.settings.read().unwrap().timezone()
+    }
+
+    /// Counts a write call against the session's `write_budget`. Once the budget is spent the user
+    /// has to approve each further write.
+    async fn spend_write_budget(&self, tool: &str, peer: &Peer<RoleServer>) -> Result<(), String> {
+        let Some(budget) = self.settings.read().unwrap().write_budget else {
+            return Ok(());
+        };
+        if self.writes.fetch_add(1, Ordering::Relaxed) < budget {
+            return Ok(());
+        }
+
+        let message = format!("This session made {} writes to Memos, its budget. Allow {}?", budget, tool);
+        match peer.elicit_with_timeout::<WriteApproval>(message, Some(APPROVAL_TIMEOUT)).await {
+            Ok(approval) => {
+                if approval.is_some_and(|approval| approval.renew_budget) {
+                    self.writes.store(1, Ordering::Relaxed);
+                }
+                Ok(())
+            }
+            Err(ElicitationError::CapabilityNotSupported) => Err(format!(
+                "Write budget of {} writes per session is spent and the client cannot ask the user to approve more",
+                budget
+            )),
+            Err(ElicitationError::UserDeclined | ElicitationError::UserCancelled) => {
+                Err(format!("The user did not approve {} after the write budget was spent", tool))
+            }
+            Err(e) => Err(format!("Failed to ask the user to approve {}: {}", tool, e)),
+        }
+    }
+
+    async fn log(&self, peer: &Peer<RoleServer>, level: LoggingLevel, data: serde_json::Value) {
+        send_log(peer, &self.log_level, "memos", level, data).await;
+    }
+
+    /// Calls the endpoint of a tool declared in `custom_tools`.
+    async fn call_custom_tool(&self, custom: &CustomTool, arguments: JsonObject) -> String {
+        let server_name = arguments.get("server").and_then(|server| server.as_str()).map(str::to_string);
+        let dry_run = arguments.get("dry_run").and_then(|dry_run| dry_run.as_bool()).unwrap_or(false);
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let (endpoint, body) = match custom_tools::endpoint(custom, &arguments).and_then(|endpoint| Ok((endpoint, custom_tools::body(custom, &arguments)?))) {
+            Ok(request) => request,
+            Err(e) => return error_response(e),
+        };
+        let method = custom_tools::method(custom);
+        let call = server.call_endpoint(method.clone(), &endpoint, body.as_ref());
+        if custom_tools::is_read_only(custom) {
+            return match call.await {
+                Ok(response) => response.to_string(),
+                Err(e) => error_response(e),
+            };
+        }
+        let result = match self.write(dry_run, call).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.audit(server_name.as_deref(), &custom.name, Some(&endpoint), format!("{} {}", method, endpoint), &result);
+        match result {
+            Ok(response) => response.to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    /// Runs a write, or in dry-run mode returns the response describing the requests it would send.
+    async fn write<T>(
+        &self,
+        dry_run: bool,
+        call: impl Future<Output = anyhow::Result<T>>,
+    ) -> Result<anyhow::Result<T>, String> {
+        if !self.is_dry_run(dry_run) {
+            return Ok(call.await);
+        }
+        let (result, planned) = memos::dry_run(call).await;
+        match result {
+            Err(e) if planned.is_empty() => Err(error_response(e)),
+            _ => Err(json!({"dry_run": true, "requests": planned}).to_string()),
+        }
+    }
+
+    /// With `confirm_deletes` set, a delete without `token` only describes the memos it would remove
+    /// and issues a token for them. `Err` holds the response to return instead of deleting.
+    async fn confirm_delete(
+        &self,
+        server_name: Option<&str>,
+        server: &dyn Backend,
+        names: &[MemoName],
+        token: Option<&str>,
+        dry_run: bool,
+    ) -> Result<(), String> {
+        if !self.settings.read().unwrap().confirm_deletes || self.is_dry_run(dry_run) {
+            return Ok(());
+        }
+        let server_name = server_name.unwrap_or(self.servers.default_name());
+        if let Some(token) = token {
+            return self.confirmations.redeem(token, server_name, names).map_err(error_response);
+        }
+
+        let mut memos = Vec::with_capacity(names.len());
+        for name in names {
+            let note = server.get_note(name).await.map_err(error_response)?;
+            memos.push(json!({
+                "name": name,
+                "preview": audit::preview(&note.content),
+                "update_time": note.update_time,
+            }));
+        }
+        let token = self.confirmations.issue(server_name, names);
+        Err(json!({
+            "confirmation_required": true,
+            "confirmation_token": token,
+            "expires_in_seconds": CONFIRMATION_TTL.as_secs(),
+            "memos": memos,
+        }).to_string())
+    }
+
+    /// Applies the configured `max_content_chars` to content about to be written. Returns a warning
+    /// when the content was truncated.
+    fn limit_content(&self, content: &mut String) -> anyhow::Result<Option<String>> {
+        let settings = self.settings.read().unwrap();
+        let Some(max) = settings.max_content_chars else {
+            return Ok(None);
+        };
+        let chars = content.chars().count();
+        if chars <= max {
+            return Ok(None);
+        }
+        if !settings.truncate_content {
+            return Err(anyhow::anyhow!("Content has {} characters, more than the limit of {}", chars, max));
+        }
+        if let Some((end, _)) = content.char_indices().nth(max) {
+            content.truncate(end);
+        }
+        Ok(Some(format!("Content was truncated from {} to {} characters", chars, max)))
+    }
+
+    /// Fetches memo `name` before it is changed so the change can be undone. Skipped in dry-run mode.
+    async fn snapshot(&self, server: &dyn Backend, name: &MemoName, dry_run: bool) -> Option<Note> {
+        if self.is_dry_run(dry_run) {
+            return None;
+        }
+        match server.get_note(name).await {
+            Ok(note) => Some(note),
+            Err(e) => {
+                tracing::warn!("Failed to snapshot {} before changing it, the change cannot be undone: {}", name, e);
+                None
+            }
+        }
+    }
+
+    /// Keeps the snapshot taken before a change once the change succeeded, in the undo log and, with
+    /// the local mirror, as a version of the memo.
+    fn remember<T>(&self, server: Option<&str>, change: Change, before: Option<Note>, result: &anyhow::Result<T>) {
+        if let (Some(note), Ok(_)) = (before, result) {
+            let server = server.unwrap_or(self.servers.default_name());
+            if let Some(mirror) = &self.mirror
+                && let Err(e) = mirror.save_version(server, &note)
+            {
+                tracing::warn!("Failed to save a version of {}: {}", note.name.as_deref().unwrap_or_default(), e);
+            }
+            self.undo.record(&self.session_id, server, change, note);
+        }
+    }
+
+    /// The local mirror keeping memo versions.
+    fn version_store(&self) -> anyhow::Result<&Arc<Mirror>> {
+        self.mirror
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Memo versions are kept in the local mirror, set MEMOS_MIRROR_DB to enable them"))
+    }
+
+    /// Writes `snapshot` back to its server, keeping it in the undo log unless that succeeded.
+    async fn restore(&self, tool: &str, snapshot: Snapshot, dry_run: bool) -> String {
+        let server = match self.servers.get(Some(&snapshot.server)) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let write = async {
+            match snapshot.change {
+                Change::Deleted => server.create_note(&snapshot.note).await,
+                Change::Updated | Change::Archived => {
+                    let update = UpdateNote::from_note(&snapshot.note)?;
+                    server.update_note(&update).await
+                }
+            }
+        };
+        let result = match self.write(dry_run, write).await {
+            Ok(result) => result,
+            Err(response) => {
+                self.undo.put_back(snapshot);
+                return response;
+            }
+        };
+        let action = match snapshot.change {
+            Change::Deleted => "recreated deleted memo",
+            Change::Updated => "reverted update",
+            Change::Archived => "unarchived",
+        };
+        let summary = format!("{}: {}", action, audit::preview(&snapshot.note.content));
+        self.audit(Some(&snapshot.server), tool, snapshot.note.name.as_deref(), summary, &result);
+        self.sync_mirror(Some(&snapshot.server), server, &result, &[]).await;
+        match result {
+            Ok(note) => json!({"restored": snapshot.change, "memo": note}).to_string(),
+            Err(e) => {
+                self.undo.put_back(snapshot);
+                error_response(e)
+            }
+        }
+    }
+
+    /// The memos matching shortcut `id` of the current user of `server_name`, now.
+    async fn read_shortcut(&self, server_name: &str, id: &str) -> anyhow::Result<String> {
+        let server = self.servers.get(Some(server_name))?;
+        let me = server.get_current_user().await?;
+        let shortcut = server
+            .list_shortcuts(&me.name)
+            .await?
+            .into_iter()
+            .find(|shortcut| shortcut.id() == id)
+            .ok_or_else(|| anyhow::anyhow!("{} has no shortcut {}", me.name, id))?;
+        let mut notes = server.list_notes_matching(&shortcut.filter).await?;
+        let redaction = self.settings.read().unwrap().content_redaction.clone();
+        for note in &mut notes {
+            note.content = redaction.apply_to_response(&note.content);
+        }
+        Ok(json!({"shortcut": shortcut, "memos": notes}).to_string())
+    }
+
+    /// The digest of the latest memos tagged `tag` on `server_name`, now.
+    async fn read_tag_digest(&self, server_name: &str, tag: &str) -> anyhow::Result<String> {
+        let server = self.servers.get(Some(server_name))?;
+        let notes = server.list_notes_matching(&tag_digest::filter(tag)).await?;
+        let now = chrono::Utc::now().with_timezone(&self.timezone());
+        let digest = tag_digest::render(tag, &notes, now, |memo| memo_uri(server_name, memo));
+        Ok(self.settings.read().unwrap().content_redaction.apply_to_response(&digest))
+    }
+
+    /// The folder `folder` of the configured export directory, which clients cannot leave.
+    fn export_path(&self, folder: &str) -> anyhow::Result<PathBuf> {
+        let Some(dir) = self.settings.read().unwrap().export_dir.clone() else {
+            return Err(anyhow::anyhow!("Exports are disabled, set EXPORT_DIR or `export_dir` to enable them"));
+        };
+        let mut components = Path::new(folder).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => Ok(dir.join(name)),
+            _ => Err(anyhow::anyhow!("Invalid export folder {:?}, expected a single folder name", folder)),
+        }
+    }
+
+    async fn require_admin(&self, server_name: Option<&str>, server: &dyn Backend) -> anyhow::Result<()> {
+        let role = self.current_user(server_name, server).await?.role;
+        match role.is_admin() {
+            true => Ok(()),
+            false => Err(anyhow::anyhow!(
+                "Insufficient role: the user of server {} is not a HOST or ADMIN user",
+                server_name.unwrap_or(self.servers.default_name()),
+            )),
+        }
+    }
+
+    /// The authenticated user of the server, looked up once per session.
+    async fn current_user(&self, server_name: Option<&str>, server: &dyn Backend) -> anyhow::Result<User> {
+        let server_name = server_name.unwrap_or(self.servers.default_name());
+        if let Some(user) = self.users.read().unwrap().get(server_name) {
+            return Ok(user.clone());
+        }
+        let user = server.get_current_user().await?;
+        self.users.write().unwrap().insert(server_name.to_string(), user.clone());
+        Ok(user)
+    }
+
+    /// Fetches memo `name`, failing unless the user of the server may change it: as its creator, or
+    /// as a HOST or ADMIN user. Spares the client the bare permission error of Memos.
+    async fn owned_note(&self, server_name: Option<&str>, server: &dyn Backend, name: &MemoName) -> anyhow::Result<Note> {
+        let note = server.get_note(name).await?;
+        let me = self.current_user(server_name, server).await?;
+        match note.creator.as_deref() {
+            Some(creator) if creator != me.name && !me.role.is_admin() => Err(anyhow::anyhow!(
+                "{} is not your memo: it was created by {}, and {} is not a HOST or ADMIN user",
+                name, creator, me.name,
+            )),
+            _ => Ok(note),
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    #[tool(description = "List the configured Memos servers and the default one.", annotations(title = "List servers", read_only_hint = true))]
+    async fn list_servers(
+        &self,
+    ) -> String {
+        json!({
+            "default": self.servers.default_name(),
+            "servers": self.servers.names().collect::<Vec<_>>(),
+        }).to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "List all notes.", annotations(title = "List notes", read_only_hint = true))]
+    async fn list_memos(
+        &self,
+        Parameters(Listing { fields, output, has, params: ServerParam { server: server_name } }): Parameters<Listing<ServerParam>>,
+    ) -> String {
+        tracing::debug!("Listing memos...");
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let name = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let notes = match self.synced_mirror(name) {
+            Ok(Some(mirror)) => mirror.list(name),
+            Ok(None) => server.list_notes().await,
+            Err(e) => Err(e),
+        };
+        match notes.and_then(|notes| listing(&notes, fields.as_deref(), output, &has, self.timezone())) {
+            Ok(notes) => notes.to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server.as_deref(), since = %since))]
+    #[tool(description = "List notes created or updated at or after a time, to fetch only what changed since the last look. Returns `until`, the latest update time seen, to pass as `since` next time.", annotations(title = "List changed notes", read_only_hint = true))]
+    async fn list_memos_updated_since(
+        &self,
+        Parameters(Listing { fields, output, has, params: UpdatedSinceParam { server, since } }): Parameters<Listing<UpdatedSinceParam>>,
+    ) -> String {
+        let server = match self.servers.get(server.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let since = match dates::span(&since, chrono::Utc::now(), self.timezone()) {
+            Ok(span) => span.start,
+            Err(e) => return error_response(e),
+        };
+        let notes = match server.list_notes_updated_since(since).await {
+            Ok(notes) => notes,
+            Err(e) => return error_response(e),
+        };
+        let until = notes.iter().filter_map(|note| note.update_time).max().unwrap_or(since);
+        match listing(&notes, fields.as_deref(), output, &has, self.timezone()) {
+            Ok(notes) => json!({"memos": notes, "until": until}).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server.as_deref(), user = %user))]
+    #[tool(description = "List the PUBLIC and PROTECTED memos created by a user, e.g. a teammate on a shared instance.", annotations(title = "List notes by creator", read_only_hint = true))]
+    async fn list_memos_by_creator(
+        &self,
+        Parameters(Listing { fields, output, has, params: CreatorParam { server, user } }): Parameters<Listing<CreatorParam>>,
+    ) -> String {
+        let server = match self.servers.get(server.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let id = match user.trim_start_matches("users/").parse::<u64>() {
+            Ok(id) => id,
+            // Not an id, so look the username up.
+            Err(_) => match server.list_users().await {
+                Ok(users) => match users.iter().find(|u| u.username == user).and_then(User::id) {
+                    Some(id) => id,
+                    None => return error_response(anyhow::anyhow!("User {} not found", user)),
+                },
+                Err(e) => return error_response(e),
+            },
+        };
+        match server.list_notes_by_creator(id).await.and_then(|notes| listing(&notes, fields.as_deref(), output, &has, self.timezone())) {
+            Ok(notes) => notes.to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server.as_deref(), days))]
+    #[tool(description = "List the reminders due from today until a number of days ahead, soonest first. A memo is due on the dates of its `@due(2025-02-01)` or `@due(2025-02-01 14:30)` markers, else on the ISO dates in its text, else on a display time set after it was created. Today and times without a zone are in the configured `timezone`, UTC by default.", annotations(title = "List upcoming notes", read_only_hint = true))]
+    async fn list_upcoming_memos(
+        &self,
+        Parameters(UpcomingParam { server, days }): Parameters<UpcomingParam>,
+    ) -> String {
+        let server = match self.servers.get(server.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        match server.list_notes().await {
+            Ok(notes) => json!(calendar::upcoming(&notes, chrono::Utc::now(), days, self.timezone())).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), query = %query))]
+    #[tool(description = "Search notes whose content contains all the given words, case-insensitive, newest first.", annotations(title = "Search notes", read_only_hint = true))]
+    async fn search_memos(
+        &self,
+        Parameters(Listing { fields, output, has, params: SearchParam { server: server_name, query, limit } }): Parameters<Listing<SearchParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let name = server_name.as_deref().unwrap_or(self.servers.default_name());
+        // With `has`, memos are filtered before they are cut to `limit`, so that the limit counts
+        // the memos that have the flags.
+        let notes = match self.memo_index(name, server).await {
+            Ok(index) => index.search(name, &query, has.is_empty().then_some(limit)),
+            Err(e) => Err(e),
+        };
+        let notes = notes.map(|notes| notes.into_iter().filter(|note| fields::has(note, &has)).take(limit).collect::<Vec<_>>());
+        match notes.and_then(|notes| listing(&notes, fields.as_deref(), output, &has, self.timezone())) {
+            Ok(notes) => notes.to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), query = %query))]
+    #[tool(description = "Full-text search over note content and tags ranked by relevance, with highlighted snippets. Supports \"exact phrases\", +required and -excluded words, and tags:name.", annotations(title = "Full-text search", read_only_hint = true))]
+    async fn full_text_search(
+        &self,
+        Parameters(SearchParam { server: server_name, query, limit }): Parameters<SearchParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let name = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let hits = match self.memo_index(name, server).await {
+            Ok(index) => index.full_text_search(name, &query, limit),
+            Err(e) => Err(e),
+        };
+        match hits {
+            Ok(hits) => json!(hits).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), query = %query))]
+    #[tool(description = "Find the notes most related in meaning to a natural-language query, with similarity scores. Requires the local mirror and an embedding provider.", annotations(title = "Semantic search", read_only_hint = true))]
+    async fn semantic_search_memos(
+        &self,
+        Parameters(SearchParam { server: server_name, query, limit }): Parameters<SearchParam>,
+    ) -> String {
+        if let Err(e) = self.servers.get(server_name.as_deref()) {
+            return error_response(e);
+        }
+        let name = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let mirror = match self.synced_mirror(name) {
+            Ok(Some(mirror)) => mirror,
+            Ok(None) => return error_response(anyhow::anyhow!(
+                "Semantic search requires the local mirror, set MEMOS_MIRROR_DB and wait for the first sync"
+            )),
+            Err(e) => return error_response(e),
+        };
+        match mirror.semantic_search(name, &query, limit).await {
+            Ok(hits) => json!(hits).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Suggest notes related to a given note, e.g. to link them. Uses embeddings when semantic search is configured, shared distinctive words otherwise.", annotations(title = "Suggest related notes", read_only_hint = true))]
+    async fn suggest_related_memos(
+        &self,
+        Parameters(RelatedParam { server: server_name, name, limit }): Parameters<RelatedParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let server_name = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let related = match self.memo_index(server_name, server).await {
+            Ok(index) => index.related(server_name, name.as_str(), limit),
+            Err(e) => Err(e),
+        };
+        match related {
+            Ok(related) => json!(related).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "List the tags in use with the number of notes using each, and the total note count. Served from an index refreshed in the background unless refresh is set.", annotations(title = "List tags", read_only_hint = true))]
+    async fn list_tags(
+        &self,
+        Parameters(ListTagsParam { server: server_name, refresh }): Parameters<ListTagsParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let name = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let summary = match self.tags.get(name) {
+            Some(summary) if !refresh => Ok(summary),
+            _ => self.tags.refresh(name, server).await,
+        };
+        match summary {
+            Ok(summary) => json!(summary).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "Count notes by visibility and list the most used tags.", annotations(title = "Note statistics", read_only_hint = true))]
+    async fn memo_stats(
+        &self,
+        Parameters(ServerParam { server: server_name }): Parameters<ServerParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let name = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let stats = match self.memo_index(name, server).await {
+            Ok(index) => index.stats(name),
+            Err(e) => Err(e),
+        };
+        match stats {
+            Ok(stats) => json!(stats).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server.as_deref()))]
+    #[tool(description = "Count the reactions on memos per emoji and per user who reacted, and list the memos with the most, in one call. Takes the memos by name or by a CEL filter, and can count only one emoji or the reactions made in a time range, e.g. which public memos got the most 👍 this month.", annotations(title = "Summarize reactions", read_only_hint = true))]
+    async fn summarize_reactions(
+        &self,
+        Parameters(ReactionSummaryParam { server, names, filter, emoji, since, until, limit }): Parameters<ReactionSummaryParam>,
+    ) -> String {
+        let server = match self.servers.get(server.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let (since, until) = match dates::bounds(since.as_deref(), until.as_deref(), chrono::Utc::now(), self.timezone()) {
+            Ok(range) => range,
+            Err(e) => return error_response(e),
+        };
+        let notes = match (names.is_empty(), filter) {
+            (false, Some(_)) => return error_response(anyhow::anyhow!("Give either `names` or `filter`, not both")),
+            (false, None) => async {
+                let mut notes = Vec::with_capacity(names.len());
+                for name in &names {
+                    notes.push(server.get_note(name).await?);
+                }
+                anyhow::Ok(notes)
+            }.await,
+            (true, Some(filter)) => server.list_notes_matching(&filter).await,
+            (true, None) => server.list_notes().await,
+        };
+        match notes {
+            Ok(notes) => json!(reactions::summarize(&notes, &ReactionFilter { emoji, since, until }, limit)).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "List the PUBLIC and PROTECTED memos of the signed-in user with their snippet and creation time, to check now and then that nothing sensitive is exposed. PUBLIC memos are visible to anyone, PROTECTED ones to every signed-in user. Optionally makes some of them PRIVATE; with dry_run, lists what it would change.", annotations(title = "Audit note visibility", read_only_hint = false))]
+    async fn audit_visibility(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: AuditVisibilityParam { make_private } }): Parameters<OnServer<AuditVisibilityParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let (me, notes) = match tokio::try_join!(self.current_user(server_name.as_deref(), server), server.list_notes()) {
+            Ok(lists) => lists,
+            Err(e) => return error_response(e),
+        };
+        // Memos lists the PUBLIC memos of other users too, which are theirs to audit.
+        let exposed = notes
+            .into_iter()
+            .filter(|note| note.creator.as_deref() == Some(me.name.as_str()))
+            .filter(|note| matches!(note.visibility, Visibility::Public | Visibility::Protected))
+            .collect::<Vec<_>>();
+        if let Some(name) = make_private.iter().find(|name| !exposed.iter().any(|note| note.name.as_deref() == Some(name.as_str()))) {
+            return error_response(anyhow::anyhow!("{} is not one of your PUBLIC or PROTECTED memos", name));
+        }
+        let count = |visibility: Visibility| exposed.iter().filter(|note| note.visibility == visibility).count();
+        let mut response = json!({
+            "public": count(Visibility::Public),
+            "protected": count(Visibility::Protected),
+            "memos": exposed.iter().map(|note| json!({
+                "name": note.name,
+                "visibility": note.visibility,
+                "snippet": note.snippet,
+                "create_time": note.create_time,
+            })).collect::<Vec<_>>(),
+        });
+        if make_private.is_empty() {
+            return response.to_string();
+        }
+        if self.is_dry_run(dry_run) {
+            response["dry_run"] = json!(true);
+            response["would_make_private"] = json!(make_private);
+            return response.to_string();
+        }
+
+        let mut changed = Vec::new();
+        let mut failed = Vec::new();
+        for name in make_private {
+            let before = exposed.iter().find(|note| note.name.as_deref() == Some(name.as_str())).cloned();
+            let mut update = UpdateNote::new(name.clone());
+            update.visibility = Some(Visibility::Private);
+            let outcome = server.update_note(&update).await;
+            self.remember(server_name.as_deref(), Change::Updated, before, &outcome);
+            self.audit(server_name.as_deref(), "audit_visibility", Some(name.as_str()), "made PRIVATE".to_string(), &outcome);
+            match outcome {
+                Ok(_) => changed.push(name),
+                Err(e) => failed.push(json!({"name": name, "error": redact(&e.to_string())})),
+            }
+        }
+        self.sync_mirror(server_name.as_deref(), server, &anyhow::Ok(()), &[]).await;
+        response["made_private"] = json!(changed);
+        response["failed"] = json!(failed);
+        response.to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), a = %a))]
+    #[tool(description = "Show what changes between the content of two memos, or between a memo and a text such as the content an update would set, as a unified diff with the number of lines added and removed. The diff is empty when they are the same.", annotations(title = "Diff notes", read_only_hint = true))]
+    async fn diff_memos(
+        &self,
+        Parameters(DiffParam { server: server_name, a, b, text }): Parameters<DiffParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let old = match server.get_note(&a).await {
+            Ok(note) => note.content,
+            Err(e) => return error_response(e),
+        };
+        let (new, label) = match (b, text) {
+            (Some(b), None) => match server.get_note(&b).await {
+                Ok(note) => (note.content, b.to_string()),
+                Err(e) => return error_response(e),
+            },
+            (None, Some(text)) => (text, "text".to_string()),
+            _ => return error_response(anyhow::anyhow!("Give either `b` or `text` to compare the memo to")),
+        };
+        let diff = diff::unified(&old, &new, a.as_str(), &label);
+        json!({"diff": diff.text, "additions": diff.additions, "deletions": diff.deletions}).to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Tidy the Markdown of a memo: headings nested one level at a time from `#`, `-` bullets and `1.` numbers, no trailing whitespace or repeated blank lines, and lines of only tags gathered into one last line. Code blocks are left as they are. Returns the fixes made and a unified diff; with dry_run the memo is not changed.", annotations(title = "Format a note", read_only_hint = false))]
+    async fn format_memo(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: FormatMemoParam { name } }): Parameters<OnServer<FormatMemoParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let note = match server.get_note(&name).await {
+            Ok(note) => note,
+            Err(e) => return error_response(e),
+        };
+        let formatted = formatter::format(&note.content);
+        let diff = diff::unified(&note.content, &formatted.content, name.as_str(), "formatted");
+        if formatted.fixes.is_empty() || self.is_dry_run(dry_run) {
+            return json!({
+                "dry_run": self.is_dry_run(dry_run),
+                "fixes": formatted.fixes,
+                "diff": diff.text,
+                "additions": diff.additions,
+                "deletions": diff.deletions,
+            }).to_string();
+        }
+
+        let mut update = UpdateNote::new(name.clone());
+        update.content = Some(formatted.content);
+        let before = Some(note);
+        let result = match self.write(dry_run, server.update_note(&update)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.remember(server_name.as_deref(), Change::Updated, before, &result);
+        self.audit(server_name.as_deref(), "format_memo", Some(name.as_str()), format!("fixed {}", formatted.fixes.join(", ")), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(note) => json!({"memo": note, "fixes": formatted.fixes, "diff": diff.text}).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), threshold))]
+    #[tool(description = "Find clusters of duplicate and near-duplicate memos, compared by the overlap of their word sequences regardless of case, punctuation and formatting. Each cluster names the memo suggested to keep (a pinned one, else the oldest) and the others with their similarity to it.", annotations(title = "Find duplicate notes", read_only_hint = true))]
+    async fn find_duplicate_memos(
+        &self,
+        Parameters(DuplicatesParam { server: server_name, threshold }): Parameters<DuplicatesParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        match server.list_notes().await {
+            Ok(notes) => json!(duplicates::clusters(&notes, threshold.clamp(0.0, 1.0))).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "Check the http(s) links in a memo, or in all memos, and report the broken ones: links that fail or answer with an error status. Each link is requested once, with the memos containing it; links on private addresses are reported broken unless `bookmark_private_urls` is set.", annotations(title = "Check note links", read_only_hint = true))]
+    async fn check_memo_links(
+        &self,
+        Parameters(CheckLinksParam { server: server_name, name, concurrency }): Parameters<CheckLinksParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let notes = match &name {
+            Some(name) => server.get_note(name).await.map(|note| vec![note]),
+            None => server.list_notes().await,
+        };
+        let notes = match notes {
+            Ok(notes) => notes,
+            Err(e) => return error_response(e),
+        };
+        let mut urls = BTreeMap::<String, Vec<String>>::new();
+        for note in &notes {
+            for url in links::urls(&note.content) {
+                urls.entry(url).or_default().push(note.name.clone().unwrap_or_default());
+            }
+        }
+        let checked = urls.len();
+        let private_addresses = self.settings.read().unwrap().bookmark_private_urls;
+        let broken = links::check(urls, concurrency.clamp(1, 32), private_addresses)
+            .await
+            .into_iter()
+            .filter(links::LinkStatus::is_broken)
+            .collect::<Vec<_>>();
+        json!({"checked": checked, "broken": broken}).to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "Export the graph of memos, their relations and tags, for Graphviz (`dot`) or a web viewer (`json` node-link). Memos link to the memos they reference or comment on and to their tags; tags used together are linked with the number of memos they share as weight.", annotations(title = "Get note graph", read_only_hint = true))]
+    async fn get_memo_graph(
+        &self,
+        Parameters(GraphParam { server: server_name, format, tag, since, until }): Parameters<GraphParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let (since, until) = match dates::bounds(since.as_deref(), until.as_deref(), chrono::Utc::now(), self.timezone()) {
+            Ok(range) => range,
+            Err(e) => return error_response(e),
+        };
+        let notes = match server.list_notes().await {
+            Ok(notes) => notes,
+            Err(e) => return error_response(e),
+        };
+        let tag = tag.as_deref().map(|tag| tag.trim_start_matches('#'));
+        let notes = notes
+            .into_iter()
+            .filter(|note| tag.is_none_or(|tag| note.tags.iter().any(|t| t == tag)))
+            .filter(|note| since.is_none_or(|since| note.create_time.is_some_and(|time| time >= since)))
+            .filter(|note| until.is_none_or(|until| note.create_time.is_some_and(|time| time < until)))
+            .collect::<Vec<_>>();
+        let graph = Graph::new(&notes);
+        match format {
+            GraphFormat::Json => graph.node_link().to_string(),
+            GraphFormat::Dot => graph.dot(),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server.as_deref(), memo = %name))]
+    #[tool(description = "Get a memo (note) by its name field. Use offset and max_chars to read a long memo in parts; the response then includes the total content length. get_memo_section reads the part under one heading.", annotations(title = "Get a note", read_only_hint = true))]
+    async fn get_memo(
+        &self,
+        Parameters(GetMemoParam { server, name, offset, max_chars, front_matter }): Parameters<GetMemoParam>,
+    ) -> String {
+        let server = match self.servers.get(server.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let mut note = match server.get_note(&name).await {
+            Ok(note) => note,
+            Err(e) => return error_response(e),
+        };
+        if front_matter {
+            note.content = match front_matter::render(&note) {
+                Ok(content) => content,
+                Err(e) => return error_response(e),
+            };
+        }
+        if offset == 0 && max_chars.is_none() {
+            return json!(note).to_string();
+        }
+        let total = note.content.chars().count();
+        note.content = content_window(&note.content, offset, max_chars);
+        let mut value = json!(note);
+        value["contentRange"] = json!({
+            "offset": offset,
+            "length": note.content.chars().count(),
+            "total": total,
+        });
+        value.to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server.as_deref(), memo = %name))]
+    #[tool(description = "Get the part of a memo under a Markdown heading, down to the next heading of the same or a higher level, for reading one section of a long memo. Lists the headings when none matches.", annotations(title = "Get a section of a note", read_only_hint = true))]
+    async fn get_memo_section(
+        &self,
+        Parameters(GetMemoSectionParam { server, name, heading }): Parameters<GetMemoSectionParam>,
+    ) -> String {
+        let server = match self.servers.get(server.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let note = match server.get_note(&name).await {
+            Ok(note) => note,
+            Err(e) => return error_response(e),
+        };
+        match sections::find(&note.content, &heading) {
+            Ok(section) => json!({
+                "name": note.name,
+                "heading": section.title,
+                "level": section.level,
+                "content": note.content[section.body].trim_matches('\n'),
+            }).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Replace the part of a memo under a Markdown heading, down to the next heading of the same or a higher level, leaving the heading and the rest of the memo as they are. Read the section first with get_memo_section.", annotations(title = "Update a section of a note", read_only_hint = false))]
+    async fn update_memo_section(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: UpdateMemoSectionParam { name, heading, content } }): Parameters<OnServer<UpdateMemoSectionParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let content = match server.get_note(&name).await {
+            Ok(note) => match sections::replace(&note.content, &heading, &content) {
+                Ok(content) => content,
+                Err(e) => return error_response(e),
+            },
+            Err(e) => return error_response(e),
+        };
+        let mut update = UpdateNote::new(name);
+        update.content = Some(content);
+        self.apply_update(server_name.as_deref(), dry_run, update, "update_memo_section").await
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "Create a new memo (note) with given content. YAML front matter at the start of the content sets the visibility, tags, pinned flag and display time instead of being stored. Pass an idempotency key to make retries safe.", annotations(title = "Create a note", read_only_hint = false))]
+    async fn create_memo(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: CreateMemoParam { mut note, idempotency_key } }): Parameters<OnServer<CreateMemoParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        if let Err(e) = front_matter::apply(&mut note) {
+            return error_response(e);
+        }
+        // Keys are only claimed for real creates, a dry run leaves them free.
+        let key = idempotency_key.filter(|_| !self.is_dry_run(dry_run));
+        let server_key = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let mut pending = None;
+        if let Some(key) = &key {
+            let mut hasher = DefaultHasher::new();
+            json!(note).to_string().hash(&mut hasher);
+            match self.idempotency_keys.claim(server_key, key, hasher.finish()) {
+                Ok(Claim::New(claimed)) => pending = Some(claimed),
+                Ok(Claim::Created(note)) => return with_warning(
+                    json!(note),
+                    Some(format!("The memo was already created with idempotency key {}, no new memo was created", key)),
+                ),
+                Err(e) => return error_response(e),
+            }
+        }
+        let warning = match self.limit_content(&mut note.content) {
+            Ok(warning) => warning,
+            Err(e) => return error_response(e),
+        };
+        let defaults = self.settings.read().unwrap().memo_defaults.clone();
+        apply_memo_defaults(&mut note, &defaults);
+        match self.current_user(server_name.as_deref(), server).await {
+            Ok(me) if note.creator.as_deref().is_some_and(|creator| creator != me.name) => {
+                return error_response(anyhow::anyhow!("Memos are created by the signed-in user {}, not {}", me.name, note.creator.as_deref().unwrap()));
+            }
+            Ok(me) => note.creator = Some(me.name),
+            Err(e) => tracing::warn!("Failed to look up the creator of the new memo: {}", e),
+        }
+        let result = match self.write(dry_run, server.create_note(&note)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        // Dropped without completing, the claim of a failed or cancelled create releases the key.
+        if let (Some(pending), Ok(created)) = (pending, &result) {
+            pending.complete(created);
+        }
+        let created = result.as_ref().ok().and_then(|note| note.name.as_deref());
+        self.audit(server_name.as_deref(), "create_memo", created, format!("created: {}", audit::preview(&note.content)), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(note) => with_warning(json!(note), warning),
+            Err(e) => error_response(e),
+        }
+    }
 
-#[derive(schemars::JsonSchema, serde::Deserialize)]
-struct CommentMemoParam {
-    #[schemars(description = "The name of the memo to comment on.")]
-    memo_name: String,
-    comment: Note,
-}
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), url = %url))]
+    #[tool(description = "Bookmark a web page: fetch it and create a memo with a link titled like the page, its description or first paragraph as summary, and the `#bookmark` tag. Optionally attach the readable text of the page as a snapshot.", annotations(title = "Bookmark a URL", read_only_hint = false))]
+    async fn bookmark_url(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: BookmarkParam { url, snapshot } }): Parameters<OnServer<BookmarkParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let private_addresses = self.settings.read().unwrap().bookmark_private_urls;
+        let page = match Page::fetch(&url, private_addresses).await {
+            Ok(page) => page,
+            Err(e) => return error_response(e),
+        };
+        let mut note = Note::new(&page.memo_content());
+        let warning = match self.limit_content(&mut note.content) {
+            Ok(warning) => warning,
+            Err(e) => return error_response(e),
+        };
+        let defaults = self.settings.read().unwrap().memo_defaults.clone();
+        apply_memo_defaults(&mut note, &defaults);
 
-pub struct MemoMCP {
-    tool_router: ToolRouter<MemoMCP>,
-    server: Server,
-}
+        let write = async {
+            let created = server.create_note(&note).await?;
+            if snapshot && !page.text.is_empty() {
+                let name = created.name.as_deref().unwrap_or_default().parse::<MemoName>()?;
+                let attachment = server.create_attachment(&snapshot_file_name(&page.title), "text/plain", page.text.as_bytes()).await?;
+                server.set_note_attachments(&name, &vec![attachment]).await?;
+                return server.get_note(&name).await;
+            }
+            Ok(created)
+        };
+        let result = match self.write(dry_run, write).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        let created = result.as_ref().ok().and_then(|note| note.name.as_deref());
+        self.audit(server_name.as_deref(), "bookmark_url", created, format!("bookmarked {}", url), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(note) => with_warning(json!(note), warning),
+            Err(e) => error_response(e),
+        }
+    }
 
-#[tool_router]
-impl MemoMCP {
-    pub fn new(host: &str, token: &str) -> Self {
-        Self {
-            tool_router: Self::tool_router(),
-            server: Server::new(host, token),
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %update.name))]
+    #[tool(description = "Update an existing memo (note) by its name field. Only the fields given are changed, the rest of the memo stays as it is. YAML front matter at the start of new content sets the fields not given, like in create_memo.", annotations(title = "Update a note", read_only_hint = false))]
+    async fn update_memo(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: mut update }): Parameters<OnServer<UpdateNote>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        if let Err(e) = front_matter::apply_to_update(&mut update) {
+            return error_response(e);
+        }
+        let warning = match update.content.as_mut().map(|content| self.limit_content(content)).transpose() {
+            Ok(warning) => warning.flatten(),
+            Err(e) => return error_response(e),
+        };
+        let before = match self.owned_note(server_name.as_deref(), server, &update.name).await {
+            Ok(note) => Some(note).filter(|_| !self.is_dry_run(dry_run)),
+            Err(e) => return error_response(e),
+        };
+        let result = match self.write(dry_run, server.update_note(&update)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.remember(server_name.as_deref(), Change::Updated, before, &result);
+        let summary = match &update.content {
+            Some(content) => format!("updated content: {}", audit::preview(content)),
+            None => format!("updated {}", update.update_mask()),
+        };
+        self.audit(server_name.as_deref(), "update_memo", Some(update.name.as_str()), summary, &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(note) => with_warning(json!(note), warning),
+            Err(e) => error_response(e),
         }
     }
 
-    #[tool(description = "List all notes.", annotations(title = "List notes", read_only_hint = true))]
-    async fn list_memos(
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Set where a memo was written, as coordinates and an optional place name.", annotations(title = "Set note location", read_only_hint = false))]
+    async fn set_memo_location(
         &self,
-        _params: Parameters<serde_json::Value>,
+        Parameters(OnServer { server: server_name, dry_run, params: SetLocationParam { name, latitude, longitude, placeholder } }): Parameters<OnServer<SetLocationParam>>,
     ) -> String {
-        tracing::debug!("Listing memos...");
-        match self.server.list_notes().await {
-            Ok(notes) => json!(notes).to_string(),
-            Err(e) => json!({"error": e.to_string()}).to_string(),
+        let mut update = UpdateNote::new(name);
+        update.location = match Location::new(latitude, longitude) {
+            Ok(location) => Some(Location { placeholder, ..location }),
+            Err(e) => return error_response(e),
+        };
+        self.apply_update(server_name.as_deref(), dry_run, update, "set_memo_location").await
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Set the time a memo is filed under, to backdate a journal entry imported or written late. Memos lists and shows memos by this time; the creation time stays as it is.", annotations(title = "Set note display time", read_only_hint = false))]
+    async fn set_memo_display_time(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: SetDisplayTimeParam { name, display_time } }): Parameters<OnServer<SetDisplayTimeParam>>,
+    ) -> String {
+        let mut update = UpdateNote::new(name);
+        update.display_time = Some(display_time);
+        self.apply_update(server_name.as_deref(), dry_run, update, "set_memo_display_time").await
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "List the memos with a location within a radius of a point, nearest first, each with its distance in kilometres.", annotations(title = "List notes nearby", read_only_hint = true))]
+    async fn list_memos_near(
+        &self,
+        Parameters(NearParam { server: server_name, latitude, longitude, radius_km }): Parameters<NearParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let notes = match server.list_notes().await {
+            Ok(notes) => notes,
+            Err(e) => return error_response(e),
+        };
+        let mut near = notes
+            .into_iter()
+            .filter_map(|note| {
+                let distance = note.location.as_ref()?.distance_km(latitude, longitude);
+                (distance <= radius_km).then_some((distance, note))
+            })
+            .collect::<Vec<_>>();
+        near.sort_by(|a, b| a.0.total_cmp(&b.0));
+        json!(near.into_iter().map(|(distance, memo)| json!({"distanceKm": distance, "memo": memo})).collect::<Vec<_>>()).to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "List the Markdown task items (`- [ ] ...` and `- [x] ...`) in all memos but the one compiled by `sync_open_tasks`, each with its memo, line index, whether it is done and its text.", annotations(title = "List tasks", read_only_hint = true))]
+    async fn list_tasks(
+        &self,
+        Parameters(TasksParam { server: server_name, done }): Parameters<TasksParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        match server.list_notes().await {
+            Ok(notes) => json!(notes
+                .iter()
+                .filter(|note| !tasks::is_open_tasks(note))
+                .flat_map(tasks::tasks)
+                .filter(|task| done.is_none_or(|done| task.done == done))
+                .collect::<Vec<_>>()).to_string(),
+            Err(e) => error_response(e),
         }
     }
 
-    #[tool(description = "Get a memo (note) by its name field.", annotations(title = "Get a note", read_only_hint = true))]
-    async fn get_memo(
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), days, save))]
+    #[tool(description = "Generate a Markdown review of the memos created or updated in the past days: counts, most used tags, pinned memos and completed tasks. Optionally save it as a new memo tagged `#review`; earlier reviews are left out of later ones.", annotations(title = "Generate weekly review", read_only_hint = false))]
+    async fn generate_weekly_review(
         &self,
-        Parameters(MemoNameParam { name }): Parameters<MemoNameParam>,
+        Parameters(OnServer { server: server_name, dry_run, params: ReviewParam { days, save } }): Parameters<OnServer<ReviewParam>>,
     ) -> String {
-        match self.server.get_note(&name).await {
-            Ok(note) => json!(note).to_string(),
-            Err(e) => json!({"error": e.to_string()}).to_string(),
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let report = match server.list_notes().await.and_then(|notes| Review::new(&notes, chrono::Utc::now().with_timezone(&self.timezone()), days)) {
+            Ok(review) => review.markdown(),
+            Err(e) => return error_response(e),
+        };
+        if !save {
+            return json!({"report": report}).to_string();
+        }
+
+        let note = Note::new(&format!("{}\n#{}", report, REVIEW_TAG));
+        let result = match self.write(dry_run, server.create_note(&note)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        let created = result.as_ref().ok().and_then(|note| note.name.as_deref());
+        self.audit(server_name.as_deref(), "generate_weekly_review", created, format!("created review of {} days", days), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(note) => json!({"report": report, "memo": note}).to_string(),
+            Err(e) => error_response(e),
         }
     }
 
-    #[tool(description = "Create a new memo (note) with given content.", annotations(title = "Create a note", read_only_hint = false))]
-    async fn create_memo(
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), days))]
+    #[tool(description = "Compile the unchecked tasks of the memos changed in the past days into a single pinned memo titled `# Open tasks`, related to the memos they come from. The memo is created on the first call and rewritten on later ones; check tasks off in their own memos, e.g. with `toggle_task`.", annotations(title = "Sync open tasks", read_only_hint = false))]
+    async fn sync_open_tasks(
         &self,
-        Parameters(note): Parameters<Note>,
+        Parameters(OnServer { server: server_name, dry_run, params: OpenTasksParam { days } }): Parameters<OnServer<OpenTasksParam>>,
     ) -> String {
-        match self.server.create_note(&note).await {
-            Ok(note) => json!(note).to_string(),
-            Err(e) => json!({"error": e.to_string()}).to_string(),
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let since = match dates::days_before(chrono::Utc::now(), days) {
+            Ok(since) => since,
+            Err(e) => return error_response(e),
+        };
+        let notes = match server.list_notes().await {
+            Ok(notes) => notes,
+            Err(e) => return error_response(e),
+        };
+        let existing = notes.iter().find(|note| tasks::is_open_tasks(note)).cloned();
+        let open = notes
+            .iter()
+            .filter(|note| !tasks::is_open_tasks(note) && note.update_time.or(note.create_time).is_some_and(|time| time >= since))
+            .flat_map(tasks::tasks)
+            .filter(|task| !task.done)
+            .collect::<Vec<_>>();
+        let content = tasks::open_tasks_content(&open);
+        let sources = open.iter().filter_map(|task| task.memo.parse::<MemoName>().ok()).collect::<BTreeSet<_>>();
+
+        let write = async {
+            let note = match existing.as_ref().and_then(|note| note.name.as_deref()) {
+                Some(name) => {
+                    let mut update = UpdateNote::new(name.parse()?);
+                    update.content = Some(content.clone());
+                    update.pinned = Some(true);
+                    server.update_note(&update).await?
+                }
+                None => {
+                    let created = server.create_note(&Note::new(&content)).await?;
+                    let mut update = UpdateNote::new(created.name.as_deref().unwrap_or_default().parse()?);
+                    update.pinned = Some(true);
+                    server.update_note(&update).await?
+                }
+            };
+            let name = note.name.as_deref().unwrap_or_default().parse::<MemoName>()?;
+            let relations = sources.iter().map(|source| Relation::new(&name, source, RelationType::Reference)).collect();
+            server.set_note_relations(&name, &relations).await?;
+            Ok(note)
+        };
+        let result = match self.write(dry_run, write).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        let before = existing.filter(|_| !self.is_dry_run(dry_run));
+        self.remember(server_name.as_deref(), Change::Updated, before, &result);
+        let synced = result.as_ref().ok().and_then(|note| note.name.as_deref());
+        self.audit(server_name.as_deref(), "sync_open_tasks", synced, format!("synced {} open tasks", open.len()), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(note) => json!({"memo": note, "tasks": open.len()}).to_string(),
+            Err(e) => error_response(e),
         }
     }
 
-    #[tool(description = "Update an existing memo (note) by its name field.", annotations(title = "Update a note", read_only_hint = false))]
-    async fn update_memo(
+    #[tracing::instrument(skip_all)]
+    #[tool(description = "List the macros configured for `run_macro`, with their parameters and steps.", annotations(title = "List macros", read_only_hint = true))]
+    async fn list_macros(
         &self,
-        Parameters(note): Parameters<Note>,
     ) -> String {
-        match self.server.update_note(&note).await {
-            Ok(note) => json!(note).to_string(),
-            Err(e) => json!({"error": e.to_string()}).to_string(),
+        let macros = self.settings.read().unwrap().macros
+            .iter()
+            .map(|entry| json!({
+                "name": entry.name,
+                "description": entry.description,
+                "params": entry.params,
+                "creates_memo": macros::creates_memo(entry),
+                "steps": entry.steps.len(),
+            }))
+            .collect::<Vec<_>>();
+        json!({"macros": macros}).to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), name = %name))]
+    #[tool(description = "Run a macro configured in the server, a named sequence of memo operations such as create, tag, pin, comment and link, see `list_macros`. The steps run in order; when one fails, the earlier ones are undone and the error tells which step failed.", annotations(title = "Run a macro", read_only_hint = false))]
+    async fn run_macro(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: RunMacroParam { name, arguments, memo } }): Parameters<OnServer<RunMacroParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let Some(entry) = self.settings.read().unwrap().macros.iter().find(|entry| entry.name == name).cloned() else {
+            return error_response(anyhow::anyhow!("Unknown macro {}, see `list_macros`", name));
+        };
+        let before = match &memo {
+            Some(memo) => self.snapshot(server, memo, dry_run).await,
+            None => None,
+        };
+        let target = memo.as_ref().map(MemoName::to_string);
+        let run = macros::run(server, &entry, &arguments, memo, dates::today(chrono::Utc::now(), self.timezone()));
+        let result = match self.write(dry_run, run).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.remember(server_name.as_deref(), Change::Updated, before, &result);
+        let target = target.or_else(|| result.as_ref().ok().and_then(|outcome| outcome.memo.name.clone()));
+        self.audit(server_name.as_deref(), "run_macro", target.as_deref(), format!("ran macro {}", name), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(outcome) => json!(outcome).to_string(),
+            Err(e) => error_response(e),
         }
     }
 
-    #[tool(description = "Delete a memo (note) by its name field.", annotations(title = "Delete a note", read_only_hint = false))]
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name, line))]
+    #[tool(description = "Check or uncheck a Markdown task item of a memo by its line index from `list_tasks`. Only that line of the memo changes.", annotations(title = "Toggle a task", read_only_hint = false))]
+    async fn toggle_task(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: ToggleTaskParam { name, line, done } }): Parameters<OnServer<ToggleTaskParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let content = match server.get_note(&name).await {
+            Ok(note) => match tasks::toggle(&note.content, line, done) {
+                Ok(content) => content,
+                Err(e) => return error_response(e),
+            },
+            Err(e) => return error_response(e),
+        };
+        let mut update = UpdateNote::new(name);
+        update.content = Some(content);
+        self.apply_update(server_name.as_deref(), dry_run, update, "toggle_task").await
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Delete a memo (note) by its name. When soft delete is configured the memo is archived instead, see `hard_delete_memo`. When deletes must be confirmed, the first call returns a confirmation token and the memo it would delete; call again with that token to delete it.", annotations(title = "Delete a note", read_only_hint = false))]
     async fn delete_memo(
         &self,
-        Parameters(note): Parameters<Note>,
+        Parameters(DeleteMemoParam { server: server_name, dry_run, name, confirmation_token }): Parameters<DeleteMemoParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let before = match self.owned_note(server_name.as_deref(), server, &name).await {
+            Ok(note) => Some(note).filter(|_| !self.is_dry_run(dry_run)),
+            Err(e) => return error_response(e),
+        };
+        let names = std::slice::from_ref(&name);
+        if let Err(response) = self.confirm_delete(server_name.as_deref(), server, names, confirmation_token.as_deref(), dry_run).await {
+            return response;
+        }
+        if self.settings.read().unwrap().soft_delete {
+            let result = match self.write(dry_run, server.archive_note(&name)).await {
+                Ok(result) => result,
+                Err(response) => return response,
+            };
+            self.remember(server_name.as_deref(), Change::Archived, before, &result);
+            self.audit(server_name.as_deref(), "delete_memo", Some(name.as_str()), "archived instead of deleted".to_string(), &result);
+            self.sync_mirror(server_name.as_deref(), server, &result, &[name.as_str()]).await;
+            return match result {
+                Ok(note) => json!({"status": "archived", "memo": note}).to_string(),
+                Err(e) => error_response(e),
+            };
+        }
+        let result = match self.write(dry_run, server.delete_note(&name)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.remember(server_name.as_deref(), Change::Deleted, before, &result);
+        self.audit(server_name.as_deref(), "delete_memo", Some(name.as_str()), "deleted".to_string(), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[name.as_str()]).await;
+        match result {
+            Ok(_) => json!({"status": "success"}).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memos = names.len()))]
+    #[tool(description = "Delete several memos (notes) by their names, archiving them when soft delete is configured. Reports which memos were deleted and which failed. When deletes must be confirmed, the first call returns a confirmation token and the memos it would delete; call again with the same names and that token to delete them.", annotations(title = "Delete notes", read_only_hint = false))]
+    async fn batch_delete_memos(
+        &self,
+        Parameters(BatchDeleteParam { server: server_name, dry_run, names, confirmation_token }): Parameters<BatchDeleteParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        if names.is_empty() {
+            return error_response(anyhow::anyhow!("No memo names given"));
+        }
+        if let Err(response) = self.confirm_delete(server_name.as_deref(), server, &names, confirmation_token.as_deref(), dry_run).await {
+            return response;
+        }
+        let soft_delete = self.settings.read().unwrap().soft_delete;
+        let mut before = Vec::with_capacity(names.len());
+        for name in &names {
+            before.push(self.snapshot(server, name, dry_run).await);
+        }
+        let deletes = async {
+            let mut outcomes = Vec::with_capacity(names.len());
+            for name in &names {
+                let outcome = match soft_delete {
+                    true => server.archive_note(name).await.map(|_| ()),
+                    false => server.delete_note(name).await,
+                };
+                outcomes.push((name, outcome));
+            }
+            anyhow::Ok(outcomes)
+        };
+        let outcomes = match self.write(dry_run, deletes).await {
+            Ok(Ok(outcomes)) => outcomes,
+            Ok(Err(e)) => return error_response(e),
+            Err(response) => return response,
+        };
+
+        let (change, summary) = match soft_delete {
+            true => (Change::Archived, "archived instead of deleted"),
+            false => (Change::Deleted, "deleted"),
+        };
+        let mut deleted = Vec::new();
+        let mut failed = Vec::new();
+        for ((name, outcome), before) in outcomes.into_iter().zip(before) {
+            self.remember(server_name.as_deref(), change, before, &outcome);
+            self.audit(server_name.as_deref(), "batch_delete_memos", Some(name.as_str()), summary.to_string(), &outcome);
+            match outcome {
+                Ok(()) => deleted.push(name),
+                Err(e) => failed.push(json!({"name": name, "error": redact(&e.to_string())})),
+            }
+        }
+        let removed = deleted.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+        self.sync_mirror(server_name.as_deref(), server, &anyhow::Ok(()), &removed).await;
+        json!({
+            "status": if soft_delete { "archived" } else { "deleted" },
+            "deleted": deleted,
+            "failed": failed,
+        }).to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), older_than_days))]
+    #[tool(description = "Archive the memos not created or updated in a number of days, optionally only those with a tag or containing a text, for periodic cleanup of fleeting notes. Pinned memos are kept unless included. With dry_run, lists the memos it would archive. Reports which memos were archived and which failed.", annotations(title = "Archive old notes", read_only_hint = false))]
+    async fn archive_old_memos(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params }): Parameters<OnServer<ArchiveOldParam>>,
+    ) -> String {
+        let ArchiveOldParam { older_than_days, tag, query, include_pinned, concurrency } = params;
+        let server = match self.servers.shared(server_name.as_deref()) {
+            Ok(server) => server.clone(),
+            Err(e) => return error_response(e),
+        };
+        let cutoff = match dates::days_before(chrono::Utc::now(), older_than_days) {
+            Ok(cutoff) => cutoff,
+            Err(e) => return error_response(e),
+        };
+        let notes = match server.list_notes().await {
+            Ok(notes) => notes,
+            Err(e) => return error_response(e),
+        };
+        let tag = tag.as_deref().map(|tag| tag.trim_start_matches('#'));
+        let query = query.map(|query| query.to_lowercase());
+        let old = notes
+            .into_iter()
+            .filter(|note| note.update_time.or(note.create_time).is_some_and(|time| time < cutoff))
+            .filter(|note| include_pinned || !note.pinned)
+            .filter(|note| tag.is_none_or(|tag| note.tags.iter().any(|t| t == tag)))
+            .filter(|note| query.as_deref().is_none_or(|query| note.content.to_lowercase().contains(query)))
+            .filter_map(|note| Some((note.name.as_deref()?.parse::<MemoName>().ok()?, note)))
+            .collect::<Vec<_>>();
+        if self.is_dry_run(dry_run) {
+            return json!({"dry_run": true, "memos": old.iter().map(|(name, _)| name).collect::<Vec<_>>()}).to_string();
+        }
+
+        // Memos archives one memo per request, so a few run at once.
+        let permits = Arc::new(tokio::sync::Semaphore::new(concurrency.clamp(1, 16)));
+        let request_limit = memos::request_limit();
+        let mut archives = tokio::task::JoinSet::new();
+        for (name, note) in old {
+            let (server, permits, request_limit) = (server.clone(), permits.clone(), request_limit.clone());
+            archives.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let archive = server.archive_note(&name);
+                let outcome = match request_limit {
+                    Some(limit) => memos::limit_requests(limit, archive).await,
+                    None => archive.await,
+                };
+                (name, note, outcome.map(|_| ()))
+            });
+        }
+        let mut outcomes = archives.join_all().await;
+        outcomes.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+        let summary = format!("archived, not changed in {} days", older_than_days);
+        let mut archived = Vec::new();
+        let mut failed = Vec::new();
+        for (name, note, outcome) in outcomes {
+            self.remember(server_name.as_deref(), Change::Archived, Some(note), &outcome);
+            self.audit(server_name.as_deref(), "archive_old_memos", Some(name.as_str()), summary.clone(), &outcome);
+            match outcome {
+                Ok(()) => archived.push(name),
+                Err(e) => failed.push(json!({"name": name, "error": redact(&e.to_string())})),
+            }
+        }
+        let removed = archived.iter().map(MemoName::as_str).collect::<Vec<_>>();
+        self.sync_mirror(server_name.as_deref(), server.as_ref(), &anyhow::Ok(()), &removed).await;
+        json!({"archived": archived, "failed": failed}).to_string()
+    }
+
+    #[tracing::instrument(skip_all)]
+    #[tool(description = "Undo the most recent update or delete made in this session by writing back the memo as it was before. A deleted memo is recreated under a new name. Only recent changes are kept, and only while the server runs.", annotations(title = "Undo the last change", read_only_hint = false))]
+    async fn undo_last_change(
+        &self,
+        Parameters(UndoParam { dry_run }): Parameters<UndoParam>,
+    ) -> String {
+        match self.undo.take_last(&self.session_id) {
+            Some(snapshot) => self.restore("undo_last_change", snapshot, dry_run).await,
+            None => error_response(anyhow::anyhow!("No change of this session left to undo")),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Restore a memo deleted or archived through this server, by the name it had. A deleted memo is recreated under a new name, an archived one is unarchived. Only recent deletes are kept, and only while the server runs.", annotations(title = "Restore a deleted note", read_only_hint = false))]
+    async fn restore_deleted_memo(
+        &self,
+        Parameters(RestoreMemoParam { server: server_name, dry_run, name }): Parameters<RestoreMemoParam>,
+    ) -> String {
+        let server = server_name.as_deref().unwrap_or(self.servers.default_name());
+        match self.undo.take_deleted(server, name.as_str()) {
+            Some(snapshot) => self.restore("restore_deleted_memo", snapshot, dry_run).await,
+            None => error_response(anyhow::anyhow!("No deleted memo {} in the undo history of {}", name, server)),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "List the saved versions of a memo, newest first. A version of the memo is saved locally before every change made through this server; Memos itself keeps no history. Requires the local mirror.", annotations(title = "List note versions", read_only_hint = true))]
+    async fn list_memo_versions(
+        &self,
+        Parameters(MemoNameParam { server: server_name, name }): Parameters<MemoNameParam>,
+    ) -> String {
+        if let Err(e) = self.servers.get(server_name.as_deref()) {
+            return error_response(e);
+        }
+        let server = server_name.as_deref().unwrap_or(self.servers.default_name());
+        match self.version_store().and_then(|mirror| mirror.versions(server, name.as_str())) {
+            Ok(versions) => json!(versions).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name, version))]
+    #[tool(description = "Get a saved version of a memo, the memo as it was before a change made through this server. Requires the local mirror.", annotations(title = "Get a note version", read_only_hint = true))]
+    async fn get_memo_version(
+        &self,
+        Parameters(GetVersionParam { server: server_name, name, version }): Parameters<GetVersionParam>,
+    ) -> String {
+        if let Err(e) = self.servers.get(server_name.as_deref()) {
+            return error_response(e);
+        }
+        let server = server_name.as_deref().unwrap_or(self.servers.default_name());
+        match self.version_store().and_then(|mirror| mirror.version(server, name.as_str(), version)) {
+            Ok(Some(note)) => json!(note).to_string(),
+            Ok(None) => error_response(anyhow::anyhow!("No version {} of {}", version, name)),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name, version))]
+    #[tool(description = "Set the content of a memo back to a saved version. The current content is saved as a new version first, so the revert can itself be reverted. Requires the local mirror.", annotations(title = "Revert a note to a version", read_only_hint = false))]
+    async fn revert_memo_to_version(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: RevertParam { name, version } }): Parameters<OnServer<RevertParam>>,
+    ) -> String {
+        if let Err(e) = self.servers.get(server_name.as_deref()) {
+            return error_response(e);
+        }
+        let server = server_name.as_deref().unwrap_or(self.servers.default_name());
+        let content = match self.version_store().and_then(|mirror| mirror.version(server, name.as_str(), version)) {
+            Ok(Some(note)) => note.content,
+            Ok(None) => return error_response(anyhow::anyhow!("No version {} of {}", version, name)),
+            Err(e) => return error_response(e),
+        };
+        let mut update = UpdateNote::new(name);
+        update.content = Some(content);
+        self.apply_update(server_name.as_deref(), dry_run, update, "revert_memo_to_version").await
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Permanently delete a memo (note) by its name, even when soft delete is configured. This cannot be undone.", annotations(title = "Permanently delete a note", read_only_hint = false, destructive_hint = true))]
+    async fn hard_delete_memo(
+        &self,
+        Parameters(DeleteMemoParam { server: server_name, dry_run, name, confirmation_token }): Parameters<DeleteMemoParam>,
     ) -> String {
-        match self.server.delete_note(note.name.as_ref().unwrap()).await {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let names = std::slice::from_ref(&name);
+        if let Err(response) = self.confirm_delete(server_name.as_deref(), server, names, confirmation_token.as_deref(), dry_run).await {
+            return response;
+        }
+        let before = self.snapshot(server, &name, dry_run).await;
+        let result = match self.write(dry_run, server.delete_note(&name)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.remember(server_name.as_deref(), Change::Deleted, before, &result);
+        self.audit(server_name.as_deref(), "hard_delete_memo", Some(name.as_str()), "permanently deleted".to_string(), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[name.as_str()]).await;
+        match result {
             Ok(_) => json!({"status": "success"}).to_string(),
-            Err(e) => json!({"error": e.to_string()}).to_string(),
+            Err(e) => error_response(e),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %memo_name))]
     #[tool(description = "Create a memo (note) comment.", annotations(title = "Create a note comment", read_only_hint = false))]
     async fn create_memo_comment(
         &self,
-        Parameters(CommentMemoParam{ memo_name, comment }): Parameters<CommentMemoParam>,
+        Parameters(CommentMemoParam { server: server_name, dry_run, memo_name, mut comment }): Parameters<CommentMemoParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let warning = match self.limit_content(&mut comment.content) {
+            Ok(warning) => warning,
+            Err(e) => return error_response(e),
+        };
+        let result = match self.write(dry_run, server.create_note_comment(&memo_name, &comment)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.audit(server_name.as_deref(), "create_memo_comment", Some(memo_name.as_str()), format!("commented: {}", audit::preview(&comment.content)), &result);
+        match result {
+            Ok(comment) => with_warning(json!(comment), warning),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), comment = %comment))]
+    #[tool(description = "Reply to a comment. Memos comments are flat, so the reply is a new comment on the same memo that starts with a quote of the comment it answers, naming its author, and references that comment.", annotations(title = "Reply to a comment", read_only_hint = false))]
+    async fn reply_to_comment(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: ReplyParam { comment, content } }): Parameters<OnServer<ReplyParam>>,
     ) -> String {
-        match self.server.create_note_comment(&memo_name, &comment).await {
-            Ok(comment) => json!(comment).to_string(),
-            Err(e) => json!({"error": e.to_string()}).to_string(),
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let replied = match server.get_note(&comment).await {
+            Ok(note) => note,
+            Err(e) => return error_response(e),
+        };
+        let memo = match replied.parent().map(str::parse::<MemoName>) {
+            Some(Ok(memo)) => memo,
+            Some(Err(e)) => return error_response(e),
+            None => return error_response(anyhow::anyhow!("{} is not a comment, use create_memo_comment to comment on a memo", comment)),
+        };
+        let mut reply = Note::new(&reply_content(&replied, &comment, &content));
+        reply.visibility = replied.visibility.clone();
+        let warning = match self.limit_content(&mut reply.content) {
+            Ok(warning) => warning,
+            Err(e) => return error_response(e),
+        };
+        let write = async {
+            let created = server.create_note_comment(&memo, &reply).await?;
+            let name = created.memo_name()?;
+            server.set_note_relations(&name, &vec![Relation::new(&name, &comment, RelationType::Reference)]).await?;
+            Ok(created)
+        };
+        let result = match self.write(dry_run, write).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.audit(server_name.as_deref(), "reply_to_comment", Some(comment.as_str()), format!("replied: {}", audit::preview(&content)), &result);
+        match result {
+            Ok(reply) => with_warning(json!(reply), warning),
+            Err(e) => error_response(e),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(server = server.as_deref(), memo = %name))]
     #[tool(description = "List comments of a memo (note) by its name field.", annotations(title = "List note comments", read_only_hint = true))]
     async fn list_memo_comments(
         &self,
-        Parameters(MemoNameParam { name }): Parameters<MemoNameParam>,
+        Parameters(MemoNameParam { server, name }): Parameters<MemoNameParam>,
     ) -> String {
-        match self.server.list_note_comments(&name).await {
+        let server = match self.servers.get(server.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        match server.list_note_comments(&name).await {
             Ok(comments) => json!(comments).to_string(),
-            Err(e) => json!({"error": e.to_string()}).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), folder = %folder))]
+    #[tool(description = "Export all memos as an Obsidian vault into a folder of the export directory: a Markdown file per memo with YAML front matter, memo references as [[wikilinks]] and attachments downloaded into assets/.", annotations(title = "Export to Obsidian", read_only_hint = false))]
+    async fn export_obsidian_vault(
+        &self,
+        Parameters(ExportParam { server: server_name, folder }): Parameters<ExportParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let dir = match self.export_path(&folder) {
+            Ok(dir) => dir,
+            Err(e) => return error_response(e),
+        };
+        match vault::export_vault(server, &dir).await {
+            Ok(export) => json!(export).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = params.server.as_deref()))]
+    #[tool(description = "Render memos to a standalone HTML document, or to PDF when the server supports it, returned as an embedded binary resource for sharing outside Memos. Select the memos by name or by tag.", annotations(title = "Render notes", read_only_hint = true))]
+    async fn export_memo_rendered(
+        &self,
+        Parameters(params): Parameters<RenderParam>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let content = match self.render_memos(params).await {
+            Ok((file, mime_type, data)) => Content::resource(ResourceContents::BlobResourceContents {
+                uri: format!("memo-export:///{}", file),
+                mime_type: Some(mime_type.to_string()),
+                blob: base64::engine::general_purpose::STANDARD.encode(data),
+                meta: None,
+            }),
+            Err(e) => Content::text(error_response(e)),
+        };
+        Ok(CallToolResult::success(vec![content]))
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), memo = %name))]
+    #[tool(description = "Recognize the text in the image attachments of a memo, e.g. a photographed whiteboard or receipt, and optionally append it to the memo to make it searchable. Needs a server built with the `ocr` feature and a configured `ocr_command`.", annotations(title = "Extract text from attachments", read_only_hint = false))]
+    async fn extract_attachment_text(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: ExtractTextParam { name, attachment, append } }): Parameters<OnServer<ExtractTextParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let note = match server.get_note(&name).await {
+            Ok(note) => note,
+            Err(e) => return error_response(e),
+        };
+        let images = note
+            .attachments
+            .iter()
+            .filter(|a| ocr::is_image(a))
+            .filter(|a| attachment.as_deref().is_none_or(|wanted| a.name == wanted || a.filename == wanted))
+            .collect::<Vec<_>>();
+        if images.is_empty() {
+            return error_response(match &attachment {
+                Some(wanted) => anyhow::anyhow!("{} has no image attachment {}", name, wanted),
+                None => anyhow::anyhow!("{} has no image attachments", name),
+            });
+        }
+
+        let mut texts = Vec::with_capacity(images.len());
+        let mut content = note.content.clone();
+        for image in images {
+            let text = match server.download_attachment(image).await {
+                Ok(data) => self.recognize_text(&data).await,
+                Err(e) => Err(e),
+            };
+            let text = match text {
+                Ok(text) => text,
+                Err(e) => return error_response(e),
+            };
+            if append && !text.is_empty() && !ocr::has_text(&content, &image.filename) {
+                content = ocr::append_text(&content, &image.filename, &text);
+            }
+            texts.push(json!({"attachment": image.name, "filename": image.filename, "text": text}));
+        }
+        if content == note.content {
+            return json!({"attachments": texts}).to_string();
+        }
+
+        let mut update = UpdateNote::new(name.clone());
+        update.content = Some(content);
+        let before = self.snapshot(server, &name, dry_run).await;
+        let result = match self.write(dry_run, server.update_note(&update)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.remember(server_name.as_deref(), Change::Updated, before, &result);
+        self.audit(server_name.as_deref(), "extract_attachment_text", Some(name.as_str()), "appended attachment text".to_string(), &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(note) => json!({"attachments": texts, "memo": note}).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), folder = %folder))]
+    #[tool(description = "Create memos from the Markdown files in a folder of the export directory, e.g. an Obsidian vault. Front matter sets visibility, tags, pinned and the creation time; embedded local files are uploaded as attachments. Files imported before are skipped.", annotations(title = "Import from Obsidian", read_only_hint = false))]
+    async fn import_obsidian_vault(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: ImportParam { folder } }): Parameters<OnServer<ImportParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let dir = match self.export_path(&folder) {
+            Ok(dir) => dir,
+            Err(e) => return error_response(e),
+        };
+        let result = match self.write(dry_run, vault::import_vault(server, &dir)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        let summary = match &result {
+            Ok(import) => format!("imported {} memos from {}", import.imported, folder),
+            Err(_) => format!("import from {}", folder),
+        };
+        self.audit(server_name.as_deref(), "import_obsidian_vault", None, summary, &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(import) => json!(import).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "Snapshot the whole account into a file of the export directory: every memo, archived ones included, with its comments, relations and attachment metadata. Attachment files are not copied. Restore it with `restore_snapshot`.", annotations(title = "Snapshot the account", read_only_hint = false))]
+    async fn snapshot_account(
+        &self,
+        Parameters(SnapshotParam { server: server_name, file }): Parameters<SnapshotParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let file = file.unwrap_or_else(|| {
+            let name = server_name.as_deref().unwrap_or(self.servers.default_name());
+            format!("snapshot-{}-{}.jsonl", name, chrono::Utc::now().format("%Y%m%d-%H%M%S"))
+        });
+        let path = match self.export_path(&file) {
+            Ok(path) => path,
+            Err(e) => return error_response(e),
+        };
+        let snapshot = async {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let partial = path.with_extension("jsonl.partial");
+            let mut out = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+            let summary = backup::backup(server, &mut out).await?;
+            drop(out);
+            std::fs::rename(&partial, &path)?;
+            anyhow::Ok(summary)
+        };
+        match snapshot.await {
+            Ok(summary) => json!({"file": file, "memos": summary.memos, "comments": summary.comments}).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), file = %file))]
+    #[tool(description = "Restore a snapshot made with `snapshot_account` from the export directory. Memos missing on the server are recreated under new names with their comments, attachments and relations; `conflict` decides what happens to memos the server still has.", annotations(title = "Restore a snapshot", read_only_hint = false))]
+    async fn restore_snapshot(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: RestoreSnapshotParam { file, conflict } }): Parameters<OnServer<RestoreSnapshotParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let snapshot = match self.export_path(&file).and_then(|path| Ok(std::fs::read(path)?)) {
+            Ok(snapshot) => snapshot,
+            Err(e) => return error_response(e),
+        };
+        let result = match self.write(dry_run, backup::restore(server, snapshot.as_slice(), conflict)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        let summary = match &result {
+            Ok(restore) => format!("restored {} and overwrote {} memos from {}", restore.memos, restore.overwritten, file),
+            Err(_) => format!("restore from {}", file),
+        };
+        self.audit(server_name.as_deref(), "restore_snapshot", None, summary, &result);
+        self.sync_mirror(server_name.as_deref(), server, &result, &[]).await;
+        match result {
+            Ok(restore) => json!(restore).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "List all users of the Memos instance. Requires a HOST or ADMIN user.", annotations(title = "List users", read_only_hint = true))]
+    async fn list_users(
+        &self,
+        Parameters(ServerParam { server: server_name }): Parameters<ServerParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        if let Err(e) = self.require_admin(server_name.as_deref(), server).await {
+            return error_response(e);
+        }
+        match server.list_users().await {
+            Ok(users) => json!(users).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "Report instance-wide statistics: users by role, archived users, the memo count of each user, storage used by the attachments of the signed-in user and recent signups. Requires a HOST or ADMIN user.", annotations(title = "Workspace statistics", read_only_hint = true))]
+    async fn get_workspace_stats(
+        &self,
+        Parameters(WorkspaceStatsParam { server: server_name, signup_days }): Parameters<WorkspaceStatsParam>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let since = match dates::days_before(chrono::Utc::now(), signup_days) {
+            Ok(since) => since,
+            Err(e) => return error_response(e),
+        };
+        if let Err(e) = self.require_admin(server_name.as_deref(), server).await {
+            return error_response(e);
+        }
+        let (users, stats, attachments) = match tokio::try_join!(server.list_users(), server.list_user_stats(), server.list_attachments()) {
+            Ok(lists) => lists,
+            Err(e) => return error_response(e),
+        };
+        json!(WorkspaceStats::new(&users, &stats, &attachments, since)).to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref()))]
+    #[tool(description = "Review the storage taken by the attachments of the signed-in user: the total, the largest attachments, and the ones flagged `unattached` (set on no memo), `orphaned` (their memo no longer exists) or `oversized` (larger than max_bytes). Optionally deletes the attachments with the given flags; with dry_run, lists what it would delete.", annotations(title = "Clean up attachments", read_only_hint = false))]
+    async fn clean_up_attachments(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: CleanUpAttachmentsParam { max_bytes, delete, limit } }): Parameters<OnServer<CleanUpAttachmentsParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let (attachments, notes, archived) = match tokio::try_join!(server.list_attachments(), server.list_notes(), server.list_archived_notes()) {
+            Ok(lists) => lists,
+            Err(e) => return error_response(e),
+        };
+        let mut memos = notes.into_iter().chain(archived).filter_map(|note| note.name).collect::<BTreeSet<_>>();
+        // Memos lists no comments, so the other memos attachments are set on are looked up one by one,
+        // and only those Memos no longer finds count as gone.
+        let unlisted = attachments
+            .iter()
+            .map(|attachment| attachment.memo.as_str())
+            .filter(|memo| !memo.is_empty() && !memos.contains(*memo))
+            .map(str::to_string)
+            .collect::<BTreeSet<_>>();
+        for memo in unlisted {
+            let Ok(name) = memo.parse::<MemoName>() else {
+                memos.insert(memo);
+                continue;
+            };
+            match server.get_note(&name).await {
+                Ok(_) => {
+                    memos.insert(memo);
+                }
+                Err(e) if MemosError::find(&e).is_some_and(|error| error.status == 404) => {}
+                Err(e) => return error_response(e),
+            }
+        }
+        let reports = housekeeping::review(&attachments, &memos, max_bytes);
+        let bytes = reports.iter().map(|report| report.bytes).sum::<u64>();
+        let flagged = reports.iter().filter(|report| !report.flags.is_empty()).cloned().collect::<Vec<_>>();
+        let doomed = flagged.iter().filter(|report| report.flags.iter().any(|flag| delete.contains(flag))).map(|report| report.name.clone()).collect::<Vec<_>>();
+        let mut response = json!({
+            "attachments": reports.len(),
+            "bytes": bytes,
+            "largest": reports.into_iter().take(limit).collect::<Vec<_>>(),
+            "flagged": flagged,
+        });
+        if doomed.is_empty() {
+            return response.to_string();
+        }
+        if self.is_dry_run(dry_run) {
+            response["dry_run"] = json!(true);
+            response["would_delete"] = json!(doomed);
+            return response.to_string();
+        }
+
+        let mut deleted = Vec::new();
+        let mut failed = Vec::new();
+        for name in doomed {
+            let outcome = server.delete_attachment(&name).await;
+            self.audit(server_name.as_deref(), "clean_up_attachments", Some(&name), "deleted".to_string(), &outcome);
+            match outcome {
+                Ok(()) => deleted.push(name),
+                Err(e) => failed.push(json!({"name": name, "error": redact(&e.to_string())})),
+            }
+        }
+        response["deleted"] = json!(deleted);
+        response["failed"] = json!(failed);
+        response.to_string()
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), user = %update.name))]
+    #[tool(description = "Update the role, state or display name of a user by its name field. Set state to ARCHIVED to offboard a user. Requires a HOST or ADMIN user.", annotations(title = "Update a user", read_only_hint = false))]
+    async fn update_user(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: update }): Parameters<OnServer<UpdateUser>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        if let Err(e) = self.require_admin(server_name.as_deref(), server).await {
+            return error_response(e);
+        }
+        let result = match self.write(dry_run, server.update_user(&update)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.audit(server_name.as_deref(), "update_user", Some(&update.name), format!("updated {}", update.update_mask()), &result);
+        match result {
+            Ok(user) => json!(user).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), user = %profile.name))]
+    #[tool(description = "Update the display name, description or avatar of a user. Defaults to the current user when no name is given. The avatar is uploaded as base64-encoded image data.", annotations(title = "Update a user profile", read_only_hint = false))]
+    async fn update_user_profile(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: mut profile }): Parameters<OnServer<UpdateUserProfile>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        if profile.name.is_empty() {
+            match server.get_current_user().await {
+                Ok(me) => profile.name = me.name,
+                Err(e) => return error_response(e),
+            }
+        }
+        let result = match self.write(dry_run, server.update_user_profile(&profile)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.audit(server_name.as_deref(), "update_user_profile", Some(&profile.name), format!("updated {}", profile.update_mask()), &result);
+        match result {
+            Ok(user) => json!(user).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), user = params.user.as_deref()))]
+    #[tool(description = "Create a personal access token, e.g. to sign in to Memos from a new device. Creates it for the current user unless another user is given, which requires a HOST or ADMIN user. The token itself is only returned by this call; store it right away.", annotations(title = "Create an access token", read_only_hint = false))]
+    async fn create_pat(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params }): Parameters<OnServer<CreatePatParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let me = match server.get_current_user().await {
+            Ok(me) => me,
+            Err(e) => return error_response(e),
+        };
+        let user = match params.user {
+            Some(name) if name != me.name => {
+                if let Err(e) = self.require_admin(server_name.as_deref(), server).await {
+                    return error_response(e);
+                }
+                match server.list_users().await {
+                    Ok(users) => match users.into_iter().find(|user| user.name == name) {
+                        Some(user) => user,
+                        None => return error_response(anyhow::anyhow!("User {} not found", name)),
+                    },
+                    Err(e) => return error_response(e),
+                }
+            }
+            // Creating a token only needs the name of its user.
+            _ => {
+                let mut user = User::new(&me.username, "", &me.email);
+                user.name = me.name;
+                user
+            }
+        };
+        let result = match self.write(dry_run, server.create_pat(&user, &params.description, params.expires_in_days)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.audit(server_name.as_deref(), "create_pat", Some(&user.name), format!("created access token {:?}", params.description), &result);
+        match result {
+            Ok((token, secret)) => json!({
+                "personalAccessToken": token,
+                "token": secret,
+                "warning": "This is the only time the token is shown, store it now",
+            }).to_string(),
+            Err(e) => error_response(e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(server = server_name.as_deref(), token = %name))]
+    #[tool(description = "Revoke a personal access token by its name, so it can no longer be used to sign in. Revoking tokens of other users requires a HOST or ADMIN user.", annotations(title = "Revoke an access token", read_only_hint = false, destructive_hint = true))]
+    async fn revoke_pat(
+        &self,
+        Parameters(OnServer { server: server_name, dry_run, params: RevokePatParam { name } }): Parameters<OnServer<RevokePatParam>>,
+    ) -> String {
+        let server = match self.servers.get(server_name.as_deref()) {
+            Ok(server) => server,
+            Err(e) => return error_response(e),
+        };
+        let Some((owner, _)) = name.split_once("/personalAccessTokens/") else {
+            return error_response(anyhow::anyhow!("Invalid token name {}, expected users/{{id}}/personalAccessTokens/{{id}}", name));
+        };
+        let me = match server.get_current_user().await {
+            Ok(me) => me,
+            Err(e) => return error_response(e),
+        };
+        if owner != me.name && let Err(e) = self.require_admin(server_name.as_deref(), server).await {
+            return error_response(e);
+        }
+        let token = Token {
+            name: name.clone(),
+            description: String::new(),
+            created_at: Default::default(),
+            expires_at: None,
+            last_used_at: None,
+        };
+        let result = match self.write(dry_run, server.delete_pat(&token)).await {
+            Ok(result) => result,
+            Err(response) => return response,
+        };
+        self.audit(server_name.as_deref(), "revoke_pat", Some(&name), "revoked access token".to_string(), &result);
+        match result {
+            Ok(()) => json!({"status": "success"}).to_string(),
+            Err(e) => error_response(e),
         }
     }
 }
 
-#[tool_handler]
+// Tool calls and listings go through the allowlist and read-only checks, which can change when the
+// config is reloaded. Admin tools are hidden once the session knows that no configured token has an
+// admin role. Tool responses pass through the configured content redaction.
 impl ServerHandler for MemoMCP {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             capabilities: ServerCapabilities::builder()
                 .enable_tools()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_logging()
                 .build(),
             ..Default::default()
         }
     }
-}
\ No newline at end of file
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        if let Some(inbox) = &self.inbox {
+            tokio::spawn(forward_inbox(inbox.subscribe(), self.log_level.clone(), context.peer.clone()));
+        }
+        if let Some(events) = &self.events {
+            tokio::spawn(forward_events(events.subscribe(), self.subscriptions.clone(), context.peer));
+        }
+        for (name, server) in self.servers.iter() {
+            match server.get_current_user().await {
+                Ok(me) => {
+                    self.users.write().unwrap().insert(name.to_string(), me);
+                }
+                Err(e) => tracing::warn!("Failed to look up the user on server {}: {}", name, e),
+            }
+        }
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let span = tracing::info_span!("request", request_id = %context.id, tool = %request.name);
+        async move {
+            if let Some(route) = self.tool_router.map.get(request.name.as_ref()) {
+                let checked = self.check_tool(&route.attr);
+                #[cfg(feature = "otel")]
+                crate::telemetry::record_tool_call(&request.name, checked.is_ok());
+                checked.map_err(|message| ErrorData::invalid_params(message, None))?;
+
+                let dry_run = request.arguments.as_ref()
+                    .and_then(|arguments| arguments.get("dry_run"))
+                    .and_then(|dry_run| dry_run.as_bool())
+                    .unwrap_or(false);
+                if !is_read_only(&route.attr) && !self.is_dry_run(dry_run) {
+                    self.spend_write_budget(&request.name, &context.peer)
+                        .await
+                        .map_err(|message| ErrorData::invalid_request(message, None))?;
+                }
+            }
+            let tool = request.name.to_string();
+            let peer = context.peer.clone();
+            let tcc = ToolCallContext::new(self, request, context);
+            let call = self.tool_router.call(tcc);
+            let mut result = match &self.request_limit {
+                Some(limit) => memos::limit_requests(limit.clone(), call).await?,
+                None => call.await?,
+            };
+            if let Some(notice) = rate_limit_notice(&tool, &result) {
+                self.log(&peer, LoggingLevel::Warning, notice).await;
+            }
+
+            let redaction = self.settings.read().unwrap().content_redaction.clone();
+            if redaction.is_enabled() {
+                for content in &mut result.content {
+                    if let RawContent::Text(text) = &mut content.raw {
+                        text.text = redaction.apply_to_response(&text.text);
+                    }
+                }
+            }
+            Ok(result)
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn set_level(&self, request: SetLevelRequestParam, _context: RequestContext<RoleServer>) -> Result<(), ErrorData> {
+        *self.log_level.write().unwrap() = Some(request.level);
+        Ok(())
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        let tags = self.settings.read().unwrap().tag_digests.clone();
+        let mut resources = Vec::new();
+        for (name, server) in self.servers.iter() {
+            let shortcuts = match server.get_current_user().await {
+                Ok(me) => server.list_shortcuts(&me.name).await,
+                Err(e) => Err(e),
+            };
+            match shortcuts {
+                Ok(shortcuts) => resources.extend(shortcuts.iter().map(|shortcut| {
+                    let mut resource = RawResource::new(shortcut_uri(name, shortcut), shortcut.title.clone());
+                    resource.description = Some(shortcut.filter.clone());
+                    resource.mime_type = Some("application/json".to_string());
+                    resource.no_annotation()
+                })),
+                Err(e) => tracing::warn!("Failed to list the shortcuts of server {}: {}", name, e),
+            }
+            match server.list_attachments().await {
+                // Linked attachments have no file in Memos to read.
+                Ok(attachments) => resources.extend(attachments.iter().filter(|a| a.external_link.is_empty()).map(|attachment| {
+                    let mut resource = RawResource::new(attachment_uri(name, attachment), attachment.filename.clone());
+                    resource.description = Some(match attachment.memo.as_str() {
+                        "" => "Not attached to a memo".to_string(),
+                        memo => format!("Attached to {}", memo),
+                    });
+                    resource.mime_type = Some(attachment.mime_type.clone());
+                    resource.size = attachment.size.parse().ok();
+                    resource.no_annotation()
+                })),
+                Err(e) => tracing::warn!("Failed to list the attachments of server {}: {}", name, e),
+            }
+            resources.extend(tags.iter().map(|tag| {
+                let tag = tag.trim_start_matches('#');
+                let mut resource = RawResource::new(tag_uri(name, tag), format!("#{}", tag));
+                resource.description = Some(format!("The latest memos tagged #{}", tag));
+                resource.mime_type = Some("text/markdown".to_string());
+                resource.no_annotation()
+            }));
+        }
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, ErrorData> {
+        let template = RawResourceTemplate {
+            uri_template: format!("{}{{server}}/{{id}}", MEMO_URI_SCHEME),
+            name: "memo".to_string(),
+            title: Some("Memo".to_string()),
+            description: Some("The Markdown content of a memo. Subscribe to be notified when it changes.".to_string()),
+            mime_type: Some("text/markdown".to_string()),
+        };
+        let shortcut = RawResourceTemplate {
+            uri_template: format!("{}{{server}}/{{id}}", SHORTCUT_URI_SCHEME),
+            name: "shortcut".to_string(),
+            title: Some("Shortcut".to_string()),
+            description: Some("The shortcut and the memos currently matching its filter, as JSON.".to_string()),
+            mime_type: Some("application/json".to_string()),
+        };
+        let attachment = RawResourceTemplate {
+            uri_template: format!("{}{{server}}/{{id}}", ATTACHMENT_URI_SCHEME),
+            name: "attachment".to_string(),
+            title: Some("Attachment".to_string()),
+            description: Some("The file of an attachment stored in Memos, e.g. an image or a PDF, with its MIME type.".to_string()),
+            mime_type: None,
+        };
+        let tag = RawResourceTemplate {
+            uri_template: format!("{}{{server}}/{{tag}}", TAG_URI_SCHEME),
+            name: "memo-tag".to_string(),
+            title: Some("Tag digest".to_string()),
+            description: Some(format!(
+                "A digest of the {} latest memos with a tag, newest first. Subscribe to be notified when memos change.",
+                tag_digest::LATEST,
+            )),
+            mime_type: Some("text/markdown".to_string()),
+        };
+        Ok(ListResourceTemplatesResult::with_all_items(vec![
+            template.no_annotation(),
+            shortcut.no_annotation(),
+            attachment.no_annotation(),
+            tag.no_annotation(),
+        ]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        if let Some((server, id)) = parse_shortcut_uri(&request.uri) {
+            let text = self
+                .read_shortcut(server, id)
+                .await
+                .map_err(|e| ErrorData::resource_not_found(redact(&e.to_string()), None))?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: request.uri,
+                    mime_type: Some("application/json".to_string()),
+                    text,
+                    meta: None,
+                }],
+            });
+        }
+        if let Some((server, tag)) = parse_tag_uri(&request.uri) {
+            let text = self
+                .read_tag_digest(server, tag)
+                .await
+                .map_err(|e| ErrorData::resource_not_found(redact(&e.to_string()), None))?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: request.uri,
+                    mime_type: Some("text/markdown".to_string()),
+                    text,
+                    meta: None,
+                }],
+            });
+        }
+        if let Some((server, name)) = parse_attachment_uri(&request.uri) {
+            let file = match self.servers.get(Some(server)) {
+                Ok(server) => match server.get_attachment(&name).await {
+                    Ok(attachment) => server.download_attachment(&attachment).await.map(|data| (attachment, data)),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            let (attachment, data) = file.map_err(|e| ErrorData::resource_not_found(redact(&e.to_string()), None))?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::BlobResourceContents {
+                    uri: request.uri,
+                    mime_type: Some(attachment.mime_type.clone()),
+                    blob: base64::engine::general_purpose::STANDARD.encode(data),
+                    meta: None,
+                }],
+            });
+        }
+        let (server, name) = parse_memo_uri(&request.uri)
+            .ok_or_else(|| ErrorData::resource_not_found(format!("Unknown resource {}", request.uri), None))?;
+        let note = match self.servers.get(Some(server)) {
+            Ok(server) => server.get_note(&name).await,
+            Err(e) => Err(e),
+        };
+        let note = note.map_err(|e| ErrorData::resource_not_found(redact(&e.to_string()), None))?;
+        let text = self.settings.read().unwrap().content_redaction.apply_to_response(&note.content);
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some("text/markdown".to_string()),
+                text,
+                meta: None,
+            }],
+        })
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        if parse_memo_uri(&request.uri).is_none() && parse_tag_uri(&request.uri).is_none() {
+            return Err(ErrorData::resource_not_found(format!("Unknown resource {}", request.uri), None));
+        }
+        self.subscriptions.write().unwrap().insert(request.uri);
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.subscriptions.write().unwrap().remove(&request.uri);
+        Ok(())
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        let tools = self.tool_router
+            .list_all()
+            .into_iter()
+            .filter(|tool| self.check_tool(tool).is_ok())
+            .collect();
+        Ok(ListToolsResult::with_all_items(tools))
+    }
+}
+
+/// Sends a log message to the client, unless it asked only for more severe messages.
+async fn send_log(
+    peer: &Peer<RoleServer>,
+    log_level: &RwLock<Option<LoggingLevel>>,
+    logger: &str,
+    level: LoggingLevel,
+    data: serde_json::Value,
+) {
+    if log_level.read().unwrap().is_some_and(|wanted| severity(level) < severity(wanted)) {
+        return;
+    }
+    let message = LoggingMessageNotificationParam { level, logger: Some(logger.to_string()), data };
+    if let Err(e) = peer.notify_logging_message(message).await {
+        tracing::debug!("Failed to send a log message to the client: {}", e);
+    }
+}
+
+/// Returns once the transport of `peer` has closed, which peers can only be asked about.
+async fn closed(peer: &Peer<RoleServer>) {
+    let mut interval = tokio::time::interval(PEER_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if peer.is_transport_closed() {
+            return;
+        }
+    }
+}
+
+/// Sends each new notification of the Memos inbox to the client as a log message, until the
+/// session ends.
+async fn forward_inbox(
+    mut inbox: tokio::sync::broadcast::Receiver<InboxEvent>,
+    log_level: Arc<RwLock<Option<LoggingLevel>>>,
+    peer: Peer<RoleServer>,
+) {
+    loop {
+        let received = tokio::select! {
+            received = inbox.recv() => received,
+            _ = closed(&peer) => return,
+        };
+        let event = match received {
+            Ok(event) => event,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Dropped {} inbox notifications the session fell behind on", skipped);
+                continue;
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if peer.is_transport_closed() {
+            return;
+        }
+        send_log(&peer, &log_level, "memos.inbox", LoggingLevel::Notice, json!(event)).await;
+    }
+}
+
+/// Sends a resource update to the client for each event on a memo it subscribed to, and on the
+/// server of a tag digest it subscribed to, until the session ends.
+async fn forward_events(
+    mut events: tokio::sync::broadcast::Receiver<MemoEvent>,
+    subscriptions: Arc<RwLock<BTreeSet<String>>>,
+    peer: Peer<RoleServer>,
+) {
+    loop {
+        let received = tokio::select! {
+            received = events.recv() => received,
+            _ = closed(&peer) => return,
+        };
+        let event = match received {
+            Ok(event) => event,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Dropped {} memo events the session fell behind on", skipped);
+                continue;
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        };
+        if peer.is_transport_closed() {
+            return;
+        }
+        // Events do not carry the tags of the memo, which a delete leaves no way to look up, so
+        // every tag digest of the server may have changed.
+        let memo = memo_uri(&event.server, &event.memo);
+        let uris = subscriptions
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|uri| **uri == memo || parse_tag_uri(uri).is_some_and(|(server, _)| server == event.server))
+            .cloned()
+            .collect::<Vec<_>>();
+        for uri in uris {
+            if let Err(e) = peer.notify_resource_updated(ResourceUpdatedNotificationParam { uri }).await {
+                tracing::debug!("Failed to notify the client of a memo change: {}", e);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,770 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use rmcp::{
+    ClientHandler, RoleClient, ServiceExt,
+    model::{CallToolRequestParam, CallToolResult, LoggingMessageNotificationParam, ReadResourceRequestParam, ResourceContents, ResourceUpdatedNotificationParam, SubscribeRequestParam},
+    service::{NotificationContext, RunningService},
+};
+use tokio::sync::mpsc;
+use base64::Engine;
+use serde_json::{Value, json};
+use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{body_partial_json, method, path}};
+
+use super::MemoMCP;
+use crate::config::Settings;
+use crate::inbox::InboxEvent;
+use crate::memos::{Servers, mock};
+use crate::mirror::Mirror;
+use crate::tags::TagCache;
+use crate::undo::UndoLog;
+use crate::webhook::{self, EventKind, MemoEvent};
+
+/// An MCP client connected in-process to a `MemoMCP` backed by the mock Memos API.
+struct Session {
+    _mock: MockServer,
+    client: RunningService<RoleClient, ()>,
+}
+
+impl Session {
+    async fn start(settings: Settings) -> Self {
+        Self::start_with_mirror(settings, None).await
+    }
+
+    /// A session serving listings from `mirror`, synchronized with the mock server first.
+    async fn start_with_mirror(settings: Settings, mirror: Option<Arc<Mirror>>) -> Self {
+        let (mock, server) = mock::start().await;
+        mock::mount_memos_api(&mock).await;
+        if let Some(mirror) = &mirror {
+            mirror.sync("default", &server, true).await.unwrap();
+        }
+        let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+        let memo_mcp = MemoMCP::new(
+            servers,
+            Arc::new(RwLock::new(settings)),
+            mirror,
+            Arc::new(TagCache::default()),
+            Arc::new(UndoLog::default()),
+        );
+
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            if let Ok(running) = memo_mcp.serve(server_io).await {
+                let _ = running.waiting().await;
+            }
+        });
+        let client = ().serve(client_io).await.unwrap();
+        Session { _mock: mock, client }
+    }
+
+    async fn call(&self, tool: &str, arguments: Value) -> Value {
+        let result = self.client
+            .call_tool(CallToolRequestParam {
+                name: tool.to_string().into(),
+                arguments: arguments.as_object().cloned(),
+            })
+            .await
+            .unwrap_or_else(|e| panic!("{} failed: {}", tool, e));
+        assert_eq!(result.content.len(), 1, "{} returned {:?}", tool, result.content);
+        let text = &result.content[0].as_text().unwrap().text;
+        serde_json::from_str(text).unwrap_or_else(|_| panic!("{} returned no JSON: {}", tool, text))
+    }
+
+    /// The result of a tool returning other content than JSON text.
+    async fn raw(&self, tool: &str, arguments: Value) -> CallToolResult {
+        self.client
+            .call_tool(CallToolRequestParam {
+                name: tool.to_string().into(),
+                arguments: arguments.as_object().cloned(),
+            })
+            .await
+            .unwrap_or_else(|e| panic!("{} failed: {}", tool, e))
+    }
+
+    async fn ok(&self, tool: &str, arguments: Value) -> Value {
+        let response = self.call(tool, arguments).await;
+        assert!(response.get("error").is_none(), "{} returned {}", tool, response);
+        response
+    }
+}
+
+#[tokio::test]
+async fn test_tool_schemas() {
+    let session = Session::start(Settings::default()).await;
+    let tools = session.client.list_all_tools().await.unwrap();
+    assert!(!tools.is_empty());
+
+    for tool in &tools {
+        let schema = Value::Object((*tool.input_schema).clone());
+        assert_eq!(schema["type"], "object", "{} input schema is not an object", tool.name);
+        assert!(jsonschema::validator_for(&schema).is_ok(), "{} has an invalid input schema", tool.name);
+        assert!(tool.description.as_deref().is_some_and(|d| !d.is_empty()), "{} has no description", tool.name);
+        let annotations = tool.annotations.as_ref().unwrap_or_else(|| panic!("{} has no annotations", tool.name));
+        assert!(annotations.title.is_some() && annotations.read_only_hint.is_some(), "{} lacks a title or hint", tool.name);
+    }
+
+    let list_memos = tools.iter().find(|tool| tool.name == "list_memos").unwrap();
+    assert!(list_memos.input_schema.get("required").is_none_or(|required| required == &json!([])));
+}
+
+#[tokio::test]
+async fn test_every_tool() {
+    let session = Session::start(Settings { bookmark_private_urls: true, ..Default::default() }).await;
+    let mut called = Vec::new();
+    macro_rules! call {
+        ($method:ident, $tool:literal, $arguments:tt) => {{
+            called.push($tool);
+            session.$method($tool, json!($arguments)).await
+        }};
+    }
+
+    assert_eq!(call!(ok, "list_servers", {}), json!({"default": "default", "servers": ["default"]}));
+    assert_eq!(call!(ok, "list_memos", {}).as_array().unwrap().len(), 2);
+    // Clients may leave out the arguments of a tool without required parameters.
+    assert_eq!(call!(ok, "list_memos", null).as_array().unwrap().len(), 2);
+    assert_eq!(call!(ok, "list_memos_updated_since", {"since": "2025-01-01T00:00:00Z"})["memos"].as_array().unwrap().len(), 2);
+    assert_eq!(call!(ok, "list_memos_by_creator", {"user": "jane"}).as_array().unwrap().len(), 2);
+    assert!(call!(call, "list_memos_by_creator", {"user": "nobody"})["error"].is_string());
+    assert_eq!(call!(ok, "search_memos", {"query": "basil"})[0]["name"], "memos/2");
+    assert_eq!(call!(ok, "search_memos", {"query": "basil", "fields": "name,snippet"}), json!([{"name": "memos/2", "snippet": "Tomatoes and basil salad #recipe"}]));
+    assert_eq!(call!(ok, "list_memos", {"fields": "name"}), json!([{"name": "memos/1"}, {"name": "memos/2"}]));
+    let summary = call!(ok, "list_memos_by_creator", {"user": "jane", "output": "summary"});
+    assert_eq!(summary[0], json!({
+        "name": "memos/1",
+        "firstLine": "Grow tomatoes on the balcony #garden",
+        "tags": [],
+        "createTime": "2025-01-31T08:00:00Z",
+        "updateTime": "2025-01-31T08:00:00Z",
+        "property": {"hasLink": false, "hasTaskList": false, "hasCode": false, "hasIncompleteTasks": false},
+    }));
+    assert_eq!(call!(ok, "list_memos", {"has": ["incomplete_tasks"]}), json!([]));
+    assert_eq!(call!(ok, "list_memos_updated_since", {"since": "2025-01-01T00:00:00Z", "fields": "name"})["memos"][1], json!({"name": "memos/2"}));
+    assert!(call!(call, "list_memos", {"fields": "name,body"})["error"].as_str().unwrap().starts_with("Unknown field body"));
+    assert_eq!(call!(ok, "full_text_search", {"query": "tomatoes"}).as_array().unwrap().len(), 2);
+    assert_eq!(call!(ok, "full_text_search", {"query": "tomatoes", "limit": 0}), json!([]));
+    assert!(call!(call, "semantic_search_memos", {"query": "vegetables"})["error"].is_string());
+    assert_eq!(call!(ok, "suggest_related_memos", {"name": "memos/1"})["method"], "lexical");
+    assert_eq!(call!(ok, "suggest_related_memos", {"name": "memos/1", "limit": 0})["memos"], json!([]));
+    assert_eq!(call!(ok, "list_tags", {})["tags"], json!({}));
+    assert_eq!(call!(ok, "memo_stats", {})["memos"], 2);
+    assert_eq!(call!(ok, "audit_visibility", {})["memos"], json!([]));
+    assert_eq!(call!(ok, "summarize_reactions", {"names": ["memos/1"], "emoji": "👍"}), json!({
+        "memos": 1, "reactions": 0, "by_emoji": {}, "by_user": {}, "top_memos": [],
+    }));
+    assert!(call!(call, "summarize_reactions", {"names": ["memos/1"], "filter": "pinned"})["error"].as_str().unwrap().contains("not both"));
+    assert_eq!(call!(ok, "list_upcoming_memos", {"days": 30}), json!([]));
+    assert_eq!(call!(ok, "find_duplicate_memos", {"threshold": 0.1}), json!([]));
+    let diff = call!(ok, "diff_memos", {"a": "memos/1", "b": "memos/2"});
+    assert_eq!(diff["diff"], "--- memos/1\n+++ memos/2\n@@ -1 +1 @@\n-Content of memos/1\n+Content of memos/2\n");
+    assert_eq!(call!(ok, "diff_memos", {"a": "memos/1", "text": "Content of memos/1"})["diff"], "");
+    assert_eq!(call!(ok, "format_memo", {"name": "memos/1"}), json!({"dry_run": false, "fixes": [], "diff": "", "additions": 0, "deletions": 0}));
+    assert_eq!(call!(ok, "check_memo_links", {"name": "memos/1"}), json!({"checked": 0, "broken": []}));
+    assert!(call!(call, "extract_attachment_text", {"name": "memos/1"})["error"].as_str().unwrap().contains("no image attachments"));
+    assert_eq!(call!(ok, "get_memo_graph", {"since": "2025-01-01T00:00:00Z"})["nodes"].as_array().unwrap().len(), 2);
+    assert_eq!(call!(ok, "get_memo_graph", {"since": "January 2025", "until": "2025-01"})["nodes"].as_array().unwrap().len(), 2);
+    assert!(call!(call, "get_memo_graph", {"since": "someday"})["error"].as_str().unwrap().starts_with("Cannot read \"someday\""));
+    assert!(call!(call, "get_memo_graph", {"since": "2025-02", "until": "2025-01"})["error"].as_str().unwrap().contains("is not before"));
+    assert!(call!(raw, "get_memo_graph", {"format": "dot"}).content[0].as_text().unwrap().text.starts_with("digraph memos {"));
+
+    let missing = call!(call, "get_memo", {"name": "memos/missing"});
+    assert_eq!(missing["data"], json!({"status": 404, "kind": "not_found", "code": 5, "message": "memo not found"}));
+    let memo = call!(ok, "get_memo", {"name": "memos/1", "offset": 11, "max_chars": 6});
+    assert_eq!(memo["content"], "memos/");
+    assert_eq!(memo["contentRange"]["total"], 18);
+    assert_eq!(call!(ok, "get_memo", {"name": "memos/1", "limit": 7})["content"], "Content");
+    assert_eq!(call!(call, "get_memo_section", {"name": "memos/1", "heading": "Goals"})["error"], "The memo has no headings");
+    assert_eq!(call!(call, "update_memo_section", {"name": "memos/1", "heading": "Goals", "content": "Ship"})["error"], "The memo has no headings");
+
+    assert_eq!(call!(ok, "create_memo", {"content": "New memo", "state": "NORMAL"})["name"], "memos/3");
+    let keyed = call!(ok, "create_memo", {"content": "Keyed memo", "state": "NORMAL", "idempotency_key": "k1"});
+    let retried = call!(ok, "create_memo", {"content": "Keyed memo", "state": "NORMAL", "idempotency_key": "k1"});
+    assert_eq!(retried["content"], keyed["content"]);
+    assert!(retried["warning"].as_str().unwrap().contains("already created"));
+    assert!(call!(call, "create_memo", {"content": "Other memo", "state": "NORMAL", "idempotency_key": "k1"})["error"].is_string());
+    let site = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/tomatoes"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("<title>Tomatoes</title><p>Grow them in the sun all summer.</p>", "text/html"))
+        .mount(&site).await;
+    let url = format!("{}/tomatoes", site.uri());
+    let bookmark = call!(ok, "bookmark_url", {"url": url});
+    assert_eq!(bookmark["content"], format!("[Tomatoes]({})\n\n> Grow them in the sun all summer.\n\n#bookmark", url));
+    let dry_run = call!(ok, "update_memo", {"name": "memos/1", "content": "Edited", "state": "NORMAL", "dry_run": true});
+    assert_eq!(dry_run["requests"][0]["method"], "PATCH");
+    assert_eq!(call!(ok, "update_memo", {"name": "memos/1", "content": "Edited"})["content"], "Edited");
+    assert!(call!(call, "update_memo", {"name": "memos/1"})["error"].is_string());
+    let located = call!(ok, "set_memo_location", {"name": "memos/1", "latitude": 48.8566, "longitude": 2.3522, "placeholder": "Paris"});
+    assert_eq!(located["location"]["placeholder"], "Paris");
+    let backdated = call!(ok, "set_memo_display_time", {"name": "memos/1", "display_time": "2025-01-01T20:00:00Z"});
+    assert_eq!(backdated["displayTime"], "2025-01-01T20:00:00Z");
+    assert_eq!(call!(ok, "list_memos_near", {"latitude": 48.85, "longitude": 2.35, "radius_km": 5}), json!([]));
+    assert_eq!(call!(ok, "list_tasks", {"done": false}), json!([]));
+    assert!(call!(call, "toggle_task", {"name": "memos/1", "line": 0})["error"].is_string());
+    assert_eq!(call!(ok, "sync_open_tasks", {"days": 30, "dry_run": true})["requests"][0]["method"], "POST");
+    assert!(call!(call, "sync_open_tasks", {"days": u32::MAX})["error"].as_str().unwrap().contains("out of range"));
+    assert_eq!(call!(ok, "list_macros", {}), json!({"macros": []}));
+    assert!(call!(call, "run_macro", {"name": "capture"})["error"].as_str().unwrap().starts_with("Unknown macro capture"));
+    let review = call!(ok, "generate_weekly_review", {"days": 36500, "save": true});
+    assert!(call!(call, "generate_weekly_review", {"days": u32::MAX})["error"].as_str().unwrap().contains("out of range"));
+    assert!(review["report"].as_str().unwrap().contains("- Memos created: 2\n"));
+    assert!(review["memo"]["content"].as_str().unwrap().ends_with("\n#review"));
+    assert_eq!(call!(ok, "undo_last_change", {})["restored"], "updated");
+    assert!(call!(call, "list_memo_versions", {"name": "memos/1"})["error"].as_str().unwrap().contains("MEMOS_MIRROR_DB"));
+    assert!(call!(call, "get_memo_version", {"name": "memos/1", "version": 1})["error"].is_string());
+    assert!(call!(call, "revert_memo_to_version", {"name": "memos/1", "version": 1})["error"].is_string());
+    let old = call!(ok, "archive_old_memos", {"older_than_days": 1, "query": "TOMATOES", "dry_run": true});
+    assert_eq!(old, json!({"dry_run": true, "memos": ["memos/1", "memos/2"]}));
+    assert_eq!(call!(ok, "archive_old_memos", {"older_than_days": 1, "query": "balcony"}), json!({"archived": ["memos/1"], "failed": []}));
+    assert!(call!(call, "archive_old_memos", {"older_than_days": u32::MAX})["error"].as_str().unwrap().contains("out of range"));
+
+    assert_eq!(call!(ok, "delete_memo", {"name": "http://memos.example.com/memos/2"}), json!({"status": "success"}));
+    assert_eq!(call!(ok, "restore_deleted_memo", {"name": "memos/2"})["restored"], "deleted");
+    assert_eq!(call!(ok, "hard_delete_memo", {"name": "memos/3"}), json!({"status": "success"}));
+    let deleted = call!(ok, "batch_delete_memos", {"names": ["memos/1", "memos/2"]});
+    assert_eq!(deleted["deleted"], json!(["memos/1", "memos/2"]));
+
+    assert_eq!(call!(ok, "create_memo_comment", {"memo_name": "memos/1", "comment": {"content": "Nice", "state": "NORMAL"}})["content"], "Nice");
+    assert!(call!(call, "reply_to_comment", {"comment": "memos/2", "content": "Thanks"})["error"].is_string());
+    assert_eq!(call!(ok, "list_memo_comments", {"name": "memos/1"}).as_array().unwrap().len(), 1);
+    assert!(call!(call, "export_obsidian_vault", {"folder": "vault"})["error"].as_str().unwrap().contains("disabled"));
+    assert!(call!(call, "import_obsidian_vault", {"folder": "vault"})["error"].as_str().unwrap().contains("disabled"));
+    assert!(call!(call, "snapshot_account", {})["error"].as_str().unwrap().contains("disabled"));
+    assert!(call!(call, "restore_snapshot", {"file": "snapshot.jsonl"})["error"].as_str().unwrap().contains("disabled"));
+    let rendered = call!(raw, "export_memo_rendered", {"names": ["memos/1"]});
+    let Some(ResourceContents::BlobResourceContents { uri, mime_type, blob, .. }) = rendered.content[0].as_resource().map(|r| &r.resource) else {
+        panic!("export_memo_rendered returned {:?}", rendered.content);
+    };
+    assert_eq!((uri.as_str(), mime_type.as_deref()), ("memo-export:///1.html", Some("text/html")));
+    let html = String::from_utf8(base64::engine::general_purpose::STANDARD.decode(blob).unwrap()).unwrap();
+    assert!(html.contains("<p>Content of memos/1</p>"));
+    assert!(session.call("export_memo_rendered", json!({})).await["error"].is_string());
+
+    assert_eq!(call!(ok, "list_users", {}).as_array().unwrap().len(), 2);
+    let workspace = call!(ok, "get_workspace_stats", {});
+    assert_eq!((workspace["users"].clone(), workspace["memos"].clone()), (json!(2), json!(2)));
+    assert_eq!(workspace["attachments"], json!({"count": 1, "bytes": 3}));
+    assert!(call!(call, "get_workspace_stats", {"signup_days": u32::MAX})["error"].as_str().unwrap().contains("out of range"));
+    let attachments = call!(ok, "clean_up_attachments", {"delete": ["unattached"], "dry_run": true});
+    assert_eq!((attachments["flagged"][0]["flags"].clone(), attachments["would_delete"].clone()), (json!(["unattached"]), json!(["attachments/1"])));
+    assert_eq!(call!(ok, "clean_up_attachments", {"delete": ["unattached"]})["deleted"], json!(["attachments/1"]));
+    assert_eq!(call!(ok, "update_user", {"name": "users/2", "state": "ARCHIVED"})["state"], "ARCHIVED");
+    assert_eq!(call!(ok, "update_user_profile", {"description": "Hello"})["description"], "Hello");
+    assert_eq!(call!(ok, "update_user_profile", {"displayName": "Jane Doe"})["displayName"], "Jane Doe");
+    let pat = call!(ok, "create_pat", {"description": "Phone"});
+    assert_eq!(pat["personalAccessToken"]["name"], "users/1/personalAccessTokens/1");
+    assert_eq!(pat["token"], "memos_pat_created");
+    assert_eq!(call!(ok, "create_pat", {"user": "users/2", "description": "Laptop"})["personalAccessToken"]["name"], "users/2/personalAccessTokens/1");
+    assert!(call!(call, "create_pat", {"user": "users/7", "description": "Laptop"})["error"].is_string());
+    assert_eq!(call!(ok, "revoke_pat", {"name": "users/2/personalAccessTokens/1"}), json!({"status": "success"}));
+    assert!(call!(call, "revoke_pat", {"name": "users/2"})["error"].is_string());
+
+    let untested = session.client
+        .list_all_tools()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tool| tool.name.to_string())
+        .filter(|tool| !called.contains(&tool.as_str()))
+        .collect::<Vec<_>>();
+    assert!(untested.is_empty(), "Tools without a conformance test: {:?}", untested);
+}
+
+#[tokio::test]
+async fn test_read_only_hides_and_rejects_writes() {
+    let session = Session::start(Settings { read_only: true, ..Default::default() }).await;
+    let tools = session.client.list_all_tools().await.unwrap();
+    assert!(tools.iter().all(|tool| tool.annotations.as_ref().and_then(|a| a.read_only_hint) == Some(true)));
+
+    let rejected = session.client
+        .call_tool(CallToolRequestParam {
+            name: "create_memo".into(),
+            arguments: json!({"content": "x", "state": "NORMAL"}).as_object().cloned(),
+        })
+        .await;
+    assert!(rejected.is_err());
+}
+
+#[tokio::test]
+async fn test_custom_tools() {
+    let custom_tools = toml::from_str::<Settings>(r#"
+        [[custom_tools]]
+        name = "get_memo_json"
+        description = "Get a memo as Memos returns it."
+        endpoint = "memos/{id}"
+        params.id = { description = "The id of the memo." }
+
+        [[custom_tools]]
+        name = "rename_memo"
+        description = "Replace the content of a memo."
+        method = "PATCH"
+        endpoint = "memos/{id}?updateMask=content"
+        body = { content = "{content}" }
+        params.id = { description = "The id of the memo." }
+        params.content = { description = "The new content." }
+
+        [[custom_tools]]
+        name = "list_memos"
+        description = "Shadows a built-in tool."
+        endpoint = "memos"
+    "#).unwrap().custom_tools;
+    let session = Session::start(Settings { custom_tools, ..Default::default() }).await;
+    let tools = session.client.list_all_tools().await.unwrap();
+    assert_eq!(tools.iter().filter(|tool| tool.name == "list_memos").count(), 1);
+
+    assert_eq!(session.ok("get_memo_json", json!({"id": "1"})).await["content"], "Content of memos/1");
+    assert!(session.call("get_memo_json", json!({})).await["error"].as_str().unwrap().contains("Missing argument id"));
+    let planned = session.ok("rename_memo", json!({"id": "1", "content": "Renamed", "dry_run": true})).await;
+    assert_eq!(planned["requests"][0]["body"], json!({"content": "Renamed"}));
+    assert_eq!(planned["requests"][0]["update_mask"], "content");
+    assert_eq!(session.ok("rename_memo", json!({"id": "1", "content": "Renamed"})).await["name"], "memos/1");
+}
+
+#[tokio::test]
+async fn test_run_macro() {
+    let macros = toml::from_str::<Settings>(r#"
+        [[macros]]
+        name = "capture"
+        description = "Capture a thought."
+        params.text = "The thought."
+        steps = [
+            { action = "create", content = "{text}" },
+            { action = "tag", tags = ["inbox"] },
+            { action = "pin" },
+        ]
+    "#).unwrap().macros;
+    let session = Session::start(Settings { macros, ..Default::default() }).await;
+
+    let listed = session.ok("list_macros", json!({})).await;
+    assert_eq!(listed["macros"][0]["params"], json!({"text": "The thought."}));
+    assert_eq!(listed["macros"][0]["creates_memo"], true);
+    let planned = session.ok("run_macro", json!({"name": "capture", "arguments": {"text": "Call the plumber"}, "dry_run": true})).await;
+    assert_eq!(planned["requests"][0]["body"]["content"], "Call the plumber");
+    let ran = session.ok("run_macro", json!({"name": "capture", "arguments": {"text": "Call the plumber"}})).await;
+    assert_eq!(ran["steps"], json!(["created memos/3", "tagged memos/3 with #inbox", "pinned memos/3"]));
+    assert_eq!(ran["memo"]["pinned"], true);
+    let error = session.call("run_macro", json!({"name": "capture", "arguments": {"txt": "typo"}})).await;
+    assert_eq!(error["error"], "Macro capture has no parameter txt");
+}
+
+#[tokio::test]
+async fn test_front_matter() {
+    let session = Session::start(Settings::default()).await;
+
+    let content = "---\nvisibility: public\ntags: journal\ndate: 2025-03-01\n---\n\nA quiet day";
+    let planned = session.ok("create_memo", json!({"content": content, "state": "NORMAL", "dry_run": true})).await;
+    let body = &planned["requests"][0]["body"];
+    assert_eq!(body["content"], "A quiet day #journal");
+    assert_eq!((body["visibility"].clone(), body["displayTime"].clone()), (json!("PUBLIC"), json!("2025-03-01T00:00:00Z")));
+
+    let memo = session.ok("get_memo", json!({"name": "memos/1", "front_matter": true})).await;
+    assert!(memo["content"].as_str().unwrap().starts_with("---\nvisibility: PRIVATE\npinned: false\n"), "{}", memo);
+    let planned = session.ok("update_memo", json!({"name": "memos/1", "content": memo["content"], "pinned": true, "dry_run": true})).await;
+    assert_eq!(planned["requests"][0]["update_mask"], "content,visibility,pinned,display_time");
+    assert_eq!(planned["requests"][0]["body"]["content"], "Content of memos/1");
+    assert_eq!(planned["requests"][0]["body"]["pinned"], true);
+}
+
+#[tokio::test]
+async fn test_export_stays_in_export_dir() {
+    let dir = std::env::temp_dir().join(format!("mcp-memos-exports-{}", std::process::id()));
+    let session = Session::start(Settings { export_dir: Some(dir.clone()), ..Default::default() }).await;
+
+    for folder in ["../escape", "/tmp/vault", "a/b", ""] {
+        assert!(session.call("export_obsidian_vault", json!({"folder": folder})).await["error"].is_string(), "{}", folder);
+    }
+    assert_eq!(session.ok("export_obsidian_vault", json!({"folder": "vault"})).await["memos"], 2);
+    assert!(dir.join("vault").join("1.md").is_file());
+    assert!(session.call("import_obsidian_vault", json!({"folder": ".."})).await["error"].is_string());
+    assert_eq!(session.ok("import_obsidian_vault", json!({"folder": "vault"})).await["imported"], 2);
+
+    assert!(session.call("snapshot_account", json!({"file": "../snapshot.jsonl"})).await["error"].is_string());
+    let snapshot = session.ok("snapshot_account", json!({})).await;
+    assert_eq!((snapshot["memos"].clone(), snapshot["comments"].clone()), (json!(2), json!(2)));
+    assert!(dir.join(snapshot["file"].as_str().unwrap()).is_file());
+    let restored = session.ok("restore_snapshot", json!({"file": snapshot["file"]})).await;
+    assert_eq!((restored["memos"].clone(), restored["skipped"].clone()), (json!(0), json!(2)));
+    let restored = session.ok("restore_snapshot", json!({"file": snapshot["file"], "conflict": "duplicate"})).await;
+    assert_eq!((restored["memos"].clone(), restored["comments"].clone()), (json!(2), json!(2)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_search_filters_before_limit() {
+    let session = Session::start(Settings::default()).await;
+    let mut newest = mock::memo("memos/5", "Water the tomatoes");
+    newest["updateTime"] = json!("2025-02-02T08:00:00Z");
+    let mut tasks = mock::memo("memos/6", "- [ ] Stake the tomatoes");
+    tasks["property"] = json!({"hasTaskList": true, "hasIncompleteTasks": true});
+    Mock::given(method("GET")).and(path("/api/v1/memos"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"memos": [newest, tasks]})))
+        .with_priority(1)
+        .mount(&session._mock).await;
+
+    let found = session.ok("search_memos", json!({"query": "tomatoes", "limit": 1, "has": ["incomplete_tasks"], "fields": "name"})).await;
+    assert_eq!(found, json!([{"name": "memos/6"}]));
+    let found = session.ok("search_memos", json!({"query": "tomatoes", "limit": 1, "fields": "name"})).await;
+    assert_eq!(found, json!([{"name": "memos/5"}]));
+}
+
+#[tokio::test]
+async fn test_writes_update_the_mirror() {
+    let mirror = Arc::new(Mirror::open(std::path::Path::new(":memory:")).unwrap());
+    let session = Session::start_with_mirror(Settings::default(), Some(mirror)).await;
+    let names = |memos: Value| memos.as_array().unwrap().iter().map(|memo| memo["name"].clone()).collect::<Vec<_>>();
+    assert_eq!(names(session.ok("list_memos", json!({})).await), ["memos/1", "memos/2"]);
+
+    // From here on the server lists the memo created below as changed since the last sync.
+    Mock::given(method("GET")).and(path("/api/v1/memos"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"memos": [mock::memo("memos/3", "Straight away")]})))
+        .with_priority(1)
+        .mount(&session._mock).await;
+    session.ok("create_memo", json!({"content": "Straight away", "state": "NORMAL"})).await;
+    assert_eq!(names(session.ok("list_memos", json!({})).await), ["memos/1", "memos/2", "memos/3"]);
+
+    session.ok("delete_memo", json!({"name": "memos/1"})).await;
+    assert_eq!(names(session.ok("list_memos", json!({})).await), ["memos/2", "memos/3"]);
+}
+
+#[tokio::test]
+async fn test_cancelled_create_releases_idempotency_key() {
+    let (mock, server) = mock::start().await;
+    mock::mount_memos_api(&mock).await;
+    Mock::given(method("POST")).and(path("/api/v1/memos"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(mock::memo("memos/3", "Slow")).set_delay(std::time::Duration::from_secs(60)))
+        .with_priority(1)
+        .mount(&mock).await;
+    let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+    let memo_mcp = MemoMCP::new(
+        servers,
+        Arc::new(RwLock::new(Settings::default())),
+        None,
+        Arc::new(TagCache::default()),
+        Arc::new(UndoLog::default()),
+    );
+
+    let arguments = json!({"content": "Slow", "state": "NORMAL", "idempotency_key": "k1"});
+    let create = memo_mcp.create_memo(super::Parameters(serde_json::from_value(arguments).unwrap()));
+    // Dropping the create while Memos has not answered cancels it.
+    assert!(tokio::time::timeout(std::time::Duration::from_millis(500), create).await.is_err());
+    assert!(matches!(memo_mcp.idempotency_keys.claim("default", "k1", 0), Ok(super::Claim::New(_))));
+}
+
+/// A client passing on the resource updates it is notified of.
+struct UpdatedResources(mpsc::UnboundedSender<String>);
+
+impl ClientHandler for UpdatedResources {
+    async fn on_resource_updated(&self, params: ResourceUpdatedNotificationParam, _context: NotificationContext<RoleClient>) {
+        let _ = self.0.send(params.uri);
+    }
+}
+
+#[tokio::test]
+async fn test_memo_resources_notify_subscribers() {
+    let (mock, server) = mock::start().await;
+    mock::mount_memos_api(&mock).await;
+    let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+    let events = webhook::events();
+    let memo_mcp = MemoMCP::new(
+        servers,
+        Arc::new(RwLock::new(Settings::default())),
+        None,
+        Arc::new(TagCache::default()),
+        Arc::new(UndoLog::default()),
+    )
+    .with_events(Some(events.clone()));
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Ok(running) = memo_mcp.serve(server_io).await {
+            let _ = running.waiting().await;
+        }
+    });
+    let (updates, mut updated) = mpsc::unbounded_channel();
+    let client = UpdatedResources(updates).serve(client_io).await.unwrap();
+
+    let read = client.read_resource(ReadResourceRequestParam { uri: "memo://default/1".to_string() }).await.unwrap();
+    let ResourceContents::TextResourceContents { text, .. } = &read.contents[0] else {
+        panic!("memo://default/1 is not text");
+    };
+    assert!(text.starts_with("Content of memos/1"));
+    assert!(client.subscribe(SubscribeRequestParam { uri: "memo://default/9/x".to_string() }).await.is_err());
+    client.subscribe(SubscribeRequestParam { uri: "memo://default/1".to_string() }).await.unwrap();
+
+    for memo in ["memos/2", "memos/1"] {
+        let event = MemoEvent { server: "default".to_string(), memo: memo.to_string(), kind: EventKind::Updated };
+        events.send(event).unwrap();
+    }
+    let uri = tokio::time::timeout(std::time::Duration::from_secs(5), updated.recv()).await.unwrap();
+    assert_eq!(uri.as_deref(), Some("memo://default/1"));
+    assert!(updated.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_shortcut_resources() {
+    let session = Session::start(Settings::default()).await;
+    let mut resources = session.client.list_resources(None).await.unwrap().resources;
+    resources.retain(|r| r.uri.starts_with("shortcut://"));
+    assert_eq!(resources.iter().map(|r| (r.uri.as_str(), r.name.as_str())).collect::<Vec<_>>(), [("shortcut://default/garden", "Garden")]);
+    assert_eq!(resources[0].description.as_deref(), Some(r#"tag in ["garden"]"#));
+
+    let read = session.client.read_resource(ReadResourceRequestParam { uri: "shortcut://default/garden".to_string() }).await.unwrap();
+    let ResourceContents::TextResourceContents { text, .. } = &read.contents[0] else {
+        panic!("shortcut://default/garden is not text");
+    };
+    let value: Value = serde_json::from_str(text).unwrap();
+    assert_eq!(value["shortcut"]["title"], "Garden");
+    assert_eq!(value["memos"].as_array().unwrap().iter().map(|memo| memo["name"].as_str().unwrap()).collect::<Vec<_>>(), ["memos/1"]);
+    assert!(session.client.read_resource(ReadResourceRequestParam { uri: "shortcut://default/missing".to_string() }).await.is_err());
+}
+
+#[tokio::test]
+async fn test_tag_digest_resources() {
+    let settings = Settings { tag_digests: vec!["#garden".to_string()], ..Settings::default() };
+    let session = Session::start(settings).await;
+    let mut resources = session.client.list_resources(None).await.unwrap().resources;
+    resources.retain(|r| r.uri.starts_with("memo-tag://"));
+    assert_eq!(resources.iter().map(|r| (r.uri.as_str(), r.name.as_str())).collect::<Vec<_>>(), [("memo-tag://default/garden", "#garden")]);
+
+    let read = session.client.read_resource(ReadResourceRequestParam { uri: "memo-tag://default/garden".to_string() }).await.unwrap();
+    let ResourceContents::TextResourceContents { text, mime_type, .. } = &read.contents[0] else {
+        panic!("memo-tag://default/garden is not text");
+    };
+    assert_eq!(mime_type.as_deref(), Some("text/markdown"));
+    assert!(text.starts_with("# #garden\n\nThe 1 latest memos tagged #garden"), "{}", text);
+    assert!(text.contains("## [Grow tomatoes on the balcony garden](memo://default/1)"), "{}", text);
+    session.client.subscribe(SubscribeRequestParam { uri: "memo-tag://default/garden".to_string() }).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_attachment_resources() {
+    let session = Session::start(Settings::default()).await;
+    let resources = session.client.list_resources(None).await.unwrap().resources;
+    let attachment = resources.iter().find(|r| r.uri == "attachment://default/1").expect("attachments/1 is listed");
+    assert_eq!((attachment.name.as_str(), attachment.mime_type.as_deref(), attachment.size), ("plan.png", Some("image/png"), Some(3)));
+
+    let read = session.client.read_resource(ReadResourceRequestParam { uri: "attachment://default/1".to_string() }).await.unwrap();
+    let ResourceContents::BlobResourceContents { blob, mime_type, .. } = &read.contents[0] else {
+        panic!("attachment://default/1 is not binary");
+    };
+    assert_eq!(mime_type.as_deref(), Some("image/png"));
+    assert_eq!(base64::engine::general_purpose::STANDARD.decode(blob).unwrap(), b"PNG");
+}
+
+struct LogMessages(mpsc::UnboundedSender<LoggingMessageNotificationParam>);
+
+impl ClientHandler for LogMessages {
+    async fn on_logging_message(&self, params: LoggingMessageNotificationParam, _context: NotificationContext<RoleClient>) {
+        let _ = self.0.send(params);
+    }
+}
+
+#[tokio::test]
+async fn test_reply_to_comment() {
+    let session = Session::start(Settings::default()).await;
+    let mut comment = mock::memo("memos/8", "Use clay pots?\n\nThey keep roots cool.");
+    comment["parent"] = json!("memos/1");
+    comment["creator"] = json!("users/2");
+    Mock::given(method("GET")).and(path("/api/v1/memos/8"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(comment))
+        .with_priority(1)
+        .mount(&session._mock)
+        .await;
+    Mock::given(method("POST")).and(path("/api/v1/memos/9/relations"))
+        .and(body_partial_json(json!({"relations": [{"memo": {"name": "memos/9"}, "relatedMemo": {"name": "memos/8"}, "type": "REFERENCE"}]})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&session._mock)
+        .await;
+
+    let reply = session.ok("reply_to_comment", json!({"comment": "memos/8", "content": "Yes, terracotta."})).await;
+    assert_eq!(reply["content"], "> users/2 wrote in memos/8:\n> Use clay pots?\n>\n> They keep roots cool.\n\nYes, terracotta.");
+    let error = session.call("reply_to_comment", json!({"comment": "memos/2", "content": "Hi"})).await;
+    assert_eq!(error["error"], "memos/2 is not a comment, use create_memo_comment to comment on a memo");
+}
+
+#[tokio::test]
+async fn test_audit_visibility() {
+    let session = Session::start(Settings::default()).await;
+    let mut public = mock::memo("memos/1", "Balcony garden plan");
+    public["visibility"] = json!("PUBLIC");
+    let mut theirs = mock::memo("memos/2", "Jane's recipes");
+    (theirs["visibility"], theirs["creator"]) = (json!("PROTECTED"), json!("users/2"));
+    Mock::given(method("GET")).and(path("/api/v1/memos"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"memos": [public, theirs, mock::memo("memos/3", "Diary")]})))
+        .with_priority(1)
+        .mount(&session._mock)
+        .await;
+    Mock::given(method("PATCH")).and(path("/api/v1/memos/1"))
+        .and(body_partial_json(json!({"visibility": "PRIVATE"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(mock::memo("memos/1", "Balcony garden plan")))
+        .with_priority(1)
+        .expect(1)
+        .mount(&session._mock)
+        .await;
+
+    let audit = session.ok("audit_visibility", json!({})).await;
+    assert_eq!(audit, json!({
+        "public": 1,
+        "protected": 0,
+        "memos": [{"name": "memos/1", "visibility": "PUBLIC", "snippet": "Balcony garden plan", "create_time": "2025-01-31T08:00:00Z"}],
+    }));
+    let error = session.call("audit_visibility", json!({"make_private": ["memos/2"]})).await;
+    assert_eq!(error["error"], "memos/2 is not one of your PUBLIC or PROTECTED memos");
+    let audit = session.ok("audit_visibility", json!({"make_private": ["memos/1"]})).await;
+    assert_eq!((audit["made_private"].clone(), audit["failed"].clone()), (json!(["memos/1"]), json!([])));
+}
+
+#[tokio::test]
+async fn test_memos_of_others_are_not_changed() {
+    let (mock, server) = mock::start().await;
+    Mock::given(method("GET")).and(path("/api/v1/auth/me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"user": mock::user("users/2", "jane")})))
+        .with_priority(1)
+        .mount(&mock)
+        .await;
+    mock::mount_memos_api(&mock).await;
+    let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+    let memo_mcp = MemoMCP::new(
+        servers,
+        Arc::new(RwLock::new(Settings::default())),
+        None,
+        Arc::new(TagCache::default()),
+        Arc::new(UndoLog::default()),
+    );
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Ok(running) = memo_mcp.serve(server_io).await {
+            let _ = running.waiting().await;
+        }
+    });
+    let client = ().serve(client_io).await.unwrap();
+    let call = async |tool: &str, arguments: Value| {
+        let result = client
+            .call_tool(CallToolRequestParam { name: tool.to_string().into(), arguments: arguments.as_object().cloned() })
+            .await
+            .unwrap();
+        serde_json::from_str::<Value>(&result.content[0].as_text().unwrap().text).unwrap()
+    };
+
+    // The mock memos are created by users/1.
+    for (tool, arguments) in [("update_memo", json!({"name": "memos/1", "content": "Mine now"})), ("delete_memo", json!({"name": "memos/1"}))] {
+        let response = call(tool, arguments).await;
+        assert_eq!(response["error"], "memos/1 is not your memo: it was created by users/1, and users/2 is not a HOST or ADMIN user", "{}", tool);
+    }
+    let planned = call("create_memo", json!({"content": "New memo", "state": "NORMAL", "dry_run": true})).await;
+    assert_eq!(planned["requests"][0]["body"]["creator"], "users/2", "{}", planned);
+}
+
+#[tokio::test]
+async fn test_rate_limit_is_reported() {
+    let (mock, server) = mock::start().await;
+    Mock::given(method("GET")).and(path("/api/v1/memos/busy"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "30").set_body_string("rate limit exceeded"))
+        .mount(&mock)
+        .await;
+    mock::mount_memos_api(&mock).await;
+    let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+    let memo_mcp = MemoMCP::new(
+        servers,
+        Arc::new(RwLock::new(Settings::default())),
+        None,
+        Arc::new(TagCache::default()),
+        Arc::new(UndoLog::default()),
+    );
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Ok(running) = memo_mcp.serve(server_io).await {
+            let _ = running.waiting().await;
+        }
+    });
+    let (messages, mut logged) = mpsc::unbounded_channel();
+    let client = LogMessages(messages).serve(client_io).await.unwrap();
+
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: "get_memo".into(),
+            arguments: json!({"name": "memos/busy"}).as_object().cloned(),
+        })
+        .await
+        .unwrap();
+    let response = serde_json::from_str::<Value>(&result.content[0].as_text().unwrap().text).unwrap();
+    assert!(response["error"].as_str().unwrap().ends_with("(retry after 30s)"));
+    assert_eq!((response["data"]["kind"].clone(), response["data"]["retry_after"].clone()), (json!("resource_exhausted"), json!(30)));
+
+    let message = tokio::time::timeout(std::time::Duration::from_secs(5), logged.recv()).await.unwrap().unwrap();
+    assert_eq!(message.data["message"], "Memos rate limited get_memo, retry after 30s");
+}
+
+#[tokio::test]
+async fn test_inbox_is_forwarded() {
+    let (mock, server) = mock::start().await;
+    mock::mount_memos_api(&mock).await;
+    let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+    let inbox = crate::inbox::inbox();
+    let memo_mcp = MemoMCP::new(
+        servers,
+        Arc::new(RwLock::new(Settings::default())),
+        None,
+        Arc::new(TagCache::default()),
+        Arc::new(UndoLog::default()),
+    ).with_inbox(Some(inbox.clone()));
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        if let Ok(running) = memo_mcp.serve(server_io).await {
+            let _ = running.waiting().await;
+        }
+    });
+    let (messages, mut logged) = mpsc::unbounded_channel();
+    let _client = LogMessages(messages).serve(client_io).await.unwrap();
+
+    let event = InboxEvent {
+        server: "default".to_string(),
+        notification: "users/1/notifications/2".to_string(),
+        kind: "MEMO_COMMENT".to_string(),
+        sender: "users/2".to_string(),
+        memo: Some("memos/1".to_string()),
+        comment: Some("memos/9".to_string()),
+        create_time: None,
+        message: "users/2 commented on memos/1 with memos/9".to_string(),
+    };
+    // The session subscribes once initialized, events sent before are not for it.
+    let message = loop {
+        let _ = inbox.send(event.clone());
+        if let Ok(message) = tokio::time::timeout(std::time::Duration::from_millis(100), logged.recv()).await {
+            break message.unwrap();
+        }
+    };
+    assert_eq!(message.logger.as_deref(), Some("memos.inbox"));
+    assert_eq!(message.data["message"], "users/2 commented on memos/1 with memos/9");
+}
+
+#[tokio::test]
+async fn test_clean_up_attachments_on_comments() {
+    let session = Session::start(Settings::default()).await;
+    let attachment = |name: &str, memo: &str| {
+        let mut attachment = mock::attachment(name, "photo.jpg", "image/jpeg");
+        attachment["memo"] = json!(memo);
+        attachment
+    };
+    // Memos lists no comments, so memos/9 is only found when looked up.
+    Mock::given(method("GET")).and(path("/api/v1/attachments"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"attachments": [
+            attachment("attachments/2", "memos/1"),
+            attachment("attachments/3", "memos/9"),
+            attachment("attachments/4", "memos/missing"),
+        ]})))
+        .with_priority(1)
+        .mount(&session._mock)
+        .await;
+
+    let cleanup = session.ok("clean_up_attachments", json!({"delete": ["orphaned"], "dry_run": true})).await;
+    assert_eq!(cleanup["would_delete"], json!(["attachments/4"]));
+    assert_eq!(cleanup["flagged"].as_array().unwrap().len(), 1);
+}
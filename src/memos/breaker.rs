@@ -0,0 +1,121 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Circuit breaker in front of each Memos origin. After a run of failed requests (connection errors,
+// timeouts and gateway errors) the circuit opens and requests fail at once instead of waiting for
+// a server that is down. Once the open period is over, a single request is let through as a probe:
+// its success closes the circuit, its failure opens it again.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+
+/// Consecutive failures that open the circuit.
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long an open circuit fails requests before letting a probe through.
+const OPEN_FOR: Duration = Duration::from_secs(30);
+
+/// The breakers of every origin the process talks to.
+pub static BREAKER: LazyLock<Breaker> = LazyLock::new(Breaker::default);
+
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: u32,
+    open_until: Option<Instant>,
+    /// When the probe in flight was let through. A probe that never reports back, e.g. because
+    /// its call was cancelled, is replaced after `OPEN_FOR`.
+    probe_started: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+pub struct Breaker {
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+/// Whether a response means the server is unavailable rather than refusing the request.
+pub fn is_unavailable(status: StatusCode) -> bool {
+    matches!(status, StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT)
+}
+
+impl Breaker {
+    /// Fails with an error naming the wait left when requests to `origin` must not be sent now.
+    pub fn check(&self, origin: &str, now: Instant) -> anyhow::Result<()> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(origin) else {
+            return Ok(());
+        };
+        let Some(open_until) = circuit.open_until else {
+            return Ok(());
+        };
+        let probing = circuit.probe_started.is_some_and(|started| now < started + OPEN_FOR);
+        if now < open_until || probing {
+            let wait = open_until.saturating_duration_since(now).max(Duration::from_secs(1));
+            return Err(anyhow::anyhow!(
+                "Memos unavailable: {} failed {} requests in a row, retrying in {}s",
+                origin,
+                circuit.failures,
+                wait.as_secs_f64().ceil() as u64,
+            ));
+        }
+        circuit.probe_started = Some(now);
+        Ok(())
+    }
+
+    /// Records the outcome of a request sent to `origin`.
+    pub fn record(&self, origin: &str, success: bool, now: Instant) {
+        let mut circuits = self.circuits.lock().unwrap();
+        if success {
+            if circuits.remove(origin).is_some_and(|circuit| circuit.open_until.is_some()) {
+                tracing::info!("Memos at {} is available again", origin);
+            }
+            return;
+        }
+        let circuit = circuits.entry(origin.to_string()).or_default();
+        circuit.failures += 1;
+        if circuit.probe_started.is_some() || circuit.failures >= FAILURE_THRESHOLD {
+            if circuit.open_until.is_none() {
+                tracing::warn!("Memos at {} failed {} requests in a row, failing fast for {}s", origin, circuit.failures, OPEN_FOR.as_secs());
+            }
+            circuit.open_until = Some(now + OPEN_FOR);
+            circuit.probe_started = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker() {
+        let breaker = Breaker::default();
+        let origin = "http://memos.example.com";
+        let start = Instant::now();
+        for _ in 1..FAILURE_THRESHOLD {
+            breaker.record(origin, false, start);
+        }
+        assert!(breaker.check(origin, start).is_ok());
+        breaker.record(origin, false, start);
+        let error = breaker.check(origin, start).unwrap_err().to_string();
+        assert!(error.starts_with("Memos unavailable: http://memos.example.com failed 5 requests in a row, retrying in 30s"), "{}", error);
+        assert!(breaker.check("http://other.example.com", start).is_ok());
+
+        // One probe once the circuit has been open long enough, which opens it again when it fails.
+        let later = start + OPEN_FOR;
+        assert!(breaker.check(origin, later).is_ok());
+        assert!(breaker.check(origin, later).is_err());
+        breaker.record(origin, false, later);
+        assert!(breaker.check(origin, later + OPEN_FOR / 2).is_err());
+
+        // A successful probe closes it.
+        let recovered = later + OPEN_FOR;
+        assert!(breaker.check(origin, recovered).is_ok());
+        breaker.record(origin, true, recovered);
+        assert!(breaker.check(origin, recovered).is_ok());
+        assert!(breaker.check(origin, recovered).is_ok());
+    }
+}
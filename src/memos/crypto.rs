@@ -0,0 +1,147 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Client-side encryption of memo content. With a content key set on a server, the content of every
+// memo and comment is encrypted with AES-256-GCM before it is sent to Memos and decrypted when it is
+// read back, so Memos only ever stores ciphertext. Encrypted memos end with the `#encrypted` tag,
+// which is all Memos can see of them: tags, links and tasks inside the content are not visible to
+// its filters. The cipher needs the `encryption` feature.
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+/// Tag of the encrypted memos.
+pub const ENCRYPTED_TAG: &str = "encrypted";
+
+/// Start of encrypted content, naming the format of the ciphertext after it.
+const PREFIX: &str = "mcp-memo:v1:";
+
+/// A 256-bit key memo content is encrypted with.
+#[derive(Clone)]
+pub struct ContentKey([u8; 32]);
+
+impl fmt::Debug for ContentKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContentKey(..)")
+    }
+}
+
+impl ContentKey {
+    /// The key encoded in base64, as made by `openssl rand -base64 32`.
+    pub fn from_base64(text: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(text.trim())
+            .map_err(|e| anyhow::anyhow!("The key is not base64: {}", e))?;
+        let key = <[u8; 32]>::try_from(bytes.as_slice())
+            .map_err(|_| anyhow::anyhow!("The key is {} bytes long instead of 32", bytes.len()))?;
+        Ok(ContentKey(key))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read key file {}: {}", path.display(), e))?;
+        Self::from_base64(&text).map_err(|e| anyhow::anyhow!("Invalid key file {}: {}", path.display(), e))
+    }
+
+    /// The content to store for `content`, ending with the `#encrypted` tag.
+    pub fn encrypt(&self, content: &str) -> Result<String> {
+        let sealed = cipher::seal(&self.0, content.as_bytes())?;
+        Ok(format!("{}{}\n\n#{}", PREFIX, STANDARD.encode(sealed), ENCRYPTED_TAG))
+    }
+
+    /// The plaintext of stored `content`, `None` when it is not encrypted.
+    pub fn decrypt(&self, content: &str) -> Option<Result<String>> {
+        let encoded = content.strip_prefix(PREFIX)?;
+        let encoded = encoded.split_whitespace().next().unwrap_or_default();
+        Some(
+            STANDARD
+                .decode(encoded)
+                .map_err(|e| anyhow::anyhow!("The ciphertext is not base64: {}", e))
+                .and_then(|sealed| cipher::open(&self.0, sealed))
+                .and_then(|plain| String::from_utf8(plain).map_err(|_| anyhow::anyhow!("The plaintext is not UTF-8"))),
+        )
+    }
+}
+
+/// Whether stored `content` is encrypted.
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(PREFIX)
+}
+
+#[cfg(feature = "encryption")]
+mod cipher {
+    use anyhow::Result;
+    use ring::aead::{AES_256_GCM, Aad, LessSafeKey, Nonce, UnboundKey};
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    const NONCE_LEN: usize = 12;
+
+    fn key(key: &[u8; 32]) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("AES-256 keys are 32 bytes"))
+    }
+
+    /// The random nonce followed by the ciphertext and its tag.
+    pub fn seal(secret: &[u8; 32], plain: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow::anyhow!("Failed to generate a nonce"))?;
+        let mut sealed = plain.to_vec();
+        key(secret)
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt the content"))?;
+        sealed.splice(0..0, nonce);
+        Ok(sealed)
+    }
+
+    pub fn open(secret: &[u8; 32], mut sealed: Vec<u8>) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("The ciphertext is too short"));
+        }
+        let nonce = Nonce::try_assume_unique_for_key(&sealed[..NONCE_LEN])
+            .map_err(|_| anyhow::anyhow!("The ciphertext is too short"))?;
+        let plain = key(secret)
+            .open_within(nonce, Aad::empty(), &mut sealed, NONCE_LEN..)
+            .map_err(|_| anyhow::anyhow!("The content does not decrypt with this key"))?;
+        Ok(plain.to_vec())
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+mod cipher {
+    use anyhow::Result;
+
+    pub fn seal(_: &[u8; 32], _: &[u8]) -> Result<Vec<u8>> {
+        Err(anyhow::anyhow!("Encrypting memos requires the `encryption` feature"))
+    }
+
+    pub fn open(_: &[u8; 32], _: Vec<u8>) -> Result<Vec<u8>> {
+        Err(anyhow::anyhow!("Decrypting memos requires the `encryption` feature"))
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let key = ContentKey::from_base64(&STANDARD.encode([7u8; 32])).unwrap();
+        let stored = key.encrypt("Salary review #work").unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("Salary"));
+        assert!(stored.ends_with("\n\n#encrypted"));
+        assert_ne!(stored, key.encrypt("Salary review #work").unwrap());
+        assert_eq!(key.decrypt(&stored).unwrap().unwrap(), "Salary review #work");
+
+        assert!(key.decrypt("Plain memo").is_none());
+        let other = ContentKey::from_base64(&STANDARD.encode([8u8; 32])).unwrap();
+        assert!(other.decrypt(&stored).unwrap().is_err());
+        assert!(ContentKey::from_base64("c2hvcnQ=").unwrap_err().to_string().contains("5 bytes"));
+    }
+}
@@ -0,0 +1,193 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Errors Memos answers requests with. The API reports them as gRPC statuses, a JSON body with a
+// numeric `code`, a `message` and `details`, behind the HTTP status of the gateway. Both are kept,
+// along with a stable `kind` naming the failure, so that MCP clients can tell a missing memo from a
+// denied one without parsing the message. When Memos, or a proxy in front of it, rate limits a
+// request, the wait it asks for in `Retry-After` is kept too.
+
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::{Response, StatusCode, header::RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+
+use crate::redact::redact;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemosError {
+    /// The HTTP status of the response.
+    pub status: u16,
+    /// The failure, e.g. `not_found` or `permission_denied`.
+    pub kind: &'static str,
+    /// The gRPC status code from the body, when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<i64>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<serde_json::Value>,
+    /// Seconds to wait before retrying, from the `Retry-After` header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    /// The whole body, redacted, for the error message.
+    #[serde(skip)]
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: Option<i64>,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    details: Vec<serde_json::Value>,
+}
+
+impl MemosError {
+    pub fn new(status: StatusCode, body: &str) -> Self {
+        let body = redact(body);
+        let parsed = serde_json::from_str::<ErrorBody>(&body).ok();
+        let code = parsed.as_ref().and_then(|parsed| parsed.code);
+        let message = match &parsed {
+            Some(parsed) if !parsed.message.is_empty() => parsed.message.clone(),
+            _ if !body.trim().is_empty() && parsed.is_none() => body.trim().to_string(),
+            _ => status.canonical_reason().unwrap_or_default().to_string(),
+        };
+        MemosError {
+            status: status.as_u16(),
+            kind: code.and_then(kind_of_code).unwrap_or_else(|| kind_of_status(status)),
+            code,
+            message,
+            details: parsed.map(|parsed| parsed.details).unwrap_or_default(),
+            retry_after: None,
+            body,
+        }
+    }
+
+    /// Reads the error out of a response with an error status.
+    pub async fn from_response(rsp: Response) -> anyhow::Error {
+        let status = rsp.status();
+        let retry_after = rsp.headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+        match rsp.text().await {
+            Ok(text) => {
+                let mut error = MemosError::new(status, &text);
+                error.retry_after = retry_after.map(|wait| wait.as_secs());
+                error.into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+
+    /// The Memos error `error` was caused by, if any.
+    pub fn find(error: &anyhow::Error) -> Option<&MemosError> {
+        error.chain().find_map(|cause| cause.downcast_ref::<MemosError>())
+    }
+}
+
+impl fmt::Display for MemosError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match StatusCode::from_u16(self.status) {
+            Ok(status) => write!(f, "Request failed: {} - {}", status, self.body)?,
+            Err(_) => write!(f, "Request failed: {} - {}", self.status, self.body)?,
+        }
+        match self.retry_after {
+            Some(retry_after) => write!(f, " (retry after {}s)", retry_after),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for MemosError {}
+
+/// The wait asked for by a `Retry-After` header, either seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or_default())
+}
+
+/// The failure named by a gRPC status code.
+fn kind_of_code(code: i64) -> Option<&'static str> {
+    Some(match code {
+        1 => "cancelled",
+        2 => "unknown",
+        3 => "invalid_argument",
+        4 => "deadline_exceeded",
+        5 => "not_found",
+        6 => "already_exists",
+        7 => "permission_denied",
+        8 => "resource_exhausted",
+        9 => "failed_precondition",
+        10 => "aborted",
+        11 => "out_of_range",
+        12 => "unimplemented",
+        13 => "internal",
+        14 => "unavailable",
+        15 => "data_loss",
+        16 => "unauthenticated",
+        _ => return None,
+    })
+}
+
+/// The failure named by an HTTP status, for bodies without a gRPC code.
+fn kind_of_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "invalid_argument",
+        StatusCode::UNAUTHORIZED => "unauthenticated",
+        StatusCode::FORBIDDEN => "permission_denied",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::CONFLICT => "already_exists",
+        StatusCode::PRECONDITION_FAILED => "failed_precondition",
+        StatusCode::TOO_MANY_REQUESTS => "resource_exhausted",
+        StatusCode::NOT_IMPLEMENTED => "unimplemented",
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+        StatusCode::GATEWAY_TIMEOUT => "deadline_exceeded",
+        status if status.is_server_error() => "internal",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memos_error() {
+        let body = r#"{"code": 7, "message": "permission denied", "details": [{"@type": "x"}]}"#;
+        let error = MemosError::new(StatusCode::FORBIDDEN, body);
+        assert_eq!((error.kind, error.code, error.message.as_str()), ("permission_denied", Some(7), "permission denied"));
+        assert_eq!(error.details.len(), 1);
+        assert_eq!(error.to_string(), format!("Request failed: 403 Forbidden - {}", body));
+
+        let error = MemosError::new(StatusCode::NOT_FOUND, "");
+        assert_eq!((error.kind, error.code, error.message.as_str()), ("not_found", None, "Not Found"));
+        let error = MemosError::new(StatusCode::BAD_GATEWAY, "upstream down");
+        assert_eq!((error.kind, error.message.as_str()), ("unavailable", "upstream down"));
+
+        let error = anyhow::Error::from(MemosError::new(StatusCode::NOT_FOUND, "")).context("Failed to get memos/9");
+        assert_eq!(MemosError::find(&error).map(|error| error.status), Some(404));
+
+        let mut error = MemosError::new(StatusCode::TOO_MANY_REQUESTS, "slow down");
+        error.retry_after = Some(30);
+        assert_eq!(error.to_string(), "Request failed: 429 Too Many Requests - slow down (retry after 30s)");
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = "2025-10-21T07:28:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Tue, 21 Oct 2025 07:28:45 GMT", now), Some(Duration::from_secs(45)));
+        assert_eq!(parse_retry_after("Tue, 21 Oct 2025 07:27:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
@@ -0,0 +1,181 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Recorded Memos responses, so the service code can be checked against several Memos releases
+// without running them. A live test records a flow against a real server into
+// `tests/fixtures/<memos version>/<flow>.json`, through a proxy in front of the server so the
+// service code runs as in release, and unit tests replay every recorded version.
+
+use std::path::Path;
+#[cfg(feature = "live-tests")]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wiremock::{Mock, MockServer, Request, ResponseTemplate, matchers::{method, path}};
+
+#[cfg(feature = "live-tests")]
+use crate::redact::redact;
+
+pub const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// One request to the Memos API and the response it got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    pub status: u16,
+    pub body: Value,
+}
+
+/// The exchanges of one flow against one Memos version, in the order they happened.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cassette {
+    pub version: String,
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Cassette {
+    #[cfg(feature = "live-tests")]
+    pub fn path(version: &str, flow: &str) -> std::path::PathBuf {
+        Path::new(FIXTURES_DIR).join(version).join(format!("{}.json", flow))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    #[cfg(feature = "live-tests")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// Every recorded version of `flow`, oldest directory name first.
+    pub fn all(flow: &str) -> Result<Vec<Self>> {
+        let mut paths = std::fs::read_dir(FIXTURES_DIR)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().join(format!("{}.json", flow)))
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.iter().map(|path| Self::load(path)).collect()
+    }
+
+    /// Mounts the exchanges on `mock`, each answering once and in the recorded order.
+    pub async fn replay(&self, mock: &MockServer) {
+        for exchange in &self.exchanges {
+            let query = exchange.query.clone();
+            Mock::given(method(exchange.method.as_str()))
+                .and(path(exchange.path.as_str()))
+                .and(move |request: &Request| request.url.query() == query.as_deref())
+                .respond_with(ResponseTemplate::new(exchange.status).set_body_json(&exchange.body))
+                .up_to_n_times(1)
+                .mount(mock)
+                .await;
+        }
+    }
+}
+
+/// A proxy in front of a real Memos server recording every exchange through it. A live test
+/// records a flow by pointing a [`Server`](crate::memos::Server) at [`Recorder::url`].
+#[cfg(feature = "live-tests")]
+pub struct Recorder {
+    url: String,
+    exchanges: Arc<Mutex<Vec<Exchange>>>,
+    proxy: tokio::task::JoinHandle<()>,
+}
+
+#[cfg(feature = "live-tests")]
+struct Proxy {
+    upstream: String,
+    client: reqwest::Client,
+    exchanges: Arc<Mutex<Vec<Exchange>>>,
+}
+
+#[cfg(feature = "live-tests")]
+impl Recorder {
+    /// Starts a proxy to the Memos API at `base_url`, e.g. `http://localhost:5230/api/v1`.
+    pub async fn start(base_url: &str) -> Result<Self> {
+        let base_url = reqwest::Url::parse(base_url)?;
+        let exchanges = Arc::new(Mutex::new(Vec::new()));
+        let proxy = Proxy {
+            upstream: base_url.origin().ascii_serialization(),
+            client: reqwest::Client::new(),
+            exchanges: exchanges.clone(),
+        };
+        let app = axum::Router::new().fallback(forward).with_state(Arc::new(proxy));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}{}", listener.local_addr()?, base_url.path().trim_end_matches('/'));
+        let proxy = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(Recorder { url, exchanges, proxy })
+    }
+
+    /// The base URL of the API through the proxy.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The exchanges so far, in the order they happened.
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+}
+
+#[cfg(feature = "live-tests")]
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.proxy.abort();
+    }
+}
+
+/// Sends `request` on to the Memos server and records the exchange, answering with the redacted
+/// body that is recorded.
+#[cfg(feature = "live-tests")]
+async fn forward(axum::extract::State(proxy): axum::extract::State<Arc<Proxy>>, request: axum::extract::Request) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    let (mut parts, body) = request.into_parts();
+    let path_and_query = parts.uri.path_and_query().map_or("/", |path_and_query| path_and_query.as_str());
+    parts.headers.remove(http::header::HOST);
+    let rsp = async {
+        let body = axum::body::to_bytes(body, usize::MAX).await?;
+        let rsp = proxy
+            .client
+            .request(parts.method.clone(), format!("{}{}", proxy.upstream, path_and_query))
+            .headers(parts.headers.clone())
+            .body(body)
+            .send()
+            .await?;
+        let status = rsp.status();
+        let content_type = rsp.headers().get(http::header::CONTENT_TYPE).cloned();
+        anyhow::Ok((status, content_type, redact(&rsp.text().await?)))
+    };
+    let (status, content_type, text) = match rsp.await {
+        Ok(rsp) => rsp,
+        Err(e) => return (http::StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    };
+
+    proxy.exchanges.lock().unwrap().push(Exchange {
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().map(str::to_string),
+        status: status.as_u16(),
+        body: serde_json::from_str(&text).unwrap_or(Value::String(text.clone())),
+    });
+    let mut rsp = (status, text).into_response();
+    if let Some(content_type) = content_type {
+        rsp.headers_mut().insert(http::header::CONTENT_TYPE, content_type);
+    }
+    rsp
+}
@@ -0,0 +1,257 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, Request, ResponseTemplate,
+    matchers::{method, path, path_regex, query_param},
+};
+
+use crate::memos::Server;
+
+pub const TOKEN: &str = "memos_pat_mocktoken";
+pub const AUTHORIZATION: &str = "Bearer memos_pat_mocktoken";
+
+/// Starts a mock Memos server and a client for it. The mock stops when it is dropped.
+pub async fn start() -> (MockServer, Server) {
+    let mock = MockServer::start().await;
+    let server = Server::new(&mock.address().to_string(), TOKEN).unwrap();
+    (mock, server)
+}
+
+/// The real Memos server named by MEMOS_TEST_HOST and MEMOS_TEST_TOKEN, or `None` so a live
+/// test can skip itself when they are not set.
+#[cfg(feature = "live-tests")]
+pub fn live_server() -> Option<Server> {
+    match (std::env::var("MEMOS_TEST_HOST"), std::env::var("MEMOS_TEST_TOKEN")) {
+        (Ok(host), Ok(token)) => Some(Server::new(&host, &token).expect("MEMOS_TEST_HOST is not a valid Memos URL")),
+        _ => {
+            eprintln!("Skipping live test, set MEMOS_TEST_HOST and MEMOS_TEST_TOKEN to run it");
+            None
+        }
+    }
+}
+
+/// Filter of the shortcut `users/1/shortcuts/garden`, matching `memos/1`.
+const GARDEN_FILTER: &str = r#"tag in ["garden"]"#;
+
+/// Mounts a stateless stand-in for the memo, comment and user endpoints of the Memos API. The
+/// server has memos `memos/1` and `memos/2`, and the token belongs to HOST user `users/1`, who has
+/// one shortcut. Attachment `attachments/1` is a 3-byte `plan.png`.
+pub async fn mount_memos_api(mock: &MockServer) {
+    let mut host = user("users/1", "host");
+    host["role"] = json!("HOST");
+
+    Mock::given(method("GET")).and(path("/api/v1/auth/me"))
+        .respond_with(ok(json!({"user": host.clone()})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/memos")).and(query_param("state", "ARCHIVED"))
+        .respond_with(ok(json!({"memos": []})))
+        .with_priority(2)
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/memos")).and(query_param("filter", GARDEN_FILTER))
+        .respond_with(ok(json!({"memos": [memo("memos/1", "Grow tomatoes on the balcony #garden")]})))
+        .with_priority(2)
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/memos"))
+        .respond_with(ok(json!({"memos": [
+            memo("memos/1", "Grow tomatoes on the balcony #garden"),
+            memo("memos/2", "Tomatoes and basil salad #recipe"),
+        ]})))
+        .mount(mock).await;
+    Mock::given(method("POST")).and(path("/api/v1/memos"))
+        .respond_with(|request: &Request| {
+            let body = request.body_json::<Value>().unwrap_or_default();
+            ok(memo("memos/3", body["content"].as_str().unwrap_or_default()))
+        })
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path_regex(r"^/api/v1/memos/[^/]+/comments$"))
+        .respond_with(ok(json!({"memos": [memo("memos/9", "A comment")]})))
+        .mount(mock).await;
+    Mock::given(method("POST")).and(path_regex(r"^/api/v1/memos/[^/]+/comments$"))
+        .respond_with(|request: &Request| {
+            let body = request.body_json::<Value>().unwrap_or_default();
+            ok(memo("memos/9", body["content"].as_str().unwrap_or_default()))
+        })
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/memos/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({"code": 5, "message": "memo not found", "details": []})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path_regex(r"^/api/v1/memos/[^/]+$"))
+        .respond_with(|request: &Request| {
+            let name = request.url.path().trim_start_matches("/api/v1/");
+            ok(memo(name, &format!("Content of {}", name)))
+        })
+        .mount(mock).await;
+    Mock::given(method("PATCH")).and(path_regex(r"^/api/v1/memos/[^/]+$"))
+        .respond_with(|request: &Request| {
+            let name = request.url.path().trim_start_matches("/api/v1/");
+            let mut updated = memo(name, "");
+            if let Ok(Value::Object(body)) = request.body_json::<Value>() {
+                updated.as_object_mut().unwrap().extend(body);
+            }
+            ok(updated)
+        })
+        .mount(mock).await;
+    Mock::given(method("DELETE")).and(path_regex(r"^/api/v1/memos/[^/]+$"))
+        .respond_with(ok(json!({})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/users"))
+        .respond_with(ok(json!({"users": [host, user("users/2", "jane")]})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/users:stats"))
+        .respond_with(ok(json!({"stats": [
+            {"name": "users/1/stats", "totalMemoCount": 2, "pinnedMemos": []},
+            {"name": "users/2/stats", "totalMemoCount": 0},
+        ]})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/attachments"))
+        .respond_with(ok(json!({"attachments": [attachment("attachments/1", "plan.png", "image/png")]})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/attachments/1"))
+        .respond_with(ok(attachment("attachments/1", "plan.png", "image/png")))
+        .mount(mock).await;
+    Mock::given(method("DELETE")).and(path_regex(r"^/api/v1/attachments/[^/]+$"))
+        .respond_with(ok(json!({})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/file/attachments/1/plan.png"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"PNG".to_vec()))
+        .mount(mock).await;
+    Mock::given(method("PATCH")).and(path_regex(r"^/api/v1/users/[^/]+$"))
+        .respond_with(|request: &Request| {
+            let name = request.url.path().trim_start_matches("/api/v1/");
+            let mut updated = user(name, "jane");
+            if let Ok(Value::Object(body)) = request.body_json::<Value>() {
+                updated.as_object_mut().unwrap().extend(body);
+            }
+            ok(updated)
+        })
+        .mount(mock).await;
+    Mock::given(method("POST")).and(path_regex(r"^/api/v1/users/[^/]+/personalAccessTokens$"))
+        .respond_with(|request: &Request| {
+            let parent = request.url.path().trim_start_matches("/api/v1/").trim_end_matches("/personalAccessTokens");
+            let body = request.body_json::<Value>().unwrap_or_default();
+            let name = format!("{}/personalAccessTokens/1", parent);
+            ok(json!({
+                "personalAccessToken": personal_access_token(&name, body["description"].as_str().unwrap_or_default()),
+                "token": "memos_pat_created",
+            }))
+        })
+        .mount(mock).await;
+    Mock::given(method("DELETE")).and(path_regex(r"^/api/v1/users/[^/]+/personalAccessTokens/[^/]+$"))
+        .respond_with(ok(json!({})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/users/1/notifications"))
+        .respond_with(ok(json!({"notifications": [
+            notification("users/1/notifications/2", "UNREAD", 7),
+            notification("users/1/notifications/1", "ARCHIVED", 6),
+        ]})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path("/api/v1/users/1/shortcuts"))
+        .respond_with(ok(json!({"shortcuts": [
+            {"name": "users/1/shortcuts/garden", "title": "Garden", "filter": GARDEN_FILTER},
+        ]})))
+        .mount(mock).await;
+    Mock::given(method("GET")).and(path_regex(r"^/api/v1/activities/[^/]+$"))
+        .respond_with(ok(json!({
+            "name": "activities/7",
+            "creator": "users/2",
+            "type": "MEMO_COMMENT",
+            "payload": {"memoComment": {"memo": "memos/9", "relatedMemo": "memos/1"}},
+        })))
+        .mount(mock).await;
+}
+
+fn ok(body: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(body)
+}
+
+/// A memo as returned by `GET /api/v1/memos/{id}`.
+pub fn memo(name: &str, content: &str) -> Value {
+    json!({
+        "name": name,
+        "state": "NORMAL",
+        "creator": "users/1",
+        "createTime": "2025-01-31T08:00:00Z",
+        "updateTime": "2025-01-31T08:00:00Z",
+        "displayTime": "2025-01-31T08:00:00Z",
+        "content": content,
+        "nodes": [],
+        "visibility": "PRIVATE",
+        "tags": [],
+        "pinned": false,
+        "attachments": [],
+        "relations": [],
+        "reactions": [],
+        "property": {"hasLink": false, "hasTaskList": false, "hasCode": false, "hasIncompleteTasks": false},
+        "snippet": content,
+    })
+}
+
+/// An attachment stored in Memos, served at `/file/{name}/{filename}`.
+pub fn attachment(name: &str, filename: &str, mime_type: &str) -> Value {
+    json!({
+        "name": name,
+        "createTime": "2025-01-31T08:00:00Z",
+        "filename": filename,
+        "externalLink": "",
+        "type": mime_type,
+        "size": "3",
+    })
+}
+
+pub fn user(name: &str, username: &str) -> Value {
+    json!({
+        "name": name,
+        "role": "USER",
+        "username": username,
+        "email": format!("{}@example.com", username),
+        "displayName": "",
+        "avatarUrl": "",
+        "description": "",
+        "state": "NORMAL",
+        "createTime": "2025-01-31T08:00:00Z",
+        "updateTime": "2025-01-31T08:00:00Z",
+    })
+}
+
+pub fn reaction(name: &str, memo: &str, reaction_type: &str) -> Value {
+    json!({
+        "name": name,
+        "creator": "users/1",
+        "contentId": memo,
+        "reactionType": reaction_type,
+        "createTime": "2025-01-31T08:00:00Z",
+    })
+}
+
+pub fn personal_access_token(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "description": description,
+        "createdAt": "2025-01-31T08:00:00Z",
+        "expiresAt": "2025-03-02T08:00:00Z",
+    })
+}
+
+/// A comment of jane on a memo of the host, in the inbox of the host.
+pub fn notification(name: &str, status: &str, activity_id: i64) -> Value {
+    json!({
+        "name": name,
+        "sender": "users/2",
+        "status": status,
+        "createTime": "2025-01-31T08:00:00Z",
+        "type": "MEMO_COMMENT",
+        "activityId": activity_id,
+    })
+}
+
+/// Serializes `value`, decodes it again and checks nothing was lost on the way.
+pub fn assert_round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+    let encoded = serde_json::to_value(value).unwrap();
+    let decoded: T = serde_json::from_value(encoded.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&decoded).unwrap(), encoded);
+}
@@ -3,86 +3,342 @@
 // Date: 2025-12-28
 // License: Proprietary
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
 use anyhow::Result;
-use serde::de::DeserializeOwned;
-use reqwest::{header::CONTENT_TYPE, Client, Response, RequestBuilder};
+use serde::{Serialize, de::DeserializeOwned};
+use reqwest::{header::CONTENT_TYPE, Client, Method, Response, RequestBuilder, Url};
+use tokio::sync::Semaphore;
+use tracing::{Instrument, field::Empty};
+use crate::redact::redact;
 
+mod breaker;
+pub mod crypto;
+mod error;
+mod name;
 pub mod service;
 
+pub use crypto::ContentKey;
+pub use error::MemosError;
+pub use name::MemoName;
+use service::{attachment::AttachmentService, auth::AuthService, endpoint::EndpointService, note::NoteService, user::UserService};
+#[cfg(test)]
+pub mod fixture;
+#[cfg(test)]
+pub mod mock;
+
+tokio::task_local! {
+    static DRY_RUN: RefCell<Vec<PlannedRequest>>;
+    static REQUEST_LIMIT: Arc<Semaphore>;
+}
+
+/// A write request that was recorded instead of sent, see [`dry_run`].
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_mask: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+}
+
+/// Runs `call` with every request except GETs recorded instead of sent. Recorded requests are
+/// answered with an empty success, so the output of `call` itself is meaningless.
+pub async fn dry_run<F: Future>(call: F) -> (F::Output, Vec<PlannedRequest>) {
+    DRY_RUN
+        .scope(RefCell::new(Vec::new()), async move {
+            let output = call.await;
+            (output, DRY_RUN.with(|planned| planned.take()))
+        })
+        .await
+}
+
+/// Runs `call` with at most as many Memos requests in flight as `limit` has permits, shared with
+/// every other call run with the same `limit`.
+pub async fn limit_requests<F: Future>(limit: Arc<Semaphore>, call: F) -> F::Output {
+    REQUEST_LIMIT.scope(limit, call).await
+}
+
+/// The limit the current call runs with, see [`limit_requests`], to pass on to the tasks it spawns.
+pub fn request_limit() -> Option<Arc<Semaphore>> {
+    REQUEST_LIMIT.try_with(Arc::clone).ok()
+}
+
+fn plan(request: &reqwest::Request) -> Option<Response> {
+    if request.method() == Method::GET {
+        return None;
+    }
+    DRY_RUN
+        .try_with(|planned| {
+            planned.borrow_mut().push(PlannedRequest {
+                method: request.method().to_string(),
+                url: redact(request.url().as_str()),
+                update_mask: request.url()
+                    .query_pairs()
+                    .find(|(key, _)| key == "updateMask")
+                    .map(|(_, mask)| mask.into_owned()),
+                body: request.body()
+                    .and_then(|body| body.as_bytes())
+                    .and_then(|body| serde_json::from_slice(body).ok()),
+            });
+            Response::from(http::Response::new("{}"))
+        })
+        .ok()
+}
+
+/// Sends requests inside an `upstream` span recording method, path, status and latency. Requests
+/// to a server that keeps failing are refused by its circuit breaker, see [`breaker`], and requests
+/// over the limit of the call wait for a permit, see [`limit_requests`].
+trait TracedSend {
+    async fn send_traced(self) -> Result<Response>;
+}
+
+impl TracedSend for RequestBuilder {
+    async fn send_traced(self) -> Result<Response> {
+        let (client, request) = self.build_split();
+        let request = request?;
+        if let Some(rsp) = plan(&request) {
+            return Ok(rsp);
+        }
+        let _permit = match request_limit() {
+            Some(limit) => Some(limit.acquire_owned().await?),
+            None => None,
+        };
+        let origin = request.url().origin().ascii_serialization();
+        breaker::BREAKER.check(&origin, Instant::now())?;
+        let span = tracing::debug_span!(
+            "upstream",
+            method = %request.method(),
+            path = request.url().path(),
+            status = Empty,
+            elapsed_ms = Empty,
+        );
+
+        #[cfg(feature = "otel")]
+        let method = request.method().clone();
+        let started = Instant::now();
+        let rsp = client.execute(request).instrument(span.clone()).await;
+        span.record("elapsed_ms", started.elapsed().as_millis() as u64);
+        if let Ok(rsp) = &rsp {
+            span.record("status", rsp.status().as_u16());
+        }
+        #[cfg(feature = "otel")]
+        crate::telemetry::record_upstream(method.as_str(), rsp.as_ref().ok().map(|rsp| rsp.status().as_u16()), started.elapsed());
+        span.in_scope(|| tracing::debug!("upstream request completed"));
+        let available = rsp.as_ref().is_ok_and(|rsp| !breaker::is_unavailable(rsp.status()));
+        breaker::BREAKER.record(&origin, available, Instant::now());
+        Ok(rsp?)
+    }
+}
+
+/// Bounds on following the page tokens of a listing, so that a server handing out tokens forever
+/// cannot keep a call running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    pub max_pages: usize,
+    pub max_items: usize,
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        PageLimits { max_pages: 10_000, max_items: 1_000_000 }
+    }
+}
+
+/// The progress of a paginated listing, checked against its [`PageLimits`].
+struct Pages {
+    limits: PageLimits,
+    pages: usize,
+    items: usize,
+    tokens: HashSet<String>,
+}
+
+impl Pages {
+    fn new(limits: PageLimits) -> Self {
+        Pages { limits, pages: 0, items: 0, tokens: HashSet::new() }
+    }
+
+    /// Counts a page of `items` items of `what`, and returns the token of the next page to fetch,
+    /// `None` once `token` is empty.
+    fn next(&mut self, what: &str, items: usize, token: String) -> Result<Option<String>> {
+        self.pages += 1;
+        self.items += items;
+        if token.is_empty() {
+            return Ok(None);
+        }
+        if self.pages >= self.limits.max_pages || self.items >= self.limits.max_items {
+            return Err(anyhow::anyhow!(
+                "Stopped listing {} after {} pages and {} items, the limit set by `max_pages` and `max_items`",
+                what,
+                self.pages,
+                self.items,
+            ));
+        }
+        if !self.tokens.insert(token.clone()) {
+            return Err(anyhow::anyhow!("Stopped listing {}: the server returned page token {} twice", what, token));
+        }
+        Ok(Some(token))
+    }
+}
+
 trait HttpServer {
     fn base_url(&self) -> &str;
-    fn token(&self) -> &str;
+    fn token(&self) -> String;
+    fn client(&self) -> &Client;
+
+    fn page_limits(&self) -> PageLimits {
+        PageLimits::default()
+    }
+
+    fn content_key(&self) -> Option<&ContentKey> {
+        None
+    }
 
     fn build_get_request(&self, endpoint: &str) -> RequestBuilder {
-        let client = Client::new();
-        client.get(format!("{}/{}", self.base_url(), endpoint))
+        self.client().get(format!("{}/{}", self.base_url(), endpoint))
             .header(CONTENT_TYPE, "application/json")
             .bearer_auth(self.token())
     }
 
     fn build_post_request(&self, endpoint: &str) -> RequestBuilder {
-        let client = Client::new();
-        client.post(format!("{}/{}", self.base_url(), endpoint))
+        self.client().post(format!("{}/{}", self.base_url(), endpoint))
             .header(CONTENT_TYPE, "application/json")
             .bearer_auth(self.token())
     }
 
     fn build_delete_request(&self, endpoint: &str) -> RequestBuilder {
-        let client = Client::new();
-        client.delete(format!("{}/{}", self.base_url(), endpoint))
+        self.client().delete(format!("{}/{}", self.base_url(), endpoint))
             .header(CONTENT_TYPE, "application/json")
             .bearer_auth(self.token())
     }
 
     fn build_patch_request(&self, endpoint: &str) -> RequestBuilder {
-        let client = Client::new();
-        client.patch(format!("{}/{}", self.base_url(), endpoint))
+        self.client().patch(format!("{}/{}", self.base_url(), endpoint))
             .header(CONTENT_TYPE, "application/json")
             .bearer_auth(self.token())
     }
 
-    async fn validate_response(&self, rsp: Response) -> Result<()> {
-        if !rsp.status().is_success() {
-            let status = rsp.status();
-            let text = rsp.text().await?;
-            return Err(anyhow::anyhow!("Request failed: {} - {}", status, text));
-        }
-        Ok(())
+    /// A GET of a file Memos serves outside the API, e.g. `/file/attachments/{id}/{filename}`.
+    fn build_file_request(&self, path: &[&str]) -> Result<RequestBuilder> {
+        let mut url = Url::parse(self.base_url().trim_end_matches(API_PATH))?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid Memos URL {}", self.base_url()))?
+            .pop_if_empty()
+            .push("file")
+            .extend(path);
+        Ok(self.client().get(url).bearer_auth(self.token()))
     }
-    async fn validate_data_response<T: DeserializeOwned>(&self, rsp: Response) -> Result<T> {
-        if !rsp.status().is_success() {
-            let status = rsp.status();
-            let text = rsp.text().await?;
-            return Err(anyhow::anyhow!("Request failed: {} - {}", status, text));
+
+    fn validate_response(&self, rsp: Response) -> impl Future<Output = Result<()>> + Send {
+        async move {
+            if !rsp.status().is_success() {
+                return Err(MemosError::from_response(rsp).await);
+            }
+            Ok(())
         }
+    }
+    fn validate_data_response<T: DeserializeOwned>(&self, rsp: Response) -> impl Future<Output = Result<T>> + Send {
+        async move {
+            if !rsp.status().is_success() {
+                return Err(MemosError::from_response(rsp).await);
+            }
 
-        let data = rsp
-            .json::<T>()
-            .await?;
+            let data = rsp
+                .json::<T>()
+                .await?;
 
-        Ok(data)
+            Ok(data)
+        }
     }
 }
 
+#[derive(Clone)]
 pub struct Server {
     base_url: String,
-    token: String,
+    token: Arc<RwLock<String>>,
+    /// Shared by every clone, so they all draw on one connection pool.
+    client: Client,
     sign_out_required: bool,
+    page_limits: PageLimits,
+    content_key: Option<ContentKey>,
+}
+
+/// Path of the Memos API below the address Memos is served at.
+const API_PATH: &str = "/api/v1";
+
+/// The base URL of the Memos API at `host`: a `host[:port]`, taken to be plain HTTP, or an `http` or
+/// `https` URL. A path in the URL is where Memos is served behind a reverse proxy, e.g.
+/// `https://example.com/memos`; a path already ending in `/api/v1` is kept as it is.
+pub fn api_url(host: &str) -> Result<String> {
+    let host = host.trim();
+    let url = match host.contains("://") {
+        true => Url::parse(host),
+        false => Url::parse(&format!("http://{}", host)),
+    };
+    let url = url.map_err(|e| anyhow::anyhow!("`{}` is not a valid Memos URL: {}", host, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow::anyhow!("`{}` must use http or https, not {}", host, url.scheme()));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(anyhow::anyhow!("`{}` has no host name", host));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(anyhow::anyhow!("`{}` must not include a query or fragment", host));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(anyhow::anyhow!("`{}` must not include credentials, use a token instead", host));
+    }
+    let base = url.as_str().trim_end_matches('/');
+    match base.ends_with(API_PATH) {
+        true => Ok(base.to_string()),
+        false => Ok(format!("{}{}", base, API_PATH)),
+    }
 }
 
 impl Server {
-    pub fn new(host: &str, token: &str) -> Self {
-        Server {
-            base_url: format!("http://{}/api/v1", host),
-            token: token.to_string(),
+    /// A client of the Memos server at `host`, see [`api_url`] for the forms it takes.
+    pub fn new(host: &str, token: &str) -> Result<Self> {
+        Ok(Server {
+            base_url: api_url(host)?,
+            token: Arc::new(RwLock::new(token.to_string())),
+            client: Client::new(),
             sign_out_required: false,
-        }
+            page_limits: PageLimits::default(),
+            content_key: None,
+        })
+    }
+
+    /// Bounds the listings of this server, see [`PageLimits`].
+    pub fn with_page_limits(mut self, page_limits: PageLimits) -> Self {
+        self.page_limits = page_limits;
+        self
+    }
+
+    /// Encrypts the content of the memos written to this server and decrypts what is read, see
+    /// [`crypto`].
+    pub fn with_content_key(mut self, content_key: Option<ContentKey>) -> Self {
+        self.content_key = content_key;
+        self
+    }
+
+    /// A client of the same server authenticating with `token`, which is not shared with this one.
+    pub fn with_token(&self, token: &str) -> Self {
+        Server { token: Arc::new(RwLock::new(token.to_string())), ..self.clone() }
+    }
+
+    /// Replaces the access token for this server and every clone of it.
+    pub fn set_token(&self, token: &str) {
+        *self.token.write().unwrap() = token.to_string();
     }
 
     pub async fn cleanup(&self) -> Result<()> {
         if self.sign_out_required {
             self.build_post_request("auth/signout")
-                .send()
+                .send_traced()
                 .await?;
         }
         Ok(())
@@ -94,7 +350,89 @@ impl HttpServer for Server {
         &self.base_url
     }
 
-    fn token(&self) -> &str {
-        &self.token
+    fn token(&self) -> String {
+        self.token.read().unwrap().clone()
+    }
+
+    fn client(&self) -> &Client {
+        &self.client
+    }
+
+    fn page_limits(&self) -> PageLimits {
+        self.page_limits
+    }
+
+    fn content_key(&self) -> Option<&ContentKey> {
+        self.content_key.as_ref()
+    }
+}
+
+/// Everything the MCP tools need from a Memos server. Implemented by [`Server`], and by anything
+/// else implementing the service traits, so tools can run against a cache or a test double.
+pub trait Backend: NoteService + UserService + AuthService + AttachmentService + EndpointService {}
+
+impl<T: NoteService + UserService + AuthService + AttachmentService + EndpointService> Backend for T {}
+
+/// Named Memos servers, with a default used when a caller does not select one.
+#[derive(Clone)]
+pub struct Servers {
+    default: String,
+    servers: BTreeMap<String, Arc<dyn Backend>>,
+}
+
+impl Servers {
+    pub fn new<B: Backend + 'static>(default: &str, servers: BTreeMap<String, B>) -> Self {
+        let servers = servers
+            .into_iter()
+            .map(|(name, server)| (name, Arc::new(server) as Arc<dyn Backend>))
+            .collect();
+        Self::with_backends(default, servers)
+    }
+
+    /// Servers with a different [`Backend`] each, e.g. a cache in front of only some of them.
+    pub fn with_backends(default: &str, servers: BTreeMap<String, Arc<dyn Backend>>) -> Self {
+        Servers {
+            default: default.to_string(),
+            servers,
+        }
+    }
+
+    pub fn get(&self, name: Option<&str>) -> Result<&dyn Backend> {
+        self.shared(name).map(|server| server.as_ref())
+    }
+
+    /// Like [`Servers::get`], for work that outlives the borrow such as spawned tasks.
+    pub fn shared(&self, name: Option<&str>) -> Result<&Arc<dyn Backend>> {
+        let name = name.unwrap_or(&self.default);
+        self.servers.get(name).ok_or_else(|| {
+            let known = self.servers.keys().cloned().collect::<Vec<_>>().join(", ");
+            anyhow::anyhow!("Unknown server `{}`, expected one of: {}", name, known)
+        })
+    }
+
+    pub fn default_name(&self) -> &str {
+        &self.default
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.servers.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &dyn Backend)> {
+        self.servers.iter().map(|(name, server)| (name.as_str(), server.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_url() {
+        let api_url = |host: &str| api_url(host).unwrap();
+        assert_eq!(api_url("localhost:5230"), "http://localhost:5230/api/v1");
+        assert_eq!(api_url(" https://memos.example.com/ "), "https://memos.example.com/api/v1");
+        assert_eq!(api_url("https://example.com/memos/"), "https://example.com/memos/api/v1");
+        assert_eq!(api_url("example.com/memos/api/v1"), "http://example.com/memos/api/v1");
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,104 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// The resource name of a memo, `memos/{id}`. Parses from the name itself, a bare memo id or
+/// UID, and links to the memo in the Memos web app (`https://host/memos/{id}` or `/m/{id}`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct MemoName(String);
+
+impl MemoName {
+    pub fn id(&self) -> &str {
+        &self.0["memos/".len()..]
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for MemoName {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let id = match text.split_once("://") {
+            Some((_, rest)) => {
+                let path = rest.split(['?', '#']).next().unwrap_or_default();
+                let segments = path.split('/').skip(1).collect::<Vec<_>>();
+                segments
+                    .windows(2)
+                    .find(|pair| pair[0] == "memos" || pair[0] == "m")
+                    .map(|pair| pair[1])
+                    .ok_or_else(|| anyhow::anyhow!("URL {} does not link to a memo", text))?
+            }
+            None => text.strip_prefix("memos/").unwrap_or(text),
+        };
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow::anyhow!("Invalid memo name {:?}, expected `memos/{{id}}`, a memo id or a memo URL", text));
+        }
+        Ok(MemoName(format!("memos/{}", id)))
+    }
+}
+
+impl TryFrom<String> for MemoName {
+    type Error = anyhow::Error;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl TryFrom<&str> for MemoName {
+    type Error = anyhow::Error;
+
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<MemoName> for String {
+    fn from(name: MemoName) -> Self {
+        name.0
+    }
+}
+
+impl AsRef<str> for MemoName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for MemoName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memo_name() {
+        for text in ["memos/7kL2xWnQ", "7kL2xWnQ", " memos/7kL2xWnQ ", "https://memos.example.com/memos/7kL2xWnQ", "http://localhost:5230/m/7kL2xWnQ?tab=comments"] {
+            let name: MemoName = text.parse().unwrap();
+            assert_eq!(name.as_str(), "memos/7kL2xWnQ", "{}", text);
+            assert_eq!(name.id(), "7kL2xWnQ");
+        }
+        for text in ["", "memos/", "users/1", "memos/1/comments", "https://memos.example.com/explore"] {
+            assert!(text.parse::<MemoName>().is_err(), "{}", text);
+        }
+
+        let name: MemoName = serde_json::from_value(serde_json::json!("12")).unwrap();
+        assert_eq!(serde_json::to_value(&name).unwrap(), "memos/12");
+        assert!(serde_json::from_value::<MemoName>(serde_json::json!("memos/a b")).is_err());
+    }
+}
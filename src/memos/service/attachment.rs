@@ -0,0 +1,113 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use crate::memos::{MemosError, Pages, TracedSend};
+use crate::memos::service::note::Attachment;
+
+#[async_trait]
+pub trait AttachmentService: Send + Sync {
+    /// Uploads a file to Memos. The attachment belongs to no memo until it is set on one.
+    async fn create_attachment(&self, filename: &str, mime_type: &str, content: &[u8]) -> Result<Attachment>;
+
+    /// Downloads the file of an attachment stored in Memos. Attachments with an external link
+    /// have no file in Memos to download.
+    async fn download_attachment(&self, attachment: &Attachment) -> Result<Vec<u8>>;
+
+    /// Lists the attachments of the current user.
+    async fn list_attachments(&self) -> Result<Vec<Attachment>>;
+
+    /// The attachment `name`, e.g. `attachments/1`, without its file.
+    async fn get_attachment(&self, name: &str) -> Result<Attachment>;
+
+    /// Deletes the attachment `name` and its file. A memo it was set on no longer shows it.
+    async fn delete_attachment(&self, name: &str) -> Result<()>;
+}
+
+#[async_trait]
+impl<T> AttachmentService for T
+where
+    T: crate::memos::HttpServer + Send + Sync,
+{
+    #[tracing::instrument(level = "debug", skip(self, content), fields(size = content.len()))]
+    async fn create_attachment(&self, filename: &str, mime_type: &str, content: &[u8]) -> Result<Attachment> {
+        #[derive(Serialize)]
+        struct RequestBody<'a> {
+            filename: &'a str,
+            #[serde(rename = "type")]
+            mime_type: &'a str,
+            content: String,
+        }
+
+        let body = RequestBody {
+            filename,
+            mime_type,
+            content: base64::engine::general_purpose::STANDARD.encode(content),
+        };
+        let rsp = self.build_post_request("attachments").json(&body).send_traced().await?;
+
+        self.validate_data_response::<Attachment>(rsp).await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(attachment = attachment.name.as_str()))]
+    async fn download_attachment(&self, attachment: &Attachment) -> Result<Vec<u8>> {
+        if !attachment.external_link.is_empty() {
+            return Err(anyhow::anyhow!("Attachment {} links to {} instead of storing a file", attachment.name, attachment.external_link));
+        }
+        let mut path = attachment.name.split('/').collect::<Vec<_>>();
+        path.push(&attachment.filename);
+        let rsp = self.build_file_request(&path)?.send_traced().await?;
+
+        if !rsp.status().is_success() {
+            return Err(MemosError::from_response(rsp).await);
+        }
+        Ok(rsp.bytes().await?.to_vec())
+    }
+
+    async fn list_attachments(&self) -> Result<Vec<Attachment>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ResponseBody {
+            #[serde(default)]
+            attachments: Vec<Attachment>,
+            #[serde(default)]
+            next_page_token: String,
+        }
+
+        let mut attachments = Vec::new();
+        let mut pages = Pages::new(self.page_limits());
+        let mut page_token = None;
+        loop {
+            let endpoint = match &page_token {
+                None => "attachments".to_string(),
+                Some(token) => format!("attachments?pageToken={}", token),
+            };
+            let rsp = self.build_get_request(&endpoint).send_traced().await?;
+            let body = self.validate_data_response::<ResponseBody>(rsp).await?;
+            let count = body.attachments.len();
+            attachments.extend(body.attachments);
+            page_token = pages.next("attachments", count, body.next_page_token)?;
+            if page_token.is_none() {
+                break;
+            }
+        }
+        Ok(attachments)
+    }
+
+    async fn get_attachment(&self, name: &str) -> Result<Attachment> {
+        let rsp = self.build_get_request(name).send_traced().await?;
+        self.validate_data_response::<Attachment>(rsp).await
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn delete_attachment(&self, name: &str) -> Result<()> {
+        let rsp = self.build_delete_request(name).send_traced().await?;
+        self.validate_response(rsp).await?;
+        Ok(())
+    }
+}
@@ -4,53 +4,26 @@
 // License: Proprietary
 
 use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Serialize, Deserialize};
+use crate::memos::TracedSend;
 use crate::memos::Server;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub enum Role {
-    #[serde(rename = "ROLE_UNSPECIFIED")]
-    RoleUnspecified,
-    #[serde(rename = "HOST")]
-    Host,
-    #[serde(rename = "ADMIN")]
-    Admin,
-    #[serde(rename = "USER")]
-    User,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub enum State {
-    #[serde(rename = "STATE_UNSPECIFIED")]
-    StateUnspecified,
-    #[serde(rename = "NORMAL")]
-    Normal,
-    #[serde(rename = "ARCHIVED")]
-    Archived,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct User {
-    #[serde(default)] pub name: String,
-    pub role: Role,
-    pub username: String,
-    #[serde(default)] pub email: String,
-    #[serde(default, rename = "displayName")] pub display_name: String,
-    #[serde(default, rename = "avatarUrl")] pub avatar_url: String,
-    #[serde(default)] pub description: String,
-    pub state: State,
-}
+// `auth/me` returns the same user as the `users` endpoints.
+pub use super::user::{Role, State, User};
 
-pub trait AuthService {
+#[async_trait]
+pub trait AuthService: Send + Sync {
     async fn get_current_user(&self) -> Result<User>;
 
     async fn sign_in(&self, username: &str, password: &str) -> Result<Server>;
 }
 
-impl<T> AuthService for T where T: crate::memos::HttpServer {
+#[async_trait]
+impl<T> AuthService for T where T: crate::memos::HttpServer + Send + Sync {
     async fn get_current_user(&self) -> Result<User> {
         let rsp = self.build_get_request("auth/me")
-            .send()
+            .send_traced()
             .await?;
 
         #[derive(Deserialize)]
@@ -81,7 +54,7 @@ impl<T> AuthService for T where T: crate::memos::HttpServer {
 
         let rsp = self.build_post_request("auth/signin")
             .json(&body)
-            .send()
+            .send_traced()
             .await?;
 
         #[derive(Deserialize)]
@@ -93,8 +66,11 @@ impl<T> AuthService for T where T: crate::memos::HttpServer {
 
         Ok(Server {
             base_url: self.base_url().to_string(),
-            token: data.access_token,
-            sign_out_required: true
+            token: std::sync::Arc::new(std::sync::RwLock::new(data.access_token)),
+            client: self.client().clone(),
+            sign_out_required: true,
+            page_limits: self.page_limits(),
+            content_key: self.content_key().cloned(),
         })
     }
 }
\ No newline at end of file
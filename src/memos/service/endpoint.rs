@@ -0,0 +1,78 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Method, header::CONTENT_TYPE};
+use serde_json::Value;
+use crate::memos::{MemosError, TracedSend};
+
+#[async_trait]
+pub trait EndpointService: Send + Sync {
+    /// Sends a request to any endpoint of the API, e.g. `users/1/shortcuts`, for the endpoints no
+    /// other service covers. Returns the JSON response, `null` when it has no body.
+    async fn call_endpoint(&self, method: Method, endpoint: &str, body: Option<&Value>) -> Result<Value>;
+}
+
+#[async_trait]
+impl<T> EndpointService for T
+where
+    T: crate::memos::HttpServer + Send + Sync,
+{
+    #[tracing::instrument(level = "debug", skip(self, body))]
+    async fn call_endpoint(&self, method: Method, endpoint: &str, body: Option<&Value>) -> Result<Value> {
+        let mut request = self.client()
+            .request(method, format!("{}/{}", self.base_url(), endpoint.trim_start_matches('/')))
+            .header(CONTENT_TYPE, "application/json")
+            .bearer_auth(self.token());
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let rsp = request.send_traced().await?;
+        if !rsp.status().is_success() {
+            return Err(MemosError::from_response(rsp).await);
+        }
+        let text = rsp.text().await?;
+        match text.trim().is_empty() {
+            true => Ok(Value::Null),
+            false => Ok(serde_json::from_str(&text)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        Mock, ResponseTemplate,
+        matchers::{body_json, method, path},
+    };
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[tokio::test]
+    async fn test_call_endpoint() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/users/1/shortcuts"))
+            .and(body_json(json!({"title": "Work"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"name": "users/1/shortcuts/1"})))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/users/1/shortcuts/1"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock)
+            .await;
+
+        let created = server.call_endpoint(Method::POST, "users/1/shortcuts", Some(&json!({"title": "Work"}))).await.unwrap();
+        assert_eq!(created["name"], "users/1/shortcuts/1");
+        assert_eq!(server.call_endpoint(Method::DELETE, "/users/1/shortcuts/1", None).await.unwrap(), Value::Null);
+        let error = server.call_endpoint(Method::GET, "users/1/missing", None).await.unwrap_err();
+        assert_eq!(MemosError::find(&error).map(|error| error.status), Some(404));
+    }
+}
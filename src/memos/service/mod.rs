@@ -5,4 +5,6 @@
 
 pub mod user;
 pub mod note;
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod attachment;
+pub mod endpoint;
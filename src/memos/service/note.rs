@@ -4,11 +4,12 @@
 // License: Proprietary
 
 use anyhow::Result;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use rmcp::schemars;
 use serde::{Deserialize, Serialize};
+use crate::memos::{MemoName, Pages, TracedSend, crypto};
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq)]
 pub enum State {
     #[serde(rename = "STATE_UNSPECIFIED")]
     StateUnspecified,
@@ -18,8 +19,9 @@ pub enum State {
     Archived,
 }
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, Default, PartialEq)]
 pub enum Visibility {
+    #[default]
     #[serde(rename = "VISIBILITY_UNSPECIFIED")]
     VisibilityUnspecified,
     #[serde(rename = "PRIVATE")]
@@ -30,48 +32,49 @@ pub enum Visibility {
     Public,
 }
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Note {
     #[schemars(description = "Unique identifier for the note.")]
     #[serde(default)]
     pub name: Option<String>,
     #[schemars(required, description = "The state of the note.")]
-    state: State,
+    pub state: State,
     #[serde(default)]
-    creator: Option<String>,
+    pub creator: Option<String>,
     #[serde(default)]
     #[schemars(description = "The creation time of the note.")]
-    create_time: Option<DateTime<Utc>>,
+    pub create_time: Option<DateTime<Utc>>,
     #[schemars(description = "The last update time of the note.")]
-    update_time: Option<DateTime<Utc>>,
+    pub update_time: Option<DateTime<Utc>>,
     #[schemars(description = "The display time of the note.")]
-    display_time: Option<DateTime<Utc>>,
+    pub display_time: Option<DateTime<Utc>>,
     #[schemars(required, description = "The content of the note in Markdown format.")]
     pub content: String,
-    #[schemars(required, description = "The visibility level of the note.")]
-    visibility: Visibility,
+    #[schemars(description = "The visibility level of the note. The configured default is used when omitted.")]
+    #[serde(default)]
+    pub visibility: Visibility,
     #[schemars(
         description = "Tags associated with the note. To update tags, add tags in `#<tag>` format within the content."
     )]
     #[serde(default)]
-    tags: Vec<String>,
+    pub tags: Vec<String>,
     #[serde(default)]
-    pinned: bool,
+    pub pinned: bool,
     #[serde(default)]
-    attachments: Vec<Attachment>,
+    pub attachments: Vec<Attachment>,
     #[serde(default)]
-    relations: Vec<Relation>,
+    pub relations: Vec<Relation>,
     #[serde(default)]
-    reactions: Vec<Reaction>,
+    pub reactions: Vec<Reaction>,
     #[serde(default)]
-    property: Option<serde_json::Value>,
+    pub property: Option<Property>,
     #[serde(default)]
-    parent: String,
+    pub parent: String,
     #[serde(default)]
-    snippet: String,
+    pub snippet: String,
     #[serde(default)]
-    location: Option<String>,
+    pub location: Option<Location>,
 }
 
 impl Note {
@@ -96,28 +99,247 @@ impl Note {
             location: None,
         }
     }
+
+    /// The validated name of a note fetched from Memos, an error for a note not yet created.
+    pub fn memo_name(&self) -> Result<MemoName> {
+        self.name
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("The note has no name, set it to the memo to change"))?
+            .parse()
+    }
+
+    /// The `parent` field as an option: the memo this note is a comment on, `None` for a memo, for
+    /// which Memos sends it empty.
+    pub fn parent(&self) -> Option<&str> {
+        Some(self.parent.as_str()).filter(|parent| !parent.is_empty())
+    }
+
+    /// Starts building a note to create, for when more than the content needs setting.
+    pub fn builder() -> NoteBuilder {
+        NoteBuilder { note: Note::new(""), tags: vec![] }
+    }
+}
+
+/// What Memos derives from the content of a memo when it is saved, so memos can be picked by it
+/// without reading their content.
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Property {
+    pub has_link: bool,
+    pub has_task_list: bool,
+    pub has_code: bool,
+    /// Whether some task of the task list is not done.
+    pub has_incomplete_tasks: bool,
+    /// The memos referenced in the content, sent by some Memos versions only.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+}
+
+/// Where a memo was written.
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+pub struct Location {
+    #[schemars(description = "Name or address of the place, shown instead of the coordinates.")]
+    #[serde(default)]
+    pub placeholder: String,
+    #[schemars(description = "Latitude in degrees, -90 to 90.")]
+    pub latitude: f64,
+    #[schemars(description = "Longitude in degrees, -180 to 180.")]
+    pub longitude: f64,
+}
+
+impl Location {
+    /// Mean radius of the Earth used for distances.
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self> {
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(anyhow::anyhow!("Invalid coordinates {}, {}", latitude, longitude));
+        }
+        Ok(Location { placeholder: String::new(), latitude, longitude })
+    }
+
+    /// Great-circle distance to the given coordinates in kilometres.
+    pub fn distance_km(&self, latitude: f64, longitude: f64) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * Self::EARTH_RADIUS_KM * a.sqrt().asin()
+    }
 }
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug)]
+/// The fields of a memo to change. Only the fields that are set are sent and updated, the rest of
+/// the memo stays as it is.
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateNote {
+    #[schemars(description = "The name of the memo to update, e.g. `memos/1`.")]
+    pub name: MemoName,
+    #[schemars(description = "The new content of the memo in Markdown format. Tags are the `#<tag>` words in it.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[schemars(description = "The new visibility of the memo.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    #[schemars(description = "Whether the memo is pinned.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<bool>,
+    #[schemars(description = "The new state of the memo. Use ARCHIVED to archive it and NORMAL to restore it.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<State>,
+    #[schemars(description = "The new tags of the memo.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[schemars(description = "The new location of the memo.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    #[schemars(description = "The time the memo is filed under, e.g. to backdate a journal entry written late.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_time: Option<DateTime<Utc>>,
+    /// Updates the location and display time even when they are unset, which clears them.
+    #[schemars(skip)]
+    #[serde(skip)]
+    pub overwrite: bool,
+}
+
+impl UpdateNote {
+    /// An update of memo `name` that changes nothing yet.
+    pub fn new(name: MemoName) -> Self {
+        UpdateNote {
+            name,
+            content: None,
+            visibility: None,
+            pinned: None,
+            state: None,
+            tags: None,
+            location: None,
+            display_time: None,
+            overwrite: false,
+        }
+    }
+
+    /// An update writing back every field of `note`, e.g. to restore an earlier version of it.
+    pub fn from_note(note: &Note) -> Result<Self> {
+        Ok(UpdateNote {
+            name: note.memo_name()?,
+            content: Some(note.content.clone()),
+            visibility: Some(note.visibility.clone()).filter(|v| *v != Visibility::VisibilityUnspecified),
+            pinned: Some(note.pinned),
+            state: Some(note.state),
+            tags: Some(note.tags.clone()),
+            location: note.location.clone(),
+            display_time: note.display_time,
+            overwrite: true,
+        })
+    }
+
+    pub fn update_mask(&self) -> String {
+        let mut mask = Vec::new();
+        if self.content.is_some() {
+            mask.push("content");
+        }
+        if self.visibility.is_some() {
+            mask.push("visibility");
+        }
+        if self.pinned.is_some() {
+            mask.push("pinned");
+        }
+        if self.state.is_some() {
+            mask.push("state");
+        }
+        if self.tags.is_some() {
+            mask.push("tags");
+        }
+        if self.location.is_some() || self.overwrite {
+            mask.push("location");
+        }
+        if self.display_time.is_some() || self.overwrite {
+            mask.push("display_time");
+        }
+        mask.join(",")
+    }
+}
+
+/// Builds a [`Note`] field by field, see [`Note::builder`].
+#[derive(Debug, Clone)]
+pub struct NoteBuilder {
+    note: Note,
+    tags: Vec<String>,
+}
+
+impl NoteBuilder {
+    pub fn content(mut self, content: &str) -> Self {
+        self.note.content = content.to_string();
+        self
+    }
+
+    /// Adds a tag. Memos reads tags from the content, so `#tag` is appended to it on build
+    /// unless the content already has it.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.trim_start_matches('#').to_string());
+        self
+    }
+
+    pub fn visibility(mut self, visibility: Visibility) -> Self {
+        self.note.visibility = visibility;
+        self
+    }
+
+    pub fn pinned(mut self, pinned: bool) -> Self {
+        self.note.pinned = pinned;
+        self
+    }
+
+    pub fn state(mut self, state: State) -> Self {
+        self.note.state = state;
+        self
+    }
+
+    pub fn display_time(mut self, display_time: DateTime<Utc>) -> Self {
+        self.note.display_time = Some(display_time);
+        self
+    }
+
+    pub fn build(self) -> Note {
+        let mut note = self.note;
+        for tag in self.tags {
+            let hashtag = format!("#{}", tag);
+            if !note.content.split_whitespace().any(|word| word == hashtag) {
+                if !note.content.is_empty() {
+                    note.content.push(' ');
+                }
+                note.content.push_str(&hashtag);
+            }
+            if !note.tags.contains(&tag) {
+                note.tags.push(tag);
+            }
+        }
+        note
+    }
+}
+
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Attachment {
     #[serde(default)]
-    name: String,
+    pub name: String,
     #[serde(default)]
-    create_time: DateTime<Utc>,
+    pub create_time: DateTime<Utc>,
     #[serde(default)]
-    filename: String,
+    pub filename: String,
     #[serde(default)]
-    external_link: String,
+    pub external_link: String,
     #[serde(rename = "type")]
-    mime_type: String,
+    pub mime_type: String,
+    /// The size in bytes. Memos sends it as a string, like all its 64-bit integers.
     #[serde(default)]
-    size: String,
+    pub size: String,
+    /// The memo the attachment belongs to, if any.
     #[serde(default)]
-    memo: String,
+    pub memo: String,
 }
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq)]
 pub enum RelationType {
     #[serde(rename = "TYPE_UNSPECIFIED")]
     RelationTypeUnspecified,
@@ -127,30 +349,43 @@ pub enum RelationType {
     Comment,
 }
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Relation {
+    /// The `name` and `snippet` of the memo holding the relation.
     #[serde(default)]
-    memo: serde_json::Value,
+    pub memo: serde_json::Value,
+    /// The `name` and `snippet` of the memo related to.
     #[serde(default)]
-    related_memo: serde_json::Value,
+    pub related_memo: serde_json::Value,
     #[serde(rename = "type")]
-    relation_type: RelationType,
+    pub relation_type: RelationType,
 }
 
-#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug)]
+#[derive(Serialize, Deserialize, schemars::JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Reaction {
     #[serde(default)]
-    name: Option<String>,
+    pub name: Option<String>,
     #[serde(default)]
-    creator: Option<String>,
+    pub creator: Option<String>,
     #[serde(default)]
-    content_id: String,
+    pub content_id: String,
     #[serde(default)]
-    reaction_type: String,
+    pub reaction_type: String,
     #[serde(default)]
-    create_time: Option<DateTime<Utc>>,
+    pub create_time: Option<DateTime<Utc>>,
+}
+
+impl Relation {
+    /// A relation from memo `memo` to `related_memo`, as set with `set_note_relations`.
+    pub fn new(memo: &MemoName, related_memo: &MemoName, relation_type: RelationType) -> Self {
+        Relation {
+            memo: serde_json::json!({"name": memo}),
+            related_memo: serde_json::json!({"name": related_memo}),
+            relation_type,
+        }
+    }
 }
 
 impl Reaction {
@@ -165,78 +400,117 @@ impl Reaction {
     }
 }
 
-pub trait NoteService {
+#[async_trait]
+pub trait NoteService: Send + Sync {
+    /// Moves a memo to the archive, from where it can still be restored.
+    async fn archive_note(&self, note_name: &MemoName) -> Result<Note>;
+
     async fn create_note(&self, note: &Note) -> Result<Note>;
 
-    async fn create_note_comment(&self, note_name: &str, comment: &Note) -> Result<Note>;
+    async fn create_note_comment(&self, note_name: &MemoName, comment: &Note) -> Result<Note>;
 
-    async fn delete_note(&self, note_name: &str) -> Result<()>;
+    async fn delete_note(&self, note_name: &MemoName) -> Result<()>;
     async fn delete_note_reaction(&self, reaction_name: &str) -> Result<()>;
 
-    async fn get_note(&self, note_name: &str) -> Result<Note>;
+    async fn get_note(&self, note_name: &MemoName) -> Result<Note>;
 
-    async fn list_note_attachments(&self, note_name: &str) -> Result<Vec<Attachment>>;
+    async fn list_note_attachments(&self, note_name: &MemoName) -> Result<Vec<Attachment>>;
 
-    async fn list_note_comments(&self, note_name: &str) -> Result<Vec<Note>>;
+    async fn list_note_comments(&self, note_name: &MemoName) -> Result<Vec<Note>>;
 
-    async fn list_note_reactions(&self, note_name: &str) -> Result<Vec<Reaction>>;
-    async fn list_note_relations(&self, note_name: &str) -> Result<Vec<Relation>>;
+    async fn list_note_reactions(&self, note_name: &MemoName) -> Result<Vec<Reaction>>;
+    async fn list_note_relations(&self, note_name: &MemoName) -> Result<Vec<Relation>>;
 
     async fn list_notes(&self) -> Result<Vec<Note>>;
-
-    async fn set_note_attachments(&self, note_name: &str, attachments: &Vec<Attachment>) -> Result<()>;
-
-    async fn set_note_relations(&self, note_name: &str, relations: &Vec<Relation>) -> Result<()>;
-
-    async fn update_note(&self, note: &Note) -> Result<Note>;
-    async fn upsert_note_reaction(&self, note_name: &str, reaction: &Reaction) -> Result<Reaction>;
+    /// Lists the archived memos, which `list_notes` leaves out.
+    async fn list_archived_notes(&self) -> Result<Vec<Note>>;
+    /// Lists the memos created or updated at or after `since`.
+    async fn list_notes_updated_since(&self, since: DateTime<Utc>) -> Result<Vec<Note>>;
+    /// Lists the PUBLIC and PROTECTED memos of the user with id `creator_id`.
+    async fn list_notes_by_creator(&self, creator_id: u64) -> Result<Vec<Note>>;
+    /// The memos matching the CEL `filter`, e.g. the filter of a shortcut.
+    async fn list_notes_matching(&self, filter: &str) -> Result<Vec<Note>>;
+
+    async fn set_note_attachments(&self, note_name: &MemoName, attachments: &Vec<Attachment>) -> Result<()>;
+
+    async fn set_note_relations(&self, note_name: &MemoName, relations: &Vec<Relation>) -> Result<()>;
+
+    /// Changes the fields set in `update`, leaving the rest of the memo as it is.
+    async fn update_note(&self, update: &UpdateNote) -> Result<Note>;
+    async fn upsert_note_reaction(&self, note_name: &MemoName, reaction: &Reaction) -> Result<Reaction>;
 }
 
+#[async_trait]
 impl<T> NoteService for T
 where
-    T: crate::memos::HttpServer,
+    T: crate::memos::HttpServer + Send + Sync,
 {
+    #[tracing::instrument(level = "debug", skip_all)]
     async fn create_note(&self, note: &Note) -> Result<Note> {
-        let rsp = self.build_post_request("memos").json(note).send().await?;
+        let note = seal(self, note)?;
+        let rsp = self.build_post_request("memos").json(&note).send_traced().await?;
 
-        self.validate_data_response::<Note>(rsp).await
+        Ok(open(self, self.validate_data_response::<Note>(rsp).await?))
     }
 
-    async fn create_note_comment(&self, note_name: &str, comment: &Note) -> Result<Note> {
+    #[tracing::instrument(level = "debug", skip(self, comment))]
+    async fn create_note_comment(&self, note_name: &MemoName, comment: &Note) -> Result<Note> {
         let rsp = self
             .build_post_request(format!("{}/comments", note_name).as_str())
-            .json(comment)
-            .send()
+            .json(&seal(self, comment)?)
+            .send_traced()
             .await?;
 
-        self.validate_data_response::<Note>(rsp).await
+        Ok(open(self, self.validate_data_response::<Note>(rsp).await?))
     }
 
-    async fn delete_note(&self, note_name: &str) -> Result<()> {
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn archive_note(&self, note_name: &MemoName) -> Result<Note> {
+        #[derive(Serialize)]
+        struct RequestBody<'a> {
+            name: &'a str,
+            state: State,
+        }
+
+        let endpoint = format!("{}?updateMask=state", note_name);
         let rsp = self
-            .build_delete_request(note_name)
-            .send()
+            .build_patch_request(endpoint.as_str())
+            .json(&RequestBody { name: note_name.as_str(), state: State::Archived })
+            .send_traced()
+            .await?;
+
+        Ok(open(self, self.validate_data_response::<Note>(rsp).await?))
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn delete_note(&self, note_name: &MemoName) -> Result<()> {
+        let rsp = self
+            .build_delete_request(note_name.as_str())
+            .send_traced()
             .await?;
 
         self.validate_response(rsp).await
     }
 
+    #[tracing::instrument(level = "debug", skip(self))]
     async fn delete_note_reaction(&self, reaction_name: &str) -> Result<()> {
         let rsp = self
             .build_delete_request(format!("{}", reaction_name).as_str())
-            .send()
+            .send_traced()
             .await?;
 
         self.validate_response(rsp).await
     }
 
-    async fn get_note(&self, note_name: &str) -> Result<Note> {
-        let rsp = self.build_get_request(note_name).send().await?;
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn get_note(&self, note_name: &MemoName) -> Result<Note> {
+        let rsp = self.build_get_request(note_name.as_str()).send_traced().await?;
 
-        self.validate_data_response::<Note>(rsp).await
+        Ok(open(self, self.validate_data_response::<Note>(rsp).await?))
     }
 
-    async fn list_note_attachments(&self, note_name: &str) -> Result<Vec<Attachment>> {
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn list_note_attachments(&self, note_name: &MemoName) -> Result<Vec<Attachment>> {
         #[derive(Deserialize, Debug)]
         struct AttachmentsResponse {
             pub attachments: Vec<Attachment>,
@@ -244,7 +518,7 @@ where
 
         let rsp = self
             .build_get_request(format!("{}/attachments", note_name).as_str())
-            .send()
+            .send_traced()
             .await?;
 
         Ok(self
@@ -253,7 +527,8 @@ where
             .attachments)
     }
 
-    async fn list_note_comments(&self, note_name: &str) -> Result<Vec<Note>> {
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn list_note_comments(&self, note_name: &MemoName) -> Result<Vec<Note>> {
         #[derive(Deserialize, Debug)]
         struct CommentsResponse {
             pub memos: Vec<Note>,
@@ -261,16 +536,20 @@ where
 
         let rsp = self
             .build_get_request(format!("{}/comments", note_name).as_str())
-            .send()
+            .send_traced()
             .await?;
 
         Ok(self
             .validate_data_response::<CommentsResponse>(rsp)
             .await?
-            .memos)
+            .memos
+            .into_iter()
+            .map(|comment| open(self, comment))
+            .collect())
     }
 
-    async fn list_note_reactions(&self, note_name: &str) -> Result<Vec<Reaction>> {
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn list_note_reactions(&self, note_name: &MemoName) -> Result<Vec<Reaction>> {
         #[derive(Deserialize, Debug)]
         struct ReactionsResponse {
             pub reactions: Vec<Reaction>,
@@ -278,7 +557,7 @@ where
 
         let rsp = self
             .build_get_request(format!("{}/reactions", note_name).as_str())
-            .send()
+            .send_traced()
             .await?;
 
         Ok(self
@@ -287,7 +566,8 @@ where
             .reactions)
     }
 
-    async fn list_note_relations(&self, note_name: &str) -> Result<Vec<Relation>> {
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn list_note_relations(&self, note_name: &MemoName) -> Result<Vec<Relation>> {
         #[derive(Deserialize, Debug)]
         struct RelationsResponse {
             pub relations: Vec<Relation>,
@@ -295,7 +575,7 @@ where
 
         let rsp = self
             .build_get_request(format!("{}/relations", note_name).as_str())
-            .send()
+            .send_traced()
             .await?;
 
         Ok(self
@@ -304,39 +584,34 @@ where
             .relations)
     }
 
+    #[tracing::instrument(level = "debug", skip(self), fields(pages = tracing::field::Empty, memos = tracing::field::Empty))]
     async fn list_notes(&self) -> Result<Vec<Note>> {
-        #[derive(Deserialize)]
-        struct NotesRespones {
-            pub memos: Vec<Note>,
-            #[serde(default, rename = "nextPageToken")]
-            pub next_page_token: String,
-        }
-
-        let mut memos = Vec::<Note>::new();
-        let mut next_page_token: String = String::new();
+        list_note_pages(self, None, None).await
+    }
 
-        loop {
-            let endpoint = if !next_page_token.is_empty() {
-                format!("memos?pageToken={}", next_page_token)
-            } else {
-                "memos".to_string()
-            };
+    #[tracing::instrument(level = "debug", skip(self), fields(pages = tracing::field::Empty, memos = tracing::field::Empty))]
+    async fn list_archived_notes(&self) -> Result<Vec<Note>> {
+        list_note_pages(self, None, Some(State::Archived)).await
+    }
 
-            let rsp = self.build_get_request(endpoint.as_str()).send().await?;
+    #[tracing::instrument(level = "debug", skip(self), fields(pages = tracing::field::Empty, memos = tracing::field::Empty))]
+    async fn list_notes_updated_since(&self, since: DateTime<Utc>) -> Result<Vec<Note>> {
+        list_note_pages(self, Some(&format!("update_time >= {}", since.timestamp())), None).await
+    }
 
-            let rsp = self.validate_data_response::<NotesRespones>(rsp).await?;
-            memos.extend(rsp.memos);
+    #[tracing::instrument(level = "debug", skip(self), fields(pages = tracing::field::Empty, memos = tracing::field::Empty))]
+    async fn list_notes_by_creator(&self, creator_id: u64) -> Result<Vec<Note>> {
+        let filter = format!("creator_id == {} && visibility in [\"PUBLIC\", \"PROTECTED\"]", creator_id);
+        list_note_pages(self, Some(&filter), None).await
+    }
 
-            if !rsp.next_page_token.is_empty() {
-                next_page_token = rsp.next_page_token;
-            } else {
-                break;
-            }
-        }
-        Ok(memos)
+    #[tracing::instrument(level = "debug", skip(self), fields(pages = tracing::field::Empty, memos = tracing::field::Empty))]
+    async fn list_notes_matching(&self, filter: &str) -> Result<Vec<Note>> {
+        list_note_pages(self, Some(filter), None).await
     }
 
-    async fn set_note_attachments(&self, note_name: &str, attachments: &Vec<Attachment>) -> Result<()> {
+    #[tracing::instrument(level = "debug", skip(self, attachments))]
+    async fn set_note_attachments(&self, note_name: &MemoName, attachments: &Vec<Attachment>) -> Result<()> {
         #[derive(Serialize)]
         struct RequestBody<'a> {
             name: &'a str,
@@ -344,20 +619,21 @@ where
         }
 
         let body = RequestBody {
-            name: note_name,
+            name: note_name.as_str(),
             attachments,
         };
 
         let rsp = self
             .build_post_request(format!("{}/attachments", note_name).as_str())
             .json(&body)
-            .send()
+            .send_traced()
             .await?;
 
         self.validate_response(rsp).await
     }
 
-    async fn set_note_relations(&self, note_name: &str, relations: &Vec<Relation>) -> Result<()> {
+    #[tracing::instrument(level = "debug", skip(self, relations))]
+    async fn set_note_relations(&self, note_name: &MemoName, relations: &Vec<Relation>) -> Result<()> {
         #[derive(Serialize)]
         struct RequestBody<'a> {
             name: &'a str,
@@ -365,31 +641,46 @@ where
         }
 
         let body = RequestBody {
-            name: note_name,
+            name: note_name.as_str(),
             relations,
         };
 
         let rsp = self
             .build_post_request(format!("{}/relations", note_name).as_str())
             .json(&body)
-            .send()
+            .send_traced()
             .await?;
 
         self.validate_response(rsp).await
     }
 
-    async fn update_note(&self, note: &Note) -> Result<Note> {
-        let endpoint = format!("{}?updateMask=content,state,visibility,tags,pinned", note.name.as_ref().unwrap());
+    #[tracing::instrument(level = "debug", skip_all, fields(note_name = %update.name))]
+    async fn update_note(&self, update: &UpdateNote) -> Result<Note> {
+        let mask = update.update_mask();
+        if mask.is_empty() {
+            return Err(anyhow::anyhow!("Nothing to update, set at least one field of {}", update.name));
+        }
+        let endpoint = format!("{}?updateMask={}", update.name, mask);
+        let mut update = update.clone();
+        if let (Some(key), Some(content)) = (self.content_key(), &update.content)
+            && !crypto::is_encrypted(content)
+        {
+            update.content = Some(key.encrypt(content)?);
+            if update.tags.is_some() {
+                update.tags = Some(vec![crypto::ENCRYPTED_TAG.to_string()]);
+            }
+        }
         let rsp = self
             .build_patch_request(endpoint.as_str())
-            .json(note)
-            .send()
+            .json(&update)
+            .send_traced()
             .await?;
 
-        self.validate_data_response::<Note>(rsp).await
+        Ok(open(self, self.validate_data_response::<Note>(rsp).await?))
     }
 
-    async fn upsert_note_reaction(&self, note_name: &str, reaction: &Reaction) -> Result<Reaction> {
+    #[tracing::instrument(level = "debug", skip(self, reaction))]
+    async fn upsert_note_reaction(&self, note_name: &MemoName, reaction: &Reaction) -> Result<Reaction> {
         #[derive(Serialize)]
         struct RequestBody<'a> {
             pub name: &'a str,
@@ -397,164 +688,863 @@ where
         }
 
         let body = RequestBody {
-            name: note_name,
+            name: note_name.as_str(),
             reaction,
         };
 
         let rsp = self
             .build_post_request(format!("{}/reactions", note_name).as_str())
             .json(&body)
-            .send()
+            .send_traced()
             .await?;
 
         self.validate_data_response::<Reaction>(rsp).await
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        super::{
-            super::{HttpServer, Server},
-            auth::AuthService,
-            user::{User, UserService},
-        },
-        *,
+/// `note` as stored on `server`: with its content encrypted when the server has a content key. Its
+/// tags are left to the `#encrypted` tag, so that they do not give the content away.
+fn seal<'a, T: crate::memos::HttpServer>(server: &T, note: &'a Note) -> Result<std::borrow::Cow<'a, Note>> {
+    let Some(key) = server.content_key().filter(|_| !crypto::is_encrypted(&note.content)) else {
+        return Ok(std::borrow::Cow::Borrowed(note));
     };
+    let mut sealed = note.clone();
+    sealed.content = key.encrypt(&note.content)?;
+    sealed.tags = vec![crypto::ENCRYPTED_TAG.to_string()];
+    Ok(std::borrow::Cow::Owned(sealed))
+}
 
-    struct UserScopedServer {
-        parent: Server,
-        child: Option<Server>,
-        user: User,
+/// `note` as read from `server`, decrypted when the server has a content key. Content that fails
+/// to decrypt is left as it is, so one bad memo does not fail a listing.
+fn open<T: crate::memos::HttpServer>(server: &T, mut note: Note) -> Note {
+    if let Some(key) = server.content_key() {
+        match key.decrypt(&note.content) {
+            Some(Ok(content)) => note.content = content,
+            Some(Err(e)) => tracing::warn!("Failed to decrypt {}: {}", note.name.as_deref().unwrap_or("a memo"), e),
+            None => {}
+        }
     }
+    note
+}
 
-    impl UserScopedServer {
-        async fn new(host: &str, token: &str) -> Result<Self> {
-            let parent = Server::new(host, token);
-
-            let rand_suffix = chrono::Utc::now().timestamp_nanos_opt().unwrap();
-            let username = format!("user-{}-{}", std::process::id(), rand_suffix);
-            let password = "TestPassword123!";
-            let user = User::new(&username, &password, "test@test.com");
-            let test_user = parent.create_user(&user).await.unwrap();
+/// Follows the page tokens of the memo list, optionally narrowed by a CEL `filter`.
+async fn list_note_pages<T: crate::memos::HttpServer + Sync>(server: &T, filter: Option<&str>, state: Option<State>) -> Result<Vec<Note>> {
+    #[derive(Deserialize)]
+    struct NotesRespones {
+        pub memos: Vec<Note>,
+        #[serde(default, rename = "nextPageToken")]
+        pub next_page_token: String,
+    }
 
-            let child = parent.sign_in(&username, &password).await.unwrap();
+    let mut memos = Vec::<Note>::new();
+    let mut pages = Pages::new(server.page_limits());
+    let mut next_page_token = None;
 
-            Ok(UserScopedServer {
-                parent,
-                child: Some(child),
-                user: test_user,
-            })
+    loop {
+        let mut request = server.build_get_request("memos");
+        if let Some(filter) = filter {
+            request = request.query(&[("filter", filter)]);
         }
-
-        async fn cleanup(&self) -> Result<()> {
-            self.parent.delete_user(&self.user).await
+        if let Some(state) = state {
+            request = request.query(&[("state", state)]);
         }
-    }
-
-    impl HttpServer for UserScopedServer {
-        fn base_url(&self) -> &str {
-            self.parent.base_url()
+        if let Some(token) = &next_page_token {
+            request = request.query(&[("pageToken", token)]);
         }
 
-        fn token(&self) -> &str {
-            &self.child.as_ref().unwrap().token()
+        let rsp = request.send_traced().await?;
+
+        let rsp = server.validate_data_response::<NotesRespones>(rsp).await?;
+        tracing::debug!(page = pages.pages + 1, memos = rsp.memos.len(), "fetched memos page");
+        let count = rsp.memos.len();
+        memos.extend(rsp.memos.into_iter().map(|memo| open(server, memo)));
+
+        next_page_token = pages.next("memos", count, rsp.next_page_token)?;
+        if next_page_token.is_none() {
+            break;
         }
     }
 
-    async fn create_server() -> Result<UserScopedServer> {
-        Ok(UserScopedServer::new(
-            "localhost:5230",
-            "memos_pat_t3pjYKgGSzYqOqMgR4mZR768afCNG6sW",
-        )
-        .await?)
+    let span = tracing::Span::current();
+    span.record("pages", pages.pages);
+    span.record("memos", memos.len());
+    Ok(memos)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::{Value, json};
+    use wiremock::{
+        Mock, ResponseTemplate,
+        matchers::{body_json, body_partial_json, header, method, path, path_regex, query_param, query_param_is_missing},
+    };
+
+    use super::*;
+    use crate::memos::{fixture, mock, PageLimits};
+
+    fn memo_name(text: &str) -> MemoName {
+        text.parse().unwrap()
     }
 
     #[tokio::test]
     async fn test_create_and_delete_memo() {
-        let server = create_server().await.unwrap();
-        let memo = Note::new("Test memo from unit test");
-
-        let created_memo = server.create_note(&memo).await.unwrap();
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/memos"))
+            .and(header("authorization", mock::AUTHORIZATION))
+            .and(body_partial_json(json!({"content": "Test memo from unit test", "state": "NORMAL"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock::memo("memos/abc", "Test memo from unit test")))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/memos/abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let created_memo = server.create_note(&Note::new("Test memo from unit test")).await.unwrap();
+        assert_eq!(created_memo.name.as_deref(), Some("memos/abc"));
         assert_eq!(created_memo.content, "Test memo from unit test");
 
-        server.delete_note(created_memo.name.as_ref().unwrap()).await.unwrap();
+        server.delete_note(&memo_name("memos/abc")).await.unwrap();
+    }
 
-        server.cleanup().await.unwrap();
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_content_is_encrypted() {
+        use base64::Engine;
+
+        let (mock, server) = mock::start().await;
+        let key = crypto::ContentKey::from_base64(&base64::engine::general_purpose::STANDARD.encode([1u8; 32])).unwrap();
+        let stored = key.encrypt("Door code 4711").unwrap();
+        let server = server.with_content_key(Some(key));
+        Mock::given(method("POST"))
+            .and(path("/api/v1/memos"))
+            .and(body_partial_json(json!({"tags": ["encrypted"]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock::memo("memos/abc", &stored)))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos/plain"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock::memo("memos/plain", "Written before the key")))
+            .mount(&mock)
+            .await;
+
+        let created = server.create_note(&Note::new("Door code 4711 #home")).await.unwrap();
+        assert_eq!(created.content, "Door code 4711");
+        let request = &mock.received_requests().await.unwrap()[0];
+        let uploaded = request.body_json::<Value>().unwrap()["content"].as_str().unwrap().to_string();
+        assert!(crypto::is_encrypted(&uploaded) && !uploaded.contains("4711") && !uploaded.contains("#home"));
+
+        assert_eq!(server.get_note(&memo_name("memos/plain")).await.unwrap().content, "Written before the key");
+    }
+
+    #[test]
+    fn test_note_builder() {
+        let note = Note::builder()
+            .content("Quarterly planning #work")
+            .tag("work")
+            .tag("#planning")
+            .visibility(Visibility::Protected)
+            .pinned(true)
+            .build();
+        assert_eq!(note.content, "Quarterly planning #work #planning");
+        assert_eq!(note.tags, ["work", "planning"]);
+        assert_eq!(note.visibility, Visibility::Protected);
+        assert!(note.pinned);
+        assert!(matches!(note.state, State::Normal));
+
+        assert_eq!(Note::builder().tag("inbox").build().content, "#inbox");
     }
 
     #[tokio::test]
-    async fn test_create_and_delete_comment() {
-        let server = create_server().await.unwrap();
-        let memo = Note::new("Test memo for comment unit test");
+    async fn test_archive_memo() {
+        let (mock, server) = mock::start().await;
+        let mut archived = mock::memo("memos/abc", "Test memo for archive unit test");
+        archived["state"] = json!("ARCHIVED");
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/memos/abc"))
+            .and(query_param("updateMask", "state"))
+            .and(body_partial_json(json!({"name": "memos/abc", "state": "ARCHIVED"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(archived))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let archived_memo = server.archive_note(&memo_name("memos/abc")).await.unwrap();
+        assert!(matches!(archived_memo.state, State::Archived));
+        assert_eq!(archived_memo.content, "Test memo for archive unit test");
+    }
 
-        let created_memo = server.create_note(&memo).await.unwrap();
-        assert_eq!(created_memo.content, "Test memo for comment unit test");
+    #[tokio::test]
+    async fn test_update_sends_only_set_fields() {
+        let (mock, server) = mock::start().await;
+        let mut pinned = mock::memo("memos/abc", "Unchanged content");
+        pinned["pinned"] = json!(true);
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/memos/abc"))
+            .and(query_param("updateMask", "pinned"))
+            .and(body_json(json!({"name": "memos/abc", "pinned": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(pinned))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let mut update = UpdateNote::new(memo_name("memos/abc"));
+        assert!(server.update_note(&update).await.is_err());
+        update.pinned = Some(true);
+        let updated = server.update_note(&update).await.unwrap();
+        assert!(updated.pinned);
+        assert_eq!(updated.content, "Unchanged content");
+
+        let note: Note = serde_json::from_str(MEMO_PAYLOAD).unwrap();
+        assert_eq!(UpdateNote::from_note(&note).unwrap().update_mask(), "content,visibility,pinned,state,tags,location,display_time");
+        let restore = UpdateNote::from_note(&Note { name: Some("memos/abc".to_string()), ..Note::new("Restored") }).unwrap();
+        assert_eq!(restore.update_mask(), "content,visibility,pinned,state,tags,location,display_time");
+        assert_eq!(serde_json::to_value(&restore).unwrap().get("location"), None);
+        assert!(UpdateNote::from_note(&Note::new("Not created yet")).is_err());
+    }
+
+    #[test]
+    fn test_location() {
+        let mut memo = mock::memo("memos/1", "Lunch");
+        memo["location"] = json!({"placeholder": "Paris", "latitude": 48.8566, "longitude": 2.3522});
+        let note: Note = serde_json::from_value(memo).unwrap();
+        let paris = note.location.as_ref().unwrap();
+        assert_eq!(paris.placeholder, "Paris");
+        assert!((paris.distance_km(51.5074, -0.1278) - 343.5).abs() < 1.0);
+        assert_eq!(paris.distance_km(48.8566, 2.3522), 0.0);
+
+        assert!(Location::new(91.0, 0.0).is_err());
+        let mut update = UpdateNote::new(memo_name("memos/1"));
+        update.location = Some(Location::new(48.8566, 2.3522).unwrap());
+        assert_eq!(update.update_mask(), "location");
+    }
+
+    #[tokio::test]
+    async fn test_create_and_get_comment() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/memos/abc/comments"))
+            .and(body_partial_json(json!({"content": "This is a test comment"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock::memo("memos/def", "This is a test comment")))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos/def"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock::memo("memos/def", "This is a test comment")))
+            .mount(&mock)
+            .await;
 
-        let comment = Note::new("This is a test comment");
         let created_comment = server
-            .create_note_comment(created_memo.name.as_ref().unwrap(), &comment)
+            .create_note_comment(&memo_name("memos/abc"), &Note::new("This is a test comment"))
             .await
             .unwrap();
-        assert_eq!(created_comment.content, "This is a test comment");
+        assert_eq!(created_comment.name.as_deref(), Some("memos/def"));
 
-        let fetched_comment = server
-            .get_note(created_comment.name.as_ref().unwrap())
-            .await
-            .unwrap();
+        let fetched_comment = server.get_note(&memo_name("memos/def")).await.unwrap();
         assert_eq!(fetched_comment.content, "This is a test comment");
-
-        server.delete_note(created_comment.name.as_ref().unwrap()).await.unwrap();
-        server.delete_note(created_memo.name.as_ref().unwrap()).await.unwrap();
-
-        server.cleanup().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_list_notes() {
-        let server = create_server().await.unwrap();
+    async fn test_list_notes_follows_pages() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos"))
+            .and(query_param_is_missing("pageToken"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "memos": [mock::memo("memos/1", "First page")],
+                "nextPageToken": "page-2",
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos"))
+            .and(query_param("pageToken", "page-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "memos": [mock::memo("memos/2", "Second page")],
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
         let notes = server.list_notes().await.unwrap();
-        let count = notes.len();
+        let names = notes.iter().filter_map(|note| note.name.as_deref()).collect::<Vec<_>>();
+        assert_eq!(names, ["memos/1", "memos/2"]);
+    }
 
-        let note = Note::new("Another test note for listing");
-        let created_note = server.create_note(&note).await.unwrap();
-        let notes_after = server.list_notes().await.unwrap();
+    #[tokio::test]
+    async fn test_list_notes_stops_on_repeated_page_token() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "memos": [mock::memo("memos/1", "Again")],
+                "nextPageToken": "page-2",
+            })))
+            .expect(2)
+            .mount(&mock)
+            .await;
+
+        let error = server.list_notes().await.unwrap_err().to_string();
+        assert_eq!(error, "Stopped listing memos: the server returned page token page-2 twice");
+    }
 
-        assert_eq!(notes_after.len(), count + 1);
+    #[tokio::test]
+    async fn test_list_notes_stops_at_max_pages() {
+        let (mock, server) = mock::start().await;
+        let server = server.with_page_limits(PageLimits { max_pages: 3, ..PageLimits::default() });
+        for page in 0..5 {
+            let mut request = Mock::given(method("GET")).and(path("/api/v1/memos"));
+            request = match page {
+                0 => request.and(query_param_is_missing("pageToken")),
+                _ => request.and(query_param("pageToken", format!("page-{}", page))),
+            };
+            request
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "memos": [mock::memo("memos/1", "More")],
+                    "nextPageToken": format!("page-{}", page + 1),
+                })))
+                .mount(&mock)
+                .await;
+        }
 
-        server.delete_note(created_note.name.as_ref().unwrap()).await.unwrap();
-        server.cleanup().await.unwrap();
+        let error = server.list_notes().await.unwrap_err().to_string();
+        assert!(error.starts_with("Stopped listing memos after 3 pages and 3 items"), "{}", error);
     }
 
     #[tokio::test]
-    async fn test_reactions() {
-        let server = create_server().await.unwrap();
-        let note = Note::new("Test note for reactions unit test");
+    async fn test_requests_wait_for_the_limit() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path_regex(r"^/api/v1/memos/[^/]+$"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(mock::memo("memos/1", "Slow"))
+                .set_delay(std::time::Duration::from_millis(200)))
+            .mount(&mock)
+            .await;
+
+        let names = ["memos/1", "memos/2", "memos/3"].map(memo_name);
+        let limit = std::sync::Arc::new(tokio::sync::Semaphore::new(1));
+        let started = std::time::Instant::now();
+        let (a, b, c) = crate::memos::limit_requests(limit, async {
+            tokio::join!(server.get_note(&names[0]), server.get_note(&names[1]), server.get_note(&names[2]))
+        })
+        .await;
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        assert!(started.elapsed() >= std::time::Duration::from_millis(600));
+    }
 
-        let created_note = server.create_note(&note).await.unwrap();
-        assert_eq!(created_note.content, "Test note for reactions unit test");
+    #[tokio::test]
+    async fn test_list_notes_updated_since_filters() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos"))
+            .and(query_param("filter", "update_time >= 1738310400"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "memos": [mock::memo("memos/1", "Changed")],
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let since = "2025-01-31T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(server.list_notes_updated_since(since).await.unwrap().len(), 1);
+    }
 
-        let reaction = Reaction::new(created_note.name.as_ref().unwrap(), "👍");
+    #[tokio::test]
+    async fn test_list_notes_by_creator_filters() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos"))
+            .and(query_param("filter", "creator_id == 2 && visibility in [\"PUBLIC\", \"PROTECTED\"]"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "memos": [mock::memo("memos/1", "Shared")],
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        assert_eq!(server.list_notes_by_creator(2).await.unwrap().len(), 1);
+    }
 
-        let created_reaction = server
-            .upsert_note_reaction(created_note.name.as_ref().unwrap(), &reaction)
-            .await
-            .unwrap();
+    #[tokio::test]
+    async fn test_reactions() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/memos/abc/reactions"))
+            .and(body_partial_json(json!({"name": "memos/abc", "reaction": {"reactionType": "👍"}})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock::reaction("reactions/1", "memos/abc", "👍")))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos/abc/reactions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "reactions": [mock::reaction("reactions/1", "memos/abc", "👍")],
+            })))
+            .mount(&mock)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/reactions/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let reaction = Reaction::new("memos/abc", "👍");
+        let created_reaction = server.upsert_note_reaction(&memo_name("memos/abc"), &reaction).await.unwrap();
         assert_eq!(created_reaction.reaction_type, "👍");
 
-        let reactions = server.list_note_reactions(created_note.name.as_ref().unwrap()).await.unwrap();
+        let reactions = server.list_note_reactions(&memo_name("memos/abc")).await.unwrap();
         assert!(reactions.iter().any(|r| r.reaction_type == "👍"));
 
-        server
-            .delete_note_reaction(created_reaction.name.as_ref().unwrap())
-            .await
-            .unwrap();
+        server.delete_note_reaction(created_reaction.name.as_ref().unwrap()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_error_response_is_redacted() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/memos/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "code": 5,
+                "message": format!("memo not found for token {}", mock::TOKEN),
+            })))
+            .mount(&mock)
+            .await;
+
+        let error = server.get_note(&memo_name("memos/missing")).await.unwrap_err().to_string();
+        assert!(error.contains("404"));
+        assert!(!error.contains(mock::TOKEN));
+    }
+
+    /// The flow recorded into `tests/fixtures/<memos version>/memo_lifecycle.json`.
+    async fn memo_lifecycle(server: &impl NoteService) -> Result<()> {
+        let memo = server.create_note(&Note::new("Fixture memo #fixture")).await?;
+        let name = memo.memo_name()?;
+        assert_eq!(server.get_note(&name).await?.content, "Fixture memo #fixture");
+
+        let comment = server.create_note_comment(&name, &Note::new("Fixture comment")).await?;
+        assert_eq!(server.list_note_comments(&name).await?.len(), 1);
+
+        let reaction = server.upsert_note_reaction(&name, &Reaction::new(name.as_str(), "👍")).await?;
+        assert_eq!(server.list_note_reactions(&name).await?[0].reaction_type, "👍");
+        server.delete_note_reaction(reaction.name.as_ref().unwrap()).await?;
+
+        assert!(matches!(server.archive_note(&name).await?.state, State::Archived));
+        server.delete_note(&comment.memo_name()?).await?;
+        server.delete_note(&name).await
+    }
+
+    #[tokio::test]
+    async fn test_replay_recorded_versions() {
+        let cassettes = fixture::Cassette::all("memo_lifecycle").unwrap();
+        assert!(!cassettes.is_empty());
+        for cassette in cassettes {
+            let (mock, server) = mock::start().await;
+            cassette.replay(&mock).await;
+            memo_lifecycle(&server)
+                .await
+                .unwrap_or_else(|e| panic!("Memos {}: {}", cassette.version, e));
+        }
+    }
+
+    /// A comment memo as returned by Memos v0.25, with an attachment, relation and reaction.
+    const MEMO_PAYLOAD: &str = r#"{
+        "name": "memos/7kL2xWnQ9bYpRt4v",
+        "state": "NORMAL",
+        "creator": "users/1",
+        "createTime": "2025-06-02T09:14:27Z",
+        "updateTime": "2025-06-02T09:20:03.512Z",
+        "displayTime": "2025-06-02T09:14:27Z",
+        "content": "Weekly groceries #shopping\n\n- [ ] milk\n- [x] eggs",
+        "nodes": [{"type": "PARAGRAPH", "paragraphNode": {"children": []}}],
+        "visibility": "PROTECTED",
+        "tags": ["shopping"],
+        "pinned": true,
+        "attachments": [{
+            "name": "attachments/Vx3mQ8",
+            "createTime": "2025-06-02T09:15:00Z",
+            "filename": "receipt.png",
+            "content": "",
+            "externalLink": "",
+            "type": "image/png",
+            "size": "48213",
+            "memo": "memos/7kL2xWnQ9bYpRt4v"
+        }],
+        "relations": [{
+            "memo": {"name": "memos/7kL2xWnQ9bYpRt4v", "snippet": "Weekly groceries"},
+            "relatedMemo": {"name": "memos/Hq8sK2", "snippet": "Meal plan"},
+            "type": "REFERENCE"
+        }],
+        "reactions": [{
+            "name": "reactions/3",
+            "creator": "users/2",
+            "contentId": "memos/7kL2xWnQ9bYpRt4v",
+            "reactionType": "👍",
+            "createTime": "2025-06-02T10:01:44Z"
+        }],
+        "property": {"hasLink": false, "hasTaskList": true, "hasCode": false, "hasIncompleteTasks": true},
+        "parent": "memos/Hq8sK2",
+        "snippet": "Weekly groceries #shopping milk eggs"
+    }"#;
+
+    fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+        (0i64..4_102_444_800).prop_map(|secs| DateTime::from_timestamp(secs, 0).unwrap())
+    }
+
+    fn state() -> impl Strategy<Value = State> {
+        (0..3usize).prop_map(|i| [State::StateUnspecified, State::Normal, State::Archived].into_iter().nth(i).unwrap())
+    }
+
+    fn visibility() -> impl Strategy<Value = Visibility> {
+        prop_oneof![
+            Just(Visibility::VisibilityUnspecified),
+            Just(Visibility::Private),
+            Just(Visibility::Protected),
+            Just(Visibility::Public),
+        ]
+    }
+
+    fn attachment() -> impl Strategy<Value = Attachment> {
+        (".*", timestamp(), ".*", ".*", "[a-z]+/[a-z0-9.+-]+", "[0-9]{1,9}", ".*").prop_map(
+            |(name, create_time, filename, external_link, mime_type, size, memo)| Attachment {
+                name,
+                create_time,
+                filename,
+                external_link,
+                mime_type,
+                size,
+                memo,
+            },
+        )
+    }
+
+    fn relation() -> impl Strategy<Value = Relation> {
+        let relation_type = (0..3usize).prop_map(|i| {
+            [RelationType::RelationTypeUnspecified, RelationType::Reference, RelationType::Comment]
+                .into_iter()
+                .nth(i)
+                .unwrap()
+        });
+        ("memos/[a-zA-Z0-9]+", "memos/[a-zA-Z0-9]+", relation_type).prop_map(|(memo, related, relation_type)| Relation {
+            memo: json!({"name": memo}),
+            related_memo: json!({"name": related}),
+            relation_type,
+        })
+    }
+
+    fn reaction() -> impl Strategy<Value = Reaction> {
+        (proptest::option::of(".*"), proptest::option::of(".*"), ".*", ".*", proptest::option::of(timestamp())).prop_map(
+            |(name, creator, content_id, reaction_type, create_time)| Reaction {
+                name,
+                creator,
+                content_id,
+                reaction_type,
+                create_time,
+            },
+        )
+    }
+
+    fn note() -> impl Strategy<Value = Note> {
+        let header = (
+            proptest::option::of("memos/[a-zA-Z0-9]+"),
+            state(),
+            proptest::option::of("users/[0-9]+"),
+            proptest::option::of(timestamp()),
+            proptest::option::of(timestamp()),
+            proptest::option::of(timestamp()),
+        );
+        let body = (
+            ".*",
+            visibility(),
+            proptest::collection::vec("[a-z]+", 0..4),
+            any::<bool>(),
+            proptest::collection::vec(attachment(), 0..3),
+            proptest::collection::vec(relation(), 0..3),
+            proptest::collection::vec(reaction(), 0..3),
+            ".*",
+            ".*",
+        );
+        (header, body).prop_map(
+            |(
+                (name, state, creator, create_time, update_time, display_time),
+                (content, visibility, tags, pinned, attachments, relations, reactions, parent, snippet),
+            )| Note {
+                name,
+                state,
+                creator,
+                create_time,
+                update_time,
+                display_time,
+                content,
+                visibility,
+                tags,
+                pinned,
+                attachments,
+                relations,
+                reactions,
+                property: None,
+                parent,
+                snippet,
+                location: None,
+            },
+        )
+    }
+
+    fn assert_schema_accepts<T: schemars::JsonSchema>(payload: &Value) {
+        let schema = schemars::schema_for!(T);
+        let validator = jsonschema::validator_for(schema.as_value()).unwrap();
+        let errors = validator.iter_errors(payload).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert!(errors.is_empty(), "{} schema rejects the payload: {:?}", std::any::type_name::<T>(), errors);
+    }
+
+    proptest! {
+        #[test]
+        fn test_note_round_trip(note in note()) {
+            mock::assert_round_trip(&note);
+        }
+
+        #[test]
+        fn test_note_parts_round_trip(attachment in attachment(), relation in relation(), reaction in reaction()) {
+            mock::assert_round_trip(&attachment);
+            mock::assert_round_trip(&relation);
+            mock::assert_round_trip(&reaction);
+        }
+    }
+
+    #[test]
+    fn test_schemas_accept_memos_payload() {
+        let payload: Value = serde_json::from_str(MEMO_PAYLOAD).unwrap();
+        assert_schema_accepts::<Note>(&payload);
+        assert_schema_accepts::<Attachment>(&payload["attachments"][0]);
+        assert_schema_accepts::<Relation>(&payload["relations"][0]);
+        assert_schema_accepts::<Reaction>(&payload["reactions"][0]);
+
+        let note: Note = serde_json::from_value(payload).unwrap();
+        assert_eq!(note.tags, ["shopping"]);
+        assert_eq!(note.attachments[0].size, "48213");
+        assert!(matches!(note.relations[0].relation_type, RelationType::Reference));
+        assert_eq!(note.reactions[0].reaction_type, "👍");
+        assert_eq!(note.parent, "memos/Hq8sK2");
+    }
+
+    #[test]
+    fn test_note_accessors() {
+        let mut note: Note = serde_json::from_str(MEMO_PAYLOAD).unwrap();
+        assert_eq!(note.state, State::Normal);
+        assert_eq!(note.creator.as_deref(), Some("users/1"));
+        assert!(note.pinned);
+        assert_eq!(note.parent(), Some("memos/Hq8sK2"));
+        assert_eq!(note.attachments[0].filename, "receipt.png");
+        assert_eq!(note.relations[0].relation_type, RelationType::Reference);
+        assert_eq!(note.reactions[0].creator.as_deref(), Some("users/2"));
+        assert_eq!(note.property.as_ref(), Some(&Property { has_task_list: true, has_incomplete_tasks: true, ..Property::default() }));
+        assert_eq!(Note::new("Top level").parent(), None);
+
+        let copy = note.clone();
+        note.pinned = false;
+        note.state = State::Archived;
+        assert_ne!(note, copy);
+        assert!(!note.pinned);
+        assert_eq!(note.state, State::Archived);
+    }
+
+    /// The same flows against the real Memos server named by MEMOS_TEST_HOST and MEMOS_TEST_TOKEN.
+    #[cfg(feature = "live-tests")]
+    mod live {
+        use super::super::{
+            super::{
+                super::{HttpServer, Server},
+                auth::AuthService,
+                user::{User, UserService},
+            },
+            *,
+        };
+        use super::{fixture, memo_lifecycle, mock};
+
+        struct UserScopedServer {
+            parent: Server,
+            child: Option<Server>,
+            user: User,
+        }
+
+        impl UserScopedServer {
+            async fn new(parent: Server) -> Result<Self> {
+                let rand_suffix = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+                let username = format!("user-{}-{}", std::process::id(), rand_suffix);
+                let password = "TestPassword123!";
+                let user = User::new(&username, &password, "test@test.com");
+                let test_user = parent.create_user(&user).await.unwrap();
+
+                let child = parent.sign_in(&username, &password).await.unwrap();
+
+                Ok(UserScopedServer {
+                    parent,
+                    child: Some(child),
+                    user: test_user,
+                })
+            }
+
+            async fn cleanup(&self) -> Result<()> {
+                self.parent.delete_user(&self.user).await
+            }
+        }
+
+        impl HttpServer for UserScopedServer {
+            fn base_url(&self) -> &str {
+                self.parent.base_url()
+            }
+
+            fn token(&self) -> String {
+                self.child.as_ref().unwrap().token()
+            }
+
+            fn client(&self) -> &reqwest::Client {
+                self.parent.client()
+            }
+        }
+
+        async fn create_server() -> Option<UserScopedServer> {
+            Some(UserScopedServer::new(mock::live_server()?).await.unwrap())
+        }
+
+        /// Records the lifecycle flow for the Memos version named by MEMOS_RECORD, if set.
+        #[tokio::test]
+        async fn test_record_memo_lifecycle() {
+            let Ok(version) = std::env::var("MEMOS_RECORD") else {
+                return;
+            };
+            let Some(server) = create_server().await else {
+                return;
+            };
+            let recorder = fixture::Recorder::start(server.base_url()).await.unwrap();
+            let result = memo_lifecycle(&Server::new(recorder.url(), &server.token()).unwrap()).await;
+            server.cleanup().await.unwrap();
+            result.unwrap();
+
+            let cassette = fixture::Cassette { version: version.clone(), exchanges: recorder.exchanges() };
+            cassette.save(&fixture::Cassette::path(&version, "memo_lifecycle")).unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_create_and_delete_memo() {
+            let Some(server) = create_server().await else {
+                return;
+            };
+            let memo = Note::new("Test memo from unit test");
+
+            let created_memo = server.create_note(&memo).await.unwrap();
+            assert_eq!(created_memo.content, "Test memo from unit test");
 
-        let reactions_after = server.list_note_reactions(created_note.name.as_ref().unwrap()).await.unwrap();
-        assert!(!reactions_after.iter().any(|r| r.reaction_type == "👍"));
+            server.delete_note(&created_memo.memo_name().unwrap()).await.unwrap();
 
-        server.delete_note(created_note.name.as_ref().unwrap()).await.unwrap();
+            server.cleanup().await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_archive_memo() {
+            let Some(server) = create_server().await else {
+                return;
+            };
+            let memo = Note::new("Test memo for archive unit test");
+
+            let created_memo = server.create_note(&memo).await.unwrap();
+            let archived_memo = server.archive_note(&created_memo.memo_name().unwrap()).await.unwrap();
+            assert!(matches!(archived_memo.state, State::Archived));
+            assert_eq!(archived_memo.content, "Test memo for archive unit test");
+
+            server.delete_note(&created_memo.memo_name().unwrap()).await.unwrap();
+
+            server.cleanup().await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_create_and_delete_comment() {
+            let Some(server) = create_server().await else {
+                return;
+            };
+            let memo = Note::new("Test memo for comment unit test");
+
+            let created_memo = server.create_note(&memo).await.unwrap();
+            assert_eq!(created_memo.content, "Test memo for comment unit test");
+
+            let comment = Note::new("This is a test comment");
+            let created_comment = server
+                .create_note_comment(&created_memo.memo_name().unwrap(), &comment)
+                .await
+                .unwrap();
+            assert_eq!(created_comment.content, "This is a test comment");
+
+            let fetched_comment = server
+                .get_note(&created_comment.memo_name().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(fetched_comment.content, "This is a test comment");
 
-        server.cleanup().await.unwrap();
+            server.delete_note(&created_comment.memo_name().unwrap()).await.unwrap();
+            server.delete_note(&created_memo.memo_name().unwrap()).await.unwrap();
+
+            server.cleanup().await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_list_notes() {
+            let Some(server) = create_server().await else {
+                return;
+            };
+            let notes = server.list_notes().await.unwrap();
+            let count = notes.len();
+
+            let note = Note::new("Another test note for listing");
+            let created_note = server.create_note(&note).await.unwrap();
+            let notes_after = server.list_notes().await.unwrap();
+
+            assert_eq!(notes_after.len(), count + 1);
+
+            server.delete_note(&created_note.memo_name().unwrap()).await.unwrap();
+            server.cleanup().await.unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_reactions() {
+            let Some(server) = create_server().await else {
+                return;
+            };
+            let note = Note::new("Test note for reactions unit test");
+
+            let created_note = server.create_note(&note).await.unwrap();
+            assert_eq!(created_note.content, "Test note for reactions unit test");
+
+            let reaction = Reaction::new(created_note.name.as_ref().unwrap(), "👍");
+
+            let created_reaction = server
+                .upsert_note_reaction(&created_note.memo_name().unwrap(), &reaction)
+                .await
+                .unwrap();
+            assert_eq!(created_reaction.reaction_type, "👍");
+
+            let reactions = server.list_note_reactions(&created_note.memo_name().unwrap()).await.unwrap();
+            assert!(reactions.iter().any(|r| r.reaction_type == "👍"));
+
+            server
+                .delete_note_reaction(created_reaction.name.as_ref().unwrap())
+                .await
+                .unwrap();
+
+            let reactions_after = server.list_note_reactions(&created_note.memo_name().unwrap()).await.unwrap();
+            assert!(!reactions_after.iter().any(|r| r.reaction_type == "👍"));
+
+            server.delete_note(&created_note.memo_name().unwrap()).await.unwrap();
+
+            server.cleanup().await.unwrap();
+        }
     }
 }
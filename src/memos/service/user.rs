@@ -3,11 +3,15 @@
 // Date: 2025-12-28
 // License: Proprietary
 
+use std::collections::BTreeMap;
+
 use anyhow::Result;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
+use crate::memos::{Pages, TracedSend};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Token {
     pub name: String,
@@ -17,17 +21,26 @@ pub struct Token {
     pub last_used_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum Role {
     #[serde(rename = "ROLE_UNSPECIFIED")]
     RoleUnspecified,
+    #[serde(rename = "HOST")]
+    Host,
     #[serde(rename = "ADMIN")]
     Admin,
     #[serde(rename = "USER")]
     User,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Role {
+    /// Whether the role may manage users and instance settings.
+    pub fn is_admin(&self) -> bool {
+        matches!(self, Role::Host | Role::Admin)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum State {
     #[serde(rename = "STATE_UNSPECIFIED")]
     StateUnspecified,
@@ -37,17 +50,96 @@ pub enum State {
     Archived,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A user of Memos, as returned by `auth/me` and the `users` endpoints.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct User {
     #[serde(default)] pub name: String,
     pub role: Role,
     pub username: String,
     #[serde(default)] pub email: String,
-    #[serde(default, rename = "displayname")] pub display_name: String,
-    #[serde(default, rename = "avatarUrl")] pub avatar_url: String,
+    #[serde(default)] pub display_name: String,
+    #[serde(default)] pub avatar_url: String,
     #[serde(default)] pub description: String,
-    #[serde(default)] pub password: String,
+    /// Only sent to create a user; Memos never returns it.
+    #[serde(default, skip_serializing_if = "String::is_empty")] pub password: String,
     pub state: State,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub create_time: Option<DateTime<Utc>>,
+}
+
+/// Memo counts of a user, as reported by Memos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStats {
+    /// The stats resource, `users/{id}/stats`, or the user itself on older Memos versions.
+    pub name: String,
+    #[serde(default)]
+    pub total_memo_count: u64,
+    #[serde(default)]
+    pub pinned_memos: Vec<String>,
+    #[serde(default)]
+    pub tag_count: BTreeMap<String, u64>,
+}
+
+/// An entry of the inbox of a user, e.g. a comment on one of their memos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserNotification {
+    /// The notification resource, `users/{id}/notifications/{id}`.
+    pub name: String,
+    /// The user whose action caused the notification.
+    #[serde(default)]
+    pub sender: String,
+    /// `UNREAD` until the user reads or archives it.
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub create_time: Option<DateTime<Utc>>,
+    /// What happened, e.g. `MEMO_COMMENT`.
+    #[serde(default, rename = "type")]
+    pub kind: String,
+    /// The activity behind the notification, see [`UserService::get_memo_comment_activity`].
+    #[serde(default)]
+    pub activity_id: Option<i64>,
+}
+
+impl UserNotification {
+    pub fn is_unread(&self) -> bool {
+        self.status == "UNREAD"
+    }
+}
+
+/// The memos of a comment activity: `memo` is the comment, `related_memo` the memo commented on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoComment {
+    pub memo: String,
+    pub related_memo: String,
+}
+
+/// A saved filter of a user, listed in the sidebar of Memos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shortcut {
+    /// The shortcut resource, `users/{id}/shortcuts/{id}`.
+    pub name: String,
+    #[serde(default)]
+    pub title: String,
+    /// The CEL filter of the memos, e.g. `tag in ["reading"]`.
+    #[serde(default)]
+    pub filter: String,
+}
+
+impl Shortcut {
+    pub fn id(&self) -> &str {
+        self.name.rsplit('/').next().unwrap_or_default()
+    }
+}
+
+impl UserStats {
+    /// The name of the user the stats are about.
+    pub fn user(&self) -> &str {
+        self.name.strip_suffix("/stats").unwrap_or(&self.name)
+    }
 }
 
 impl User {
@@ -62,30 +154,140 @@ impl User {
             description: String::new(),
             password: password.to_string(),
             state: State::Normal,
+            create_time: None,
+        }
+    }
+
+    /// The id in the name of the user, e.g. 1 for `users/1`.
+    pub fn id(&self) -> Option<u64> {
+        self.name.strip_prefix("users/")?.parse().ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUser {
+    #[schemars(description = "The resource name of the user, e.g. `users/1`.")]
+    pub name: String,
+    #[schemars(description = "The new role of the user.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    #[schemars(description = "The new state of the user. Use ARCHIVED to offboard a user.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<State>,
+    #[schemars(description = "The new display name of the user.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+}
+
+impl UpdateUser {
+    pub fn update_mask(&self) -> String {
+        let mut mask = Vec::new();
+        if self.role.is_some() {
+            mask.push("role");
+        }
+        if self.state.is_some() {
+            mask.push("state");
+        }
+        if self.display_name.is_some() {
+            mask.push("display_name");
+        }
+        mask.join(",")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUserProfile {
+    #[schemars(description = "The resource name of the user, e.g. `users/1`. Defaults to the current user.")]
+    #[serde(default)]
+    pub name: String,
+    #[schemars(description = "The new display name of the user.")]
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[schemars(description = "The new profile description of the user.")]
+    #[serde(default)]
+    pub description: Option<String>,
+    #[schemars(description = "The new avatar image, encoded as base64.")]
+    #[serde(default)]
+    pub avatar_base64: Option<String>,
+    #[schemars(description = "The MIME type of the avatar image, e.g. `image/png`.")]
+    #[serde(default)]
+    pub avatar_mime_type: Option<String>,
+}
+
+impl UpdateUserProfile {
+    pub fn update_mask(&self) -> String {
+        let mut mask = Vec::new();
+        if self.display_name.is_some() {
+            mask.push("display_name");
+        }
+        if self.description.is_some() {
+            mask.push("description");
+        }
+        if self.avatar_base64.is_some() {
+            mask.push("avatar_url");
+        }
+        mask.join(",")
+    }
+
+    fn avatar_url(&self) -> Result<Option<String>> {
+        let Some(data) = &self.avatar_base64 else {
+            return Ok(None);
+        };
+        let mime_type = self.avatar_mime_type.as_deref().unwrap_or("image/png");
+        if !mime_type.starts_with("image/") {
+            return Err(anyhow::anyhow!("Avatar must be an image, got {}", mime_type));
         }
+        Ok(Some(format!("data:{};base64,{}", mime_type, data.trim())))
     }
 }
 
-pub trait UserService {
+#[async_trait]
+pub trait UserService: Send + Sync {
     async fn create_user(&self, user: &User) -> Result<User>;
 
     async fn delete_user(&self, user: &User) -> Result<()>;
 
+    async fn list_users(&self) -> Result<Vec<User>>;
+
+    /// Memo counts of every user of the instance. Requires a HOST or ADMIN user.
+    async fn list_user_stats(&self) -> Result<Vec<UserStats>>;
+
+    async fn update_user(&self, update: &UpdateUser) -> Result<User>;
+
+    async fn update_user_profile(&self, profile: &UpdateUserProfile) -> Result<User>;
+
     async fn create_pat(&self, user: &User, desc: &str, expires_in_days: u32) -> Result<(Token, String)>;
 
     async fn delete_pat(&self, token: &Token) -> Result<()>;
+
+    /// The personal access tokens of `user`, e.g. `users/1`, without their secrets.
+    async fn list_pats(&self, user: &str) -> Result<Vec<Token>>;
+
+    /// The inbox of `user`, e.g. `users/1`, newest first.
+    async fn list_user_notifications(&self, user: &str) -> Result<Vec<UserNotification>>;
+
+    /// The memos of comment activity `id`, none for the other kinds of activity.
+    async fn get_memo_comment_activity(&self, id: i64) -> Result<Option<MemoComment>>;
+
+    /// The shortcuts of `user`, e.g. `users/1`. Run one with [`NoteService::list_notes_matching`].
+    ///
+    /// [`NoteService::list_notes_matching`]: crate::memos::service::note::NoteService::list_notes_matching
+    async fn list_shortcuts(&self, user: &str) -> Result<Vec<Shortcut>>;
 }
 
 
+#[async_trait]
 impl<T> UserService for T
 where
-    T: crate::memos::HttpServer,
+    T: crate::memos::HttpServer + Send + Sync,
 {
     async fn create_user(&self, user: &User) -> Result<User> {
         let request = self.build_post_request("users")
             .json(user);
 
-        let response = request.send().await?;
+        let response = request.send_traced().await?;
 
         let created_user = self.validate_data_response::<User>(response).await?;
 
@@ -96,13 +298,106 @@ where
         let endpoint = format!("{}", user.name);
         let request = self.build_delete_request(&endpoint);
 
-        let response = request.send().await?;
+        let response = request.send_traced().await?;
 
         self.validate_response(response).await?;
 
         Ok(())
     }
 
+    async fn list_users(&self) -> Result<Vec<User>> {
+        #[derive(Deserialize)]
+        struct UsersResponse {
+            #[serde(default)]
+            pub users: Vec<User>,
+            #[serde(default, rename = "nextPageToken")]
+            pub next_page_token: String,
+        }
+
+        let mut users = Vec::<User>::new();
+        let mut pages = Pages::new(self.page_limits());
+        let mut next_page_token = None;
+
+        loop {
+            let endpoint = match &next_page_token {
+                Some(token) => format!("users?pageToken={}", token),
+                None => "users".to_string(),
+            };
+
+            let rsp = self.build_get_request(endpoint.as_str()).send_traced().await?;
+
+            let rsp = self.validate_data_response::<UsersResponse>(rsp).await?;
+            let count = rsp.users.len();
+            users.extend(rsp.users);
+
+            next_page_token = pages.next("users", count, rsp.next_page_token)?;
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+        Ok(users)
+    }
+
+    async fn list_user_stats(&self) -> Result<Vec<UserStats>> {
+        #[derive(Deserialize)]
+        struct StatsResponse {
+            #[serde(default)]
+            pub stats: Vec<UserStats>,
+        }
+
+        let rsp = self.build_get_request("users:stats").send_traced().await?;
+        Ok(self.validate_data_response::<StatsResponse>(rsp).await?.stats)
+    }
+
+    async fn update_user(&self, update: &UpdateUser) -> Result<User> {
+        let mask = update.update_mask();
+        if mask.is_empty() {
+            return Err(anyhow::anyhow!("Nothing to update for user {}", update.name));
+        }
+
+        let endpoint = format!("{}?updateMask={}", update.name, mask);
+        let rsp = self.build_patch_request(&endpoint)
+            .json(update)
+            .send_traced()
+            .await?;
+
+        self.validate_data_response::<User>(rsp).await
+    }
+
+    async fn update_user_profile(&self, profile: &UpdateUserProfile) -> Result<User> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct RequestBody<'a> {
+            name: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            display_name: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            description: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            avatar_url: Option<String>,
+        }
+
+        let body = RequestBody {
+            name: &profile.name,
+            display_name: profile.display_name.as_deref(),
+            description: profile.description.as_deref(),
+            avatar_url: profile.avatar_url()?,
+        };
+
+        let mask = profile.update_mask();
+        if mask.is_empty() {
+            return Err(anyhow::anyhow!("Nothing to update for user {}", profile.name));
+        }
+
+        let endpoint = format!("{}?updateMask={}", profile.name, mask);
+        let rsp = self.build_patch_request(&endpoint)
+            .json(&body)
+            .send_traced()
+            .await?;
+
+        self.validate_data_response::<User>(rsp).await
+    }
+
     async fn create_pat(&self, user: &User, desc: &str, expires_in_days: u32) -> Result<(Token, String)> {
         #[derive(Serialize)]
         struct RequestBody {
@@ -121,7 +416,7 @@ where
         let endpoint = format!("{}/personalAccessTokens", user.name);
         let rsp = self.build_post_request(&endpoint)
             .json(&body)
-            .send()
+            .send_traced()
             .await?;
         
         #[derive(Deserialize)]
@@ -137,7 +432,7 @@ where
     async fn delete_pat(&self, token: &Token) -> Result<()> {
         let endpoint = format!("{}", token.name);
         let rsp = self.build_delete_request(&endpoint)
-            .send()
+            .send_traced()
             .await?;
 
         self.validate_response(rsp).await?;
@@ -145,35 +440,461 @@ where
         Ok(())
     }
 
+    async fn list_pats(&self, user: &str) -> Result<Vec<Token>> {
+        #[derive(Deserialize)]
+        struct TokensResponse {
+            #[serde(default, rename = "personalAccessTokens")]
+            pub personal_access_tokens: Vec<Token>,
+            #[serde(default, rename = "nextPageToken")]
+            pub next_page_token: String,
+        }
+
+        let mut tokens = Vec::<Token>::new();
+        let mut pages = Pages::new(self.page_limits());
+        let mut next_page_token = None;
+
+        loop {
+            let endpoint = match &next_page_token {
+                Some(token) => format!("{}/personalAccessTokens?pageToken={}", user, token),
+                None => format!("{}/personalAccessTokens", user),
+            };
+
+            let rsp = self.build_get_request(endpoint.as_str()).send_traced().await?;
+
+            let rsp = self.validate_data_response::<TokensResponse>(rsp).await?;
+            let count = rsp.personal_access_tokens.len();
+            tokens.extend(rsp.personal_access_tokens);
+
+            next_page_token = pages.next("access tokens", count, rsp.next_page_token)?;
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+        Ok(tokens)
+    }
+
+    async fn list_user_notifications(&self, user: &str) -> Result<Vec<UserNotification>> {
+        #[derive(Deserialize)]
+        struct NotificationsResponse {
+            #[serde(default)]
+            pub notifications: Vec<UserNotification>,
+            #[serde(default, rename = "nextPageToken")]
+            pub next_page_token: String,
+        }
+
+        let mut notifications = Vec::<UserNotification>::new();
+        let mut pages = Pages::new(self.page_limits());
+        let mut next_page_token = None;
+
+        loop {
+            let endpoint = match &next_page_token {
+                Some(token) => format!("{}/notifications?pageToken={}", user, token),
+                None => format!("{}/notifications", user),
+            };
+
+            let rsp = self.build_get_request(endpoint.as_str()).send_traced().await?;
+
+            let rsp = self.validate_data_response::<NotificationsResponse>(rsp).await?;
+            let count = rsp.notifications.len();
+            notifications.extend(rsp.notifications);
+
+            next_page_token = pages.next("notifications", count, rsp.next_page_token)?;
+            if next_page_token.is_none() {
+                break;
+            }
+        }
+        Ok(notifications)
+    }
+
+    async fn get_memo_comment_activity(&self, id: i64) -> Result<Option<MemoComment>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Payload {
+            #[serde(default)]
+            memo_comment: Option<MemoComment>,
+        }
+
+        #[derive(Deserialize)]
+        struct Activity {
+            #[serde(default)]
+            payload: Option<Payload>,
+        }
+
+        let rsp = self.build_get_request(&format!("activities/{}", id)).send_traced().await?;
+        let activity = self.validate_data_response::<Activity>(rsp).await?;
+        Ok(activity.payload.and_then(|payload| payload.memo_comment))
+    }
+
+    async fn list_shortcuts(&self, user: &str) -> Result<Vec<Shortcut>> {
+        #[derive(Deserialize)]
+        struct ShortcutsResponse {
+            #[serde(default)]
+            pub shortcuts: Vec<Shortcut>,
+        }
+
+        let rsp = self.build_get_request(&format!("{}/shortcuts", user)).send_traced().await?;
+        Ok(self.validate_data_response::<ShortcutsResponse>(rsp).await?.shortcuts)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{*, super::{auth::AuthService, super::Server}};
-    
+    use proptest::prelude::*;
+    use serde_json::json;
+    use wiremock::{
+        Mock, ResponseTemplate,
+        matchers::{body_partial_json, header, method, path, query_param, query_param_is_missing},
+    };
+
+    use super::{*, super::{auth::AuthService, note::NoteService}};
+    use crate::memos::mock;
+
     #[tokio::test]
     async fn test_create_and_delete_user() {
-        let server = Server::new("localhost:5230", "memos_pat_t3pjYKgGSzYqOqMgR4mZR768afCNG6sW");
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/users"))
+            .and(body_partial_json(json!({"username": "testuser", "password": "testpassword", "role": "USER"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock::user("users/2", "testuser")))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/users/2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
         let user = User::new("testuser", "testpassword", "test@example.com");
         let created_user = server.create_user(&user).await.expect("Failed to create user");
         assert_eq!(created_user.username, user.username);
+        assert_eq!(created_user.name, "users/2");
 
         server.delete_user(&created_user).await.expect("Failed to delete user");
     }
 
+    #[tokio::test]
+    async fn test_list_and_update_user() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users"))
+            .and(query_param_is_missing("pageToken"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "users": [mock::user("users/1", "admin")],
+                "nextPageToken": "page-2",
+            })))
+            .mount(&mock)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users"))
+            .and(query_param("pageToken", "page-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "users": [mock::user("users/3", "testuser3")],
+            })))
+            .mount(&mock)
+            .await;
+        let mut archived = mock::user("users/3", "testuser3");
+        archived["state"] = json!("ARCHIVED");
+        archived["displayName"] = json!("Test User 3");
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/users/3"))
+            .and(query_param("updateMask", "state,display_name"))
+            .and(body_partial_json(json!({"state": "ARCHIVED", "displayName": "Test User 3"})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(archived))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let users = server.list_users().await.expect("Failed to list users");
+        assert_eq!(users.len(), 2);
+        assert!(users.iter().any(|u| u.username == "testuser3"));
+
+        let update = UpdateUser {
+            name: "users/3".to_string(),
+            role: None,
+            state: Some(State::Archived),
+            display_name: Some("Test User 3".to_string()),
+        };
+        let updated_user = server.update_user(&update).await.expect("Failed to update user");
+        assert!(matches!(updated_user.state, State::Archived));
+    }
+
+    #[tokio::test]
+    async fn test_list_user_stats() {
+        let (mock, server) = mock::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/users:stats"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"stats": [
+                {"name": "users/1/stats", "totalMemoCount": 12, "pinnedMemos": ["memos/3"], "tagCount": {"garden": 2}},
+                {"name": "users/2", "totalMemoCount": 0},
+            ]})))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let stats = server.list_user_stats().await.expect("Failed to list user stats");
+        assert_eq!(stats.iter().map(|s| (s.user(), s.total_memo_count)).collect::<Vec<_>>(), [("users/1", 12), ("users/2", 0)]);
+        assert_eq!(stats[0].tag_count["garden"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_profile() {
+        let (mock, server) = mock::start().await;
+        let avatar = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+        let mut updated = mock::user("users/4", "testuser4");
+        updated["description"] = json!("Updated from unit test");
+        updated["avatarUrl"] = json!("/file/users/4/avatar");
+        Mock::given(method("PATCH"))
+            .and(path("/api/v1/users/4"))
+            .and(query_param("updateMask", "description,avatar_url"))
+            .and(body_partial_json(json!({
+                "description": "Updated from unit test",
+                "avatarUrl": format!("data:image/png;base64,{}", avatar),
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(updated))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let profile = UpdateUserProfile {
+            name: "users/4".to_string(),
+            display_name: None,
+            description: Some("Updated from unit test".to_string()),
+            avatar_base64: Some(avatar.to_string()),
+            avatar_mime_type: Some("image/png".to_string()),
+        };
+        let updated_user = server.update_user_profile(&profile).await.expect("Failed to update profile");
+        assert_eq!(updated_user.description, "Updated from unit test");
+        assert!(!updated_user.avatar_url.is_empty());
+    }
+
     #[tokio::test]
     async fn test_create_and_delete_pat() {
-        let server = Server::new("localhost:5230", "memos_pat_t3pjYKgGSzYqOqMgR4mZR768afCNG6sW");
-        let user = User::new("testuser2", "testpassword2", "test2@example.com");
-        let created_user = server.create_user(&user).await.expect("Failed to create user");
-        {
-            let server = server.sign_in("testuser2", "testpassword2").await.expect("Failed to sign in");
-            let (token, plain_text) = server.create_pat(&created_user, "Test PAT", 30).await.expect("Failed to create PAT");
-            assert_eq!(token.description, "Test PAT");
-            assert!(!plain_text.is_empty());
+        let (mock, server) = mock::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/auth/signin"))
+            .and(body_partial_json(json!({"passwordCredentials": {"username": "testuser2", "password": "testpassword2"}})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"accessToken": "session-token"})))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/users/2/personalAccessTokens"))
+            .and(header("authorization", "Bearer session-token"))
+            .and(body_partial_json(json!({"parent": "users/2", "description": "Test PAT", "expiresInDays": 30})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "personalAccessToken": mock::personal_access_token("users/2/personalAccessTokens/1", "Test PAT"),
+                "token": "memos_pat_created",
+            })))
+            .expect(1)
+            .mount(&mock)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/users/2/personalAccessTokens/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let user: User = serde_json::from_value(mock::user("users/2", "testuser2")).unwrap();
+        let server = server.sign_in("testuser2", "testpassword2").await.expect("Failed to sign in");
+        let (token, plain_text) = server.create_pat(&user, "Test PAT", 30).await.expect("Failed to create PAT");
+        assert_eq!(token.description, "Test PAT");
+        assert_eq!(plain_text, "memos_pat_created");
+
+        server.delete_pat(&token).await.expect("Failed to delete PAT");
+    }
+
+    #[tokio::test]
+    async fn test_list_user_notifications() {
+        let (mock, server) = mock::start().await;
+        mock::mount_memos_api(&mock).await;
+
+        let notifications = server.list_user_notifications("users/1").await.expect("Failed to list notifications");
+        assert_eq!(notifications.iter().map(|n| (n.name.as_str(), n.is_unread())).collect::<Vec<_>>(), [
+            ("users/1/notifications/2", true),
+            ("users/1/notifications/1", false),
+        ]);
+        assert_eq!(notifications[0].kind, "MEMO_COMMENT");
+        let comment = server.get_memo_comment_activity(7).await.expect("Failed to get activity");
+        assert_eq!(comment, Some(MemoComment { memo: "memos/9".to_string(), related_memo: "memos/1".to_string() }));
+    }
+
+    #[tokio::test]
+    async fn test_list_shortcuts() {
+        let (mock, server) = mock::start().await;
+        mock::mount_memos_api(&mock).await;
+
+        let shortcuts = server.list_shortcuts("users/1").await.expect("Failed to list shortcuts");
+        assert_eq!(shortcuts.iter().map(|s| (s.id(), s.title.as_str())).collect::<Vec<_>>(), [("garden", "Garden")]);
+        let memos = server.list_notes_matching(&shortcuts[0].filter).await.expect("Failed to run the shortcut");
+        assert_eq!(memos.iter().map(|memo| memo.name.as_deref().unwrap()).collect::<Vec<_>>(), ["memos/1"]);
+    }
+
+    /// A user as returned by Memos v0.25.
+    const USER_PAYLOAD: &str = r#"{
+        "name": "users/2",
+        "role": "ADMIN",
+        "username": "jane",
+        "email": "jane@example.com",
+        "displayName": "Jane",
+        "avatarUrl": "/file/users/2/avatar",
+        "description": "Notes about everything",
+        "state": "NORMAL",
+        "createTime": "2025-01-31T08:00:00Z",
+        "updateTime": "2025-02-01T10:30:00Z"
+    }"#;
 
-            server.delete_pat(&token).await.expect("Failed to delete PAT");
+    /// A personal access token as returned by Memos.
+    const TOKEN_PAYLOAD: &str = r#"{
+        "name": "users/2/personalAccessTokens/5",
+        "description": "mcp-memo",
+        "createdAt": "2025-01-31T08:00:00Z",
+        "expiresAt": null,
+        "lastUsedAt": "2025-02-01T10:30:00.125Z"
+    }"#;
+
+    fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+        (0i64..4_102_444_800).prop_map(|secs| DateTime::from_timestamp(secs, 0).unwrap())
+    }
+
+    fn user() -> impl Strategy<Value = User> {
+        let role = (0..4usize).prop_map(|i| [Role::RoleUnspecified, Role::Host, Role::Admin, Role::User].into_iter().nth(i).unwrap());
+        let state = (0..3usize).prop_map(|i| [State::StateUnspecified, State::Normal, State::Archived].into_iter().nth(i).unwrap());
+        (("users/[0-9]+", role, ".*", ".*"), (".*", ".*", ".*", ".*", state, proptest::option::of(timestamp()))).prop_map(
+            |((name, role, username, email), (display_name, avatar_url, description, password, state, create_time))| User {
+                name,
+                role,
+                username,
+                email,
+                display_name,
+                avatar_url,
+                description,
+                password,
+                state,
+                create_time,
+            },
+        )
+    }
+
+    fn token() -> impl Strategy<Value = Token> {
+        (".*", ".*", timestamp(), proptest::option::of(timestamp()), proptest::option::of(timestamp())).prop_map(
+            |(name, description, created_at, expires_at, last_used_at)| Token {
+                name,
+                description,
+                created_at,
+                expires_at,
+                last_used_at,
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn test_user_and_token_round_trip(user in user(), token in token()) {
+            mock::assert_round_trip(&user);
+            mock::assert_round_trip(&token);
         }
-        server.delete_user(&created_user).await.expect("Failed to delete user");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_decode_memos_payloads() {
+        let user: User = serde_json::from_str(USER_PAYLOAD).unwrap();
+        assert_eq!(user.username, "jane");
+        assert!(matches!(user.role, Role::Admin));
+        assert_eq!((user.display_name.as_str(), user.avatar_url.as_str()), ("Jane", "/file/users/2/avatar"));
+        assert_eq!(user.create_time, Some("2025-01-31T08:00:00Z".parse().unwrap()));
+        assert_eq!(user.id(), Some(2));
+        let json = serde_json::to_value(&user).unwrap();
+        assert_eq!(json["displayName"], "Jane");
+        assert!(json.get("password").is_none());
+
+        let token: Token = serde_json::from_str(TOKEN_PAYLOAD).unwrap();
+        assert_eq!(token.description, "mcp-memo");
+        assert!(token.expires_at.is_none());
+        assert!(token.last_used_at.is_some());
+    }
+
+    /// The same flows against the real Memos server named by MEMOS_TEST_HOST and MEMOS_TEST_TOKEN.
+    #[cfg(feature = "live-tests")]
+    mod live {
+        use super::super::{*, super::auth::AuthService};
+        use crate::memos::mock;
+
+        #[tokio::test]
+        async fn test_create_and_delete_user() {
+            let Some(server) = mock::live_server() else {
+                return;
+            };
+            let user = User::new("testuser", "testpassword", "test@example.com");
+            let created_user = server.create_user(&user).await.expect("Failed to create user");
+            assert_eq!(created_user.username, user.username);
+
+            server.delete_user(&created_user).await.expect("Failed to delete user");
+        }
+
+        #[tokio::test]
+        async fn test_list_and_update_user() {
+            let Some(server) = mock::live_server() else {
+                return;
+            };
+            let user = User::new("testuser3", "testpassword3", "test3@example.com");
+            let created_user = server.create_user(&user).await.expect("Failed to create user");
+
+            let users = server.list_users().await.expect("Failed to list users");
+            assert!(users.iter().any(|u| u.username == "testuser3"));
+
+            let update = UpdateUser {
+                name: created_user.name.clone(),
+                role: None,
+                state: Some(State::Archived),
+                display_name: Some("Test User 3".to_string()),
+            };
+            let updated_user = server.update_user(&update).await.expect("Failed to update user");
+            assert!(matches!(updated_user.state, State::Archived));
+
+            server.delete_user(&created_user).await.expect("Failed to delete user");
+        }
+
+        #[tokio::test]
+        async fn test_update_user_profile() {
+            let Some(server) = mock::live_server() else {
+                return;
+            };
+            let user = User::new("testuser4", "testpassword4", "test4@example.com");
+            let created_user = server.create_user(&user).await.expect("Failed to create user");
+
+            let profile = UpdateUserProfile {
+                name: created_user.name.clone(),
+                display_name: None,
+                description: Some("Updated from unit test".to_string()),
+                avatar_base64: Some("iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==".to_string()),
+                avatar_mime_type: Some("image/png".to_string()),
+            };
+            let updated_user = server.update_user_profile(&profile).await.expect("Failed to update profile");
+            assert_eq!(updated_user.description, "Updated from unit test");
+            assert!(!updated_user.avatar_url.is_empty());
+
+            server.delete_user(&created_user).await.expect("Failed to delete user");
+        }
+
+        #[tokio::test]
+        async fn test_create_and_delete_pat() {
+            let Some(server) = mock::live_server() else {
+                return;
+            };
+            let user = User::new("testuser2", "testpassword2", "test2@example.com");
+            let created_user = server.create_user(&user).await.expect("Failed to create user");
+            {
+                let server = server.sign_in("testuser2", "testpassword2").await.expect("Failed to sign in");
+                let (token, plain_text) = server.create_pat(&created_user, "Test PAT", 30).await.expect("Failed to create PAT");
+                assert_eq!(token.description, "Test PAT");
+                assert!(!plain_text.is_empty());
+
+                server.delete_pat(&token).await.expect("Failed to delete PAT");
+            }
+            server.delete_user(&created_user).await.expect("Failed to delete user");
+        }
+    }
+}
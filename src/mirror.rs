@@ -0,0 +1,570 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+use serde::Serialize;
+
+use crate::embedding::{self, Embedder};
+use crate::memos::{Servers, service::note::{Note, NoteService}};
+use crate::search::{SNIPPET_CHARS, SearchHit, SearchIndex};
+
+/// Every this many sync rounds the whole memo list is fetched to drop deleted and archived memos.
+const FULL_SYNC_EVERY: u64 = 12;
+const TOP_TAGS: usize = 20;
+const EMBED_BATCH: usize = 32;
+/// Longer memos are cut before embedding to stay within the model's input limit.
+const EMBED_MAX_CHARS: usize = 8000;
+/// Versions kept per memo, older ones are dropped.
+const MAX_VERSIONS: usize = 50;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS memos (
+        server TEXT NOT NULL,
+        name TEXT NOT NULL,
+        content TEXT NOT NULL,
+        update_time TEXT,
+        data TEXT NOT NULL,
+        PRIMARY KEY (server, name)
+    );
+    CREATE TABLE IF NOT EXISTS sync_state (
+        server TEXT PRIMARY KEY,
+        last_sync TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS embeddings (
+        server TEXT NOT NULL,
+        name TEXT NOT NULL,
+        model TEXT NOT NULL,
+        update_time TEXT,
+        vector BLOB NOT NULL,
+        PRIMARY KEY (server, name, model)
+    );
+    CREATE TABLE IF NOT EXISTS versions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        server TEXT NOT NULL,
+        name TEXT NOT NULL,
+        update_time TEXT,
+        saved_at TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS versions_by_memo ON versions (server, name, id);
+";
+
+/// Local SQLite copy of the memos of every configured server, with a full-text index kept in step.
+pub struct Mirror {
+    conn: Mutex<Connection>,
+    index: SearchIndex,
+    embedder: Option<Embedder>,
+}
+
+/// Memos similar to a given one and whether they were found by embedding or by shared words.
+#[derive(Debug, Serialize)]
+pub struct Related {
+    pub method: &'static str,
+    pub memos: Vec<SearchHit>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SemanticHit {
+    pub score: f32,
+    pub memo: Note,
+}
+
+/// A saved copy of a memo as it was before a change made through this server.
+#[derive(Debug, Serialize)]
+pub struct MemoVersion {
+    pub version: i64,
+    pub saved_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_time: Option<String>,
+    pub preview: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MirrorStats {
+    pub memos: u64,
+    pub pinned: u64,
+    pub by_visibility: BTreeMap<String, u64>,
+    pub top_tags: Vec<(String, u64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_sync: Option<String>,
+}
+
+impl Mirror {
+    /// Opens or creates the mirror database. `:memory:` gives a throwaway mirror.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open mirror database {}: {}", path.display(), e))?;
+        conn.execute_batch(SCHEMA)?;
+
+        let mut stored = BTreeMap::<String, Vec<Note>>::new();
+        {
+            let mut stmt = conn.prepare("SELECT server, data FROM memos")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (server, data) = row?;
+                stored.entry(server).or_default().push(serde_json::from_str(&data)?);
+            }
+        }
+        let index = SearchIndex::new()?;
+        for (server, notes) in &stored {
+            index.index(server, notes, true)?;
+        }
+
+        Ok(Mirror { conn: Mutex::new(conn), index, embedder: None })
+    }
+
+    /// Embeds memos during sync with `embedder`, enabling [`Mirror::semantic_search`].
+    pub fn with_embedder(mut self, embedder: Option<Embedder>) -> Self {
+        self.embedder = embedder;
+        self
+    }
+
+    /// Mirror holding only `notes`, used to answer queries from a live listing.
+    pub fn from_notes(server: &str, notes: &[Note]) -> Result<Self> {
+        let mirror = Mirror::open(Path::new(":memory:"))?;
+        mirror.store(server, notes, true)?;
+        Ok(mirror)
+    }
+
+    /// Fetches the memos changed since the last sync, or all memos when `full` is set.
+    pub async fn sync(&self, name: &str, server: &dyn NoteService, full: bool) -> Result<usize> {
+        let since = if full { None } else { self.last_update_time(name)? };
+        let notes = match since {
+            Some(since) => server.list_notes_updated_since(since).await?,
+            None => server.list_notes().await?,
+        };
+        self.store(name, &notes, since.is_none())?;
+        if let Some(embedder) = &self.embedder {
+            let embedded = self.embed_pending(name, embedder).await?;
+            tracing::debug!(server = name, memos = embedded, "embedded memos");
+        }
+        Ok(notes.len())
+    }
+
+    /// Drops `names` of `server`, deleted or archived through this server, which a delta sync does not
+    /// notice before the next full one.
+    pub fn remove(&self, server: &str, names: &[&str]) -> Result<()> {
+        if names.is_empty() {
+            return Ok(());
+        }
+        let conn = self.conn.lock().unwrap();
+        for name in names {
+            conn.execute("DELETE FROM memos WHERE server = ?1 AND name = ?2", params![server, name])?;
+        }
+        drop(conn);
+        self.index.remove(server, names)
+    }
+
+    /// Embeds memos that are new or changed since they were last embedded with the current model.
+    async fn embed_pending(&self, server: &str, embedder: &Embedder) -> Result<usize> {
+        let pending = {
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "DELETE FROM embeddings WHERE server = ?1 AND NOT EXISTS
+                 (SELECT 1 FROM memos WHERE memos.server = embeddings.server AND memos.name = embeddings.name)",
+                params![server],
+            )?;
+            let mut stmt = conn.prepare(
+                "SELECT m.name, m.content, m.update_time FROM memos m
+                 LEFT JOIN embeddings e ON e.server = m.server AND e.name = m.name AND e.model = ?2
+                 WHERE m.server = ?1 AND (e.name IS NULL OR e.update_time IS NOT m.update_time)",
+            )?;
+            stmt.query_map(params![server, embedder.model()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+
+        for batch in pending.chunks(EMBED_BATCH) {
+            let texts = batch
+                .iter()
+                .map(|(_, content, _)| content.chars().take(EMBED_MAX_CHARS).collect())
+                .collect::<Vec<String>>();
+            let vectors = embedder.embed(&texts).await?;
+            if vectors.len() != batch.len() {
+                return Err(anyhow::anyhow!("Expected {} embeddings, got {}", batch.len(), vectors.len()));
+            }
+
+            let conn = self.conn.lock().unwrap();
+            for ((name, _, update_time), vector) in batch.iter().zip(vectors) {
+                conn.execute(
+                    "INSERT OR REPLACE INTO embeddings (server, name, model, update_time, vector) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![server, name, embedder.model(), update_time, embedding::to_bytes(&vector)],
+                )?;
+            }
+        }
+        Ok(pending.len())
+    }
+
+    /// The `limit` memos of `server` whose embedding is closest to the one of `query`.
+    pub async fn semantic_search(&self, server: &str, query: &str, limit: usize) -> Result<Vec<SemanticHit>> {
+        let embedder = self.embedder.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("Semantic search is not configured, set EMBEDDINGS_PROVIDER")
+        })?;
+        let query = embedder
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("The embedding provider returned no vector"))?;
+
+        let mut scored = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT e.vector, m.data FROM embeddings e
+                 JOIN memos m ON m.server = e.server AND m.name = e.name
+                 WHERE e.server = ?1 AND e.model = ?2",
+            )?;
+            stmt.query_map(params![server, embedder.model()], |row| {
+                let vector: Vec<u8> = row.get(0)?;
+                Ok((embedding::cosine_similarity(&query, &embedding::from_bytes(&vector)), row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(limit);
+
+        scored
+            .into_iter()
+            .map(|(score, data)| Ok(SemanticHit { score, memo: serde_json::from_str(&data)? }))
+            .collect()
+    }
+
+    /// Runs the periodic delta sync of all servers until the process exits.
+    pub async fn run(self: Arc<Self>, servers: Arc<Servers>, interval: Duration) {
+        let mut round = 0u64;
+        loop {
+            let full = round.is_multiple_of(FULL_SYNC_EVERY);
+            for (name, server) in servers.iter() {
+                match self.sync(name, server, full).await {
+                    Ok(count) => tracing::debug!(server = name, full, memos = count, "mirror synchronized"),
+                    Err(e) => tracing::warn!("Failed to synchronize mirror of server {}: {}", name, e),
+                }
+            }
+            round += 1;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    fn store(&self, server: &str, notes: &[Note], replace: bool) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        if replace {
+            tx.execute("DELETE FROM memos WHERE server = ?1", params![server])?;
+        }
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO memos (server, name, content, update_time, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for note in notes {
+                let Some(name) = &note.name else {
+                    continue;
+                };
+                let data = serde_json::to_value(note)?;
+                insert.execute(params![
+                    server,
+                    name,
+                    note.content,
+                    data.get("updateTime").and_then(|time| time.as_str()),
+                    data.to_string(),
+                ])?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO sync_state (server, last_sync) VALUES (?1, ?2)",
+            params![server, Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        drop(conn);
+
+        self.index.index(server, notes, replace)
+    }
+
+    fn last_update_time(&self, server: &str) -> Result<Option<DateTime<Utc>>> {
+        let conn = self.conn.lock().unwrap();
+        let time: Option<String> = conn.query_row(
+            "SELECT MAX(update_time) FROM memos WHERE server = ?1",
+            params![server],
+            |row| row.get(0),
+        )?;
+        Ok(time
+            .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+            .map(|time| time.with_timezone(&Utc)))
+    }
+
+    /// Whether the server has been synchronized at least once.
+    pub fn is_synced(&self, server: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let synced = conn
+            .query_row("SELECT 1 FROM sync_state WHERE server = ?1", params![server], |_| Ok(()))
+            .optional()?;
+        Ok(synced.is_some())
+    }
+
+    pub fn list(&self, server: &str) -> Result<Vec<Note>> {
+        self.query_notes("SELECT data FROM memos WHERE server = ?1 ORDER BY update_time DESC", &[server])
+    }
+
+    /// Memos whose content contains every whitespace-separated term of `query`, newest first, at
+    /// most `limit` of them when given.
+    pub fn search(&self, server: &str, query: &str, limit: Option<usize>) -> Result<Vec<Note>> {
+        let terms = query
+            .split_whitespace()
+            .map(|term| format!("%{}%", term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+            .collect::<Vec<_>>();
+        let mut sql = "SELECT data FROM memos WHERE server = ?1".to_string();
+        for i in 0..terms.len() {
+            sql.push_str(&format!(" AND content LIKE ?{} ESCAPE '\\'", i + 2));
+        }
+        sql.push_str(" ORDER BY update_time DESC");
+        if let Some(limit) = limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let args = std::iter::once(server).chain(terms.iter().map(String::as_str)).collect::<Vec<_>>();
+        self.query_notes(&sql, &args)
+    }
+
+    /// Relevance-ranked memos of `server` matching `query`, see [`SearchIndex::search`].
+    pub fn full_text_search(&self, server: &str, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        self.index.search(server, query, limit)
+    }
+
+    /// Memos of `server` similar to memo `name`, by embedding when the memo has one.
+    pub fn related(&self, server: &str, name: &str, limit: usize) -> Result<Related> {
+        if let Some(embedder) = &self.embedder {
+            let conn = self.conn.lock().unwrap();
+            let target = conn
+                .query_row(
+                    "SELECT vector FROM embeddings WHERE server = ?1 AND name = ?2 AND model = ?3",
+                    params![server, name, embedder.model()],
+                    |row| row.get::<_, Vec<u8>>(0),
+                )
+                .optional()?;
+            if let Some(target) = target {
+                let target = embedding::from_bytes(&target);
+                let mut stmt = conn.prepare(
+                    "SELECT e.name, e.vector, m.content FROM embeddings e
+                     JOIN memos m ON m.server = e.server AND m.name = e.name
+                     WHERE e.server = ?1 AND e.model = ?2 AND e.name != ?3",
+                )?;
+                let mut memos = stmt
+                    .query_map(params![server, embedder.model(), name], |row| {
+                        let vector: Vec<u8> = row.get(1)?;
+                        Ok(SearchHit {
+                            name: row.get(0)?,
+                            score: embedding::cosine_similarity(&target, &embedding::from_bytes(&vector)),
+                            snippet: row.get::<_, String>(2)?.chars().take(SNIPPET_CHARS).collect(),
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                memos.sort_by(|a, b| b.score.total_cmp(&a.score));
+                memos.truncate(limit);
+                return Ok(Related { method: "semantic", memos });
+            }
+        }
+
+        Ok(Related {
+            method: "lexical",
+            memos: self.index.related(server, name, limit)?,
+        })
+    }
+
+    pub fn stats(&self, server: &str) -> Result<MirrorStats> {
+        let conn = self.conn.lock().unwrap();
+        let (memos, pinned) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(json_extract(data, '$.pinned')), 0) FROM memos WHERE server = ?1",
+            params![server],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut by_visibility = BTreeMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(json_extract(data, '$.visibility'), 'VISIBILITY_UNSPECIFIED'), COUNT(*)
+             FROM memos WHERE server = ?1 GROUP BY 1",
+        )?;
+        for row in stmt.query_map(params![server], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))? {
+            let (visibility, count) = row?;
+            by_visibility.insert(visibility, count);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT tag.value, COUNT(*) FROM memos, json_each(memos.data, '$.tags') AS tag
+             WHERE memos.server = ?1 GROUP BY tag.value ORDER BY 2 DESC, 1 LIMIT ?2",
+        )?;
+        let top_tags = stmt
+            .query_map(params![server, TOP_TAGS], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let last_sync = conn
+            .query_row("SELECT last_sync FROM sync_state WHERE server = ?1", params![server], |row| row.get(0))
+            .optional()?;
+
+        Ok(MirrorStats {
+            memos,
+            pinned,
+            by_visibility,
+            top_tags,
+            last_sync,
+        })
+    }
+
+    /// Saves `note` of `server` as a version of it, unless the latest version already holds the
+    /// same revision. Returns the number of the version.
+    pub fn save_version(&self, server: &str, note: &Note) -> Result<Option<i64>> {
+        let Some(name) = &note.name else {
+            return Ok(None);
+        };
+        let data = serde_json::to_value(note)?;
+        let update_time = data.get("updateTime").and_then(|time| time.as_str());
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let latest = tx
+            .query_row(
+                "SELECT id, update_time, json_extract(data, '$.content') FROM versions
+                 WHERE server = ?1 AND name = ?2 ORDER BY id DESC LIMIT 1",
+                params![server, name],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, String>(2)?)),
+            )
+            .optional()?;
+        if let Some((id, time, content)) = latest
+            && time.as_deref() == update_time
+            && content == note.content
+        {
+            return Ok(Some(id));
+        }
+        tx.execute(
+            "INSERT INTO versions (server, name, update_time, saved_at, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![server, name, update_time, Utc::now().to_rfc3339(), data.to_string()],
+        )?;
+        let id = tx.last_insert_rowid();
+        tx.execute(
+            "DELETE FROM versions WHERE server = ?1 AND name = ?2 AND id NOT IN
+             (SELECT id FROM versions WHERE server = ?1 AND name = ?2 ORDER BY id DESC LIMIT ?3)",
+            params![server, name, MAX_VERSIONS],
+        )?;
+        tx.commit()?;
+        Ok(Some(id))
+    }
+
+    /// The saved versions of memo `name`, newest first.
+    pub fn versions(&self, server: &str, name: &str) -> Result<Vec<MemoVersion>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, saved_at, update_time, json_extract(data, '$.content') FROM versions
+             WHERE server = ?1 AND name = ?2 ORDER BY id DESC",
+        )?;
+        let versions = stmt
+            .query_map(params![server, name], |row| {
+                Ok(MemoVersion {
+                    version: row.get(0)?,
+                    saved_at: row.get(1)?,
+                    update_time: row.get(2)?,
+                    preview: crate::audit::preview(&row.get::<_, String>(3)?),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(versions)
+    }
+
+    /// Version `version` of memo `name`, if it is still kept.
+    pub fn version(&self, server: &str, name: &str, version: i64) -> Result<Option<Note>> {
+        let conn = self.conn.lock().unwrap();
+        let data = conn
+            .query_row(
+                "SELECT data FROM versions WHERE server = ?1 AND name = ?2 AND id = ?3",
+                params![server, name, version],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+    }
+
+    fn query_notes(&self, sql: &str, args: &[&str]) -> Result<Vec<Note>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |row| row.get::<_, String>(0))?;
+        let mut notes = Vec::new();
+        for data in rows {
+            notes.push(serde_json::from_str(&data?)?);
+        }
+        Ok(notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: &str, content: &str, update_time: &str, tags: &[&str]) -> Note {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "state": "NORMAL",
+            "content": content,
+            "visibility": "PRIVATE",
+            "updateTime": update_time,
+            "tags": tags,
+            "pinned": name == "memos/1",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_store_search_and_stats() {
+        let mirror = Mirror::from_notes("default", &[
+            note("memos/1", "Buy milk #shopping", "2025-01-01T10:00:00Z", &["shopping"]),
+            note("memos/2", "Call the plumber about 100% of the leak", "2025-01-02T10:00:00Z", &[]),
+        ]).unwrap();
+        assert!(mirror.is_synced("default").unwrap());
+        assert!(!mirror.is_synced("work").unwrap());
+
+        mirror.store("default", &[note("memos/3", "Buy bread #shopping", "2025-01-03T10:00:00Z", &["shopping"])], false).unwrap();
+        assert_eq!(mirror.list("default").unwrap()[0].name.as_deref(), Some("memos/3"));
+        assert_eq!(
+            mirror.last_update_time("default").unwrap(),
+            Some("2025-01-03T10:00:00Z".parse().unwrap())
+        );
+
+        assert_eq!(mirror.search("default", "buy shopping", Some(10)).unwrap().len(), 2);
+        assert_eq!(mirror.search("default", "100%", Some(10)).unwrap().len(), 1);
+        assert_eq!(mirror.search("default", "bread", Some(10)).unwrap()[0].name.as_deref(), Some("memos/3"));
+        assert!(mirror.search("work", "buy", Some(10)).unwrap().is_empty());
+
+        let stats = mirror.stats("default").unwrap();
+        assert_eq!(stats.memos, 3);
+        assert_eq!(stats.pinned, 1);
+        assert_eq!(stats.by_visibility["PRIVATE"], 3);
+        assert_eq!(stats.top_tags, vec![("shopping".to_string(), 2)]);
+    }
+
+    #[test]
+    fn test_versions() {
+        let mirror = Mirror::open(Path::new(":memory:")).unwrap();
+        let first = mirror.save_version("default", &note("memos/1", "Buy milk", "2025-01-01T10:00:00Z", &[])).unwrap();
+        // The same revision is not saved twice.
+        assert_eq!(mirror.save_version("default", &note("memos/1", "Buy milk", "2025-01-01T10:00:00Z", &[])).unwrap(), first);
+        let second = mirror.save_version("default", &note("memos/1", "Buy oat milk", "2025-01-02T10:00:00Z", &[])).unwrap();
+        mirror.save_version("work", &note("memos/1", "Other server", "2025-01-02T10:00:00Z", &[])).unwrap();
+
+        let versions = mirror.versions("default", "memos/1").unwrap();
+        assert_eq!(versions.iter().map(|v| Some(v.version)).collect::<Vec<_>>(), [second, first]);
+        assert_eq!(versions[0].preview, "Buy oat milk");
+        assert_eq!(mirror.version("default", "memos/1", first.unwrap()).unwrap().unwrap().content, "Buy milk");
+        assert!(mirror.version("default", "memos/2", first.unwrap()).unwrap().is_none());
+
+        for i in 0..MAX_VERSIONS {
+            mirror.save_version("default", &note("memos/1", &format!("Edit {}", i), "2025-01-03T10:00:00Z", &[])).unwrap();
+        }
+        assert_eq!(mirror.versions("default", "memos/1").unwrap().len(), MAX_VERSIONS);
+        assert!(mirror.version("default", "memos/1", first.unwrap()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,89 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Text recognized in the image attachments of memos, for `extract_attachment_text`. Recognition
+// runs the external command configured as `ocr_command` with the `ocr` feature: tesseract, or a
+// script handing the image to an OCR service. The text can be appended to the memo, under a line
+// naming the attachment, so photographed whiteboards and receipts become searchable.
+
+use crate::memos::service::note::Attachment;
+
+pub fn is_image(attachment: &Attachment) -> bool {
+    attachment.mime_type.starts_with("image/")
+}
+
+/// The line introducing the text of attachment `filename` in the memo.
+fn heading(filename: &str) -> String {
+    format!("Text in `{}`:", filename.replace('`', "'"))
+}
+
+/// Whether the text of attachment `filename` was appended to `content` before.
+pub fn has_text(content: &str, filename: &str) -> bool {
+    let heading = heading(filename);
+    content.lines().any(|line| line.trim() == heading)
+}
+
+/// `content` with `text`, recognized in attachment `filename`, appended as a quote.
+pub fn append_text(content: &str, filename: &str, text: &str) -> String {
+    let quote = text
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\n{}\n\n{}", content.trim_end(), heading(filename), quote)
+}
+
+/// Recognizes the text of `image` with `command`, a program and its arguments that read the image on
+/// stdin and write the text to stdout, e.g. `["tesseract", "stdin", "stdout"]`.
+#[cfg(feature = "ocr")]
+pub async fn image_to_text(command: &[String], image: &[u8]) -> anyhow::Result<String> {
+    use std::process::Stdio;
+    use tokio::io::AsyncWriteExt;
+
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("The OCR command is empty"))?;
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to start the OCR command {}: {}", program, e))?;
+    let mut stdin = child.stdin.take().unwrap();
+    let input = image.to_vec();
+    let write = tokio::spawn(async move {
+        let _ = stdin.write_all(&input).await;
+    });
+    let output = child.wait_with_output().await?;
+    let _ = write.await;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "The OCR command failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_text() {
+        let content = append_text("Sprint planning #work\n", "board.jpg", "  Goals\n\n- ship v2   \n");
+        assert_eq!(content, "Sprint planning #work\n\nText in `board.jpg`:\n\n> Goals\n>\n> - ship v2");
+        assert!(has_text(&content, "board.jpg"));
+        assert!(!has_text(&content, "receipt.png"));
+    }
+}
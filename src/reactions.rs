@@ -0,0 +1,125 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Reaction counts over a set of memos for `summarize_reactions`, per emoji, per user and per memo,
+// so that questions like which notes got the most 👍 this month take one call. Memos returns the
+// reactions with every memo, so no request per memo is needed.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::memos::service::note::{Note, Reaction};
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ReactionSummary {
+    /// The memos looked at.
+    pub memos: usize,
+    /// The reactions counted.
+    pub reactions: usize,
+    pub by_emoji: BTreeMap<String, usize>,
+    /// By the user who reacted, e.g. `users/2`.
+    pub by_user: BTreeMap<String, usize>,
+    /// The memos with the most reactions, most first.
+    pub top_memos: Vec<MemoReactions>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MemoReactions {
+    pub name: String,
+    pub snippet: String,
+    pub reactions: usize,
+    pub by_emoji: BTreeMap<String, usize>,
+}
+
+/// Which reactions [`summarize`] counts.
+#[derive(Debug, Default)]
+pub struct ReactionFilter {
+    /// Only this emoji, e.g. `👍`.
+    pub emoji: Option<String>,
+    /// Only reactions made at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only reactions made before this time.
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ReactionFilter {
+    fn matches(&self, reaction: &Reaction) -> bool {
+        self.emoji.as_deref().is_none_or(|emoji| reaction.reaction_type == emoji)
+            && self.since.is_none_or(|since| reaction.create_time.is_some_and(|time| time >= since))
+            && self.until.is_none_or(|until| reaction.create_time.is_some_and(|time| time < until))
+    }
+}
+
+/// The reactions on `notes` that `filter` lets through, with the `top` memos that got the most.
+pub fn summarize(notes: &[Note], filter: &ReactionFilter, top: usize) -> ReactionSummary {
+    let mut summary = ReactionSummary { memos: notes.len(), ..Default::default() };
+    for note in notes {
+        let mut by_emoji = BTreeMap::new();
+        let mut count = 0;
+        for reaction in note.reactions.iter().filter(|reaction| filter.matches(reaction)) {
+            count += 1;
+            *by_emoji.entry(reaction.reaction_type.clone()).or_default() += 1;
+            *summary.by_emoji.entry(reaction.reaction_type.clone()).or_default() += 1;
+            *summary.by_user.entry(reaction.creator.clone().unwrap_or_default()).or_default() += 1;
+        }
+        summary.reactions += count;
+        if count > 0 {
+            summary.top_memos.push(MemoReactions {
+                name: note.name.clone().unwrap_or_default(),
+                snippet: note.snippet.clone(),
+                reactions: count,
+                by_emoji,
+            });
+        }
+    }
+    // Stable, so memos with as many reactions keep the order they were listed in.
+    summary.top_memos.sort_by_key(|memo| std::cmp::Reverse(memo.reactions));
+    summary.top_memos.truncate(top);
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn note(name: &str, reactions: serde_json::Value) -> Note {
+        serde_json::from_value(json!({"name": name, "state": "NORMAL", "content": name, "reactions": reactions})).unwrap()
+    }
+
+    fn reaction(creator: &str, emoji: &str, time: &str) -> serde_json::Value {
+        json!({"creator": creator, "contentId": "", "reactionType": emoji, "createTime": time})
+    }
+
+    #[test]
+    fn test_summarize() {
+        let notes = [
+            note("memos/1", json!([reaction("users/2", "👍", "2025-03-02T10:00:00Z"), reaction("users/3", "🎉", "2025-03-03T10:00:00Z")])),
+            note("memos/2", json!([])),
+            note("memos/3", json!([
+                reaction("users/2", "👍", "2025-03-04T10:00:00Z"),
+                reaction("users/3", "👍", "2025-03-05T10:00:00Z"),
+                reaction("users/3", "👍", "2025-02-05T10:00:00Z"),
+            ])),
+        ];
+        let summary = summarize(&notes, &ReactionFilter::default(), 10);
+        assert_eq!((summary.memos, summary.reactions), (3, 5));
+        assert_eq!(summary.by_emoji, BTreeMap::from([("🎉".to_string(), 1), ("👍".to_string(), 4)]));
+        assert_eq!(summary.by_user, BTreeMap::from([("users/2".to_string(), 2), ("users/3".to_string(), 3)]));
+        assert_eq!(summary.top_memos.iter().map(|memo| (memo.name.as_str(), memo.reactions)).collect::<Vec<_>>(), [("memos/3", 3), ("memos/1", 2)]);
+
+        let march = ReactionFilter {
+            emoji: Some("👍".to_string()),
+            since: Some("2025-03-01T00:00:00Z".parse().unwrap()),
+            until: Some("2025-04-01T00:00:00Z".parse().unwrap()),
+        };
+        let summary = summarize(&notes, &march, 1);
+        assert_eq!(summary.reactions, 3);
+        assert_eq!(summary.top_memos.iter().map(|memo| (memo.name.as_str(), memo.reactions)).collect::<Vec<_>>(), [("memos/3", 2)]);
+    }
+}
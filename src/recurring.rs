@@ -0,0 +1,171 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Memos created from templates on a cron schedule, e.g. a planning note every Monday morning. The
+// last run of each entry is kept in a JSON state file, so a run missed while the server was down is
+// made up once at startup. New entries start from the time they are first seen, never from the past.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::backup::schedule::parse_schedule;
+use crate::config::{RecurringMemo, RecurringSettings, SharedSettings};
+use crate::memos::{Servers, service::note::Note};
+
+/// How often the scheduler looks for due entries, and at the settings again after a reload.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The time each entry last ran, by entry name.
+type LastRuns = BTreeMap<String, DateTime<Utc>>;
+
+/// Creates the memos of the configured entries whenever they are due. Idles while none are
+/// configured, and follows changes made by a configuration reload.
+pub async fn run(servers: Arc<Servers>, settings: SharedSettings) {
+    loop {
+        let recurring = settings.read().unwrap().recurring.clone();
+        if let Some(recurring) = recurring
+            && let Err(e) = run_due(&servers, &recurring, Utc::now()).await
+        {
+            warn!("Recurring memos failed: {}", e);
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Creates a memo for every entry due at `now` and records the run. Returns the names of the
+/// created memos.
+pub async fn run_due(servers: &Servers, settings: &RecurringSettings, now: DateTime<Utc>) -> Result<Vec<String>> {
+    let mut last_runs = load(&settings.state_file)?;
+    let mut changed = false;
+    let mut created = Vec::new();
+    for entry in &settings.memos {
+        let Some(last_run) = last_runs.get(&entry.name).copied() else {
+            last_runs.insert(entry.name.clone(), now);
+            changed = true;
+            continue;
+        };
+        let schedule = match parse_schedule(&entry.schedule) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                warn!("Skipping recurring memo {}: {}", entry.name, e);
+                continue;
+            }
+        };
+        // Only the latest of several missed runs is made up.
+        let Some(due) = schedule.after(&last_run).take_while(|time| *time <= now).last() else {
+            continue;
+        };
+        match create(servers, entry, due).await {
+            Ok(note) => {
+                let name = note.name.unwrap_or_default();
+                info!("Created recurring memo {} as {}", entry.name, name);
+                created.push(name);
+            }
+            // Tried again at the next check.
+            Err(e) => {
+                warn!("Failed to create recurring memo {}: {}", entry.name, e);
+                continue;
+            }
+        }
+        last_runs.insert(entry.name.clone(), now);
+        changed = true;
+    }
+    if changed {
+        save(&settings.state_file, &last_runs)?;
+    }
+    Ok(created)
+}
+
+async fn create(servers: &Servers, entry: &RecurringMemo, due: DateTime<Utc>) -> Result<Note> {
+    let server = servers.get(entry.server.as_deref())?;
+    let mut note = Note::new(&render(&entry.content, due));
+    if let Some(visibility) = &entry.visibility {
+        note.visibility = visibility.clone();
+    }
+    server.create_note(&note).await
+}
+
+/// Fills in the `{date}`, `{time}`, `{weekday}` and `{week}` placeholders of a template with the
+/// time the memo was due, in UTC.
+fn render(template: &str, due: DateTime<Utc>) -> String {
+    template
+        .replace("{date}", &due.format("%Y-%m-%d").to_string())
+        .replace("{time}", &due.format("%H:%M").to_string())
+        .replace("{weekday}", &due.format("%A").to_string())
+        .replace("{week}", &due.format("%V").to_string())
+}
+
+fn load(path: &Path) -> Result<LastRuns> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Failed to parse recurring memo state {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LastRuns::new()),
+        Err(e) => Err(anyhow::anyhow!("Failed to read recurring memo state {}: {}", path.display(), e)),
+    }
+}
+
+fn save(path: &Path, last_runs: &LastRuns) -> Result<()> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, serde_json::to_string_pretty(last_runs)?)?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memos::{mock, service::note::Visibility};
+
+    #[test]
+    fn test_render() {
+        let due = "2025-02-03T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(render("# Plan {weekday} {date} {time} (week {week})", due), "# Plan Monday 2025-02-03 08:00 (week 06)");
+    }
+
+    #[tokio::test]
+    async fn test_run_due() {
+        let (mock, server) = mock::start().await;
+        mock::mount_memos_api(&mock).await;
+        let servers = Servers::new("default", BTreeMap::from([("default".to_string(), server)]));
+        let state_file = std::env::temp_dir().join(format!("mcp-memos-recurring-{}.json", std::process::id()));
+        let settings = RecurringSettings {
+            state_file: state_file.clone(),
+            memos: vec![RecurringMemo {
+                name: "planning".to_string(),
+                schedule: "0 8 * * MON".to_string(),
+                server: None,
+                content: "# Planning {date} #weekly".to_string(),
+                visibility: Some(Visibility::Protected),
+            }],
+        };
+        let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+
+        // Starts from the first time the entry is seen.
+        assert!(run_due(&servers, &settings, at("2025-01-31T08:00:00Z")).await.unwrap().is_empty());
+        assert!(run_due(&servers, &settings, at("2025-02-03T07:59:00Z")).await.unwrap().is_empty());
+        assert_eq!(run_due(&servers, &settings, at("2025-02-03T08:00:30Z")).await.unwrap(), ["memos/3"]);
+        assert!(run_due(&servers, &settings, at("2025-02-03T08:01:00Z")).await.unwrap().is_empty());
+        // Two missed Mondays make one memo, for the latest.
+        assert_eq!(run_due(&servers, &settings, at("2025-02-18T09:00:00Z")).await.unwrap(), ["memos/3"]);
+
+        let posts = mock.received_requests().await.unwrap()
+            .into_iter()
+            .filter(|request| request.method.as_str() == "POST")
+            .map(|request| request.body_json::<serde_json::Value>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(posts[0]["content"], "# Planning 2025-02-03 #weekly");
+        assert_eq!(posts[0]["visibility"], "PROTECTED");
+        assert_eq!(posts[1]["content"], "# Planning 2025-02-17 #weekly");
+
+        std::fs::remove_file(&state_file).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Rendering of memos to a standalone HTML document for sharing outside the Memos UI, and to PDF
+// through an external HTML converter with the `pdf` feature. The Markdown renderer covers what
+// memos use: headings, paragraphs, lists and task lists, block quotes, fenced code, rules,
+// emphasis, code spans, links, images and tags. Raw HTML in memos is escaped, never passed through.
+
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+use crate::memos::service::note::Note;
+
+static INLINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"`(?P<code>[^`]+)`",
+        r"|!\[(?P<alt>[^\]]*)\]\((?P<src>[^)\s]+)\)",
+        r"|\[(?P<label>[^\]]+)\]\((?P<href>[^)\s]+)\)",
+        r"|\*\*(?P<strong>[^*]+)\*\*",
+        r"|~~(?P<strike>[^~]+)~~",
+        r"|\*(?P<em>[^*]+)\*",
+        r"|(?P<url>https?://[^\s<]+[^\s<.,;:!?)])",
+        r"|\B#(?P<tag>[\p{L}\p{N}_/-]+)",
+    ))
+    .unwrap()
+});
+static ORDERED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d+[.)]\s+").unwrap());
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:46rem;margin:2rem auto;padding:0 1rem;line-height:1.55;color:#222}\
+article{border-bottom:1px solid #ddd;padding-bottom:1.5rem;margin-bottom:1.5rem}\
+time{color:#777;font-size:.85rem}\
+pre{background:#f5f5f5;padding:.75rem;overflow-x:auto}\
+code{font-family:ui-monospace,monospace;font-size:.9em}\
+blockquote{border-left:3px solid #ccc;margin-left:0;padding-left:1rem;color:#555}\
+.tag{color:#2a6ebb}\
+li.task{list-style:none}\
+img{max-width:100%}";
+
+/// A standalone HTML document of `notes`, one `<article>` each, titled `title`.
+pub fn html_document(title: &str, notes: &[Note]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(title),
+        STYLE
+    );
+    for note in notes {
+        html.push_str("<article>\n");
+        if let Some(created) = note.create_time {
+            html.push_str(&format!(
+                "<time datetime=\"{}\">{}</time>\n",
+                created.to_rfc3339(),
+                created.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+        html.push_str(&markdown_to_html(&note.content));
+        html.push_str("</article>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Renders the Markdown of a memo to HTML.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph = Vec::<&str>::new();
+    let mut list: Option<&str> = None;
+    let mut lines = markdown.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        let item = list_item(trimmed);
+        if trimmed.is_empty() || item.is_some() || is_block_start(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+        }
+        if item.is_none_or(|(tag, _)| Some(tag) != list) && let Some(tag) = list.take() {
+            html.push_str(&format!("</{}>\n", tag));
+        }
+
+        if let Some((tag, text)) = item {
+            if list.is_none() {
+                html.push_str(&format!("<{}>\n", tag));
+                list = Some(tag);
+            }
+            html.push_str(&task_item(text));
+        } else if let Some(fence) = trimmed.strip_prefix("```") {
+            let language = fence.trim();
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+            let class = if language.is_empty() { String::new() } else { format!(" class=\"language-{}\"", escape(language)) };
+            html.push_str(&format!("<pre><code{}>{}</code></pre>\n", class, escape(&code.join("\n"))));
+        } else if let Some((level, text)) = heading(trimmed) {
+            html.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(text)));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            let mut quoted = vec![quote.trim()];
+            while let Some(next) = lines.peek().and_then(|line| line.trim().strip_prefix('>')) {
+                quoted.push(next.trim());
+                lines.next();
+            }
+            html.push_str(&format!("<blockquote>\n{}</blockquote>\n", markdown_to_html(&quoted.join("\n"))));
+        } else if is_rule(trimmed) {
+            html.push_str("<hr>\n");
+        } else if !trimmed.is_empty() {
+            paragraph.push(trimmed);
+        }
+    }
+    flush_paragraph(&mut html, &mut paragraph);
+    if let Some(tag) = list {
+        html.push_str(&format!("</{}>\n", tag));
+    }
+    html
+}
+
+fn flush_paragraph(html: &mut String, paragraph: &mut Vec<&str>) {
+    if !paragraph.is_empty() {
+        let lines = paragraph.drain(..).map(inline).collect::<Vec<_>>();
+        html.push_str(&format!("<p>{}</p>\n", lines.join("<br>\n")));
+    }
+}
+
+fn is_block_start(line: &str) -> bool {
+    line.starts_with("```") || line.starts_with('>') || heading(line).is_some() || is_rule(line)
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact = line.replace(' ', "");
+    compact.len() >= 3 && ["-", "*", "_"].iter().any(|c| compact.chars().all(|d| d.to_string() == *c))
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+/// The list element and text of a list item line.
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    if let Some(text) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ ")) {
+        return Some(("ul", text));
+    }
+    ORDERED.find(line).map(|marker| ("ol", &line[marker.end()..]))
+}
+
+fn task_item(text: &str) -> String {
+    let checkbox = |checked: &str, rest: &str| {
+        format!("<li class=\"task\"><input type=\"checkbox\" disabled{}> {}</li>\n", checked, inline(rest.trim_start()))
+    };
+    if let Some(rest) = text.strip_prefix("[ ]") {
+        checkbox("", rest)
+    } else if let Some(rest) = text.strip_prefix("[x]").or_else(|| text.strip_prefix("[X]")) {
+        checkbox(" checked", rest)
+    } else {
+        format!("<li>{}</li>\n", inline(text))
+    }
+}
+
+/// Renders the inline Markdown of a line, escaping everything else.
+fn inline(text: &str) -> String {
+    let mut html = String::new();
+    let mut last = 0;
+    for captures in INLINE.captures_iter(text) {
+        let whole = captures.get(0).unwrap();
+        html.push_str(&escape(&text[last..whole.start()]));
+        html.push_str(&inline_element(&captures));
+        last = whole.end();
+    }
+    html.push_str(&escape(&text[last..]));
+    html
+}
+
+fn inline_element(captures: &Captures) -> String {
+    let group = |name: &str| captures.name(name).map(|m| m.as_str());
+    if let Some(code) = group("code") {
+        format!("<code>{}</code>", escape(code))
+    } else if let (Some(alt), Some(src)) = (group("alt"), group("src")) {
+        format!("<img src=\"{}\" alt=\"{}\">", escape(&safe_url(src)), escape(alt))
+    } else if let (Some(label), Some(href)) = (group("label"), group("href")) {
+        format!("<a href=\"{}\">{}</a>", escape(&safe_url(href)), inline(label))
+    } else if let Some(strong) = group("strong") {
+        format!("<strong>{}</strong>", inline(strong))
+    } else if let Some(strike) = group("strike") {
+        format!("<del>{}</del>", inline(strike))
+    } else if let Some(em) = group("em") {
+        format!("<em>{}</em>", inline(em))
+    } else if let Some(url) = group("url") {
+        format!("<a href=\"{0}\">{0}</a>", escape(url))
+    } else if let Some(tag) = group("tag") {
+        format!("<span class=\"tag\">#{}</span>", escape(tag))
+    } else {
+        escape(captures.get(0).unwrap().as_str())
+    }
+}
+
+/// Drops URLs with schemes that run code when opened, such as `javascript:`.
+fn safe_url(url: &str) -> String {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme.to_ascii_lowercase());
+    match scheme.as_deref() {
+        None | Some("http" | "https" | "mailto") => url.to_string(),
+        Some(scheme) if scheme.contains('/') || scheme.contains('#') || scheme.contains('?') => url.to_string(),
+        Some(_) => "#".to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Converts `html` to PDF with `command`, a program and its arguments that read HTML on stdin and
+/// write the PDF to stdout, e.g. `["wkhtmltopdf", "--quiet", "-", "-"]`.
+#[cfg(feature = "pdf")]
+pub async fn html_to_pdf(command: &[String], html: &str) -> anyhow::Result<Vec<u8>> {
+    use std::process::Stdio;
+    use tokio::io::AsyncWriteExt;
+
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("The PDF renderer command is empty"))?;
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to start the PDF renderer {}: {}", program, e))?;
+    let mut stdin = child.stdin.take().unwrap();
+    let input = html.as_bytes().to_vec();
+    let write = tokio::spawn(async move {
+        let _ = stdin.write_all(&input).await;
+    });
+    let output = child.wait_with_output().await?;
+    let _ = write.await;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "The PDF renderer failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_html() {
+        let markdown = "# Plan <b>\nBuy **seeds** and *soil*, see [guide](https://example.com/a?b=1&c) #garden\nhttps://example.com/x.\n\n- [ ] water\n- [x] sow\n1. first\n\n> quoted `code`\n\n```rust\nlet x = 1 < 2;\n```\n---\n[bad](javascript:alert(1))";
+        let html = markdown_to_html(markdown);
+        assert_eq!(
+            html,
+            "<h1>Plan &lt;b&gt;</h1>\n\
+             <p>Buy <strong>seeds</strong> and <em>soil</em>, see <a href=\"https://example.com/a?b=1&amp;c\">guide</a> <span class=\"tag\">#garden</span><br>\n\
+             <a href=\"https://example.com/x\">https://example.com/x</a>.</p>\n\
+             <ul>\n<li class=\"task\"><input type=\"checkbox\" disabled> water</li>\n<li class=\"task\"><input type=\"checkbox\" disabled checked> sow</li>\n</ul>\n\
+             <ol>\n<li>first</li>\n</ol>\n\
+             <blockquote>\n<p>quoted <code>code</code></p>\n</blockquote>\n\
+             <pre><code class=\"language-rust\">let x = 1 &lt; 2;</code></pre>\n\
+             <hr>\n\
+             <p><a href=\"#\">bad</a>)</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_html_document() {
+        let note: Note = serde_json::from_value(crate::memos::mock::memo("memos/1", "Hello")).unwrap();
+        let html = html_document("Notes & more", &[note]);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Notes &amp; more</title>"));
+        assert!(html.contains("<time datetime=\"2025-01-31T08:00:00+00:00\">2025-01-31 08:00 UTC</time>\n<p>Hello</p>"));
+    }
+}
@@ -0,0 +1,156 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// The review of the memos created or updated over the past days, built by `generate_weekly_review`:
+// how many there were, the tags used most, the pinned memos and the tasks completed, as a Markdown
+// report that can be saved as a memo tagged `#review`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::digest::DIGEST_TAG;
+use crate::memos::service::note::Note;
+use crate::tasks::{self, Task};
+
+/// Tag of the memos saved from a review, which later reviews leave out.
+pub const REVIEW_TAG: &str = "review";
+
+/// Number of most used tags in the report.
+const TOP_TAGS: usize = 10;
+
+#[derive(Debug)]
+pub struct Review {
+    /// In the time zone of the user, which the dates of the report are in.
+    pub since: DateTime<Tz>,
+    pub until: DateTime<Tz>,
+    pub created: usize,
+    pub updated: usize,
+    /// The most used tags with their number of memos, most used first.
+    pub tags: Vec<(String, usize)>,
+    /// Names and titles of the pinned memos.
+    pub pinned: Vec<(String, String)>,
+    pub completed: Vec<Task>,
+    pub open: usize,
+}
+
+impl Review {
+    /// The review of `notes` changed in the `days` days up to `now`, an error when the window
+    /// starts out of range.
+    pub fn new(notes: &[Note], now: DateTime<Tz>, days: u32) -> Result<Self> {
+        let since = crate::dates::days_before(now.to_utc(), days)?.with_timezone(&now.timezone());
+        let in_window = |time: Option<DateTime<Utc>>| time.is_some_and(|time| time >= since && time <= now);
+        let notes = notes
+            .iter()
+            .filter(|note| !note.tags.iter().any(|tag| tag == REVIEW_TAG || tag == DIGEST_TAG) && !tasks::is_open_tasks(note))
+            .filter(|note| in_window(note.create_time) || in_window(note.update_time))
+            .collect::<Vec<_>>();
+
+        let created = notes.iter().filter(|note| in_window(note.create_time)).count();
+        let mut tags = BTreeMap::<&str, usize>::new();
+        for tag in notes.iter().flat_map(|note| &note.tags) {
+            *tags.entry(tag).or_default() += 1;
+        }
+        let mut tags = tags.into_iter().map(|(tag, count)| (tag.to_string(), count)).collect::<Vec<_>>();
+        tags.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        tags.truncate(TOP_TAGS);
+        let (completed, open): (Vec<_>, Vec<_>) = notes.iter().flat_map(|note| tasks::tasks(note)).partition(|task| task.done);
+
+        Ok(Review {
+            since,
+            until: now,
+            created,
+            updated: notes.len() - created,
+            tags,
+            pinned: notes
+                .iter()
+                .filter(|note| note.pinned)
+                .map(|note| (note.name.clone().unwrap_or_default(), crate::vault::title(note)))
+                .collect(),
+            completed,
+            open: open.len(),
+        })
+    }
+
+    pub fn markdown(&self) -> String {
+        let mut report = format!(
+            "# Review {} to {}\n\n",
+            self.since.format("%Y-%m-%d"),
+            self.until.format("%Y-%m-%d"),
+        );
+        let _ = writeln!(report, "- Memos created: {}", self.created);
+        let _ = writeln!(report, "- Memos updated: {}", self.updated);
+        let _ = writeln!(report, "- Tasks completed: {}", self.completed.len());
+        let _ = writeln!(report, "- Tasks still open: {}", self.open);
+
+        if !self.tags.is_empty() {
+            report.push_str("\n## Tags\n\n");
+            for (tag, count) in &self.tags {
+                let _ = writeln!(report, "- `{}`: {}", tag, count);
+            }
+        }
+        if !self.pinned.is_empty() {
+            report.push_str("\n## Pinned\n\n");
+            for (name, title) in &self.pinned {
+                let _ = writeln!(report, "- [{}](/{})", title, name);
+            }
+        }
+        if !self.completed.is_empty() {
+            report.push_str("\n## Completed\n\n");
+            for task in &self.completed {
+                let _ = writeln!(report, "- [x] {} ([{}](/{}))", task.text, task.memo, task.memo);
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[test]
+    fn test_review() {
+        let now = "2025-02-03T08:00:00Z".parse::<DateTime<Utc>>().unwrap().with_timezone(&Tz::UTC);
+        let memo = |name: &str, content: &str, tags: &[&str], created: &str| {
+            let mut memo = mock::memo(name, content);
+            memo["tags"] = json!(tags);
+            memo["createTime"] = json!(created);
+            memo["updateTime"] = json!(created);
+            memo
+        };
+        let mut pinned = memo("memos/1", "# Plan\n- [x] Ship it\n- [ ] Write docs", &["work"], "2025-02-01T10:00:00Z");
+        pinned["pinned"] = json!(true);
+        let mut edited = memo("memos/2", "Lunch ideas #food", &["food", "work"], "2025-01-10T10:00:00Z");
+        edited["updateTime"] = json!("2025-02-02T10:00:00Z");
+        let notes = [
+            pinned,
+            edited,
+            memo("memos/3", "Old", &["work"], "2025-01-10T10:00:00Z"),
+            memo("memos/4", "# Review", &["review"], "2025-02-02T10:00:00Z"),
+        ].map(|memo| serde_json::from_value::<Note>(memo).unwrap());
+
+        let review = Review::new(&notes, now, 7).unwrap();
+        assert_eq!((review.created, review.updated, review.open), (1, 1, 1));
+        assert_eq!(review.tags, [("work".to_string(), 2), ("food".to_string(), 1)]);
+        assert_eq!(review.pinned, [("memos/1".to_string(), "Plan".to_string())]);
+
+        let report = review.markdown();
+        assert!(report.starts_with("# Review 2025-01-27 to 2025-02-03\n"));
+        assert!(report.contains("- Tasks completed: 1\n"));
+        assert!(report.contains("- [x] Ship it ([memos/1](/memos/1))\n"));
+
+        // The evening of 3 February in New York is already the 4th in UTC.
+        let evening = "2025-02-04T02:00:00Z".parse::<DateTime<Utc>>().unwrap().with_timezone(&"America/New_York".parse().unwrap());
+        assert!(Review::new(&notes, evening, 7).unwrap().markdown().starts_with("# Review 2025-01-27 to 2025-02-03\n"));
+        assert!(Review::new(&notes, now, u32::MAX).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Rotation of the personal access token of a server before it expires. The token in use is checked
+// every hour, and once its expiry is within the configured number of days a replacement with the
+// same lifetime is created. The replacement is tried, written to the token file so that restarts
+// pick it up, swapped in for every clone of the server at once, and only then is the old token
+// revoked. Memos does not say which token a request was made with, so before the first rotation the
+// token in use is taken to be the newest one rotation created, else the one used last. A token only
+// guessed by its last use may belong to another client, so it is left for the user to revoke.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::memos::Server;
+use crate::memos::service::auth::AuthService;
+use crate::memos::service::user::{Token, UserService};
+
+/// Time between checks of the expiry of the token.
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Description of the tokens created by rotation.
+const DESCRIPTION: &str = "mcp-memo (rotated)";
+
+/// The rotation of the token of one server.
+#[derive(Debug)]
+pub struct Rotation {
+    token_file: PathBuf,
+    before: chrono::Duration,
+    /// The name of the token in use, once the first rotation made it.
+    current: Option<String>,
+}
+
+impl Rotation {
+    /// Rotates the token in `token_file` once it expires within `days` days.
+    pub fn new(token_file: PathBuf, days: u32) -> Self {
+        Rotation { token_file, before: chrono::Duration::days(days.into()), current: None }
+    }
+
+    /// Checks the token of `server` every hour and rotates it when it is due, for good.
+    pub async fn run(mut self, name: String, server: Server) {
+        loop {
+            match self.check(&server).await {
+                Ok(Some(token)) => info!("Rotated the access token of server {}, the new one expires at {:?}", name, token.expires_at),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to rotate the access token of server {}: {}", name, e),
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Rotates the token of `server` if it is due, returning the new token.
+    pub async fn check(&mut self, server: &Server) -> Result<Option<Token>> {
+        let me = server.get_current_user().await?;
+        let tokens = server.list_pats(&me.name).await?;
+        let rotated = tokens.iter().filter(|token| token.description == DESCRIPTION).max_by_key(|token| token.created_at).cloned();
+        // Whether the token is known to be the one of this server, and so safe to revoke.
+        let (current, ours) = match (&self.current, rotated) {
+            (Some(name), _) => (tokens.into_iter().find(|token| &token.name == name), true),
+            (None, Some(rotated)) => (Some(rotated), true),
+            (None, None) => (tokens.into_iter().filter(|token| token.last_used_at.is_some()).max_by_key(|token| token.last_used_at), false),
+        };
+        let current = current.ok_or_else(|| anyhow::anyhow!("Cannot tell which access token of {} is in use", me.name))?;
+        let Some(expires_at) = current.expires_at else {
+            return Ok(None);
+        };
+        if expires_at - Utc::now() > self.before {
+            return Ok(None);
+        }
+
+        let days = (expires_at - current.created_at).num_days().max(1) as u32;
+        let (token, secret) = server.create_pat(&me, DESCRIPTION, days).await?;
+        let replacement = server.with_token(&secret);
+        if let Err(e) = replacement.get_current_user().await {
+            if let Err(e) = server.delete_pat(&token).await {
+                warn!("Failed to revoke the unusable access token {}: {}", token.name, e);
+            }
+            return Err(anyhow::anyhow!("The new access token does not work: {}", e));
+        }
+        write_token_file(&self.token_file, &secret)?;
+        server.set_token(&secret);
+        self.current = Some(token.name.clone());
+        if !ours {
+            warn!(
+                "Replaced the access token {} ({}) of {}, which was taken to be the one in use as it was used last. \
+                 It is not revoked in case another client uses it; revoke it once it is unused",
+                current.name, current.description, me.name,
+            );
+        } else if let Err(e) = server.delete_pat(&current).await {
+            warn!("Failed to revoke the replaced access token {}: {}", current.name, e);
+        }
+        Ok(Some(token))
+    }
+}
+
+/// Replaces the content of `path` with `token` in one step, so a reader never sees half of it. The
+/// file keeps its permissions, and a new one is readable by its owner only.
+fn write_token_file(path: &Path, token: &str) -> Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let write = || -> std::io::Result<()> {
+        let _ = std::fs::remove_file(&partial);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&partial)?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(format!("{}\n", token).as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&partial, path)
+    };
+    write().map_err(|e| anyhow::anyhow!("Failed to write the new access token to {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[tokio::test]
+    async fn test_check() {
+        let (mock, server) = mock::start().await;
+        let mut expiring = mock::personal_access_token("users/1/personalAccessTokens/7", "Laptop");
+        expiring["createdAt"] = json!((Utc::now() - chrono::Duration::days(85)).to_rfc3339());
+        expiring["expiresAt"] = json!((Utc::now() + chrono::Duration::days(5)).to_rfc3339());
+        expiring["lastUsedAt"] = json!(Utc::now().to_rfc3339());
+        let mut unused = mock::personal_access_token("users/1/personalAccessTokens/8", "Phone");
+        unused["expiresAt"] = json!((Utc::now() + chrono::Duration::days(1)).to_rfc3339());
+        let mut rotated = mock::personal_access_token("users/1/personalAccessTokens/5", DESCRIPTION);
+        (rotated["createdAt"], rotated["expiresAt"]) = (expiring["createdAt"].clone(), expiring["expiresAt"].clone());
+        for tokens in [json!([unused, expiring]), json!([unused, expiring, rotated])] {
+            Mock::given(method("GET")).and(path("/api/v1/users/1/personalAccessTokens"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({"personalAccessTokens": tokens})))
+                .up_to_n_times(1)
+                .mount(&mock).await;
+        }
+        // A token only guessed to be in use by its last use is left alone.
+        Mock::given(method("DELETE")).and(path("/api/v1/users/1/personalAccessTokens/7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(0)
+            .mount(&mock).await;
+        // One an earlier rotation created is revoked, with the new token.
+        Mock::given(method("DELETE")).and(path("/api/v1/users/1/personalAccessTokens/5"))
+            .and(header("Authorization", "Bearer memos_pat_created"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock).await;
+        mock::mount_memos_api(&mock).await;
+
+        let token_file = std::env::temp_dir().join(format!("mcp-memos-token-{}", std::process::id()));
+        std::fs::write(&token_file, mock::TOKEN).unwrap();
+        let mut rotation = Rotation::new(token_file.clone(), 30);
+        let token = rotation.check(&server).await.unwrap().expect("The token is due");
+        assert_eq!(token.name, "users/1/personalAccessTokens/1");
+        assert_eq!(std::fs::read_to_string(&token_file).unwrap(), "memos_pat_created\n");
+        assert_eq!(rotation.current.as_deref(), Some("users/1/personalAccessTokens/1"));
+
+        // After a restart the token rotation created last is the one in use.
+        server.set_token(mock::TOKEN);
+        assert!(Rotation::new(token_file.clone(), 30).check(&server).await.unwrap().is_some());
+
+        // Not due with more time left than the window.
+        let mut rotation = Rotation::new(token_file, 3);
+        Mock::given(method("GET")).and(path("/api/v1/users/1/personalAccessTokens"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"personalAccessTokens": [
+                {"name": "users/1/personalAccessTokens/1", "description": DESCRIPTION, "createdAt": Utc::now(),
+                 "expiresAt": Utc::now() + chrono::Duration::days(80), "lastUsedAt": Utc::now()},
+            ]})))
+            .mount(&mock).await;
+        assert!(rotation.check(&server).await.unwrap().is_none());
+        let _ = std::fs::remove_file(rotation.token_file);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_token_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let path = std::env::temp_dir().join(format!("mcp-memos-token-mode-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        write_token_file(&path, "memos_pat_1").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        write_token_file(&path, "memos_pat_2").unwrap();
+        assert_eq!((std::fs::read_to_string(&path).unwrap(), mode(&path)), ("memos_pat_2\n".to_string(), 0o640));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,244 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::Serialize;
+use tantivy::{
+    DocAddress, Index, IndexReader, IndexWriter, ReloadPolicy, Score, TantivyDocument, Term,
+    collector::TopDocs,
+    doc,
+    query::{BooleanQuery, MoreLikeThisQuery, Occur, Query, QueryParser, TermQuery},
+    schema::{Field, IndexRecordOption, STORED, STRING, Schema, TEXT, Value},
+    snippet::SnippetGenerator,
+};
+
+use crate::memos::service::note::Note;
+
+const WRITER_MEMORY: usize = 15_000_000;
+pub const SNIPPET_CHARS: usize = 200;
+
+/// In-memory full-text index over memo content and tags of every server.
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    key: Field,
+    server: Field,
+    name: Field,
+    content: Field,
+    tags: Field,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub name: String,
+    pub score: Score,
+    /// Best matching fragment with the matched words in `**bold**`, or the start of the memo
+    /// for related memos.
+    pub snippet: String,
+}
+
+impl SearchIndex {
+    pub fn new() -> Result<Self> {
+        let mut schema = Schema::builder();
+        let key = schema.add_text_field("key", STRING);
+        let server = schema.add_text_field("server", STRING);
+        let name = schema.add_text_field("name", STRING | STORED);
+        let content = schema.add_text_field("content", TEXT | STORED);
+        let tags = schema.add_text_field("tags", TEXT);
+
+        let index = Index::create_in_ram(schema.build());
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        let reader = index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into()?;
+
+        Ok(SearchIndex {
+            index,
+            reader,
+            writer: Mutex::new(writer),
+            key,
+            server,
+            name,
+            content,
+            tags,
+        })
+    }
+
+    /// Adds or replaces `notes` of `server`. With `replace` all other memos of the server are dropped.
+    pub fn index(&self, server: &str, notes: &[Note], replace: bool) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        if replace {
+            writer.delete_term(Term::from_field_text(self.server, server));
+        }
+        for note in notes {
+            let Some(name) = &note.name else {
+                continue;
+            };
+            let key = format!("{}\n{}", server, name);
+            writer.delete_term(Term::from_field_text(self.key, &key));
+
+            writer.add_document(doc!(
+                self.key => key,
+                self.server => server,
+                self.name => name.as_str(),
+                self.content => note.content.as_str(),
+                self.tags => note.tags.join(" "),
+            ))?;
+        }
+        writer.commit()?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Drops `names` of `server`.
+    pub fn remove(&self, server: &str, names: &[&str]) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        for name in names {
+            writer.delete_term(Term::from_field_text(self.key, &format!("{}\n{}", server, name)));
+        }
+        writer.commit()?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Ranks the memos of `server` against `query`, which supports `"exact phrases"`,
+    /// `+required`/`-excluded` words and `tags:name`.
+    pub fn search(&self, server: &str, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        // TopDocs panics on a limit of 0.
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let parser = QueryParser::for_index(&self.index, vec![self.content, self.tags]);
+        let (parsed, _) = parser.parse_query_lenient(query);
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(TermQuery::new(
+                Term::from_field_text(self.server, server),
+                IndexRecordOption::Basic,
+            )) as Box<dyn Query>),
+            (Occur::Must, parsed),
+        ]);
+
+        let searcher = self.reader.searcher();
+        let mut snippets = SnippetGenerator::create(&searcher, &query, self.content)?;
+        snippets.set_max_num_chars(SNIPPET_CHARS);
+
+        let top: Vec<(Score, DocAddress)> = searcher.search(&query, &TopDocs::with_limit(limit))?;
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let snippet = snippets.snippet_from_doc(&doc);
+            hits.push(SearchHit {
+                name: self.stored_text(&doc, self.name),
+                score,
+                snippet: highlight(snippet.fragment(), snippet.highlighted()),
+            });
+        }
+        Ok(hits)
+    }
+
+    /// Memos of `server` sharing the most distinctive words with memo `name`.
+    pub fn related(&self, server: &str, name: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let searcher = self.reader.searcher();
+        let key = Term::from_field_text(self.key, &format!("{}\n{}", server, name));
+        let found: Vec<(Score, DocAddress)> = searcher.search(
+            &TermQuery::new(key.clone(), IndexRecordOption::Basic),
+            &TopDocs::with_limit(1),
+        )?;
+        let Some((_, address)) = found.first() else {
+            return Err(anyhow::anyhow!("Memo {} is not in the search index", name));
+        };
+
+        let similar = MoreLikeThisQuery::builder()
+            .with_min_doc_frequency(1)
+            .with_min_term_frequency(1)
+            .with_min_word_length(3)
+            .with_document(*address);
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(TermQuery::new(
+                Term::from_field_text(self.server, server),
+                IndexRecordOption::Basic,
+            )) as Box<dyn Query>),
+            (Occur::Must, Box::new(similar)),
+            (Occur::MustNot, Box::new(TermQuery::new(key, IndexRecordOption::Basic))),
+        ]);
+
+        let top: Vec<(Score, DocAddress)> = searcher.search(&query, &TopDocs::with_limit(limit))?;
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            hits.push(SearchHit {
+                name: self.stored_text(&doc, self.name),
+                score,
+                snippet: self.stored_text(&doc, self.content).chars().take(SNIPPET_CHARS).collect(),
+            });
+        }
+        Ok(hits)
+    }
+
+    fn stored_text(&self, doc: &TantivyDocument, field: Field) -> String {
+        doc.get_first(field).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+    }
+}
+
+fn highlight(fragment: &str, ranges: &[std::ops::Range<usize>]) -> String {
+    let mut text = String::with_capacity(fragment.len() + ranges.len() * 4);
+    let mut start = 0;
+    for range in ranges {
+        text.push_str(&fragment[start..range.start]);
+        text.push_str("**");
+        text.push_str(&fragment[range.clone()]);
+        text.push_str("**");
+        start = range.end;
+    }
+    text.push_str(&fragment[start..]);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: &str, content: &str, tags: &[&str]) -> Note {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "state": "NORMAL",
+            "content": content,
+            "tags": tags,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_search_ranks_and_highlights() {
+        let index = SearchIndex::new().unwrap();
+        index.index("default", &[
+            note("memos/1", "The quick brown fox jumps over the lazy dog", &["animals"]),
+            note("memos/2", "A brown paper bag, quick to fold", &[]),
+        ], true).unwrap();
+        index.index("work", &[note("memos/9", "quick brown fox standup notes", &[])], true).unwrap();
+
+        let hits = index.search("default", "\"brown fox\"", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].name, "memos/1");
+        assert!(hits[0].snippet.contains("**brown** **fox**"));
+
+        assert_eq!(index.search("default", "quick brown", 10).unwrap().len(), 2);
+        assert_eq!(index.search("default", "tags:animals", 10).unwrap()[0].name, "memos/1");
+        assert!(index.search("default", "quick", 0).unwrap().is_empty());
+
+        let related = index.related("default", "memos/2", 10).unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].name, "memos/1");
+        assert!(index.related("default", "memos/2", 0).unwrap().is_empty());
+
+        index.index("default", &[note("memos/1", "Renamed to something else", &[])], false).unwrap();
+        assert_eq!(index.search("default", "fox", 10).unwrap().len(), 0);
+        assert_eq!(index.search("work", "fox", 10).unwrap().len(), 1);
+    }
+}
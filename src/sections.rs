@@ -0,0 +1,128 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// The sections of a memo under its Markdown headings, so that tools can read or replace a part of
+// a long memo by heading. A section runs from its heading to the next heading of the same or a
+// higher level, so it includes its subsections. Headings are found in the pulldown-cmark AST, so a
+// `#` in a code block or a `#tag` at the start of a line is not taken for one.
+
+use std::ops::Range;
+
+use anyhow::Result;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// The text of the heading, without the `#`s.
+    pub title: String,
+    pub level: usize,
+    /// Byte range of the heading and the content under it.
+    pub range: Range<usize>,
+    /// Byte range of the content under the heading.
+    pub body: Range<usize>,
+}
+
+/// Every section of `content`, in order.
+pub fn sections(content: &str) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut title = None;
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { .. }) => title = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(title) = &mut title {
+                    title.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let level = level as usize;
+                // Ends the open sections this heading is not nested in.
+                for section in sections.iter_mut().filter(|section| section.range.end == content.len() && section.level >= level) {
+                    section.range.end = range.start;
+                    section.body.end = range.start;
+                }
+                sections.push(Section {
+                    title: title.take().unwrap_or_default().trim().to_string(),
+                    level,
+                    range: range.start..content.len(),
+                    body: range.end..content.len(),
+                });
+            }
+            _ => {}
+        }
+    }
+    sections
+}
+
+/// The first section whose heading is `heading`, ignoring case and leading `#`s.
+pub fn find(content: &str, heading: &str) -> Result<Section> {
+    let wanted = heading.trim().trim_start_matches('#').trim();
+    let sections = sections(content);
+    if let Some(section) = sections.iter().find(|section| section.title.eq_ignore_ascii_case(wanted)) {
+        return Ok(section.clone());
+    }
+    match sections.is_empty() {
+        true => Err(anyhow::anyhow!("The memo has no headings")),
+        false => Err(anyhow::anyhow!(
+            "No heading {:?} in the memo, its headings are {}",
+            wanted,
+            sections.iter().map(|section| format!("{:?}", section.title)).collect::<Vec<_>>().join(", "),
+        )),
+    }
+}
+
+/// `content` with the content under `heading` replaced by `body`. The heading and the rest of the
+/// memo stay as they are.
+pub fn replace(content: &str, heading: &str, body: &str) -> Result<String> {
+    let section = find(content, heading)?;
+    let (before, after) = (&content[..section.body.start], &content[section.body.end..]);
+    let body = body.trim_matches(['\r', '\n']);
+    let mut result = before.to_string();
+    if !result.ends_with('\n') {
+        result.push('\n');
+    }
+    result.push_str(body);
+    match after.is_empty() {
+        true if content.ends_with('\n') => result.push('\n'),
+        true => {}
+        false if body.is_empty() => {}
+        false => result.push_str("\n\n"),
+    }
+    result.push_str(after);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMO: &str = "Intro #plan\n\n# Goals\nShip it.\n\n## Stretch\nDocs.\n\n```\n# not a heading\n```\n## Risks\nNone.\n# Notes\nLast `bit`.\n";
+
+    #[test]
+    fn test_sections() {
+        let sections = sections(MEMO);
+        assert_eq!(sections.iter().map(|s| (s.title.as_str(), s.level)).collect::<Vec<_>>(), [("Goals", 1), ("Stretch", 2), ("Risks", 2), ("Notes", 1)]);
+        assert_eq!(&MEMO[sections[0].body.clone()], "Ship it.\n\n## Stretch\nDocs.\n\n```\n# not a heading\n```\n## Risks\nNone.\n");
+        assert_eq!(&MEMO[sections[1].range.clone()], "## Stretch\nDocs.\n\n```\n# not a heading\n```\n");
+        assert_eq!(&MEMO[sections[3].body.clone()], "Last `bit`.\n");
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find(MEMO, "## stretch").unwrap().title, "Stretch");
+        let error = find(MEMO, "Ideas").unwrap_err().to_string();
+        assert!(error.contains(r#"its headings are "Goals", "Stretch", "Risks", "Notes""#), "{}", error);
+        assert!(find("No headings #tag", "tag").is_err());
+    }
+
+    #[test]
+    fn test_replace() {
+        let replaced = replace(MEMO, "Stretch", "\nDocs and a demo.\n").unwrap();
+        assert!(replaced.contains("## Stretch\nDocs and a demo.\n\n## Risks\nNone.\n# Notes"), "{}", replaced);
+        assert_eq!(replace(MEMO, "notes", "Done.").unwrap(), MEMO.replace("Last `bit`.", "Done."));
+        assert_eq!(replace("# Log", "Log", "- one").unwrap(), "# Log\n- one");
+        assert_eq!(replace("# A\nold\n# B\nb", "A", "").unwrap(), "# A\n# B\nb");
+    }
+}
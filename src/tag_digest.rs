@@ -0,0 +1,77 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// The digest of a tag served as the `memo-tag://{server}/{tag}` resource: the latest memos with the
+// tag, newest first, each with its title, time and snippet, like the entries of a feed. It is
+// rendered from the memos on every read, so a client subscribed to a topic always gets it current.
+
+use std::fmt::Write;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use crate::memos::service::note::Note;
+
+/// Number of latest memos in a digest.
+pub const LATEST: usize = 20;
+
+/// The Memos filter of the memos tagged `tag`.
+pub fn filter(tag: &str) -> String {
+    format!("tag in [{}]", serde_json::Value::from(tag.trim_start_matches('#')))
+}
+
+/// The Markdown digest of the latest of `notes`, the memos matching [`filter`] of `tag`, where `link`
+/// gives the link to a memo by its name. Times are shown in the time zone of `now`.
+pub fn render(tag: &str, notes: &[Note], now: DateTime<Tz>, link: impl Fn(&str) -> String) -> String {
+    let tag = tag.trim_start_matches('#');
+    let mut notes = notes.iter().collect::<Vec<_>>();
+    notes.sort_by_key(|note| std::cmp::Reverse(note.create_time));
+    notes.truncate(LATEST);
+
+    let mut digest = format!("# #{}\n\n", tag);
+    let _ = writeln!(digest, "The {} latest memos tagged #{} as of {}.", notes.len(), tag, now.format("%Y-%m-%d %H:%M %Z"));
+    for note in notes {
+        let name = note.name.as_deref().unwrap_or_default();
+        let _ = writeln!(digest, "\n## [{}]({})\n", crate::vault::title(note), link(name));
+        if let Some(created) = note.create_time {
+            let _ = writeln!(digest, "*{} · {}*\n", created.with_timezone(&now.timezone()).format("%Y-%m-%d %H:%M %Z"), name);
+        }
+        let snippet = match note.snippet.trim() {
+            "" => note.content.trim(),
+            snippet => snippet,
+        };
+        let _ = writeln!(digest, "{}", snippet);
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[test]
+    fn test_render() {
+        let memo = |name: &str, content: &str, tags: &[&str], created: &str| {
+            let mut memo = mock::memo(name, content);
+            (memo["tags"], memo["createTime"]) = (json!(tags), json!(created));
+            serde_json::from_value::<Note>(memo).unwrap()
+        };
+        let notes = [
+            memo("memos/1", "Sprint plan #work", &["work"], "2025-02-01T10:00:00Z"),
+            memo("memos/3", "Retro notes #work", &["work"], "2025-02-02T16:30:00Z"),
+        ];
+        let now = "2025-02-04T08:00:00Z".parse::<DateTime<chrono::Utc>>().unwrap();
+        let digest = render("#work", &notes, now.with_timezone(&Tz::UTC), |name| format!("memo://default/{}", name));
+        assert_eq!(digest, "# #work\n\nThe 2 latest memos tagged #work as of 2025-02-04 08:00 UTC.\n\
+            \n## [Retro notes work](memo://default/memos/3)\n\n*2025-02-02 16:30 UTC · memos/3*\n\nRetro notes #work\n\
+            \n## [Sprint plan work](memo://default/memos/1)\n\n*2025-02-01 10:00 UTC · memos/1*\n\nSprint plan #work\n");
+        let digest = render("work", &notes, now.with_timezone(&"Europe/Berlin".parse().unwrap()), |name| name.to_string());
+        assert!(digest.contains("as of 2025-02-04 09:00 CET.\n") && digest.contains("*2025-02-02 17:30 CET · memos/3*"), "{}", digest);
+        assert_eq!(filter("#work"), r#"tag in ["work"]"#);
+    }
+}
@@ -0,0 +1,94 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::memos::{Servers, service::note::{Note, NoteService}};
+
+/// Tag vocabulary and memo count of one server at the time of the last refresh.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagSummary {
+    pub memos: usize,
+    /// Number of memos using each tag.
+    pub tags: BTreeMap<String, usize>,
+    pub refreshed: DateTime<Utc>,
+}
+
+impl TagSummary {
+    fn from_notes(notes: &[Note]) -> Self {
+        let mut tags = BTreeMap::new();
+        for tag in notes.iter().flat_map(|note| &note.tags) {
+            *tags.entry(tag.clone()).or_default() += 1;
+        }
+        TagSummary {
+            memos: notes.len(),
+            tags,
+            refreshed: Utc::now(),
+        }
+    }
+}
+
+/// Tag summaries of every server, prefetched so tag listings don't page through all memos.
+#[derive(Default)]
+pub struct TagCache {
+    summaries: RwLock<BTreeMap<String, TagSummary>>,
+}
+
+impl TagCache {
+    pub fn get(&self, name: &str) -> Option<TagSummary> {
+        self.summaries.read().unwrap().get(name).cloned()
+    }
+
+    /// Forgets the summary of server `name`, so the next listing fetches the tags again.
+    pub fn invalidate(&self, name: &str) {
+        self.summaries.write().unwrap().remove(name);
+    }
+
+    pub async fn refresh(&self, name: &str, server: &dyn NoteService) -> Result<TagSummary> {
+        let summary = TagSummary::from_notes(&server.list_notes().await?);
+        self.summaries.write().unwrap().insert(name.to_string(), summary.clone());
+        Ok(summary)
+    }
+
+    /// Refreshes the summaries of all servers now and then every `interval`.
+    pub async fn run(self: Arc<Self>, servers: Arc<Servers>, interval: Duration) {
+        loop {
+            for (name, server) in servers.iter() {
+                match self.refresh(name, server).await {
+                    Ok(summary) => tracing::debug!(server = name, memos = summary.memos, tags = summary.tags.len(), "tag index refreshed"),
+                    Err(e) => tracing::warn!("Failed to refresh tag index of server {}: {}", name, e),
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_counts_tags() {
+        let notes = ["#work #ideas", "#ideas", "no tags"]
+            .into_iter()
+            .map(|content| {
+                let tags = content.split_whitespace().filter_map(|word| word.strip_prefix('#')).collect::<Vec<_>>();
+                serde_json::from_value(serde_json::json!({"state": "NORMAL", "content": content, "tags": tags})).unwrap()
+            })
+            .collect::<Vec<Note>>();
+
+        let summary = TagSummary::from_notes(&notes);
+        assert_eq!(summary.memos, 3);
+        assert_eq!(summary.tags["ideas"], 2);
+        assert_eq!(summary.tags["work"], 1);
+    }
+}
@@ -0,0 +1,148 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Markdown task items (`- [ ] ...` and `- [x] ...`) in memos, listed by `list_tasks` and checked or
+// unchecked by `toggle_task`. A task is addressed by the memo and the index of its line, and
+// toggling rewrites only the box on that line. Lines inside code fences are not tasks.
+//
+// `sync_open_tasks` compiles the open tasks of recent memos into a single pinned memo starting with
+// `# Open tasks`, related to the memos the tasks come from. It is rewritten on every sync, so tasks
+// are checked off in their own memos and drop out of it at the next sync.
+
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::memos::service::note::Note;
+
+/// First line of the memo the open tasks are compiled into.
+pub const OPEN_TASKS_TITLE: &str = "# Open tasks";
+
+static TASK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*[-*+] \[)([ xX])(\] ?)(.*)$").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    pub memo: String,
+    /// Index of the line of the task in the memo content, starting at 0.
+    pub line: usize,
+    pub done: bool,
+    pub text: String,
+}
+
+/// The lines of `content` that are task items, with their index.
+fn task_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut fenced = false;
+    content.lines().enumerate().filter(move |(_, line)| {
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+            return false;
+        }
+        !fenced && TASK.is_match(line)
+    })
+}
+
+/// The task items of a memo, in the order they appear.
+pub fn tasks(note: &Note) -> Vec<Task> {
+    let memo = note.name.clone().unwrap_or_default();
+    task_lines(&note.content)
+        .map(|(line, text)| {
+            let captures = TASK.captures(text).unwrap();
+            Task {
+                memo: memo.clone(),
+                line,
+                done: &captures[2] != " ",
+                text: captures[4].trim().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Whether `note` is the memo the open tasks are compiled into.
+pub fn is_open_tasks(note: &Note) -> bool {
+    note.content.lines().next().is_some_and(|line| line.trim_end() == OPEN_TASKS_TITLE)
+}
+
+/// The content of the memo compiling `tasks`, each linking to its memo.
+pub fn open_tasks_content(tasks: &[Task]) -> String {
+    let mut content = format!("{}\n", OPEN_TASKS_TITLE);
+    if tasks.is_empty() {
+        content.push_str("\nNothing to do.\n");
+    } else {
+        content.push('\n');
+    }
+    for task in tasks {
+        let _ = writeln!(content, "- [ ] {} ([{}](/{}))", task.text, task.memo, task.memo);
+    }
+    content
+}
+
+/// The content with the task on line `line` checked when it was open and unchecked when it was
+/// done, or set to `done` when given. Every other line is left as it is.
+pub fn toggle(content: &str, line: usize, done: Option<bool>) -> Result<String> {
+    if !task_lines(content).any(|(index, _)| index == line) {
+        return Err(anyhow::anyhow!("Line {} is not a task item", line));
+    }
+    let mut toggled = String::with_capacity(content.len());
+    for (index, text) in content.split_inclusive('\n').enumerate() {
+        if index != line {
+            toggled.push_str(text);
+            continue;
+        }
+        let (text, ending) = match text.strip_suffix("\r\n").or_else(|| text.strip_suffix('\n')) {
+            Some(stripped) => (stripped, &text[stripped.len()..]),
+            None => (text, ""),
+        };
+        let captures = TASK.captures(text).unwrap();
+        let done = done.unwrap_or(&captures[2] == " ");
+        toggled.push_str(&captures[1]);
+        toggled.push(if done { 'x' } else { ' ' });
+        toggled.push_str(&captures[3]);
+        toggled.push_str(&captures[4]);
+        toggled.push_str(ending);
+    }
+    Ok(toggled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memos::mock;
+
+    const CONTENT: &str = "# Today\n- [ ] Buy milk\n  * [x] Call Ann\n```\n- [ ] not a task\n```\n- [X] Done\r\nText";
+
+    #[test]
+    fn test_tasks() {
+        let note = serde_json::from_value::<Note>(mock::memo("memos/1", CONTENT)).unwrap();
+        let tasks = tasks(&note);
+        assert_eq!(tasks.iter().map(|task| (task.line, task.done)).collect::<Vec<_>>(), [(1, false), (2, true), (6, true)]);
+        assert_eq!(tasks[0], Task { memo: "memos/1".to_string(), line: 1, done: false, text: "Buy milk".to_string() });
+    }
+
+    #[test]
+    fn test_toggle() {
+        assert_eq!(toggle(CONTENT, 1, None).unwrap(), CONTENT.replace("- [ ] Buy", "- [x] Buy"));
+        assert_eq!(toggle(CONTENT, 2, None).unwrap(), CONTENT.replace("* [x] Call", "* [ ] Call"));
+        assert_eq!(toggle(CONTENT, 6, Some(false)).unwrap(), CONTENT.replace("- [X] Done", "- [ ] Done"));
+        assert_eq!(toggle(CONTENT, 1, Some(false)).unwrap(), CONTENT);
+        assert!(toggle(CONTENT, 0, None).is_err());
+        assert!(toggle(CONTENT, 4, None).is_err());
+        assert!(toggle(CONTENT, 99, None).is_err());
+    }
+
+    #[test]
+    fn test_open_tasks_content() {
+        let note = serde_json::from_value::<Note>(mock::memo("memos/1", CONTENT)).unwrap();
+        let open = tasks(&note).into_iter().filter(|task| !task.done).collect::<Vec<_>>();
+        let content = open_tasks_content(&open);
+        assert_eq!(content, "# Open tasks\n\n- [ ] Buy milk ([memos/1](/memos/1))\n");
+
+        let compiled = serde_json::from_value::<Note>(mock::memo("memos/2", &content)).unwrap();
+        assert!(is_open_tasks(&compiled));
+        assert!(!is_open_tasks(&note));
+    }
+}
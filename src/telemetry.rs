@@ -0,0 +1,118 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+//! OTLP export of traces and metrics, configured through the standard `OTEL_*` environment
+//! variables (`OTEL_EXPORTER_OTLP_ENDPOINT`, `OTEL_SERVICE_NAME`, `OTEL_RESOURCE_ATTRIBUTES`, ...).
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::Result;
+use opentelemetry::{KeyValue, global, metrics::{Counter, Histogram}, trace::TracerProvider as _};
+use opentelemetry_otlp::{MetricExporter, SpanExporter};
+use opentelemetry_sdk::{Resource, metrics::SdkMeterProvider, trace::{SdkTracer, SdkTracerProvider}};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+const SERVICE_NAME: &str = "mcp-memos";
+
+/// Providers that must be flushed before the process exits.
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// Sets up the OTLP exporters when an OTLP endpoint is configured and the SDK is not disabled.
+    pub fn from_env<S>() -> Result<Option<(Telemetry, OpenTelemetryLayer<S, SdkTracer>)>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let configured = ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "OTEL_EXPORTER_OTLP_METRICS_ENDPOINT"]
+            .iter()
+            .any(|name| std::env::var_os(name).is_some());
+        let disabled = std::env::var("OTEL_SDK_DISABLED").is_ok_and(|v| v.eq_ignore_ascii_case("true"));
+        if !configured || disabled {
+            return Ok(None);
+        }
+
+        let mut resource = Resource::builder();
+        if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+            resource = resource.with_service_name(SERVICE_NAME);
+        }
+        let resource = resource
+            .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+            .build();
+
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_resource(resource.clone())
+            .with_batch_exporter(SpanExporter::builder().with_http().build()?)
+            .build();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_resource(resource)
+            .with_periodic_exporter(MetricExporter::builder().with_http().build()?)
+            .build();
+
+        global::set_tracer_provider(tracer_provider.clone());
+        global::set_meter_provider(meter_provider.clone());
+
+        let layer = tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(SERVICE_NAME));
+        Ok(Some((Telemetry { tracer_provider, meter_provider }, layer)))
+    }
+
+    /// Flushes pending spans and metrics.
+    pub fn shutdown(self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            eprintln!("Failed to flush metrics: {}", e);
+        }
+    }
+}
+
+struct Instruments {
+    upstream_requests: Counter<u64>,
+    upstream_duration: Histogram<f64>,
+    tool_calls: Counter<u64>,
+}
+
+static INSTRUMENTS: LazyLock<Instruments> = LazyLock::new(|| {
+    let meter = global::meter(SERVICE_NAME);
+    Instruments {
+        upstream_requests: meter
+            .u64_counter("memos.upstream.requests")
+            .with_description("Requests sent to the Memos API")
+            .build(),
+        upstream_duration: meter
+            .f64_histogram("memos.upstream.duration")
+            .with_description("Duration of requests sent to the Memos API")
+            .with_unit("s")
+            .build(),
+        tool_calls: meter
+            .u64_counter("mcp.tool.calls")
+            .with_description("MCP tool calls")
+            .build(),
+    }
+});
+
+/// Records a completed request to the Memos API. `status` is `None` when no response arrived.
+pub fn record_upstream(method: &str, status: Option<u16>, elapsed: Duration) {
+    let attributes = [
+        KeyValue::new("http.request.method", method.to_string()),
+        KeyValue::new("http.response.status_code", status.map_or(0, i64::from)),
+    ];
+    INSTRUMENTS.upstream_requests.add(1, &attributes);
+    INSTRUMENTS.upstream_duration.record(elapsed.as_secs_f64(), &attributes);
+}
+
+/// Records a tool call and whether it was accepted.
+pub fn record_tool_call(tool: &str, allowed: bool) {
+    INSTRUMENTS.tool_calls.add(1, &[
+        KeyValue::new("tool", tool.to_string()),
+        KeyValue::new("allowed", allowed),
+    ]);
+}
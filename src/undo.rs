@@ -0,0 +1,112 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::memos::service::note::Note;
+
+/// Number of snapshots kept before the oldest are dropped.
+const CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Updated,
+    Archived,
+    Deleted,
+}
+
+/// A memo as it was right before a change made through MCP.
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    pub session: String,
+    pub server: String,
+    pub change: Change,
+    pub time: DateTime<Utc>,
+    pub note: Note,
+}
+
+/// Bounded in-memory history of overwritten and deleted memos, shared by all sessions.
+#[derive(Default)]
+pub struct UndoLog {
+    snapshots: Mutex<VecDeque<Snapshot>>,
+}
+
+impl UndoLog {
+    pub fn record(&self, session: &str, server: &str, change: Change, note: Note) {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        if snapshots.len() == CAPACITY {
+            snapshots.pop_front();
+        }
+        snapshots.push_back(Snapshot {
+            session: session.to_string(),
+            server: server.to_string(),
+            change,
+            time: Utc::now(),
+            note,
+        });
+    }
+
+    /// Removes the latest change made by `session`.
+    pub fn take_last(&self, session: &str) -> Option<Snapshot> {
+        self.take(|snapshot| snapshot.session == session)
+    }
+
+    /// Removes the latest snapshot of memo `name` taken when it was deleted or archived.
+    pub fn take_deleted(&self, server: &str, name: &str) -> Option<Snapshot> {
+        self.take(|snapshot| {
+            snapshot.server == server
+                && snapshot.change != Change::Updated
+                && snapshot.note.name.as_deref() == Some(name)
+        })
+    }
+
+    /// Puts back a snapshot that could not be restored.
+    pub fn put_back(&self, snapshot: Snapshot) {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let position = snapshots.partition_point(|other| other.time <= snapshot.time);
+        snapshots.insert(position, snapshot);
+    }
+
+    fn take(&self, matches: impl Fn(&Snapshot) -> bool) -> Option<Snapshot> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let position = snapshots.iter().rposition(matches)?;
+        snapshots.remove(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: &str) -> Note {
+        let mut note = Note::new(name);
+        note.name = Some(name.to_string());
+        note
+    }
+
+    #[test]
+    fn test_take_and_put_back() {
+        let log = UndoLog::default();
+        log.record("1-1", "default", Change::Deleted, note("memos/1"));
+        log.record("1-1", "default", Change::Updated, note("memos/2"));
+        log.record("1-2", "default", Change::Updated, note("memos/3"));
+
+        let last = log.take_last("1-1").unwrap();
+        assert_eq!(last.note.name.as_deref(), Some("memos/2"));
+        log.put_back(last);
+        assert_eq!(log.take_last("1-2").unwrap().note.name.as_deref(), Some("memos/3"));
+
+        assert!(log.take_deleted("default", "memos/2").is_none());
+        assert!(log.take_deleted("work", "memos/1").is_none());
+        assert_eq!(log.take_deleted("default", "memos/1").unwrap().change, Change::Deleted);
+        assert_eq!(log.take_last("1-1").unwrap().note.name.as_deref(), Some("memos/2"));
+        assert!(log.take_last("1-1").is_none());
+    }
+}
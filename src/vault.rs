@@ -0,0 +1,440 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Export of a Memos server as an Obsidian vault: one Markdown file per memo, named after the memo
+// id, with YAML front matter. Memo references become `[[wikilinks]]` and attachment files are
+// downloaded into `assets/` and embedded. The import goes the other way for any folder of Markdown
+// files, uploading the local files they embed as attachments.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+use crate::front_matter::{self, parse_time};
+use crate::memos::{
+    Backend, MemoName,
+    service::note::{Attachment, Note, RelationType, Visibility},
+};
+
+/// Folder of the vault the attachment files are written to.
+pub const ASSETS_DIR: &str = "assets";
+
+/// File in an imported folder recording the memo created for each Markdown file, so that
+/// importing the folder again skips the files already imported.
+pub const IMPORT_LEDGER: &str = ".mcp-memo-import.json";
+
+/// `![[file.png]]` and `![alt](path/file.png "title")` embeds, capturing the file.
+static EMBED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"!\[\[([^\]|#]+)[^\]]*\]\]|!\[[^\]]*\]\(\s*<?([^)<>"]+?)>?(?:\s+"[^"]*")?\s*\)"#).unwrap()
+});
+
+/// What an export wrote.
+#[derive(Debug, Default, Serialize)]
+pub struct VaultExport {
+    pub memos: usize,
+    pub attachments: usize,
+    /// Attachments that could not be downloaded, with the reason. Their memos are still exported.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// What an import created.
+#[derive(Debug, Default, Serialize)]
+pub struct VaultImport {
+    pub imported: usize,
+    /// Files imported by an earlier run.
+    pub skipped: usize,
+    pub attachments: usize,
+    /// Files that could not be imported and embeds that could not be uploaded, with the reason.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct FrontMatter<'a> {
+    memo: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated: Option<DateTime<Utc>>,
+    /// Only when the memo was filed under another time than its creation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_time: Option<DateTime<Utc>>,
+    visibility: &'a Visibility,
+    pinned: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Lets Obsidian find the memo by its title although the file is named after its id.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+}
+
+/// Writes every memo of `server` into the vault at `dir`, creating it when missing. Files of
+/// memos exported before are overwritten.
+pub async fn export_vault(server: &dyn Backend, dir: &Path) -> Result<VaultExport> {
+    let notes = server.list_notes().await?;
+    let assets = dir.join(ASSETS_DIR);
+    std::fs::create_dir_all(&assets)?;
+
+    let titles = notes
+        .iter()
+        .filter_map(|note| Some((note.name.clone()?, title(note))))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut export = VaultExport::default();
+    for note in &notes {
+        let name = note.memo_name()?;
+        let mut embeds = Vec::new();
+        for attachment in &note.attachments {
+            if !attachment.external_link.is_empty() {
+                embeds.push(format!("![{}]({})", attachment.filename, attachment.external_link));
+                continue;
+            }
+            let file = asset_file_name(attachment);
+            match server.download_attachment(attachment).await {
+                Ok(data) => {
+                    std::fs::write(assets.join(&file), data)?;
+                    embeds.push(format!("![[{}/{}]]", ASSETS_DIR, file));
+                    export.attachments += 1;
+                }
+                Err(e) => export.errors.push(format!("{} of {}: {}", attachment.filename, name, e)),
+            }
+        }
+        std::fs::write(dir.join(format!("{}.md", name.id())), render(note, &name, &titles, &embeds)?)?;
+        export.memos += 1;
+    }
+    Ok(export)
+}
+
+/// Creates a memo for every Markdown file under `dir` not imported before. Front matter sets the
+/// visibility, tags, pinned flag and creation and update times; local files embedded in the
+/// content are uploaded and attached instead.
+pub async fn import_vault(server: &dyn Backend, dir: &Path) -> Result<VaultImport> {
+    let dir = dir.canonicalize()?;
+    let mut files = Vec::new();
+    walk(&dir, &mut files)?;
+    files.sort();
+    let mut by_file_name = BTreeMap::new();
+    for path in &files {
+        if let Some(file_name) = path.file_name().and_then(|name| name.to_str()) {
+            by_file_name.entry(file_name.to_string()).or_insert_with(|| path.clone());
+        }
+    }
+
+    let ledger_path = dir.join(IMPORT_LEDGER);
+    let mut ledger: BTreeMap<String, MemoName> = match std::fs::read_to_string(&ledger_path) {
+        Ok(text) => serde_json::from_str(&text)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut import = VaultImport::default();
+    for path in files.iter().filter(|path| path.extension().is_some_and(|ext| ext == "md")) {
+        let key = path.strip_prefix(&dir)?.to_string_lossy().replace('\\', "/");
+        if ledger.contains_key(&key) {
+            import.skipped += 1;
+            continue;
+        }
+        match import_file(server, &dir, path, &by_file_name, &mut import).await {
+            Ok(name) => {
+                ledger.insert(key, name);
+                std::fs::write(&ledger_path, serde_json::to_string_pretty(&ledger)? + "\n")?;
+                import.imported += 1;
+            }
+            Err(e) => import.errors.push(format!("{}: {}", key, e)),
+        }
+    }
+    Ok(import)
+}
+
+async fn import_file(
+    server: &dyn Backend,
+    dir: &Path,
+    path: &Path,
+    by_file_name: &BTreeMap<String, PathBuf>,
+    import: &mut VaultImport,
+) -> Result<MemoName> {
+    let text = std::fs::read_to_string(path)?;
+    let (front_matter, body) = front_matter::split(&text)?;
+    let front_matter = front_matter.unwrap_or_default();
+
+    let mut embeds = Vec::new();
+    let content = EMBED.replace_all(body, |caps: &Captures| {
+        let target = caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str().trim()).unwrap_or_default();
+        let is_file = !target.contains("://")
+            && !target.starts_with("data:")
+            && Path::new(target).extension().is_some_and(|ext| ext != "md");
+        if !is_file {
+            return caps[0].to_string();
+        }
+        embeds.push(target.replace("%20", " "));
+        String::new()
+    });
+    let mut content = content.trim().to_string();
+    while content.contains("\n\n\n") {
+        content = content.replace("\n\n\n", "\n\n");
+    }
+
+    let mut builder = Note::builder().content(&content).pinned(front_matter.pinned.unwrap_or(false));
+    if let Some(visibility) = front_matter.visibility()? {
+        builder = builder.visibility(visibility);
+    }
+    for tag in front_matter.tags() {
+        builder = builder.tag(&tag);
+    }
+    if let Some(display_time) = front_matter.display_time()? {
+        builder = builder.display_time(display_time);
+    }
+    let created = front_matter.created.as_deref().map(parse_time).transpose()?;
+    let mut note = builder.build();
+    note.create_time = created;
+    note.update_time = front_matter.updated.as_deref().map(parse_time).transpose()?;
+
+    let name = server.create_note(&note).await?.memo_name()?;
+
+    let mut attachments = Vec::new();
+    for embed in embeds {
+        let uploaded = match resolve_embed(dir, path, &embed, by_file_name) {
+            Some(file) => upload(server, &file).await,
+            None => Err(anyhow::anyhow!("not found in the folder")),
+        };
+        match uploaded {
+            Ok(attachment) => attachments.push(attachment),
+            Err(e) => import.errors.push(format!("{} embedded in {}: {}", embed, name, e)),
+        }
+    }
+    if !attachments.is_empty() {
+        match server.set_note_attachments(&name, &attachments).await {
+            Ok(()) => import.attachments += attachments.len(),
+            Err(e) => import.errors.push(format!("attachments of {}: {}", name, e)),
+        }
+    }
+    Ok(name)
+}
+
+/// Markdown files and the files they may embed under `dir`, leaving out hidden entries such as
+/// `.obsidian/`, `.trash/` and the import ledger.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// The file an embed refers to: relative to the embedding file, to the folder, or anywhere in the
+/// folder by file name like Obsidian does. Never a file outside the folder.
+fn resolve_embed(dir: &Path, path: &Path, embed: &str, by_file_name: &BTreeMap<String, PathBuf>) -> Option<PathBuf> {
+    let parent = path.parent().unwrap_or(dir);
+    [parent.join(embed), dir.join(embed)]
+        .into_iter()
+        .filter_map(|candidate| candidate.canonicalize().ok())
+        .find(|candidate| candidate.is_file() && candidate.starts_with(dir))
+        .or_else(|| by_file_name.get(Path::new(embed).file_name()?.to_str()?).cloned())
+}
+
+async fn upload(server: &dyn Backend, file: &Path) -> Result<Attachment> {
+    let filename = file.file_name().and_then(|name| name.to_str()).unwrap_or("attachment");
+    let extension = file.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+    let mime_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    };
+    server.create_attachment(filename, mime_type, &std::fs::read(file)?).await
+}
+
+/// The Markdown file of `note`, with `embeds` appended to its content.
+fn render(note: &Note, name: &MemoName, titles: &BTreeMap<String, String>, embeds: &[String]) -> Result<String> {
+    let title = titles.get(name.as_str()).cloned().unwrap_or_default();
+    let front_matter = FrontMatter {
+        memo: name.as_str(),
+        created: note.create_time,
+        updated: note.update_time,
+        display_time: note.display_time.filter(|time| Some(*time) != note.create_time),
+        visibility: &note.visibility,
+        pinned: note.pinned,
+        tags: note.tags.clone(),
+        aliases: Some(title).into_iter().filter(|title| !title.is_empty()).collect(),
+    };
+    let mut markdown = format!("---\n{}---\n\n{}\n", serde_yaml::to_string(&front_matter)?, note.content.trim_end());
+
+    if !embeds.is_empty() {
+        markdown.push('\n');
+        for embed in embeds {
+            markdown.push_str(embed);
+            markdown.push('\n');
+        }
+    }
+
+    let links = note
+        .relations
+        .iter()
+        .filter(|relation| relation.relation_type == RelationType::Reference && relation.memo["name"] == name.as_str())
+        .filter_map(|relation| relation.related_memo["name"].as_str()?.parse::<MemoName>().ok())
+        .map(|related| match titles.get(related.as_str()).filter(|title| !title.is_empty()) {
+            Some(title) => format!("- [[{}|{}]]\n", related.id(), title),
+            None => format!("- [[{}]]\n", related.id()),
+        })
+        .collect::<String>();
+    if !links.is_empty() {
+        markdown.push_str("\n## Related\n\n");
+        markdown.push_str(&links);
+    }
+    Ok(markdown)
+}
+
+/// The first line of the memo without heading marks, stripped of what breaks a wikilink.
+pub(crate) fn title(note: &Note) -> String {
+    let line = note
+        .content
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    line.chars()
+        .filter(|c| !matches!(c, '[' | ']' | '|' | '#' | '^'))
+        .take(80)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// `{attachment id}-{filename}`, unique within the vault and safe as a file name on every platform.
+fn asset_file_name(attachment: &Attachment) -> String {
+    let id = attachment.name.rsplit('/').next().unwrap_or_default();
+    let filename = attachment
+        .filename
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '[' | ']' | '#' | '^' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    format!("{}-{}", id, filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::{Value, json};
+    use wiremock::{Mock, Request, ResponseTemplate, matchers::{method, path, path_regex}};
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[tokio::test]
+    async fn test_export_vault() {
+        let (mock, server) = mock::start().await;
+        let mut first = mock::memo("memos/1", "# Balcony garden\n\nGrow tomatoes #garden");
+        first["tags"] = json!(["garden"]);
+        first["pinned"] = json!(true);
+        first["attachments"] = json!([
+            mock::attachment("attachments/a1", "plan: v2.png", "image/png"),
+            {"name": "attachments/a2", "filename": "seeds.pdf", "type": "application/pdf", "externalLink": "https://example.com/seeds.pdf"},
+            mock::attachment("attachments/a3", "missing.txt", "text/plain"),
+        ]);
+        first["relations"] = json!([
+            {"memo": {"name": "memos/1"}, "relatedMemo": {"name": "memos/2"}, "type": "REFERENCE"},
+            {"memo": {"name": "memos/3"}, "relatedMemo": {"name": "memos/1"}, "type": "REFERENCE"},
+        ]);
+        Mock::given(method("GET")).and(path("/api/v1/memos"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"memos": [first, mock::memo("memos/2", "Basil [salad]")]})))
+            .mount(&mock).await;
+        Mock::given(method("GET")).and(path("/file/attachments/a1/plan:%20v2.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"PNG".to_vec()))
+            .mount(&mock).await;
+
+        let dir = std::env::temp_dir().join(format!("mcp-memos-vault-{}", std::process::id()));
+        let export = export_vault(&server, &dir).await.unwrap();
+        assert_eq!((export.memos, export.attachments, export.errors.len()), (2, 1, 1));
+
+        let first = std::fs::read_to_string(dir.join("1.md")).unwrap();
+        assert!(first.starts_with("---\nmemo: memos/1\ncreated: 2025-01-31T08:00:00Z\n"), "{}", first);
+        assert!(first.contains("visibility: PRIVATE\npinned: true\ntags:\n- garden\naliases:\n- Balcony garden\n---\n\n# Balcony garden"));
+        assert!(first.contains("\n![[assets/a1-plan_ v2.png]]\n![seeds.pdf](https://example.com/seeds.pdf)\n"));
+        assert!(first.ends_with("\n## Related\n\n- [[2|Basil salad]]\n"), "{}", first);
+        assert_eq!(std::fs::read(dir.join(ASSETS_DIR).join("a1-plan_ v2.png")).unwrap(), b"PNG");
+        assert!(!std::fs::read_to_string(dir.join("2.md")).unwrap().contains("## Related"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_vault() {
+        let dir = std::env::temp_dir().join(format!("mcp-memos-import-{}", std::process::id()));
+        for folder in ["assets", "img", "notes", ".obsidian"] {
+            std::fs::create_dir_all(dir.join(folder)).unwrap();
+        }
+        std::fs::write(dir.join("Garden.md"), "---\nvisibility: public\ntags: [garden, balcony]\ncreated: 2024-05-01\npinned: true\n---\n\nTomatoes\n\n![[plan.png]]\n\nSee ![photo](img/photo%20one.jpg) and ![[Other note]]\n").unwrap();
+        std::fs::write(dir.join("assets/plan.png"), b"PNG").unwrap();
+        std::fs::write(dir.join("img/photo one.jpg"), b"JPG").unwrap();
+        std::fs::write(dir.join("notes/Plain.md"), "Just text ![x](../../outside.png)").unwrap();
+        std::fs::write(dir.join(".obsidian/workspace.md"), "Not a note").unwrap();
+
+        let (mock, server) = mock::start().await;
+        let created = AtomicUsize::new(0);
+        Mock::given(method("POST")).and(path("/api/v1/memos"))
+            .respond_with(move |request: &Request| {
+                let body = request.body_json::<Value>().unwrap();
+                let name = format!("memos/{}", created.fetch_add(1, Ordering::Relaxed) + 1);
+                ResponseTemplate::new(200).set_body_json(mock::memo(&name, body["content"].as_str().unwrap()))
+            })
+            .mount(&mock).await;
+        Mock::given(method("POST")).and(path("/api/v1/attachments"))
+            .respond_with(|request: &Request| {
+                let body = request.body_json::<Value>().unwrap();
+                let filename = body["filename"].as_str().unwrap();
+                ResponseTemplate::new(200).set_body_json(mock::attachment(&format!("attachments/{}", filename), filename, body["type"].as_str().unwrap()))
+            })
+            .mount(&mock).await;
+        Mock::given(method("POST")).and(path_regex(r"^/api/v1/memos/[^/]+/attachments$"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock).await;
+
+        let import = import_vault(&server, &dir).await.unwrap();
+        assert_eq!((import.imported, import.skipped, import.attachments), (2, 0, 2));
+        assert_eq!(import.errors.len(), 1, "{:?}", import.errors);
+        assert!(import.errors[0].starts_with("../../outside.png embedded in memos/2"));
+
+        let requests = mock.received_requests().await.unwrap();
+        let garden = requests[0].body_json::<Value>().unwrap();
+        assert_eq!(garden["content"], "Tomatoes\n\nSee  and ![[Other note]] #garden #balcony");
+        assert_eq!((&garden["visibility"], &garden["pinned"]), (&json!("PUBLIC"), &json!(true)));
+        assert_eq!(garden["createTime"], "2024-05-01T00:00:00Z");
+        let uploads = requests.iter().filter(|request| request.url.path() == "/api/v1/attachments").collect::<Vec<_>>();
+        assert_eq!(uploads[0].body_json::<Value>().unwrap()["content"], "UE5H");
+        assert_eq!(uploads[1].body_json::<Value>().unwrap()["type"], "image/jpeg");
+
+        let again = import_vault(&server, &dir).await.unwrap();
+        assert_eq!((again.imported, again.skipped), (0, 2));
+        assert_eq!(mock.received_requests().await.unwrap().len(), requests.len());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Receiver of Memos webhooks at `/webhooks/memos/{server}`. Each memo created, updated or deleted
+// on that server invalidates the local caches built from its memos and is broadcast to the MCP
+// sessions, which notify their clients of the memo resources they subscribed to. The tool list
+// never changes with memos, custom tools and macros coming from the config file.
+//
+// Memos cannot sign its callbacks, so the webhook URL registered in Memos carries the shared secret
+// as `?secret=...`. It is also accepted in an `X-Webhook-Secret` header, e.g. from a proxy.
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::calendar::Calendar;
+use crate::feed::Feed;
+use crate::memos::Servers;
+use crate::mirror::Mirror;
+use crate::tags::TagCache;
+
+/// Events kept for MCP sessions that fall behind before older ones are dropped.
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A change to a memo reported by a Memos webhook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoEvent {
+    pub server: String,
+    /// The memo name, e.g. `memos/1`.
+    pub memo: String,
+    pub kind: EventKind,
+}
+
+/// The broadcast of memo events, which every MCP session subscribes to.
+pub type Events = broadcast::Sender<MemoEvent>;
+
+pub fn events() -> Events {
+    broadcast::channel(EVENT_BUFFER).0
+}
+
+/// The body Memos posts to a webhook.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+    activity_type: String,
+    #[serde(default)]
+    memo: Option<PayloadMemo>,
+}
+
+#[derive(Deserialize)]
+struct PayloadMemo {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct WebhookQuery {
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+pub struct Webhook {
+    secret: String,
+    events: Events,
+    servers: Arc<Servers>,
+    tags: Arc<TagCache>,
+    mirror: Option<Arc<Mirror>>,
+    feed: Option<Arc<Feed>>,
+    calendar: Option<Arc<Calendar>>,
+}
+
+impl Webhook {
+    pub fn new(secret: &str, events: Events, servers: Arc<Servers>, tags: Arc<TagCache>) -> Self {
+        Webhook {
+            secret: secret.to_string(),
+            events,
+            servers,
+            tags,
+            mirror: None,
+            feed: None,
+            calendar: None,
+        }
+    }
+
+    /// The events this webhook broadcasts, for the MCP sessions to subscribe to.
+    pub fn events(&self) -> Events {
+        self.events.clone()
+    }
+
+    /// Also synchronizes the local mirror with each change.
+    pub fn with_mirror(mut self, mirror: Option<Arc<Mirror>>) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Also renders the Atom feed and the calendar again after each change.
+    pub fn with_feeds(mut self, feed: Option<Arc<Feed>>, calendar: Option<Arc<Calendar>>) -> Self {
+        self.feed = feed;
+        self.calendar = calendar;
+        self
+    }
+
+    /// Drops what the caches know about `event.server` and tells the MCP sessions about the event.
+    async fn apply(&self, event: MemoEvent) {
+        self.tags.invalidate(&event.server);
+        if event.server == self.servers.default_name() {
+            if let Some(feed) = &self.feed {
+                feed.invalidate().await;
+            }
+            if let Some(calendar) = &self.calendar {
+                calendar.invalidate().await;
+            }
+        }
+        if let (Some(mirror), Ok(server)) = (&self.mirror, self.servers.get(Some(&event.server))) {
+            // A delta sync cannot see deletions.
+            let full = event.kind == EventKind::Deleted;
+            if let Err(e) = mirror.sync(&event.server, server, full).await {
+                warn!("Failed to synchronize mirror of server {}: {}", event.server, e);
+            }
+        }
+        // Fails only while no session is subscribed.
+        let _ = self.events.send(event);
+    }
+}
+
+/// Handler of `POST /webhooks/memos/{server}`.
+pub async fn receive(
+    State(webhook): State<Arc<Webhook>>,
+    Path(server): Path<String>,
+    Query(query): Query<WebhookQuery>,
+    headers: HeaderMap,
+    Json(payload): Json<Payload>,
+) -> StatusCode {
+    let secret = query
+        .secret
+        .as_deref()
+        .or_else(|| headers.get("x-webhook-secret").and_then(|secret| secret.to_str().ok()));
+    if !secret.is_some_and(|secret| is_secret(secret, &webhook.secret)) {
+        return StatusCode::UNAUTHORIZED;
+    }
+    if webhook.servers.get(Some(&server)).is_err() {
+        return StatusCode::NOT_FOUND;
+    }
+
+    let kind = match payload.activity_type.as_str() {
+        "memos.memo.created" => EventKind::Created,
+        "memos.memo.updated" => EventKind::Updated,
+        "memos.memo.deleted" => EventKind::Deleted,
+        other => {
+            debug!("Ignoring webhook activity {}", other);
+            return StatusCode::NO_CONTENT;
+        }
+    };
+    let Some(memo) = payload.memo else {
+        return StatusCode::BAD_REQUEST;
+    };
+    debug!(server, memo = memo.name, ?kind, "memo event");
+    webhook.apply(MemoEvent { server, memo: memo.name, kind }).await;
+    StatusCode::NO_CONTENT
+}
+
+/// Whether `given` is `secret`, compared in a time that does not depend on where they differ.
+fn is_secret(given: &str, secret: &str) -> bool {
+    given.len() == secret.len() && given.bytes().zip(secret.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[tokio::test]
+    async fn test_receive() {
+        let (_mock, server) = mock::start().await;
+        let servers = Arc::new(Servers::new("default", BTreeMap::from([("default".to_string(), server)])));
+        let events = events();
+        let mut received = events.subscribe();
+        let webhook = Arc::new(Webhook::new("s3cret", events, servers, Arc::new(TagCache::default())));
+
+        let receive = |server: &str, secret: Option<&str>, headers: HeaderMap| {
+            let payload = json!({"activityType": "memos.memo.updated", "creator": "users/1", "memo": mock::memo("memos/1", "Edited")});
+            receive(
+                State(webhook.clone()),
+                Path(server.to_string()),
+                Query(WebhookQuery { secret: secret.map(str::to_string) }),
+                headers,
+                Json(serde_json::from_value(payload).unwrap()),
+            )
+        };
+
+        assert_eq!(receive("default", None, HeaderMap::new()).await, StatusCode::UNAUTHORIZED);
+        for wrong in ["wrong", "s3cre7", "s3cret2", ""] {
+            assert_eq!(receive("default", Some(wrong), HeaderMap::new()).await, StatusCode::UNAUTHORIZED, "{}", wrong);
+        }
+        assert_eq!(receive("other", Some("s3cret"), HeaderMap::new()).await, StatusCode::NOT_FOUND);
+        assert!(received.try_recv().is_err());
+
+        assert_eq!(receive("default", Some("s3cret"), HeaderMap::new()).await, StatusCode::NO_CONTENT);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-webhook-secret", "s3cret".parse().unwrap());
+        assert_eq!(receive("default", None, headers).await, StatusCode::NO_CONTENT);
+        for _ in 0..2 {
+            assert_eq!(
+                received.try_recv().unwrap(),
+                MemoEvent { server: "default".to_string(), memo: "memos/1".to_string(), kind: EventKind::Updated },
+            );
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// Project: MCP Memo App
+// Author: Rajeshwar Raja
+// Date: 2025-12-28
+// License: Proprietary
+
+// Instance-wide statistics for administrators, reported by `get_workspace_stats`: the users by role
+// and state, the memo count of each user, the storage taken by attachments and the latest signups.
+// Memos only lists the attachments of the signed-in user, so the storage covers that user alone.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::memos::service::note::Attachment;
+use crate::memos::service::user::{State, User, UserStats};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserMemos {
+    pub user: String,
+    pub username: String,
+    pub memos: u64,
+    pub pinned: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttachmentUsage {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Signup {
+    pub user: String,
+    pub username: String,
+    pub create_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkspaceStats {
+    pub users: usize,
+    pub users_by_role: BTreeMap<String, usize>,
+    pub archived_users: usize,
+    pub memos: u64,
+    /// Users with the most memos first.
+    pub memos_per_user: Vec<UserMemos>,
+    /// Attachments of the signed-in user.
+    pub attachments: AttachmentUsage,
+    /// Users created since the cutoff, newest first.
+    pub recent_signups: Vec<Signup>,
+}
+
+impl WorkspaceStats {
+    pub fn new(users: &[User], stats: &[UserStats], attachments: &[Attachment], signups_since: DateTime<Utc>) -> Self {
+        let mut users_by_role = BTreeMap::new();
+        for user in users {
+            let role = serde_json::to_value(user.role).ok().and_then(|role| role.as_str().map(str::to_string));
+            *users_by_role.entry(role.unwrap_or_default()).or_default() += 1;
+        }
+
+        let username = |name: &str| users.iter().find(|user| user.name == name).map(|user| user.username.clone());
+        let mut memos_per_user = stats
+            .iter()
+            .map(|stats| UserMemos {
+                user: stats.user().to_string(),
+                username: username(stats.user()).unwrap_or_default(),
+                memos: stats.total_memo_count,
+                pinned: stats.pinned_memos.len(),
+            })
+            .collect::<Vec<_>>();
+        memos_per_user.sort_by(|a, b| b.memos.cmp(&a.memos).then_with(|| a.user.cmp(&b.user)));
+
+        let mut recent_signups = users
+            .iter()
+            .filter_map(|user| {
+                let create_time = user.create_time.filter(|time| *time >= signups_since)?;
+                Some(Signup { user: user.name.clone(), username: user.username.clone(), create_time })
+            })
+            .collect::<Vec<_>>();
+        recent_signups.sort_by_key(|signup| std::cmp::Reverse(signup.create_time));
+
+        WorkspaceStats {
+            users: users.len(),
+            users_by_role,
+            archived_users: users.iter().filter(|user| user.state == State::Archived).count(),
+            memos: stats.iter().map(|stats| stats.total_memo_count).sum(),
+            memos_per_user,
+            attachments: AttachmentUsage {
+                count: attachments.len(),
+                bytes: attachments.iter().map(|attachment| attachment.size.parse::<u64>().unwrap_or(0)).sum(),
+            },
+            recent_signups,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::memos::mock;
+
+    #[test]
+    fn test_workspace_stats() {
+        let mut host = mock::user("users/1", "host");
+        host["role"] = json!("HOST");
+        host["createTime"] = json!("2024-06-01T00:00:00Z");
+        let mut jane = mock::user("users/2", "jane");
+        jane["createTime"] = json!("2025-02-01T00:00:00Z");
+        let mut old = mock::user("users/3", "old");
+        old["state"] = json!("ARCHIVED");
+        old["createTime"] = json!("2023-03-01T00:00:00Z");
+        let users = [host, jane, old].map(|user| serde_json::from_value::<User>(user).unwrap());
+        let stats = [
+            json!({"name": "users/1/stats", "totalMemoCount": 3, "pinnedMemos": ["memos/1"]}),
+            json!({"name": "users/2/stats", "totalMemoCount": 9}),
+        ]
+        .map(|stats| serde_json::from_value::<UserStats>(stats).unwrap());
+        let mut attachment = mock::attachment("attachments/1", "plan.png", "image/png");
+        attachment["size"] = json!("2048");
+        let attachments = [serde_json::from_value::<Attachment>(attachment).unwrap()];
+
+        let workspace = WorkspaceStats::new(&users, &stats, &attachments, "2025-01-01T00:00:00Z".parse().unwrap());
+        assert_eq!(workspace.users, 3);
+        assert_eq!(workspace.users_by_role, BTreeMap::from([("HOST".to_string(), 1), ("USER".to_string(), 2)]));
+        assert_eq!(workspace.archived_users, 1);
+        assert_eq!(workspace.memos, 12);
+        assert_eq!(
+            workspace.memos_per_user.iter().map(|u| (u.username.as_str(), u.memos, u.pinned)).collect::<Vec<_>>(),
+            [("jane", 9, 0), ("host", 3, 1)],
+        );
+        assert_eq!(workspace.attachments, AttachmentUsage { count: 1, bytes: 2048 });
+        assert_eq!(workspace.recent_signups.iter().map(|s| s.username.as_str()).collect::<Vec<_>>(), ["jane"]);
+    }
+}